@@ -119,6 +119,18 @@ impl ZSlice {
         self.buf.as_any().downcast_ref::<T>()
     }
 
+    /// Returns the bytes of this [`ZSlice`] for in-place modification if the underlying buffer
+    /// is not shared with any other [`ZSlice`].
+    #[inline]
+    #[must_use]
+    pub fn as_mut_slice(&mut self) -> Option<&mut [u8]> {
+        let range = self.range();
+        let buf = Arc::get_mut(&mut self.buf)?;
+        // SAFETY: bounds checks are performed at `ZSlice` construction via `make()` or `subslice()`
+        // and the underlying buffer cannot be resized through `ZSliceBuffer::as_mut_slice()`.
+        Some(crate::unsafe_slice_mut!(buf.as_mut_slice(), range))
+    }
+
     #[inline]
     #[must_use]
     pub const fn range(&self) -> Range<usize> {
@@ -408,4 +420,18 @@ mod tests {
 
         assert_eq!(buf.as_slice(), zslice.as_slice());
     }
+
+    #[test]
+    fn zslice_as_mut_slice() {
+        let mut zslice = ZSlice::from(alloc::vec![0u8; 16]).subslice(4, 8).unwrap();
+        zslice.as_mut_slice().unwrap()[0] = 1;
+        assert_eq!(zslice.as_mut_slice().unwrap().len(), 4);
+        assert_eq!(zslice[0], 1);
+
+        // a shared buffer cannot be mutated
+        let shared = zslice.clone();
+        assert!(zslice.as_mut_slice().is_none());
+        drop(shared);
+        assert!(zslice.as_mut_slice().is_some());
+    }
 }
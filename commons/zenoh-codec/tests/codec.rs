@@ -546,6 +546,66 @@ fn codec_response_final() {
     run!(ResponseFinal, ResponseFinal::rand());
 }
 
+// The nodes that do not know the Cancel extension skip the unknown extensions: they fail to decode
+// a cancelling ResponseFinal, the extension being mandatory, and then close the session
+#[test]
+fn codec_response_final_cancel_unknown() {
+    let codec = Zenoh080::new();
+    let x = ResponseFinal {
+        rid: 1,
+        ext_qos: network::ext::QoSType::response_final_default(),
+        ext_tstamp: None,
+        ext_cancel: Some(network::response::ext::CancelType::new()),
+    };
+    let mut buff = vec![];
+    let mut writer = buff.writer();
+    codec.write(&mut writer, &x).unwrap();
+
+    let mut reader = buff.reader();
+    let header: u8 = codec.read(&mut reader).unwrap();
+    let _rid: RequestId = Zenoh080Bounded::<RequestId>::new()
+        .read(&mut reader)
+        .unwrap();
+    // the extensions loop of the decoder of these nodes
+    let mut has_ext = imsg::has_flag(header, network::response::flag::Z);
+    let mut result = Ok(());
+    while has_ext {
+        let ext: u8 = codec.read(&mut reader).unwrap();
+        let eodec = Zenoh080Header::new(ext);
+        match iext::eid(ext) {
+            network::response::ext::QoS::ID => {
+                let (_, ext): (network::response::ext::QoSType, bool) =
+                    eodec.read(&mut reader).unwrap();
+                has_ext = ext;
+            }
+            _ => match common::extension::skip(&mut reader, "ResponseFinal", ext) {
+                Ok(ext) => has_ext = ext,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            },
+        }
+    }
+    assert!(result.is_err());
+}
+
+// The nodes that do not know the Patch extension skip it, it is not mandatory
+#[test]
+fn codec_init_patch_unknown() {
+    let codec = Zenoh080::new();
+    let ext = transport::init::ext::Patch::new(transport::init::ext::PATCH_CURRENT);
+    let mut buff = vec![];
+    let mut writer = buff.writer();
+    codec.write(&mut writer, (&ext, false)).unwrap();
+
+    let mut reader = buff.reader();
+    let header: u8 = codec.read(&mut reader).unwrap();
+    let has_ext = common::extension::skip(&mut reader, "InitSyn", header).unwrap();
+    assert!(!has_ext);
+    assert!(!reader.can_read());
+}
+
 #[test]
 fn codec_network_oam() {
     run!(network::Oam, network::Oam::rand());
@@ -481,3 +481,22 @@ pub fn register_param(input: proc_macro::TokenStream) -> proc_macro::TokenStream
         .unwrap_or_else(syn::Error::into_compile_error)
         .into()
 }
+
+mod rpc_service;
+use rpc_service::derive_rpc_service;
+
+/// Generate the client stub and the server bindings of a `zenoh-ext` RPC service from a trait.
+/// ```rust,ignore
+/// #[zenoh_ext::rpc::service]
+/// trait Service {
+///    async fn method(&self, arg: Arg) -> Result<Response, Error>;
+///    ...
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn rpc_service(_attr: TokenStream, tokens: TokenStream) -> TokenStream {
+    let service: syn::ItemTrait = parse_macro_input!(tokens);
+    derive_rpc_service(service)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,256 @@
+//
+// Copyright (c) 2024 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse_quote, Error, FnArg, GenericArgument, Ident, ItemTrait, Pat, PathArguments, ReturnType,
+    TraitItem, TraitItemFn, Type,
+};
+
+// The names of the methods generated on the service trait and its client stub
+const RESERVED: [&str; 4] = ["into_rpc_server", "new", "with_timeout", "client"];
+
+struct Method {
+    name: Ident,
+    docs: Vec<syn::Attribute>,
+    args: Vec<Ident>,
+    types: Vec<Type>,
+    ok: Type,
+    err: Type,
+}
+
+fn parse_method(method: &TraitItemFn) -> syn::Result<Method> {
+    let sig = &method.sig;
+    if RESERVED.iter().any(|name| sig.ident == name) {
+        return Err(Error::new_spanned(
+            &sig.ident,
+            "this name is reserved by the generated code",
+        ));
+    }
+    if sig.asyncness.is_none() {
+        return Err(Error::new_spanned(sig, "service methods must be `async`"));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &sig.generics,
+            "service methods cannot be generic",
+        ));
+    }
+    let mut inputs = sig.inputs.iter();
+    match inputs.next() {
+        Some(FnArg::Receiver(receiver))
+            if receiver.reference.is_some() && receiver.mutability.is_none() => {}
+        _ => {
+            return Err(Error::new_spanned(
+                sig,
+                "service methods must take `&self` as first argument",
+            ))
+        }
+    }
+    let mut args = Vec::new();
+    let mut types = Vec::new();
+    for input in inputs {
+        match input {
+            FnArg::Typed(arg) => match arg.pat.as_ref() {
+                Pat::Ident(pat) if pat.by_ref.is_none() && pat.subpat.is_none() => {
+                    args.push(pat.ident.clone());
+                    types.push(arg.ty.as_ref().clone());
+                }
+                pat => {
+                    return Err(Error::new_spanned(
+                        pat,
+                        "service method arguments must be identifiers",
+                    ))
+                }
+            },
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new_spanned(receiver, "unexpected receiver"))
+            }
+        }
+    }
+    let (ok, err) = parse_result(&sig.output).ok_or_else(|| {
+        Error::new_spanned(&sig.output, "service methods must return a `Result<T, E>`")
+    })?;
+    Ok(Method {
+        name: sig.ident.clone(),
+        docs: method
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"))
+            .cloned()
+            .collect(),
+        args,
+        types,
+        ok,
+        err,
+    })
+}
+
+/// Returns `T` and `E` from a `Result<T, E>` return type.
+fn parse_result(output: &ReturnType) -> Option<(Type, Type)> {
+    let ReturnType::Type(_, ty) = output else {
+        return None;
+    };
+    let Type::Path(path) = ty.as_ref() else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(generics) = &segment.arguments else {
+        return None;
+    };
+    let mut types = generics.args.iter().filter_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty.clone()),
+        _ => None,
+    });
+    match (types.next(), types.next(), types.next()) {
+        (Some(ok), Some(err), None) => Some((ok, err)),
+        _ => None,
+    }
+}
+
+pub(crate) fn derive_rpc_service(mut service: ItemTrait) -> syn::Result<TokenStream> {
+    let methods = service
+        .items
+        .iter()
+        .map(|item| match item {
+            TraitItem::Fn(method) => parse_method(method),
+            item => Err(Error::new_spanned(
+                item,
+                "services can only contain methods",
+            )),
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    if !service.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &service.generics,
+            "services cannot be generic",
+        ));
+    }
+
+    let vis = &service.vis;
+    let ident = &service.ident;
+    let client = format_ident!("{}Client", ident);
+    let client_doc = format!("The client stub of the [`{ident}`] service.");
+
+    let bindings = methods.iter().map(|method| {
+        let Method {
+            name, args, types, ..
+        } = method;
+        let name_str = name.to_string();
+        quote! {
+            .method(#name_str, {
+                let this = this.clone();
+                move |(#(#args,)*): (#(#types,)*)| {
+                    let this = this.clone();
+                    async move { this.#name(#(#args),*).await }
+                }
+            })
+        }
+    });
+    service.items.push(parse_quote! {
+        /// Returns the builder of a server of this implementation of the service.
+        fn into_rpc_server<K>(
+            self,
+            session: ::zenoh_ext::rpc::__private::Arc<::zenoh_ext::rpc::__private::Session>,
+            service: K,
+        ) -> ::zenoh_ext::rpc::RpcServerBuilder
+        where
+            Self: Sized,
+            K: ::std::convert::TryInto<::zenoh_ext::rpc::__private::OwnedKeyExpr>,
+            <K as ::std::convert::TryInto<::zenoh_ext::rpc::__private::OwnedKeyExpr>>::Error:
+                Into<::zenoh_ext::rpc::__private::ZError>,
+        {
+            let this = ::zenoh_ext::rpc::__private::Arc::new(self);
+            ::zenoh_ext::rpc::RpcServerBuilder::new(session, service)
+                #(#bindings)*
+        }
+    });
+    service.supertraits.push(parse_quote!(::std::marker::Send));
+    service.supertraits.push(parse_quote!(::std::marker::Sync));
+    service.supertraits.push(parse_quote!('static));
+    if service.colon_token.is_none() {
+        service.colon_token = Some(Default::default());
+    }
+
+    let stubs = methods.iter().map(|method| {
+        let Method {
+            name,
+            docs,
+            args,
+            types,
+            ok,
+            err,
+        } = method;
+        let name_str = name.to_string();
+        quote! {
+            #(#docs)*
+            pub async fn #name(&self, #(#args: #types),*)
+                -> ::std::result::Result<#ok, ::zenoh_ext::rpc::RpcError<#err>>
+            {
+                self.client.call(#name_str, &(#(#args,)*)).await
+            }
+        }
+    });
+
+    Ok(quote! {
+        #[::zenoh_ext::rpc::async_trait]
+        #service
+
+        #[doc = #client_doc]
+        #[derive(Clone)]
+        #vis struct #client {
+            client: ::zenoh_ext::rpc::RpcClient,
+        }
+
+        impl #client {
+            /// Creates a client of the given service.
+            pub async fn new<K>(
+                session: ::zenoh_ext::rpc::__private::Arc<::zenoh_ext::rpc::__private::Session>,
+                service: K,
+            ) -> ::zenoh_ext::rpc::__private::ZResult<Self>
+            where
+                K: ::std::convert::TryInto<::zenoh_ext::rpc::__private::OwnedKeyExpr>,
+                <K as ::std::convert::TryInto<::zenoh_ext::rpc::__private::OwnedKeyExpr>>::Error:
+                    Into<::zenoh_ext::rpc::__private::ZError>,
+            {
+                Ok(Self {
+                    client: ::zenoh_ext::rpc::RpcClient::new(session, service).await?,
+                })
+            }
+
+            /// Sets the deadline of the calls.
+            pub fn with_timeout(self, timeout: ::std::time::Duration) -> Self {
+                Self {
+                    client: self.client.with_timeout(timeout),
+                }
+            }
+
+            /// Returns the underlying untyped client.
+            pub fn client(&self) -> &::zenoh_ext::rpc::RpcClient {
+                &self.client
+            }
+
+            #(#stubs)*
+        }
+
+        impl ::std::convert::From<::zenoh_ext::rpc::RpcClient> for #client {
+            fn from(client: ::zenoh_ext::rpc::RpcClient) -> Self {
+                Self { client }
+            }
+        }
+    })
+}
@@ -57,8 +57,8 @@ pub struct Response {
 
 pub mod ext {
     use crate::{
-        common::{ZExtZ64, ZExtZBuf},
-        zextz64, zextzbuf,
+        common::{ZExtUnit, ZExtZ64, ZExtZBuf},
+        zextunit, zextz64, zextzbuf,
     };
     pub type QoS = zextz64!(0x1, false);
     pub type QoSType = crate::network::ext::QoSType<{ QoS::ID }>;
@@ -68,6 +68,17 @@ pub mod ext {
 
     pub type ResponderId = zextzbuf!(0x3, false);
     pub type ResponderIdType = crate::network::ext::EntityIdType<{ ResponderId::ID }>;
+
+    /// # Cancel extension
+    ///
+    /// Used on a ResponseFinal sent by the requester to the responder, i.e. in the opposite
+    /// direction of a regular ResponseFinal: the request `rid` is cancelled and no more
+    /// responses are expected for it. It is mandatory, so that it is never taken for the final
+    /// response of an unrelated request: the nodes that do not support it fail to decode the message
+    /// and close the session. It is thus only sent to the nodes that negotiated the
+    /// [`crate::transport::init::ext::PATCH_QUERY_CANCELLATION`] revision of the protocol.
+    pub type Cancel = zextunit!(0x4, true);
+    pub type CancelType = Cancel;
 }
 
 impl Response {
@@ -113,11 +124,14 @@ impl Response {
 ///
 /// (*) The resolution of the request id is negotiated during the session establishment.
 ///     This implementation limits the resolution to 32bit.
+///
+/// With the Cancel extension, it is sent by the requester to cancel the request, see [`ext::Cancel`].
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ResponseFinal {
     pub rid: RequestId,
     pub ext_qos: ext::QoSType,
     pub ext_tstamp: Option<ext::TimestampType>,
+    pub ext_cancel: Option<ext::CancelType>,
 }
 
 impl ResponseFinal {
@@ -129,11 +143,13 @@ impl ResponseFinal {
         let rid: RequestId = rng.gen();
         let ext_qos = ext::QoSType::rand();
         let ext_tstamp = rng.gen_bool(0.5).then(ext::TimestampType::rand);
+        let ext_cancel = rng.gen_bool(0.5).then(ext::CancelType::rand);
 
         Self {
             rid,
             ext_qos,
             ext_tstamp,
+            ext_cancel,
         }
     }
 }
@@ -119,13 +119,14 @@ pub struct InitSyn {
     pub ext_mlink: Option<ext::MultiLink>,
     pub ext_lowlatency: Option<ext::LowLatency>,
     pub ext_compression: Option<ext::Compression>,
+    pub ext_patch: Option<ext::Patch>,
 }
 
 // Extensions
 pub mod ext {
     use crate::{
-        common::{ZExtUnit, ZExtZBuf},
-        zextunit, zextzbuf,
+        common::{ZExtUnit, ZExtZ64, ZExtZBuf},
+        zextunit, zextz64, zextzbuf,
     };
 
     /// # QoS extension
@@ -151,12 +152,23 @@ pub mod ext {
     /// # Compression extension
     /// Used to negotiate the use of compression on the link
     pub type Compression = zextunit!(0x6, false);
+
+    /// # Patch extension
+    /// Used to negotiate the revision of the protocol, i.e. the lowest of the revisions of the two nodes.
+    /// Its absence stands for the revision 0 of the nodes that do not know this extension.
+    pub type Patch = zextz64!(0x7, false);
+
+    /// The requester can cancel a request with a ResponseFinal carrying the Cancel extension
+    /// (see [`crate::network::response::ext::Cancel`])
+    pub const PATCH_QUERY_CANCELLATION: u64 = 1;
+    /// The revision of the protocol implemented by this node
+    pub const PATCH_CURRENT: u64 = PATCH_QUERY_CANCELLATION;
 }
 
 impl InitSyn {
     #[cfg(feature = "test")]
     pub fn rand() -> Self {
-        use crate::common::{ZExtUnit, ZExtZBuf};
+        use crate::common::{ZExtUnit, ZExtZ64, ZExtZBuf};
         use rand::Rng;
 
         let mut rng = rand::thread_rng();
@@ -172,6 +184,7 @@ impl InitSyn {
         let ext_mlink = rng.gen_bool(0.5).then_some(ZExtZBuf::rand());
         let ext_lowlatency = rng.gen_bool(0.5).then_some(ZExtUnit::rand());
         let ext_compression = rng.gen_bool(0.5).then_some(ZExtUnit::rand());
+        let ext_patch = rng.gen_bool(0.5).then_some(ZExtZ64::rand());
 
         Self {
             version,
@@ -185,6 +198,7 @@ impl InitSyn {
             ext_mlink,
             ext_lowlatency,
             ext_compression,
+            ext_patch,
         }
     }
 }
@@ -203,12 +217,13 @@ pub struct InitAck {
     pub ext_mlink: Option<ext::MultiLink>,
     pub ext_lowlatency: Option<ext::LowLatency>,
     pub ext_compression: Option<ext::Compression>,
+    pub ext_patch: Option<ext::Patch>,
 }
 
 impl InitAck {
     #[cfg(feature = "test")]
     pub fn rand() -> Self {
-        use crate::common::{ZExtUnit, ZExtZBuf};
+        use crate::common::{ZExtUnit, ZExtZ64, ZExtZBuf};
         use rand::Rng;
 
         let mut rng = rand::thread_rng();
@@ -229,6 +244,7 @@ impl InitAck {
         let ext_mlink = rng.gen_bool(0.5).then_some(ZExtZBuf::rand());
         let ext_lowlatency = rng.gen_bool(0.5).then_some(ZExtUnit::rand());
         let ext_compression = rng.gen_bool(0.5).then_some(ZExtUnit::rand());
+        let ext_patch = rng.gen_bool(0.5).then_some(ZExtZ64::rand());
 
         Self {
             version,
@@ -243,6 +259,7 @@ impl InitAck {
             ext_mlink,
             ext_lowlatency,
             ext_compression,
+            ext_patch,
         }
     }
 }
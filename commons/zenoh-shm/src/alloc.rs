@@ -0,0 +1,507 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use crate::{align_addr_at, ChunkHeaderType, CHUNK_HEADER_SIZE};
+use std::{
+    cmp,
+    collections::{binary_heap::BinaryHeap, BTreeSet, HashMap},
+    fmt,
+    sync::atomic::Ordering,
+};
+
+const MIN_FREE_CHUNK_SIZE: usize = 1_024;
+
+/// The shared memory segment of a [`SharedMemoryManager`](crate::SharedMemoryManager), as seen by its allocator.
+///
+/// Every chunk of the segment starts with its reference count: the chunk is busy while it is not zero.
+#[derive(Clone, Copy, Debug)]
+pub struct ShmSegment {
+    base_addr: *mut u8,
+    size: usize,
+}
+
+unsafe impl Send for ShmSegment {}
+
+impl ShmSegment {
+    pub(crate) fn new(base_addr: *mut u8, size: usize) -> Self {
+        ShmSegment { base_addr, size }
+    }
+
+    /// The size of the segment.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The reference count of the chunk at the given offset of the segment.
+    ///
+    /// # Safety
+    /// The segment is mapped as long as its [`SharedMemoryManager`](crate::SharedMemoryManager) is alive:
+    /// this operation is marked unsafe since we cannot guarantee that the manager was not dropped,
+    /// for instance by an allocator keeping a copy of the segment. The allocators given to a manager
+    /// are dropped along with it, thus can safely call it on the segment they were initialized with.
+    ///
+    /// # Panics
+    /// If the offset is out of the segment or not aligned for the reference count.
+    pub unsafe fn ref_count(&self, offset: usize) -> &ChunkHeaderType {
+        assert!(offset
+            .checked_add(CHUNK_HEADER_SIZE)
+            .map_or(false, |end| end <= self.size));
+        let addr = self.base_addr.add(offset);
+        assert_eq!(addr as usize % std::mem::align_of::<ChunkHeaderType>(), 0);
+        &*(addr as *const ChunkHeaderType)
+    }
+
+    fn is_free(&self, offset: usize) -> bool {
+        // SAFETY: the built-in allocators are dropped along with the manager of their segment
+        unsafe { self.ref_count(offset) }.load(Ordering::SeqCst) == 0
+    }
+}
+
+/// A chunk of a shared memory segment, allocated by a [`ShmAllocator`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShmChunk {
+    /// The offset of the chunk in the segment.
+    pub offset: usize,
+    /// The size of the chunk, header included.
+    pub size: usize,
+}
+
+/// A strategy of allocation of the chunks of the segment of a [`SharedMemoryManager`](crate::SharedMemoryManager).
+///
+/// The requested sizes are aligned for the reference count header of the chunks, and include it.
+/// A chunk is released by its last [`SharedMemoryBuf`](crate::SharedMemoryBuf), in any process, when its
+/// reference count drops to zero: the allocator reclaims it on [`ShmAllocator::garbage_collect`], or on its own.
+pub trait ShmAllocator: fmt::Debug + Send {
+    /// Sets the segment managed by the allocator, called once by the manager when the segment is created.
+    fn init(&mut self, segment: ShmSegment);
+
+    /// Allocates a chunk of at least `size` bytes, or returns `None` if no free chunk is large enough.
+    fn alloc(&mut self, size: usize) -> Option<ShmChunk>;
+
+    /// Reclaims the chunks whose reference count dropped to zero, returns the amount of memory freed.
+    fn garbage_collect(&mut self) -> usize;
+
+    /// Merges the adjacent free chunks, returns the amount of memory de-fragmented.
+    fn defragment(&mut self) -> usize {
+        0
+    }
+
+    /// Returns the amount of free memory, not counting the chunks to be garbage collected.
+    fn available(&self) -> usize;
+
+    /// Returns the size of the largest chunk that can be allocated, not counting the chunks to be garbage
+    /// collected, or 0 if it is not tracked.
+    fn largest_free_chunk(&self) -> usize {
+        0
+    }
+
+    /// Returns the number of allocated chunks, including the chunks to be garbage collected, or 0 if it is not tracked.
+    fn busy_chunks(&self) -> usize {
+        0
+    }
+}
+
+/*************************************/
+/*          HEAP ALLOCATOR           */
+/*************************************/
+#[derive(Eq, Copy, Clone, Debug)]
+struct Chunk {
+    offset: usize,
+    size: usize,
+}
+
+impl Ord for Chunk {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.size.cmp(&other.size)
+    }
+}
+
+impl PartialOrd for Chunk {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Chunk {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size
+    }
+}
+
+/// An allocator of chunks of any size, always taken from the biggest free chunk, that requires
+/// explicit de-fragmentation. This is the default allocator of the [`SharedMemoryManager`](crate::SharedMemoryManager).
+#[derive(Default)]
+pub struct HeapAllocator {
+    segment: Option<ShmSegment>,
+    available: usize,
+    free_list: BinaryHeap<Chunk>,
+    busy_list: Vec<Chunk>,
+}
+
+impl HeapAllocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn try_merge_adjacent_chunks(a: &Chunk, b: &Chunk) -> Option<Chunk> {
+        if a.offset + a.size == b.offset {
+            Some(Chunk {
+                offset: a.offset,
+                size: a.size + b.size,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl ShmAllocator for HeapAllocator {
+    fn init(&mut self, segment: ShmSegment) {
+        self.segment = Some(segment);
+        self.available = segment.size();
+        self.free_list.push(Chunk {
+            offset: 0,
+            size: segment.size(),
+        });
+    }
+
+    fn alloc(&mut self, size: usize) -> Option<ShmChunk> {
+        if self.available < size {
+            return None;
+        }
+        // The strategy taken is the same for some Unix System V implementations -- as described in the
+        // famous Bach's book --  in essence keep an ordered list of free slot and always look for the
+        // biggest as that will give the biggest left-over.
+        match self.free_list.pop() {
+            Some(mut chunk) if chunk.size >= size => {
+                self.available -= size;
+                tracing::trace!("Allocator selected Chunk ({:?})", &chunk);
+                if chunk.size - size >= MIN_FREE_CHUNK_SIZE {
+                    let free_chunk = Chunk {
+                        offset: chunk.offset + size,
+                        size: chunk.size - size,
+                    };
+                    tracing::trace!("The allocation will leave a Free Chunk: {:?}", &free_chunk);
+                    self.free_list.push(free_chunk);
+                }
+                chunk.size = size;
+                self.busy_list.push(chunk);
+                Some(ShmChunk {
+                    offset: chunk.offset,
+                    size: chunk.size,
+                })
+            }
+            Some(c) => {
+                self.free_list.push(c);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn garbage_collect(&mut self) -> usize {
+        let Some(segment) = self.segment else {
+            return 0;
+        };
+        let mut freed = 0;
+        let (free, busy) = self
+            .busy_list
+            .iter()
+            .partition(|c| segment.is_free(c.offset));
+        self.busy_list = busy;
+
+        for f in free {
+            freed += f.size;
+            tracing::trace!("Garbage Collecting Chunk: {:?}", f);
+            self.free_list.push(f)
+        }
+        self.available += freed;
+        freed
+    }
+
+    fn defragment(&mut self) -> usize {
+        if self.free_list.len() > 1 {
+            let mut fbs: Vec<Chunk> = self.free_list.drain().collect();
+            fbs.sort_by(|x, y| x.offset.cmp(&y.offset));
+            let mut current = fbs.remove(0);
+            let mut defrag_mem = 0;
+            let mut i = 0;
+            let n = fbs.len();
+            for chunk in fbs.iter() {
+                i += 1;
+                let next = *chunk;
+                match HeapAllocator::try_merge_adjacent_chunks(&current, &next) {
+                    Some(c) => {
+                        current = c;
+                        defrag_mem += current.size;
+                        if i == n {
+                            self.free_list.push(current)
+                        }
+                    }
+                    None => {
+                        self.free_list.push(current);
+                        if i == n {
+                            self.free_list.push(next);
+                        } else {
+                            current = next;
+                        }
+                    }
+                }
+            }
+            defrag_mem
+        } else {
+            0
+        }
+    }
+
+    fn available(&self) -> usize {
+        self.available
+    }
+
+    fn largest_free_chunk(&self) -> usize {
+        self.free_list.peek().map(|c| c.size).unwrap_or(0)
+    }
+
+    fn busy_chunks(&self) -> usize {
+        self.busy_list.len()
+    }
+}
+
+impl fmt::Debug for HeapAllocator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HeapAllocator")
+            .field("available", &self.available)
+            .field("free_list.len", &self.free_list.len())
+            .field("busy_list.len", &self.busy_list.len())
+            .finish()
+    }
+}
+
+/*************************************/
+/*          POOL ALLOCATOR           */
+/*************************************/
+/// An allocator of fixed-size chunks, suited for the publication of same-size payloads at a high rate.
+///
+/// It requires neither garbage collection nor de-fragmentation: a chunk is free as soon as its reference count
+/// drops to zero, in any process, and is claimed with a compare-and-swap of its reference count. Like the other
+/// allocators, it is called by its [`SharedMemoryManager`](crate::SharedMemoryManager), which must not be shared
+/// between threads without a lock. The allocation of more than the chunk size fails.
+#[derive(Debug)]
+pub struct PoolAllocator {
+    chunk_size: usize,
+    segment: Option<ShmSegment>,
+    chunks: usize,
+    // index of the chunk where the search for a free chunk starts
+    next: usize,
+}
+
+impl PoolAllocator {
+    /// Creates an allocator of chunks of `chunk_size` bytes (header included, rounded up for alignment).
+    pub fn new(chunk_size: usize) -> Self {
+        let chunk_size = cmp::max(chunk_size, CHUNK_HEADER_SIZE);
+        PoolAllocator {
+            chunk_size: align_addr_at(chunk_size, std::mem::align_of::<ChunkHeaderType>()),
+            segment: None,
+            chunks: 0,
+            next: 0,
+        }
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+}
+
+impl ShmAllocator for PoolAllocator {
+    fn init(&mut self, segment: ShmSegment) {
+        self.segment = Some(segment);
+        self.chunks = segment.size() / self.chunk_size;
+    }
+
+    fn alloc(&mut self, size: usize) -> Option<ShmChunk> {
+        let segment = self.segment?;
+        if size > self.chunk_size {
+            return None;
+        }
+        for i in 0..self.chunks {
+            let index = (self.next + i) % self.chunks;
+            let offset = index * self.chunk_size;
+            // SAFETY: the allocator is dropped along with the manager of its segment
+            if unsafe { segment.ref_count(offset) }
+                .compare_exchange(0, 1, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                self.next = (index + 1) % self.chunks;
+                return Some(ShmChunk {
+                    offset,
+                    size: self.chunk_size,
+                });
+            }
+        }
+        None
+    }
+
+    fn garbage_collect(&mut self) -> usize {
+        0
+    }
+
+    fn available(&self) -> usize {
+        match self.segment {
+            Some(segment) => {
+                (0..self.chunks)
+                    .filter(|i| segment.is_free(i * self.chunk_size))
+                    .count()
+                    * self.chunk_size
+            }
+            None => 0,
+        }
+    }
+
+    fn largest_free_chunk(&self) -> usize {
+        match self.available() {
+            0 => 0,
+            _ => self.chunk_size,
+        }
+    }
+
+    fn busy_chunks(&self) -> usize {
+        self.chunks - self.available() / self.chunk_size
+    }
+}
+
+/*************************************/
+/*          BUDDY ALLOCATOR          */
+/*************************************/
+/// A buddy allocator, allocating chunks of a power of two size, that merges the freed chunks with their
+/// free buddies on garbage collection, and thus never requires de-fragmentation.
+///
+/// The segment is initially split in chunks of decreasing power of two sizes, so that only its tail smaller
+/// than the smallest chunk is not used.
+#[derive(Debug)]
+pub struct BuddyAllocator {
+    min_chunk_size: usize,
+    segment: Option<ShmSegment>,
+    // offsets of the free chunks, indexed by order (i.e. the chunk of order `n` is `min_chunk_size << n` bytes)
+    free_lists: Vec<BTreeSet<usize>>,
+    // orders of the busy chunks, indexed by offset
+    busy: HashMap<usize, usize>,
+    available: usize,
+}
+
+impl BuddyAllocator {
+    /// Creates a buddy allocator whose smallest chunks are `min_chunk_size` bytes (rounded up to a power of two).
+    pub fn new(min_chunk_size: usize) -> Self {
+        BuddyAllocator {
+            min_chunk_size: cmp::max(min_chunk_size, CHUNK_HEADER_SIZE).next_power_of_two(),
+            segment: None,
+            free_lists: Vec::new(),
+            busy: HashMap::new(),
+            available: 0,
+        }
+    }
+
+    fn chunk_size(&self, order: usize) -> usize {
+        self.min_chunk_size << order
+    }
+
+    fn free(&mut self, mut offset: usize, mut order: usize) {
+        self.available += self.chunk_size(order);
+        while order + 1 < self.free_lists.len() {
+            let buddy = offset ^ self.chunk_size(order);
+            if !self.free_lists[order].remove(&buddy) {
+                break;
+            }
+            offset = cmp::min(offset, buddy);
+            order += 1;
+        }
+        self.free_lists[order].insert(offset);
+    }
+}
+
+impl ShmAllocator for BuddyAllocator {
+    fn init(&mut self, segment: ShmSegment) {
+        self.segment = Some(segment);
+        if segment.size() < self.min_chunk_size {
+            return;
+        }
+        let mut max_order = 0;
+        while self.chunk_size(max_order + 1) <= segment.size() {
+            max_order += 1;
+        }
+        self.free_lists = vec![BTreeSet::new(); max_order + 1];
+        // each chunk is aligned on its size, and its buddy is out of the segment or split,
+        // so that it is never merged beyond its initial size
+        let mut offset = 0;
+        for order in (0..=max_order).rev() {
+            if offset + self.chunk_size(order) <= segment.size() {
+                self.free_lists[order].insert(offset);
+                offset += self.chunk_size(order);
+            }
+        }
+        self.available = offset;
+    }
+
+    fn alloc(&mut self, size: usize) -> Option<ShmChunk> {
+        let order = (0..self.free_lists.len()).find(|o| self.chunk_size(*o) >= size)?;
+        let mut from = (order..self.free_lists.len()).find(|o| !self.free_lists[*o].is_empty())?;
+        let offset = self.free_lists[from].pop_first()?;
+        // split the chunk until it has the requested order, freeing the upper halves
+        while from > order {
+            from -= 1;
+            let upper_half = offset + self.chunk_size(from);
+            self.free_lists[from].insert(upper_half);
+        }
+        self.busy.insert(offset, order);
+        self.available -= self.chunk_size(order);
+        Some(ShmChunk {
+            offset,
+            size: self.chunk_size(order),
+        })
+    }
+
+    fn garbage_collect(&mut self) -> usize {
+        let Some(segment) = self.segment else {
+            return 0;
+        };
+        let free: Vec<(usize, usize)> = self
+            .busy
+            .iter()
+            .filter(|(offset, _)| segment.is_free(**offset))
+            .map(|(offset, order)| (*offset, *order))
+            .collect();
+        let mut freed = 0;
+        for (offset, order) in free {
+            tracing::trace!("Garbage Collecting Chunk at {} of order {}", offset, order);
+            self.busy.remove(&offset);
+            freed += self.chunk_size(order);
+            self.free(offset, order);
+        }
+        freed
+    }
+
+    fn available(&self) -> usize {
+        self.available
+    }
+
+    fn largest_free_chunk(&self) -> usize {
+        (0..self.free_lists.len())
+            .rev()
+            .find(|o| !self.free_lists[*o].is_empty())
+            .map(|o| self.chunk_size(o))
+            .unwrap_or(0)
+    }
+
+    fn busy_chunks(&self) -> usize {
+        self.busy.len()
+    }
+}
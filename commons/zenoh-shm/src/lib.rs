@@ -11,23 +11,36 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
-use shared_memory::{Shmem, ShmemConf, ShmemError};
+use segment::Segment;
 use std::{
     any::Any,
-    cmp,
-    collections::{binary_heap::BinaryHeap, HashMap},
+    collections::HashMap,
     fmt, mem,
-    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+    sync::{
+        atomic::{AtomicPtr, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
+use watchdog::{ChunkLoans, ManagerMonitor};
 use zenoh_buffers::ZSliceBuffer;
 use zenoh_result::{zerror, ShmError, ZResult};
 
-const MIN_FREE_CHUNK_SIZE: usize = 1_024;
+pub mod alloc;
+pub use alloc::{BuddyAllocator, HeapAllocator, PoolAllocator, ShmAllocator, ShmChunk, ShmSegment};
+pub mod segment;
+pub use segment::{cleanup_stale_segments, segment_creator, ShmBackend};
+pub mod watchdog;
+pub use watchdog::{managers_stats, reclaim_reader, SharedMemoryManagerStats, ShmReaderId};
+
 const ACCOUNTED_OVERHEAD: usize = 4_096;
 const ZENOH_SHM_PREFIX: &str = "zenoh_shm_zid";
+// Bounds of the period between two attempts of a blocking allocation, doubled after each attempt
+const MIN_ALLOC_RETRY_PERIOD: Duration = Duration::from_micros(10);
+const MAX_ALLOC_RETRY_PERIOD: Duration = Duration::from_millis(10);
 
 // Chunk header
-type ChunkHeaderType = AtomicUsize;
+pub type ChunkHeaderType = AtomicUsize;
 const CHUNK_HEADER_SIZE: usize = std::mem::size_of::<ChunkHeaderType>();
 
 fn align_addr_at(addr: usize, align: usize) -> usize {
@@ -37,31 +50,6 @@ fn align_addr_at(addr: usize, align: usize) -> usize {
     }
 }
 
-#[derive(Eq, Copy, Clone, Debug)]
-struct Chunk {
-    base_addr: *mut u8,
-    offset: usize,
-    size: usize,
-}
-
-impl Ord for Chunk {
-    fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.size.cmp(&other.size)
-    }
-}
-
-impl PartialOrd for Chunk {
-    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl PartialEq for Chunk {
-    fn eq(&self, other: &Self) -> bool {
-        self.size == other.size
-    }
-}
-
 /// Informations about a [`SharedMemoryBuf`].
 ///
 /// This that can be serialized and can be used to retrieve the [`SharedMemoryBuf`] in a remote process.
@@ -71,7 +59,7 @@ pub struct SharedMemoryBufInfo {
     pub offset: usize,
     /// The length of the buffer.
     pub length: usize,
-    /// The identifier of the shm manager that manages the shm segment this buffer points to.
+    /// The identifier of the shm segment this buffer points to.
     pub shm_manager: String,
     /// The kind of buffer.
     pub kind: u8,
@@ -95,6 +83,8 @@ pub struct SharedMemoryBuf {
     pub buf: AtomicPtr<u8>,
     pub len: usize,
     pub info: SharedMemoryBufInfo,
+    // The references on the chunk, if allocated by a manager of this process
+    loans: Option<Arc<ChunkLoans>>,
 }
 
 impl std::fmt::Debug for SharedMemoryBuf {
@@ -141,9 +131,29 @@ impl SharedMemoryBuf {
         unsafe { (*rc).fetch_add(1, Ordering::SeqCst) };
     }
 
+    /// Decrements the reference count, which is left to 0 rather than wrapped around if already released.
     pub fn dec_ref_count(&self) {
         let rc = self.rc_ptr.load(Ordering::SeqCst);
-        unsafe { (*rc).fetch_sub(1, Ordering::SeqCst) };
+        let res =
+            unsafe { (*rc).fetch_update(Ordering::SeqCst, Ordering::SeqCst, |c| c.checked_sub(1)) };
+        if res.is_err() {
+            tracing::warn!(
+                "Released shared memory buffer {}:{} with no reference left",
+                self.info.shm_manager,
+                self.info.offset
+            );
+        }
+    }
+
+    /// Increments the reference count on behalf of the given remote reader, which is expected to release it.
+    ///
+    /// If the buffer was allocated by a [`SharedMemoryManager`] of this process, the reference is accounted
+    /// to the reader, and can be reclaimed with [`reclaim_reader`] once the process of the reader died.
+    pub fn lend(&self, reader: &ShmReaderId) {
+        if let Some(loans) = self.loans.as_ref() {
+            loans.lend(reader);
+        }
+        self.inc_ref_count();
     }
 
     pub fn as_slice(&self) -> &[u8] {
@@ -172,11 +182,17 @@ impl SharedMemoryBuf {
 impl Drop for SharedMemoryBuf {
     fn drop(&mut self) {
         self.dec_ref_count();
+        if let Some(loans) = self.loans.as_ref() {
+            loans.drop_local();
+        }
     }
 }
 
 impl Clone for SharedMemoryBuf {
     fn clone(&self) -> Self {
+        if let Some(loans) = self.loans.as_ref() {
+            loans.clone_local();
+        }
         self.inc_ref_count();
         let rc = self.rc_ptr.load(Ordering::SeqCst);
         let bp = self.buf.load(Ordering::SeqCst);
@@ -185,6 +201,7 @@ impl Clone for SharedMemoryBuf {
             buf: AtomicPtr::new(bp),
             len: self.len,
             info: self.info.clone(),
+            loans: self.loans.clone(),
         }
     }
 }
@@ -192,8 +209,11 @@ impl Clone for SharedMemoryBuf {
 /*************************************/
 /*       SHARED MEMORY READER        */
 /*************************************/
+/// Maps the segments of the [`SharedMemoryBuf`]s received from other processes.
+///
+/// The segments are mapped lazily, the first time one of their buffers is read.
 pub struct SharedMemoryReader {
-    segments: HashMap<String, Shmem>,
+    segments: HashMap<String, Segment>,
 }
 
 unsafe impl Send for SharedMemoryReader {}
@@ -207,29 +227,32 @@ impl SharedMemoryReader {
     }
 
     pub fn connect_map_to_shm(&mut self, info: &SharedMemoryBufInfo) -> ZResult<()> {
-        match ShmemConf::new().flink(&info.shm_manager).open() {
-            Ok(shm) => {
-                self.segments.insert(info.shm_manager.clone(), shm);
-                Ok(())
-            }
-            Err(e) => {
-                let e = zerror!(
-                    "Unable to bind shared memory segment {}: {:?}",
-                    info.shm_manager,
-                    e
-                );
-                tracing::trace!("{}", e);
-                Err(ShmError(e).into())
-            }
+        let segment = Segment::open(&info.shm_manager)?;
+        if !segment.is_writable() {
+            return Err(ShmError(zerror!(
+                "Unable to reference the buffers of read-only shared memory segment {}",
+                info.shm_manager
+            ))
+            .into());
         }
+        self.segments.insert(info.shm_manager.clone(), segment);
+        Ok(())
     }
 
     pub fn try_read_shmbuf(&self, info: &SharedMemoryBufInfo) -> ZResult<SharedMemoryBuf> {
         // Try read does not increment the reference count as it is assumed
         // that the sender of this buffer has incremented for us.
         match self.segments.get(&info.shm_manager) {
-            Some(shm) => {
-                let base_ptr = shm.as_ptr();
+            Some(segment)
+                if info.length >= CHUNK_HEADER_SIZE
+                    // the reference count at the beginning of the buffer is accessed atomically
+                    && info.offset % std::mem::align_of::<ChunkHeaderType>() == 0
+                    && info
+                        .offset
+                        .checked_add(info.length)
+                        .is_some_and(|end| end <= segment.len()) =>
+            {
+                let base_ptr = segment.as_ptr();
                 let rc = unsafe { base_ptr.add(info.offset) as *mut ChunkHeaderType };
                 let rc_ptr = AtomicPtr::<ChunkHeaderType>::new(rc);
                 let buf = unsafe { base_ptr.add(info.offset + CHUNK_HEADER_SIZE) };
@@ -238,9 +261,19 @@ impl SharedMemoryReader {
                     buf: AtomicPtr::new(buf),
                     len: info.length - CHUNK_HEADER_SIZE,
                     info: info.clone(),
+                    loans: None,
                 };
                 Ok(shmb)
             }
+            Some(_) => {
+                let e = zerror!(
+                    "Buffer out of shared memory segment {} or misaligned: {:?}",
+                    info.shm_manager,
+                    info
+                );
+                tracing::trace!("{}", e);
+                Err(ShmError(e).into())
+            }
             None => {
                 let e = zerror!("Unable to find shared memory segment: {}", info.shm_manager);
                 tracing::trace!("{}", e);
@@ -274,227 +307,387 @@ impl fmt::Debug for SharedMemoryReader {
 
 /// A shared memory segment manager.
 ///
-/// Allows to access a shared memory segment and reserve some parts of this segment for writting.
+/// Allows to access shared memory segments and reserve some parts of these segments for writting,
+/// with the allocation strategy of its [`ShmAllocator`]s. A manager starts with a single segment, and
+/// grows by adding segments on demand, up to the maximum number of segments it was built with.
 pub struct SharedMemoryManager {
-    segment_path: String,
-    size: usize,
-    available: usize,
-    own_segment: Shmem,
-    free_list: BinaryHeap<Chunk>,
-    busy_list: Vec<Chunk>,
+    id: String,
+    segment_size: usize,
+    backend: ShmBackend,
+    max_segments: usize,
+    allocator: AllocatorFactory,
+    segments: Vec<ManagedSegment>,
     alignment: usize,
+    monitor: Arc<ManagerMonitor>,
 }
 
 unsafe impl Send for SharedMemoryManager {}
 
+type AllocatorFactory = Box<dyn Fn() -> Box<dyn ShmAllocator> + Send>;
+
+struct ManagedSegment {
+    id: String,
+    segment: Segment,
+    allocator: Box<dyn ShmAllocator>,
+}
+
+/// A builder of [`SharedMemoryManager`], created with [`SharedMemoryManager::builder`].
+pub struct SharedMemoryManagerBuilder {
+    id: String,
+    segment_size: usize,
+    backend: ShmBackend,
+    max_segments: usize,
+    cleanup_stale: bool,
+    allocator: AllocatorFactory,
+}
+
+impl SharedMemoryManagerBuilder {
+    /// Sets the kind of operating system object backing the segments, [`ShmBackend::FileLink`] by default.
+    pub fn backend(mut self, backend: ShmBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Sets the maximum number of segments the manager can grow to, 1 by default.
+    pub fn max_segments(mut self, max_segments: usize) -> Self {
+        self.max_segments = max_segments.max(1);
+        self
+    }
+
+    /// Sets the function creating the allocator of each segment, a [`HeapAllocator`] by default.
+    pub fn allocator<A, F>(mut self, allocator: F) -> Self
+    where
+        A: ShmAllocator + 'static,
+        F: Fn() -> A + Send + 'static,
+    {
+        self.allocator = Box::new(move || Box::new(allocator()));
+        self
+    }
+
+    /// Sets whether the segments left by crashed processes are removed when the manager is made,
+    /// which is disabled by default (see [`cleanup_stale_segments`]).
+    ///
+    /// It should be disabled when the segments are shared with processes of other pid namespaces,
+    /// whose liveness cannot be checked.
+    pub fn cleanup_stale(mut self, cleanup_stale: bool) -> Self {
+        self.cleanup_stale = cleanup_stale;
+        self
+    }
+
+    /// Makes the manager, with its first segment.
+    pub fn make(self) -> ZResult<SharedMemoryManager> {
+        let allocator = (self.allocator)();
+        self.make_with(allocator)
+    }
+
+    fn make_with(self, allocator: Box<dyn ShmAllocator>) -> ZResult<SharedMemoryManager> {
+        if self.cleanup_stale {
+            let removed = cleanup_stale_segments();
+            if removed > 0 {
+                tracing::debug!("Removed {} stale shared memory segments", removed);
+            }
+        }
+        let mut shm = SharedMemoryManager {
+            monitor: ManagerMonitor::new(self.id.clone()),
+            id: self.id,
+            segment_size: self.segment_size,
+            backend: self.backend,
+            max_segments: self.max_segments,
+            allocator: self.allocator,
+            segments: Vec::with_capacity(1),
+            alignment: mem::align_of::<ChunkHeaderType>(),
+        };
+        shm.add_segment(allocator)?;
+        shm.update_stats();
+        tracing::trace!("Created {:?}", shm);
+        Ok(shm)
+    }
+}
+
 impl SharedMemoryManager {
     /// Creates a new SharedMemoryManager managing allocations of a region of the
-    /// given size.
+    /// given size, with a [`HeapAllocator`].
     pub fn make(id: String, size: usize) -> ZResult<SharedMemoryManager> {
-        let mut temp_dir = std::env::temp_dir();
-        let file_name: String = format!("{ZENOH_SHM_PREFIX}_{id}");
-        temp_dir.push(file_name);
-        let path: String = temp_dir
-            .to_str()
-            .ok_or_else(|| ShmError(zerror!("Unable to parse tmp directory: {:?}", temp_dir)))?
-            .to_string();
-        tracing::trace!("Creating file at: {}", path);
-        let real_size = size + ACCOUNTED_OVERHEAD;
-        let shmem = match ShmemConf::new()
-            .size(real_size)
-            .flink(path.clone())
-            .create()
+        Self::builder(id, size).make()
+    }
+
+    /// Creates a new SharedMemoryManager managing allocations of a region of the
+    /// given size, with the given allocator.
+    pub fn make_with_allocator<A: ShmAllocator + 'static>(
+        id: String,
+        size: usize,
+        allocator: A,
+    ) -> ZResult<SharedMemoryManager> {
+        Self::builder(id, size).make_with(Box::new(allocator))
+    }
+
+    /// Creates a builder of SharedMemoryManager, whose segments allow the allocation of `segment_size` bytes.
+    pub fn builder(id: String, segment_size: usize) -> SharedMemoryManagerBuilder {
+        SharedMemoryManagerBuilder {
+            id,
+            segment_size,
+            backend: ShmBackend::default(),
+            max_segments: 1,
+            cleanup_stale: false,
+            allocator: Box::new(|| Box::new(HeapAllocator::new())),
+        }
+    }
+
+    fn add_segment(&mut self, mut allocator: Box<dyn ShmAllocator>) -> ZResult<()> {
+        let real_size = self.segment_size + ACCOUNTED_OVERHEAD;
+        let (id, segment) = Segment::create(self.backend, &self.id, self.segments.len(), real_size)
+            .map_err(|e| ShmError(zerror!("Unable to open SharedMemoryManager: {}", e)))?;
+        allocator.init(ShmSegment::new(segment.as_ptr(), real_size));
+        tracing::trace!("Added segment {} to SharedMemoryManager {}", id, self.id);
+        self.segments.push(ManagedSegment {
+            id,
+            segment,
+            allocator,
+        });
+        Ok(())
+    }
+
+    // Adds a segment to allocate a chunk of the given size from, if the maximum number of segments is not reached
+    fn grow(&mut self, len: usize) -> Option<(usize, ShmChunk)> {
+        if self.segments.len() >= self.max_segments || len > self.segment_size + ACCOUNTED_OVERHEAD
         {
-            Ok(m) => m,
-            Err(ShmemError::LinkExists) => {
-                return Err(ShmError(zerror!(
-                    "Unable to open SharedMemoryManager: SharedMemory already exists"
-                ))
-                .into())
-            }
-            Err(e) => {
-                return Err(ShmError(zerror!("Unable to open SharedMemoryManager: {}", e)).into())
-            }
-        };
-        let base_ptr = shmem.as_ptr();
+            return None;
+        }
+        if let Err(e) = self.add_segment((self.allocator)()) {
+            tracing::warn!("Unable to grow SharedMemoryManager {}: {}", self.id, e);
+            return None;
+        }
+        self.update_stats();
+        let index = self.segments.len() - 1;
+        self.segments[index]
+            .allocator
+            .alloc(len)
+            .map(|c| (index, c))
+    }
 
-        let mut free_list = BinaryHeap::new();
-        let chunk = Chunk {
-            base_addr: base_ptr,
-            offset: 0,
-            size: real_size,
-        };
-        free_list.push(chunk);
-        let busy_list = vec![];
-        let shm = SharedMemoryManager {
-            segment_path: path,
-            size,
-            available: real_size,
-            own_segment: shmem,
-            free_list,
-            busy_list,
-            alignment: mem::align_of::<ChunkHeaderType>(),
-        };
-        tracing::trace!(
-            "Created SharedMemoryManager for {:?}",
-            shm.own_segment.as_ptr()
-        );
-        Ok(shm)
+    fn alloc_chunk(&mut self, len: usize) -> Option<(usize, ShmChunk)> {
+        self.segments
+            .iter_mut()
+            .enumerate()
+            .find_map(|(i, s)| s.allocator.alloc(len).map(|c| (i, c)))
     }
 
-    fn free_chunk_map_to_shmbuf(&self, chunk: &Chunk) -> SharedMemoryBuf {
+    fn chunk_map_to_shmbuf(&self, segment: usize, chunk: &ShmChunk, len: usize) -> SharedMemoryBuf {
+        let segment = &self.segments[segment];
         let info = SharedMemoryBufInfo {
             offset: chunk.offset,
-            length: chunk.size,
-            shm_manager: self.segment_path.clone(),
+            length: len,
+            shm_manager: segment.id.clone(),
             kind: 0,
         };
-        let rc = chunk.base_addr as *mut ChunkHeaderType;
+        let base_addr = unsafe { segment.segment.as_ptr().add(chunk.offset) };
+        let rc = base_addr as *mut ChunkHeaderType;
+        // The chunk was released, so were the references lent on its previous buffer
+        let loans = self.monitor.allocate(rc);
         unsafe { (*rc).store(1, Ordering::SeqCst) };
         let rc_ptr = AtomicPtr::<ChunkHeaderType>::new(rc);
         SharedMemoryBuf {
             rc_ptr,
-            buf: AtomicPtr::<u8>::new(unsafe { chunk.base_addr.add(CHUNK_HEADER_SIZE) }),
-            len: chunk.size - CHUNK_HEADER_SIZE,
+            buf: AtomicPtr::<u8>::new(unsafe { base_addr.add(CHUNK_HEADER_SIZE) }),
+            len: len - CHUNK_HEADER_SIZE,
             info,
+            loans: Some(loans),
         }
     }
 
-    pub fn alloc(&mut self, len: usize) -> ZResult<SharedMemoryBuf> {
-        tracing::trace!("SharedMemoryManager::alloc({})", len);
+    fn try_alloc(&mut self, len: usize) -> Option<SharedMemoryBuf> {
         // Always allocate a size that will keep the proper alignment requirements
         let required_len = align_addr_at(len + CHUNK_HEADER_SIZE, self.alignment);
-        if self.available < required_len {
-            self.garbage_collect();
-        }
-        if self.available >= required_len {
-            // The strategy taken is the same for some Unix System V implementations -- as described in the
-            // famous Bach's book --  in essence keep an ordered list of free slot and always look for the
-            // biggest as that will give the biggest left-over.
-            match self.free_list.pop() {
-                Some(mut chunk) if chunk.size >= required_len => {
-                    self.available -= required_len;
-                    tracing::trace!("Allocator selected Chunk ({:?})", &chunk);
-                    if chunk.size - required_len >= MIN_FREE_CHUNK_SIZE {
-                        let free_chunk = Chunk {
-                            base_addr: unsafe { chunk.base_addr.add(required_len) },
-                            offset: chunk.offset + required_len,
-                            size: chunk.size - required_len,
-                        };
-                        tracing::trace!(
-                            "The allocation will leave a Free Chunk: {:?}",
-                            &free_chunk
-                        );
-                        self.free_list.push(free_chunk);
-                    }
-                    chunk.size = required_len;
-                    let shm_buf = self.free_chunk_map_to_shmbuf(&chunk);
-                    tracing::trace!("The allocated Chunk is ({:?})", &chunk);
-                    tracing::trace!("Allocated Shared Memory Buffer: {:?}", &shm_buf);
-                    self.busy_list.push(chunk);
-                    Ok(shm_buf)
-                }
-                Some(c) => {
-                    self.free_list.push(c);
-                    let e = zerror!("SharedMemoryManager::alloc({}) cannot find any available chunk\nSharedMemoryManager::free_list = {:?}", len, self.free_list);
-                    Err(e.into())
-                }
-                None => {
-                    let e = zerror!("SharedMemoryManager::alloc({}) cannot find any available chunk\nSharedMemoryManager::free_list = {:?}", len, self.free_list);
-                    tracing::trace!("{}", e);
-                    Err(e.into())
+        let (segment, chunk) = match self.alloc_chunk(required_len) {
+            Some(chunk) => chunk,
+            None => {
+                self.garbage_collect();
+                match self.alloc_chunk(required_len) {
+                    Some(chunk) => chunk,
+                    None => match self.grow(required_len) {
+                        Some(chunk) => chunk,
+                        None => {
+                            self.update_stats();
+                            return None;
+                        }
+                    },
                 }
             }
-        } else {
-            let e = zerror!( "SharedMemoryManager does not have sufficient free memory to allocate {} bytes, try de-fragmenting!", len);
-            tracing::warn!("{}", e);
-            Err(e.into())
-        }
+        };
+        tracing::trace!(
+            "The allocated Chunk is ({:?}) in segment {}",
+            &chunk,
+            segment
+        );
+        let shm_buf = self.chunk_map_to_shmbuf(segment, &chunk, required_len);
+        tracing::trace!("Allocated Shared Memory Buffer: {:?}", &shm_buf);
+        Some(shm_buf)
     }
 
-    fn is_free_chunk(chunk: &Chunk) -> bool {
-        let rc_ptr = chunk.base_addr as *mut ChunkHeaderType;
-        let rc = unsafe { (*rc_ptr).load(Ordering::SeqCst) };
-        rc == 0
+    /// Allocates a buffer of the given length, garbage collecting the released chunks and growing
+    /// if needed.
+    ///
+    /// Fails if no chunk is available.
+    pub fn alloc(&mut self, len: usize) -> ZResult<SharedMemoryBuf> {
+        tracing::trace!("SharedMemoryManager::alloc({})", len);
+        self.try_alloc(len).ok_or_else(|| {
+            let e = zerror!(
+                "SharedMemoryManager::alloc({}) cannot find any available chunk: {:?}",
+                len,
+                self
+            );
+            tracing::trace!("{}", e);
+            ShmError(e).into()
+        })
     }
 
-    fn try_merge_adjacent_chunks(a: &Chunk, b: &Chunk) -> Option<Chunk> {
-        let end_addr = unsafe { a.base_addr.add(a.size) };
-        if end_addr == b.base_addr {
-            Some(Chunk {
-                base_addr: a.base_addr,
-                size: a.size + b.size,
-                offset: a.offset,
-            })
-        } else {
-            None
+    /// Allocates a buffer of the given length, waiting up to `timeout` for chunks to be released
+    /// if none is available.
+    ///
+    /// As the chunks may be released by other processes, the allocation is retried periodically.
+    pub fn alloc_blocking(&mut self, len: usize, timeout: Duration) -> ZResult<SharedMemoryBuf> {
+        tracing::trace!(
+            "SharedMemoryManager::alloc_blocking({}, {:?})",
+            len,
+            timeout
+        );
+        let deadline = Instant::now() + timeout;
+        let mut period = MIN_ALLOC_RETRY_PERIOD;
+        loop {
+            if let Some(shm_buf) = self.try_alloc(len) {
+                return Ok(shm_buf);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(self.alloc_timeout_error(len, timeout));
+            }
+            std::thread::sleep(period.min(deadline - now));
+            period = (period * 2).min(MAX_ALLOC_RETRY_PERIOD);
         }
     }
-    // Returns the amount of memory that it was able to de-fragment
-    pub fn defragment(&mut self) -> usize {
-        if self.free_list.len() > 1 {
-            let mut fbs: Vec<Chunk> = self.free_list.drain().collect();
-            fbs.sort_by(|x, y| x.offset.partial_cmp(&y.offset).unwrap());
-            let mut current = fbs.remove(0);
-            let mut defrag_mem = 0;
-            let mut i = 0;
-            let n = fbs.len();
-            for chunk in fbs.iter() {
-                i += 1;
-                let next = *chunk;
-                match SharedMemoryManager::try_merge_adjacent_chunks(&current, &next) {
-                    Some(c) => {
-                        current = c;
-                        defrag_mem += current.size;
-                        if i == n {
-                            self.free_list.push(current)
-                        }
-                    }
-                    None => {
-                        self.free_list.push(current);
-                        if i == n {
-                            self.free_list.push(next);
-                        } else {
-                            current = next;
-                        }
-                    }
-                }
+
+    /// Allocates a buffer of the given length, waiting asynchronously up to `timeout` for chunks to
+    /// be released if none is available.
+    ///
+    /// As the chunks may be released by other processes, the allocation is retried periodically.
+    pub async fn alloc_async(&mut self, len: usize, timeout: Duration) -> ZResult<SharedMemoryBuf> {
+        tracing::trace!("SharedMemoryManager::alloc_async({}, {:?})", len, timeout);
+        let deadline = Instant::now() + timeout;
+        let mut period = MIN_ALLOC_RETRY_PERIOD;
+        loop {
+            if let Some(shm_buf) = self.try_alloc(len) {
+                return Ok(shm_buf);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(self.alloc_timeout_error(len, timeout));
             }
-            defrag_mem
-        } else {
-            0
+            async_io::Timer::after(period.min(deadline - now)).await;
+            period = (period * 2).min(MAX_ALLOC_RETRY_PERIOD);
         }
     }
 
+    fn alloc_timeout_error(&self, len: usize, timeout: Duration) -> zenoh_result::Error {
+        let e = zerror!(
+            "SharedMemoryManager::alloc({}) cannot find any available chunk within {:?}: {:?}",
+            len,
+            timeout,
+            self
+        );
+        tracing::trace!("{}", e);
+        ShmError(e).into()
+    }
+
+    /// Returns the amount of memory that it was able to de-fragment
+    pub fn defragment(&mut self) -> usize {
+        let defragmented = self
+            .segments
+            .iter_mut()
+            .map(|s| s.allocator.defragment())
+            .sum();
+        self.update_stats();
+        defragmented
+    }
+
     /// Returns the amount of memory freed
     pub fn garbage_collect(&mut self) -> usize {
         tracing::trace!("Running Garbage Collector");
+        let freed = self
+            .segments
+            .iter_mut()
+            .map(|s| s.allocator.garbage_collect())
+            .sum();
+        self.update_stats();
+        freed
+    }
 
-        let mut freed = 0;
-        let (free, busy) = self
-            .busy_list
+    /// Returns the amount of free memory, not counting the buffers to be garbage collected
+    pub fn available(&self) -> usize {
+        self.segments.iter().map(|s| s.allocator.available()).sum()
+    }
+
+    /// Returns the number of segments of the manager
+    pub fn segments(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Returns the current statistics of the manager.
+    ///
+    /// The statistics reported by [`managers_stats`] are the ones as of the last garbage collection,
+    /// de-fragmentation, growth or allocation failure of the manager, or of the last call to this function.
+    pub fn stats(&self) -> SharedMemoryManagerStats {
+        self.update_stats();
+        self.monitor.stats()
+    }
+
+    fn update_stats(&self) {
+        let size = self.segments.len() * (self.segment_size + ACCOUNTED_OVERHEAD);
+        let available = self.available();
+        let largest_free_chunk = self
+            .segments
+            .iter()
+            .map(|s| s.allocator.largest_free_chunk())
+            .max()
+            .unwrap_or(0);
+        let busy_chunks = self
+            .segments
             .iter()
-            .partition(|&c| SharedMemoryManager::is_free_chunk(c));
-        self.busy_list = busy;
+            .map(|s| s.allocator.busy_chunks())
+            .sum();
+        self.monitor.update(|stats| {
+            stats.segments = self.segments.len();
+            stats.size = size;
+            stats.allocated = size.saturating_sub(available);
+            stats.largest_free_chunk = largest_free_chunk;
+            stats.busy_chunks = busy_chunks;
+        });
+    }
+}
 
-        for f in free {
-            freed += f.size;
-            tracing::trace!("Garbage Collecting Chunk: {:?}", f);
-            self.free_list.push(f)
-        }
-        self.available += freed;
-        freed
+impl Drop for SharedMemoryManager {
+    fn drop(&mut self) {
+        // The segments are unmapped, the references lent on their buffers cannot be reclaimed anymore
+        self.monitor.close();
     }
 }
 
 impl fmt::Debug for SharedMemoryManager {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SharedMemoryManager")
-            .field("segment_path", &self.segment_path)
-            .field("size", &self.size)
-            .field("available", &self.available)
-            .field("free_list.len", &self.free_list.len())
-            .field("busy_list.len", &self.busy_list.len())
+            .field("id", &self.id)
+            .field("segment_size", &self.segment_size)
+            .field("backend", &self.backend)
+            .field("max_segments", &self.max_segments)
+            .field("segments", &self.segments)
+            .finish()
+    }
+}
+
+impl fmt::Debug for ManagedSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Segment")
+            .field("id", &self.id)
+            .field("allocator", &self.allocator)
             .finish()
     }
 }
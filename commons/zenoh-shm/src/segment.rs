@@ -0,0 +1,468 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use crate::ZENOH_SHM_PREFIX;
+use shared_memory::{Shmem, ShmemConf, ShmemError};
+use std::{fmt, path::Path};
+use zenoh_result::{zerror, ShmError, ZResult};
+
+// Prefixes of the identifiers of the segments that are not backed by a file link,
+// the identifiers of the file link segments being the paths of their file links.
+const POSIX_SHM_ID_PREFIX: &str = "posix:";
+const MEMFD_ID_PREFIX: &str = "memfd:";
+// Separates the name given to a segment from the pid of its creator and its index
+const SEGMENT_OWNER_SEPARATOR: char = '@';
+
+/// The kind of operating system object backing the segments of a [`SharedMemoryManager`](crate::SharedMemoryManager).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShmBackend {
+    /// A POSIX shared memory object with a random name, found through a file link in [`std::env::temp_dir`].
+    #[default]
+    FileLink,
+    /// A named POSIX shared memory object, i.e. a file in `/dev/shm` on Linux.
+    PosixShm,
+    /// An anonymous file created with `memfd_create`, only available on Linux.
+    ///
+    /// The readers open it through the `/proc/<pid>/fd` directory of the creator process,
+    /// and the kernel releases it when the creator and all the readers unmapped it, even if they crashed.
+    /// Only the zenoh memfds of the same user are accepted for reading.
+    Memfd,
+}
+
+/// A mapped shared memory segment, either created or opened by this process.
+pub(crate) enum Segment {
+    Shmem(Shmem),
+    #[cfg(target_os = "linux")]
+    Memfd(memfd::MemfdSegment),
+}
+
+unsafe impl Send for Segment {}
+unsafe impl Sync for Segment {}
+
+impl Segment {
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        match self {
+            Segment::Shmem(shm) => shm.as_ptr(),
+            #[cfg(target_os = "linux")]
+            Segment::Memfd(memfd) => memfd.as_ptr(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Segment::Shmem(shm) => shm.len(),
+            #[cfg(target_os = "linux")]
+            Segment::Memfd(memfd) => memfd.len(),
+        }
+    }
+
+    /// Returns false if the segment is mapped read-only, its buffers then cannot be referenced.
+    pub(crate) fn is_writable(&self) -> bool {
+        match self {
+            Segment::Shmem(_) => true,
+            #[cfg(target_os = "linux")]
+            Segment::Memfd(memfd) => memfd.is_writable(),
+        }
+    }
+
+    /// Creates a segment of the given size, returns it with its identifier.
+    ///
+    /// The segment is named after `name`, the pid of this process and `index`, so that it can be
+    /// identified as stale by [`cleanup_stale_segments`] if this process crashes.
+    pub(crate) fn create(
+        backend: ShmBackend,
+        name: &str,
+        index: usize,
+        size: usize,
+    ) -> ZResult<(String, Segment)> {
+        let name = format!(
+            "{ZENOH_SHM_PREFIX}_{name}{SEGMENT_OWNER_SEPARATOR}{}.{index}",
+            std::process::id()
+        );
+        match backend {
+            ShmBackend::FileLink => {
+                let mut temp_dir = std::env::temp_dir();
+                temp_dir.push(name);
+                let path: String = temp_dir
+                    .to_str()
+                    .ok_or_else(|| {
+                        ShmError(zerror!("Unable to parse tmp directory: {:?}", temp_dir))
+                    })?
+                    .to_string();
+                tracing::trace!("Creating file at: {}", path);
+                let shmem = ShmemConf::new()
+                    .size(size)
+                    .flink(&path)
+                    .create()
+                    .map_err(|e| Self::create_error(&path, e))?;
+                Ok((path, Segment::Shmem(shmem)))
+            }
+            ShmBackend::PosixShm => {
+                let os_id = posix_shm_os_id(&name);
+                tracing::trace!("Creating shared memory object: {}", os_id);
+                let shmem = ShmemConf::new()
+                    .size(size)
+                    .os_id(&os_id)
+                    .create()
+                    .map_err(|e| Self::create_error(&os_id, e))?;
+                Ok((
+                    format!("{POSIX_SHM_ID_PREFIX}{os_id}"),
+                    Segment::Shmem(shmem),
+                ))
+            }
+            #[cfg(target_os = "linux")]
+            ShmBackend::Memfd => {
+                let memfd = memfd::MemfdSegment::create(&name, size)?;
+                let id = format!("{MEMFD_ID_PREFIX}{}:{}", std::process::id(), memfd.fd());
+                Ok((id, Segment::Memfd(memfd)))
+            }
+            #[cfg(not(target_os = "linux"))]
+            ShmBackend::Memfd => Err(ShmError(zerror!(
+                "Unable to create shared memory segment {}: memfd is only available on Linux",
+                name
+            ))
+            .into()),
+        }
+    }
+
+    /// Opens the segment with the given identifier, created by this or another process.
+    pub(crate) fn open(id: &str) -> ZResult<Segment> {
+        if let Some(os_id) = id.strip_prefix(POSIX_SHM_ID_PREFIX) {
+            ShmemConf::new()
+                .os_id(os_id)
+                .open()
+                .map(Segment::Shmem)
+                .map_err(|e| Self::open_error(id, e))
+        } else if let Some(_memfd) = id.strip_prefix(MEMFD_ID_PREFIX) {
+            #[cfg(target_os = "linux")]
+            {
+                memfd::MemfdSegment::open(_memfd).map(Segment::Memfd)
+            }
+            #[cfg(not(target_os = "linux"))]
+            {
+                Err(Self::open_error(id, "memfd is only available on Linux"))
+            }
+        } else {
+            ShmemConf::new()
+                .flink(id)
+                .open()
+                .map(Segment::Shmem)
+                .map_err(|e| Self::open_error(id, e))
+        }
+    }
+
+    fn create_error(id: &str, e: ShmemError) -> zenoh_result::Error {
+        match e {
+            ShmemError::LinkExists | ShmemError::MappingIdExists => ShmError(zerror!(
+                "Unable to create shared memory segment {}: it already exists",
+                id
+            ))
+            .into(),
+            e => ShmError(zerror!(
+                "Unable to create shared memory segment {}: {}",
+                id,
+                e
+            ))
+            .into(),
+        }
+    }
+
+    fn open_error<E: fmt::Debug>(id: &str, e: E) -> zenoh_result::Error {
+        let e = zerror!("Unable to bind shared memory segment {}: {:?}", id, e);
+        tracing::trace!("{}", e);
+        ShmError(e).into()
+    }
+}
+
+// The name of a POSIX shared memory object, which is limited to 31 characters on macOS: the name is then
+// replaced by its hash, the pid and index being enough to make it unique
+#[cfg(target_os = "macos")]
+fn posix_shm_os_id(name: &str) -> String {
+    use std::hash::{Hash, Hasher};
+    let (name, owner) = name
+        .rsplit_once(SEGMENT_OWNER_SEPARATOR)
+        .unwrap_or((name, ""));
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    name.hash(&mut hasher);
+    format!(
+        "/z{:08x}{SEGMENT_OWNER_SEPARATOR}{owner}",
+        hasher.finish() as u32
+    )
+}
+
+#[cfg(not(target_os = "macos"))]
+fn posix_shm_os_id(name: &str) -> String {
+    format!("/{name}")
+}
+
+/// Removes the segments left by the crashed processes, returns the number of removed segments.
+///
+/// Only the segments backed by file links or named POSIX shared memory objects need to be removed,
+/// as the kernel releases the [`ShmBackend::Memfd`] segments on its own. The liveness of the creator of
+/// a segment is only checked on Unix, and the POSIX shared memory objects can only be listed on Linux.
+///
+/// Only the segments owned by the current user are removed, as the liveness of the processes of other users,
+/// or of other pid namespaces sharing the same directories, cannot be reliably checked.
+pub fn cleanup_stale_segments() -> usize {
+    let mut removed = 0;
+    for path in stale_entries(&std::env::temp_dir()) {
+        let Some(flink) = path.to_str() else {
+            continue;
+        };
+        tracing::debug!("Removing stale shared memory segment: {}", flink);
+        match ShmemConf::new().flink(flink).open() {
+            Ok(mut shmem) => {
+                // the segment is removed with its file link when dropped by its owner
+                shmem.set_owner(true);
+            }
+            Err(e) => {
+                tracing::trace!("Unable to open stale segment {}: {}", flink, e);
+                if let Err(e) = std::fs::remove_file(&path) {
+                    tracing::warn!("Unable to remove stale file link {}: {}", flink, e);
+                    continue;
+                }
+            }
+        }
+        removed += 1;
+    }
+    #[cfg(target_os = "linux")]
+    for path in stale_entries(Path::new("/dev/shm")) {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        tracing::debug!("Removing stale shared memory object: {}", name);
+        match ShmemConf::new().os_id(format!("/{name}")).open() {
+            Ok(mut shmem) => {
+                shmem.set_owner(true);
+                removed += 1;
+            }
+            Err(e) => tracing::warn!(
+                "Unable to remove stale shared memory object {}: {}",
+                name,
+                e
+            ),
+        }
+    }
+    removed
+}
+
+// Lists the segments of the given directory whose creator is not alive anymore
+fn stale_entries(dir: &Path) -> Vec<std::path::PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| is_owned(path))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .and_then(segment_owner)
+                .map_or(false, |pid| !is_alive(pid))
+        })
+        .collect()
+}
+
+// Returns the pid of the creator of the segment with the given file name, if it is a zenoh segment
+fn segment_owner(file_name: &str) -> Option<u32> {
+    let name = file_name.strip_prefix(ZENOH_SHM_PREFIX)?;
+    owner_pid(name)
+}
+
+// Returns the pid from the owner suffix of a segment name, i.e. `{name}@{pid}.{index}`
+fn owner_pid(name: &str) -> Option<u32> {
+    let (_, owner) = name.rsplit_once(SEGMENT_OWNER_SEPARATOR)?;
+    let (pid, _index) = owner.split_once('.')?;
+    pid.parse().ok()
+}
+
+/// Returns the pid of the process that created the segment with the given identifier,
+/// i.e. the [`SharedMemoryBufInfo::shm_manager`](crate::SharedMemoryBufInfo::shm_manager) of its buffers,
+/// if it is a segment created by zenoh.
+pub fn segment_creator(id: &str) -> Option<u32> {
+    if let Some(memfd) = id.strip_prefix(MEMFD_ID_PREFIX) {
+        let (pid, _fd) = memfd.split_once(':')?;
+        return pid.parse().ok();
+    }
+    let id = id.strip_prefix(POSIX_SHM_ID_PREFIX).unwrap_or(id);
+    let name = Path::new(id).file_name()?.to_str()?;
+    owner_pid(name)
+}
+
+#[cfg(unix)]
+fn is_owned(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    // the metadata of a file link, not of the segment it points to
+    std::fs::symlink_metadata(path).map_or(false, |m| m.uid() == unsafe { libc::geteuid() })
+}
+
+#[cfg(not(unix))]
+fn is_owned(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(unix)]
+pub(crate) fn is_alive(pid: u32) -> bool {
+    if pid == std::process::id() {
+        return true;
+    }
+    match libc::pid_t::try_from(pid) {
+        // the process exists if it can be signaled, or if it belongs to another user
+        Ok(pid) => {
+            let signaled = unsafe { libc::kill(pid, 0) } == 0;
+            signaled || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+        }
+        Err(_) => true,
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn is_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(target_os = "linux")]
+mod memfd {
+    use std::{
+        ffi::CString,
+        fs::{File, OpenOptions},
+        os::{
+            fd::{AsRawFd, FromRawFd, RawFd},
+            unix::fs::MetadataExt,
+        },
+    };
+    use zenoh_result::{zerror, ShmError, ZResult};
+
+    pub(crate) struct MemfdSegment {
+        ptr: *mut u8,
+        len: usize,
+        // the reference counts of the chunks can only be updated in a writable segment
+        writable: bool,
+        // kept open by the creator, so that the readers can open it through /proc/<pid>/fd
+        file: Option<File>,
+    }
+
+    impl MemfdSegment {
+        pub(crate) fn create(name: &str, size: usize) -> ZResult<Self> {
+            let c_name = CString::new(name)
+                .map_err(|e| ShmError(zerror!("Invalid memfd name {}: {}", name, e)))?;
+            let fd = unsafe { libc::memfd_create(c_name.as_ptr(), libc::MFD_CLOEXEC) };
+            if fd < 0 {
+                return Err(Self::error("create memfd", name).into());
+            }
+            let file = unsafe { File::from_raw_fd(fd) };
+            file.set_len(size as u64)
+                .map_err(|e| ShmError(zerror!("Unable to resize memfd {}: {}", name, e)))?;
+            let ptr = Self::map(file.as_raw_fd(), size, true, name)?;
+            Ok(MemfdSegment {
+                ptr,
+                len: size,
+                writable: true,
+                file: Some(file),
+            })
+        }
+
+        // Opens the memfd from its "<pid>:<fd>" identifier. The identifier is received from remote peers:
+        // it must designate a zenoh memfd, which is mapped read-only if it belongs to another user.
+        pub(crate) fn open(id: &str) -> ZResult<Self> {
+            let (pid, fd) = id
+                .split_once(':')
+                .and_then(|(pid, fd)| Some((pid.parse::<u32>().ok()?, fd.parse::<u32>().ok()?)))
+                .ok_or_else(|| ShmError(zerror!("Invalid memfd identifier: {}", id)))?;
+            let path = format!("/proc/{pid}/fd/{fd}");
+            let file = OpenOptions::new()
+                .read(true)
+                .open(&path)
+                .map_err(|e| ShmError(zerror!("Unable to open memfd {}: {}", path, e)))?;
+            // checked on the opened descriptor, which cannot be replaced in the meantime
+            let own_path = format!("/proc/self/fd/{}", file.as_raw_fd());
+            let is_zenoh_memfd = std::fs::read_link(&own_path).map_or(false, |target| {
+                target
+                    .to_str()
+                    .and_then(|target| target.strip_prefix("/memfd:"))
+                    .map_or(false, |name| name.starts_with(crate::ZENOH_SHM_PREFIX))
+            });
+            if !is_zenoh_memfd {
+                return Err(ShmError(zerror!("Not a zenoh memfd: {}", path)).into());
+            }
+            let metadata = file
+                .metadata()
+                .map_err(|e| ShmError(zerror!("Unable to read size of memfd {}: {}", path, e)))?;
+            let len = metadata.len() as usize;
+            let writable = metadata.uid() == unsafe { libc::geteuid() };
+            let file = if writable {
+                OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(&own_path)
+                    .map_err(|e| ShmError(zerror!("Unable to open memfd {}: {}", path, e)))?
+            } else {
+                file
+            };
+            // the mapping outlives the file descriptor
+            let ptr = Self::map(file.as_raw_fd(), len, writable, &path)?;
+            Ok(MemfdSegment {
+                ptr,
+                len,
+                writable,
+                file: None,
+            })
+        }
+
+        fn map(fd: RawFd, len: usize, writable: bool, name: &str) -> ZResult<*mut u8> {
+            let prot = if writable {
+                libc::PROT_READ | libc::PROT_WRITE
+            } else {
+                libc::PROT_READ
+            };
+            let ptr =
+                unsafe { libc::mmap(std::ptr::null_mut(), len, prot, libc::MAP_SHARED, fd, 0) };
+            if ptr == libc::MAP_FAILED {
+                return Err(Self::error("map memfd", name).into());
+            }
+            Ok(ptr as *mut u8)
+        }
+
+        fn error(action: &str, name: &str) -> ShmError {
+            ShmError(zerror!(
+                "Unable to {} {}: {}",
+                action,
+                name,
+                std::io::Error::last_os_error()
+            ))
+        }
+
+        pub(crate) fn as_ptr(&self) -> *mut u8 {
+            self.ptr
+        }
+
+        pub(crate) fn len(&self) -> usize {
+            self.len
+        }
+
+        pub(crate) fn is_writable(&self) -> bool {
+            self.writable
+        }
+
+        pub(crate) fn fd(&self) -> RawFd {
+            self.file.as_ref().map_or(-1, |f| f.as_raw_fd())
+        }
+    }
+
+    impl Drop for MemfdSegment {
+        fn drop(&mut self) {
+            unsafe { libc::munmap(self.ptr as *mut libc::c_void, self.len) };
+        }
+    }
+}
@@ -0,0 +1,313 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use crate::ChunkHeaderType;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+};
+
+// The monitors of the live managers of this process
+static MONITORS: Mutex<Vec<Weak<ManagerMonitor>>> = Mutex::new(Vec::new());
+
+/// The identifier of a remote reader of shared memory buffers, typically a transport.
+///
+/// The references taken on behalf of a reader with [`SharedMemoryBuf::lend`](crate::SharedMemoryBuf::lend)
+/// are accounted to it, so that they can be reclaimed with [`reclaim_reader`] once its process died.
+#[derive(Clone, Debug)]
+pub struct ShmReaderId {
+    id: u64,
+    name: String,
+    process: Option<u32>,
+}
+
+impl ShmReaderId {
+    /// Creates a new reader identifier, unique in this process, with the given name for reporting.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        ShmReaderId {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            name: name.into(),
+            process: None,
+        }
+    }
+
+    /// Sets the pid of the process of the reader, without which its references are never reclaimed.
+    pub fn process(mut self, pid: u32) -> Self {
+        self.process = Some(pid);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Whether the process of the reader is known to be dead, so that it cannot read its buffers anymore
+    fn is_dead(&self) -> bool {
+        self.process
+            .map_or(false, |pid| !crate::segment::is_alive(pid))
+    }
+}
+
+impl PartialEq for ShmReaderId {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for ShmReaderId {}
+
+impl Hash for ShmReaderId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state)
+    }
+}
+
+/// The statistics of a [`SharedMemoryManager`](crate::SharedMemoryManager).
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SharedMemoryManagerStats {
+    /// The identifier of the manager.
+    pub id: String,
+    /// The number of segments of the manager.
+    pub segments: usize,
+    /// The total size of the segments.
+    pub size: usize,
+    /// The amount of allocated memory, including the chunks to be garbage collected.
+    pub allocated: usize,
+    /// The size of the largest free chunk, 0 if not reported by the allocator.
+    pub largest_free_chunk: usize,
+    /// The share of the free memory that is not in the largest free chunk, from 0 to 1.
+    pub fragmentation: f64,
+    /// The number of allocated chunks, including the chunks to be garbage collected, 0 if not reported by the allocator.
+    pub busy_chunks: usize,
+    /// The references on the buffers lent per remote reader, whose chunks were not allocated again since.
+    ///
+    /// As the releases of the remote readers are not observed, it is an upper bound of their outstanding references.
+    pub lent: HashMap<String, usize>,
+    /// The number of references reclaimed from the remote readers that failed to release them.
+    pub reclaimed: usize,
+}
+
+/// The state of a manager shared with its buffers, to account the references lent to remote readers.
+pub(crate) struct ManagerMonitor {
+    state: Mutex<MonitorState>,
+}
+
+struct MonitorState {
+    // Cleared when the manager is dropped, as its segments are unmapped
+    alive: bool,
+    // The chunks lent to remote readers since their allocation, indexed by address of their header
+    loans: HashMap<usize, Arc<ChunkLoans>>,
+    // The statistics of the manager as of their last update
+    stats: SharedMemoryManagerStats,
+}
+
+impl ManagerMonitor {
+    pub(crate) fn new(id: String) -> Arc<Self> {
+        let monitor = Arc::new(ManagerMonitor {
+            state: Mutex::new(MonitorState {
+                alive: true,
+                loans: HashMap::new(),
+                stats: SharedMemoryManagerStats {
+                    id,
+                    ..Default::default()
+                },
+            }),
+        });
+        let mut monitors = MONITORS.lock().unwrap();
+        monitors.retain(|m| m.strong_count() > 0);
+        monitors.push(Arc::downgrade(&monitor));
+        monitor
+    }
+
+    // Called when a chunk is allocated: all the references previously lent on it were released
+    pub(crate) fn allocate(self: &Arc<Self>, chunk: *const ChunkHeaderType) -> Arc<ChunkLoans> {
+        let mut state = self.state.lock().unwrap();
+        if !state.loans.is_empty() {
+            state.loans.remove(&(chunk as usize));
+        }
+        Arc::new(ChunkLoans {
+            monitor: Arc::downgrade(self),
+            chunk: chunk as usize,
+            local: AtomicUsize::new(1),
+            registered: AtomicBool::new(false),
+            readers: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn register(&self, loans: &Arc<ChunkLoans>) {
+        let mut state = self.state.lock().unwrap();
+        if state.alive {
+            state.loans.insert(loans.chunk, loans.clone());
+        }
+    }
+
+    pub(crate) fn update(&self, update: impl FnOnce(&mut SharedMemoryManagerStats)) {
+        let mut state = self.state.lock().unwrap();
+        update(&mut state.stats);
+        let available = state.stats.size - state.stats.allocated;
+        state.stats.fragmentation = if available == 0 || state.stats.largest_free_chunk == 0 {
+            0.0
+        } else {
+            1.0 - state.stats.largest_free_chunk as f64 / available as f64
+        };
+    }
+
+    pub(crate) fn stats(&self) -> SharedMemoryManagerStats {
+        let state = self.state.lock().unwrap();
+        let mut stats = state.stats.clone();
+        for loans in state.loans.values() {
+            for (reader, count) in loans.readers.lock().unwrap().iter() {
+                *stats.lent.entry(reader.name().to_string()).or_insert(0) += count;
+            }
+        }
+        stats
+    }
+
+    pub(crate) fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.alive = false;
+        state.loans.clear();
+    }
+
+    fn reclaim(&self, reader: &ShmReaderId) -> usize {
+        let mut state = self.state.lock().unwrap();
+        if !state.alive {
+            return 0;
+        }
+        // the loans of a chunk are forgotten when it is allocated again
+        let reclaimed: usize = state.loans.values().map(|l| l.reclaim(reader)).sum();
+        state.stats.reclaimed += reclaimed;
+        reclaimed
+    }
+}
+
+/// The references on a chunk allocated by a manager of this process, from its allocation.
+pub(crate) struct ChunkLoans {
+    monitor: Weak<ManagerMonitor>,
+    // The address of the chunk header
+    chunk: usize,
+    // The number of buffers of this process referencing the chunk, incremented before the reference count
+    // and decremented after it, so that it is never lower than their share of the reference count
+    local: AtomicUsize,
+    // Set on the first loan, when the chunk is registered in the monitor of the manager
+    registered: AtomicBool,
+    // The references lent per remote reader, recorded before the reference count is incremented
+    readers: Mutex<HashMap<ShmReaderId, usize>>,
+}
+
+impl ChunkLoans {
+    pub(crate) fn clone_local(&self) {
+        self.local.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn drop_local(&self) {
+        self.local.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn lend(self: &Arc<Self>, reader: &ShmReaderId) {
+        if !self.registered.swap(true, Ordering::SeqCst) {
+            if let Some(monitor) = self.monitor.upgrade() {
+                monitor.register(self);
+            }
+        }
+        *self
+            .readers
+            .lock()
+            .unwrap()
+            .entry(reader.clone())
+            .or_insert(0) += 1;
+    }
+
+    // The releases of the remote readers are not observed: the references of the dead reader are only
+    // reclaimed up to what the local buffers and the references lent to the other readers leave. The
+    // references the dead reader passed on to other processes are not accounted here, which is why
+    // it must not have forwarded the buffers it read.
+    fn reclaim(&self, reader: &ShmReaderId) -> usize {
+        let mut readers = self.readers.lock().unwrap();
+        let Some(count) = readers.remove(reader) else {
+            return 0;
+        };
+        let others: usize = readers.values().sum();
+        // SAFETY: the segments of the manager are mapped while it is alive
+        let rc = unsafe { &*(self.chunk as *const ChunkHeaderType) };
+        loop {
+            let current = rc.load(Ordering::SeqCst);
+            let held = self.local.load(Ordering::SeqCst) + others;
+            let released = count.min(current.saturating_sub(held));
+            if released == 0 {
+                return 0;
+            }
+            if rc
+                .compare_exchange(
+                    current,
+                    current - released,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                )
+                .is_ok()
+            {
+                return released;
+            }
+        }
+    }
+}
+
+/// Reclaims the references on the buffers of the managers of this process that were lent to the given reader,
+/// returns the number of reclaimed references.
+///
+/// Nothing is reclaimed unless the [process](ShmReaderId::process) of the reader is known to be dead, as a
+/// live process may still read the buffers. As the references it released on its own cannot be told apart
+/// from the ones it still holds, only the references not possibly held by the buffers of this process or by
+/// the other readers of a chunk are reclaimed, so that a chunk shared with other readers may not be reclaimed.
+///
+/// The references the reader passed on to third processes, e.g. when it is a router forwarding the buffers
+/// to other peers, are not accounted: the references of such readers must not be reclaimed, as the chunks
+/// could then be released while the third processes still read them.
+pub fn reclaim_reader(reader: &ShmReaderId) -> usize {
+    if !reader.is_dead() {
+        return 0;
+    }
+    let monitors: Vec<Arc<ManagerMonitor>> = MONITORS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|m| m.upgrade())
+        .collect();
+    let reclaimed: usize = monitors.iter().map(|m| m.reclaim(reader)).sum();
+    if reclaimed > 0 {
+        tracing::warn!(
+            "Reclaimed {} shared memory buffer references from dead reader {}",
+            reclaimed,
+            reader.name()
+        );
+    }
+    reclaimed
+}
+
+/// Returns the statistics of all the [`SharedMemoryManager`](crate::SharedMemoryManager)s of this process,
+/// as of their last allocation failure or garbage collection.
+pub fn managers_stats() -> Vec<SharedMemoryManagerStats> {
+    let monitors: Vec<Arc<ManagerMonitor>> = MONITORS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|m| m.upgrade())
+        .collect();
+    monitors.iter().map(|m| m.stats()).collect()
+}
@@ -0,0 +1,131 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use std::time::{Duration, Instant};
+use zenoh_shm::{BuddyAllocator, HeapAllocator, PoolAllocator, SharedMemoryManager};
+
+const SEGMENT_SIZE: usize = 64 * 1_024;
+
+fn segment_id(name: &str) -> String {
+    format!("test_allocators_{}_{}", name, std::process::id())
+}
+
+#[test]
+fn heap_allocator() {
+    let mut manager = SharedMemoryManager::make_with_allocator(
+        segment_id("heap"),
+        SEGMENT_SIZE,
+        HeapAllocator::new(),
+    )
+    .unwrap();
+    let available = manager.available();
+
+    let mut buf = manager.alloc(1_000).unwrap();
+    assert_eq!(buf.len(), 1_000);
+    unsafe { buf.as_mut_slice() }.fill(0xab);
+    assert!(buf.as_slice().iter().all(|b| *b == 0xab));
+    assert!(manager.available() < available);
+
+    drop(buf);
+    assert!(manager.garbage_collect() > 0);
+    manager.defragment();
+    assert_eq!(manager.available(), available);
+}
+
+#[test]
+fn pool_allocator() {
+    let chunk_size = 1_024;
+    let mut manager = SharedMemoryManager::make_with_allocator(
+        segment_id("pool"),
+        SEGMENT_SIZE,
+        PoolAllocator::new(chunk_size),
+    )
+    .unwrap();
+    let chunks = manager.available() / chunk_size;
+
+    // a chunk is allocated for each buffer, whatever its size
+    assert!(manager.alloc(chunk_size + 1).is_err());
+    let bufs: Vec<_> = (0..chunks)
+        .map(|i| manager.alloc(i % 1_000).unwrap())
+        .collect();
+    assert_eq!(manager.available(), 0);
+    assert!(manager.alloc(1).is_err());
+
+    // the chunks are reusable as soon as they are released, without garbage collection
+    let buf = bufs[chunks / 2].clone();
+    drop(bufs);
+    assert_eq!(manager.available(), (chunks - 1) * chunk_size);
+    drop(buf);
+    let bufs: Vec<_> = (0..chunks).map(|_| manager.alloc(100).unwrap()).collect();
+    assert_eq!(bufs.len(), chunks);
+}
+
+#[test]
+fn buddy_allocator() {
+    let mut manager = SharedMemoryManager::make_with_allocator(
+        segment_id("buddy"),
+        SEGMENT_SIZE,
+        BuddyAllocator::new(256),
+    )
+    .unwrap();
+    let available = manager.available();
+    // the tail of the segment past its largest power of two is used as well
+    let largest = manager.stats().largest_free_chunk;
+    assert!(largest.is_power_of_two());
+    assert!(available > largest);
+    assert_eq!(available % 256, 0);
+
+    // the small buffer is allocated in the tail
+    let small = manager.alloc(100).unwrap();
+    let medium = manager.alloc(largest / 4).unwrap();
+    // with its header, the buffer does not fit in half of the largest chunk
+    assert!(manager.alloc(largest / 2).is_err());
+    assert_eq!(manager.available(), available - 256 - largest / 2);
+
+    // the released chunks are merged with their buddies on garbage collection
+    drop(small);
+    drop(medium);
+    manager.garbage_collect();
+    assert_eq!(manager.available(), available);
+    let whole = manager.alloc(largest - 8).unwrap();
+    assert_eq!(whole.len(), largest - 8);
+}
+
+#[test]
+fn alloc_blocking() {
+    let mut manager = SharedMemoryManager::make_with_allocator(
+        segment_id("blocking"),
+        SEGMENT_SIZE,
+        PoolAllocator::new(SEGMENT_SIZE / 2),
+    )
+    .unwrap();
+    let bufs: Vec<_> = std::iter::from_fn(|| manager.alloc(16).ok()).collect();
+    assert!(!bufs.is_empty());
+
+    // the allocation times out while all the chunks are busy
+    let timeout = Duration::from_millis(50);
+    let now = Instant::now();
+    assert!(manager.alloc_blocking(16, timeout).is_err());
+    assert!(now.elapsed() >= timeout);
+
+    // and succeeds once a chunk is released by another thread
+    let releaser = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        drop(bufs);
+    });
+    assert!(manager.alloc_blocking(16, Duration::from_secs(5)).is_ok());
+    releaser.join().unwrap();
+
+    let buf = async_io::block_on(manager.alloc_async(16, timeout)).unwrap();
+    assert_eq!(buf.len(), 16);
+}
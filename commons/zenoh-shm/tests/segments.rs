@@ -0,0 +1,147 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use zenoh_shm::{
+    cleanup_stale_segments, segment_creator, PoolAllocator, SharedMemoryManager,
+    SharedMemoryReader, ShmBackend,
+};
+
+const SEGMENT_SIZE: usize = 16 * 1_024;
+
+fn manager_id(name: &str) -> String {
+    format!("test_segments_{}_{}", name, std::process::id())
+}
+
+fn grow_and_read(backend: ShmBackend) {
+    let mut manager =
+        SharedMemoryManager::builder(manager_id(&format!("{backend:?}")), SEGMENT_SIZE)
+            .backend(backend)
+            .max_segments(3)
+            .allocator(|| PoolAllocator::new(SEGMENT_SIZE / 4))
+            .make()
+            .unwrap();
+    assert_eq!(manager.segments(), 1);
+
+    // a segment is added each time the existing ones are full, up to the maximum
+    let mut bufs = vec![];
+    while let Ok(mut buf) = manager.alloc(100) {
+        unsafe { buf.as_mut_slice() }.fill(bufs.len() as u8);
+        bufs.push(buf);
+    }
+    assert_eq!(manager.segments(), 3);
+    let owners: std::collections::HashSet<_> = bufs.iter().map(|b| b.owner()).collect();
+    assert_eq!(owners.len(), 3);
+    // the segments are identified as created by this process
+    assert!(owners
+        .iter()
+        .all(|o| segment_creator(o) == Some(std::process::id())));
+
+    // the reader maps the segments of the buffers it reads
+    let mut reader = SharedMemoryReader::new();
+    for (i, buf) in bufs.iter().enumerate() {
+        buf.inc_ref_count();
+        let read = reader.read_shmbuf(&buf.info).unwrap();
+        // the buffers are rounded up to the alignment of the chunks
+        assert!(read.len() >= 100);
+        assert!(read.as_slice().iter().all(|b| *b == i as u8));
+    }
+}
+
+#[test]
+fn file_link_segments() {
+    grow_and_read(ShmBackend::FileLink);
+}
+
+#[cfg(unix)]
+#[test]
+fn posix_shm_segments() {
+    grow_and_read(ShmBackend::PosixShm);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn memfd_segments() {
+    grow_and_read(ShmBackend::Memfd);
+}
+
+#[test]
+fn no_growth_beyond_segment_size() {
+    let mut manager = SharedMemoryManager::builder(manager_id("large"), SEGMENT_SIZE)
+        .max_segments(2)
+        .make()
+        .unwrap();
+    assert!(manager.alloc(2 * SEGMENT_SIZE).is_err());
+    assert_eq!(manager.segments(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn stale_segments_cleanup() {
+    // a segment of a process that does not exist anymore, whose shared memory object is already gone
+    let mut flink = std::env::temp_dir();
+    flink.push(format!(
+        "zenoh_shm_zid_{}@{}.0",
+        manager_id("stale"),
+        i32::MAX
+    ));
+    std::fs::write(&flink, "/missing").unwrap();
+
+    assert!(cleanup_stale_segments() >= 1);
+    assert!(!flink.exists());
+}
+
+#[test]
+fn remote_bounds_and_alignment() {
+    let mut manager = SharedMemoryManager::make(manager_id("remote"), SEGMENT_SIZE).unwrap();
+    let buf = manager.alloc(100).unwrap();
+    let mut reader = SharedMemoryReader::new();
+
+    // the buffers out of the segment, or whose reference count is misaligned, are rejected
+    let mut info = buf.info.clone();
+    info.offset = 16 * SEGMENT_SIZE;
+    assert!(reader.read_shmbuf(&info).is_err());
+    info.offset = usize::MAX;
+    assert!(reader.read_shmbuf(&info).is_err());
+    info.offset = buf.info.offset + 1;
+    assert!(reader.read_shmbuf(&info).is_err());
+
+    buf.inc_ref_count();
+    assert!(reader.read_shmbuf(&buf.info).is_ok());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn memfd_invalid_identifiers() {
+    use std::os::fd::AsRawFd;
+    use zenoh_shm::SharedMemoryBufInfo;
+
+    // a regular file opened by this process, which must not be mapped as a segment
+    let mut path = std::env::temp_dir();
+    path.push(manager_id("memfd_file"));
+    let file = std::fs::File::create(&path).unwrap();
+    file.set_len(SEGMENT_SIZE as u64).unwrap();
+
+    let pid = std::process::id();
+    let mut reader = SharedMemoryReader::new();
+    for id in [
+        format!("memfd:self:{}", file.as_raw_fd()),
+        format!("memfd:{pid}:{}", file.as_raw_fd()),
+        format!("memfd:{pid}:../../../{}", path.display()),
+        format!("memfd:{pid}:-1"),
+        "memfd:1".to_string(),
+    ] {
+        let info = SharedMemoryBufInfo::new(0, 16, id, 0);
+        assert!(reader.read_shmbuf(&info).is_err());
+    }
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,148 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use zenoh_shm::{managers_stats, reclaim_reader, PoolAllocator, SharedMemoryManager, ShmReaderId};
+
+const SEGMENT_SIZE: usize = 4 * 1_024;
+const CHUNK_SIZE: usize = 1_024;
+
+fn manager(name: &str) -> SharedMemoryManager {
+    let id = format!("test_watchdog_{}_{}", name, std::process::id());
+    SharedMemoryManager::builder(id, SEGMENT_SIZE)
+        .allocator(|| PoolAllocator::new(CHUNK_SIZE))
+        .make()
+        .unwrap()
+}
+
+// Returns the pid of a process that already exited
+fn dead_process() -> u32 {
+    let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+        .arg("--list")
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let pid = child.id();
+    child.wait().unwrap();
+    pid
+}
+
+#[cfg(unix)]
+#[test]
+fn reclaim_failed_reader() {
+    let mut manager = manager("reclaim");
+    let failed = ShmReaderId::new("failed").process(dead_process());
+    let alive = ShmReaderId::new("alive");
+
+    // the buffer is lent twice to the failed reader and once to the alive one
+    let buf = manager.alloc(100).unwrap();
+    buf.lend(&failed);
+    buf.lend(&failed);
+    buf.lend(&alive);
+    assert_eq!(buf.ref_count(), 4);
+    let stats = manager.stats();
+    assert_eq!(stats.lent.get("failed"), Some(&2));
+    assert_eq!(stats.lent.get("alive"), Some(&1));
+
+    // only the references of the failed reader are reclaimed, once
+    assert_eq!(reclaim_reader(&failed), 2);
+    assert_eq!(reclaim_reader(&failed), 0);
+    assert_eq!(buf.ref_count(), 2);
+    let stats = manager.stats();
+    assert_eq!(stats.lent.get("failed"), None);
+    assert_eq!(stats.reclaimed, 2);
+
+    // the chunk is available again once the other references are released
+    buf.dec_ref_count();
+    drop(buf);
+    let bufs: Vec<_> = std::iter::from_fn(|| manager.alloc(100).ok()).collect();
+    assert!(bufs.len() >= SEGMENT_SIZE / CHUNK_SIZE);
+    // the loans of the released chunk are forgotten when it is allocated again
+    assert!(manager.stats().lent.is_empty());
+    drop(bufs);
+}
+
+#[cfg(unix)]
+#[test]
+fn reclaim_released_references() {
+    let mut manager = manager("released");
+    let failed = ShmReaderId::new("failed").process(dead_process());
+
+    // the failed reader released one of its two references before failing
+    let buf = manager.alloc(100).unwrap();
+    buf.lend(&failed);
+    buf.lend(&failed);
+    buf.dec_ref_count();
+    assert_eq!(buf.ref_count(), 2);
+
+    // the reference of the local buffer is preserved
+    assert_eq!(reclaim_reader(&failed), 1);
+    assert_eq!(buf.ref_count(), 1);
+
+    // a reader that released all its references has nothing to reclaim
+    let local = buf.clone();
+    buf.lend(&failed);
+    buf.dec_ref_count();
+    assert_eq!(reclaim_reader(&failed), 0);
+    assert_eq!(buf.ref_count(), 2);
+    drop(local);
+
+    // the references of a chunk not referenced locally anymore are all reclaimed
+    buf.lend(&failed);
+    drop(buf);
+    assert_eq!(reclaim_reader(&failed), 1);
+    let stats = manager.stats();
+    assert!(stats.lent.is_empty());
+    assert_eq!(stats.reclaimed, 2);
+}
+
+#[test]
+fn no_reclaim_from_live_reader() {
+    let mut manager = manager("live");
+    let unknown = ShmReaderId::new("unknown");
+    let live = ShmReaderId::new("live").process(std::process::id());
+
+    // the references of a reader whose process is unknown or alive are never reclaimed
+    let buf = manager.alloc(100).unwrap();
+    buf.lend(&unknown);
+    buf.lend(&live);
+    assert_eq!(reclaim_reader(&unknown), 0);
+    assert_eq!(reclaim_reader(&live), 0);
+    assert_eq!(buf.ref_count(), 3);
+    assert_eq!(manager.stats().reclaimed, 0);
+
+    // releasing more references than taken does not wrap the reference count around
+    for _ in 0..4 {
+        buf.dec_ref_count();
+    }
+    assert_eq!(buf.ref_count(), 0);
+    buf.inc_ref_count();
+}
+
+#[test]
+fn managers_statistics() {
+    let mut manager = manager("stats");
+    let _bufs: Vec<_> = (0..2).map(|_| manager.alloc(100).unwrap()).collect();
+
+    let stats = manager.stats();
+    assert_eq!(stats.segments, 1);
+    assert_eq!(stats.busy_chunks, 2);
+    assert_eq!(stats.largest_free_chunk, CHUNK_SIZE);
+    assert!(stats.allocated >= 2 * CHUNK_SIZE);
+    assert!(managers_stats().iter().any(|s| s.id == stats.id));
+
+    // the statistics of a dropped manager are not reported anymore
+    let id = stats.id.clone();
+    drop(_bufs);
+    drop(manager);
+    assert!(!managers_stats().iter().any(|s| s.id == id));
+}
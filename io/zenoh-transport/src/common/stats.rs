@@ -267,5 +267,17 @@ stats_struct! {
         # HELP "Counter of received bytes in zenoh reply message payloads."
         # TYPE "counter"
         pub rx_z_reply_pl_bytes DiscriminatedStats,
+
+        # HELP "Counter of sent network messages with shared memory payloads."
+        # TYPE "counter"
+        pub tx_shm_msgs,
+
+        # HELP "Counter of received network messages with shared memory payloads."
+        # TYPE "counter"
+        pub rx_shm_msgs,
+
+        # HELP "Counter of shared memory buffer references reclaimed from failed peers."
+        # TYPE "counter"
+        pub shm_reclaimed,
     }
 }
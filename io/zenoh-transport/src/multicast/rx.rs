@@ -41,8 +41,14 @@ impl TransportMulticastInner {
     ) -> ZResult<()> {
         #[cfg(feature = "shared-memory")]
         {
-            if self.manager.config.multicast.is_shm {
-                crate::shm::map_zmsg_to_shmbuf(&mut msg, &self.manager.state.multicast.shm.reader)?;
+            if self.manager.config.multicast.is_shm
+                && crate::shm::map_zmsg_to_shmbuf(
+                    &mut msg,
+                    &self.manager.state.multicast.shm.reader,
+                )?
+            {
+                #[cfg(feature = "stats")]
+                self.stats.inc_rx_shm_msgs(1);
             }
         }
 
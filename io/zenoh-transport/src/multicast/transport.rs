@@ -88,6 +88,10 @@ pub(crate) struct TransportMulticastInner {
     // Transport statistics
     #[cfg(feature = "stats")]
     pub(super) stats: Arc<TransportStats>,
+    // The reader the shared memory buffers are lent to, never reclaimed as the peers of the group
+    // cannot be told apart
+    #[cfg(feature = "shared-memory")]
+    pub(super) shm_reader: Arc<zenoh_shm::ShmReaderId>,
 }
 
 impl TransportMulticastInner {
@@ -109,6 +113,11 @@ impl TransportMulticastInner {
         #[cfg(feature = "stats")]
         let stats = Arc::new(TransportStats::new(Some(manager.get_stats().clone())));
 
+        #[cfg(feature = "shared-memory")]
+        let shm_reader = Arc::new(zenoh_shm::ShmReaderId::new(
+            config.link.link.get_dst().to_string(),
+        ));
+
         let ti = TransportMulticastInner {
             manager,
             priority_tx: priority_tx.into_boxed_slice().into(),
@@ -119,6 +128,8 @@ impl TransportMulticastInner {
             task_controller: TaskController::default(),
             #[cfg(feature = "stats")]
             stats,
+            #[cfg(feature = "shared-memory")]
+            shm_reader,
         };
 
         let link = TransportLinkMulticastUniversal::new(ti.clone(), config.link);
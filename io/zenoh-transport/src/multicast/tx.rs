@@ -54,13 +54,18 @@ impl TransportMulticastInner {
         #[cfg(feature = "shared-memory")]
         {
             let res = if self.manager.config.multicast.is_shm {
-                crate::shm::map_zmsg_to_shminfo(&mut msg)
+                crate::shm::map_zmsg_to_shminfo(&mut msg, &self.shm_reader)
             } else {
                 crate::shm::map_zmsg_to_shmbuf(&mut msg, &self.manager.state.multicast.shm.reader)
             };
-            if let Err(e) = res {
-                tracing::trace!("Failed SHM conversion: {}", e);
-                return false;
+            match res {
+                #[cfg(feature = "stats")]
+                Ok(true) if self.manager.config.multicast.is_shm => self.stats.inc_tx_shm_msgs(1),
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::trace!("Failed SHM conversion: {}", e);
+                    return false;
+                }
             }
         }
 
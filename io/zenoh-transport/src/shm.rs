@@ -11,11 +11,14 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
+use crate::unicast::TransportConfigUnicast;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::RwLock;
 use zenoh_buffers::{reader::HasReader, writer::HasWriter, ZBuf, ZSlice, ZSliceKind};
 use zenoh_codec::{RCodec, WCodec, Zenoh080};
 use zenoh_core::{zasyncread, zasyncwrite, zerror};
 use zenoh_protocol::{
+    core::WhatAmI,
     network::{NetworkBody, NetworkMessage, Push, Request, Response},
     zenoh::{
         err::{ext::ErrBodyType, Err},
@@ -25,17 +28,63 @@ use zenoh_protocol::{
     },
 };
 use zenoh_result::ZResult;
-use zenoh_shm::{SharedMemoryBuf, SharedMemoryBufInfo, SharedMemoryReader};
+use zenoh_shm::{SharedMemoryBuf, SharedMemoryBufInfo, SharedMemoryReader, ShmReaderId};
+
+/// The references on the shared memory buffers lent to the remote peer of a transport.
+///
+/// When the transport fails, e.g. on lease expiration, the peer may have crashed without releasing them:
+/// they are reclaimed so that its chunks can be allocated again, but only once its process is known to be
+/// dead. Only the buffers sent by this process to the peer are covered, not the ones it received from other
+/// processes through this one. As the references a router passes on to the peers it forwards the buffers to
+/// are not accounted, nothing is reclaimed from a router.
+pub(crate) struct ShmLender {
+    reader: ShmReaderId,
+    is_router: bool,
+    closed: AtomicBool,
+}
+
+impl ShmLender {
+    pub(crate) fn new(config: &TransportConfigUnicast) -> Self {
+        let mut reader = ShmReaderId::new(config.zid.to_string());
+        if let Some(pid) = config.shm_peer_process {
+            reader = reader.process(pid);
+        }
+        ShmLender {
+            reader,
+            is_router: config.whatami == WhatAmI::Router,
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn reader(&self) -> &ShmReaderId {
+        &self.reader
+    }
+
+    /// Marks the transport as closed by either side: the peer is alive and releases its references on its own.
+    pub(crate) fn set_closed(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    /// Reclaims the references lent to the peer if the transport was not closed and the process of the peer,
+    /// which is not a router, is dead, returns the number of reclaimed references.
+    pub(crate) fn reclaim_on_failure(&self) -> usize {
+        if self.closed.load(Ordering::Acquire) || self.is_router {
+            0
+        } else {
+            zenoh_shm::reclaim_reader(&self.reader)
+        }
+    }
+}
 
 // Traits
 trait MapShm {
-    fn map_to_shminfo(&mut self) -> ZResult<bool>;
+    fn map_to_shminfo(&mut self, reader: &ShmReaderId) -> ZResult<bool>;
     fn map_to_shmbuf(&mut self, shmr: &RwLock<SharedMemoryReader>) -> ZResult<bool>;
 }
 
 macro_rules! map_to_shminfo {
-    ($zbuf:expr, $ext_shm:expr) => {{
-        let res = map_zbuf_to_shminfo($zbuf)?;
+    ($zbuf:expr, $ext_shm:expr, $reader:expr) => {{
+        let res = map_zbuf_to_shminfo($zbuf, $reader)?;
         if res {
             *$ext_shm = Some(ShmType::new());
         }
@@ -56,11 +105,11 @@ macro_rules! map_to_shmbuf {
 
 // Impl - Put
 impl MapShm for Put {
-    fn map_to_shminfo(&mut self) -> ZResult<bool> {
+    fn map_to_shminfo(&mut self, reader: &ShmReaderId) -> ZResult<bool> {
         let Self {
             payload, ext_shm, ..
         } = self;
-        map_to_shminfo!(payload, ext_shm)
+        map_to_shminfo!(payload, ext_shm, reader)
     }
 
     fn map_to_shmbuf(&mut self, shmr: &RwLock<SharedMemoryReader>) -> ZResult<bool> {
@@ -73,7 +122,7 @@ impl MapShm for Put {
 
 // Impl - Query
 impl MapShm for Query {
-    fn map_to_shminfo(&mut self) -> ZResult<bool> {
+    fn map_to_shminfo(&mut self, reader: &ShmReaderId) -> ZResult<bool> {
         if let Self {
             ext_body: Some(QueryBodyType {
                 payload, ext_shm, ..
@@ -81,7 +130,7 @@ impl MapShm for Query {
             ..
         } = self
         {
-            map_to_shminfo!(payload, ext_shm)
+            map_to_shminfo!(payload, ext_shm, reader)
         } else {
             Ok(false)
         }
@@ -104,11 +153,11 @@ impl MapShm for Query {
 
 // Impl - Reply
 impl MapShm for Reply {
-    fn map_to_shminfo(&mut self) -> ZResult<bool> {
+    fn map_to_shminfo(&mut self, reader: &ShmReaderId) -> ZResult<bool> {
         let Self {
             payload, ext_shm, ..
         } = self;
-        map_to_shminfo!(payload, ext_shm)
+        map_to_shminfo!(payload, ext_shm, reader)
     }
 
     fn map_to_shmbuf(&mut self, shmr: &RwLock<SharedMemoryReader>) -> ZResult<bool> {
@@ -121,7 +170,7 @@ impl MapShm for Reply {
 
 // Impl - Err
 impl MapShm for Err {
-    fn map_to_shminfo(&mut self) -> ZResult<bool> {
+    fn map_to_shminfo(&mut self, reader: &ShmReaderId) -> ZResult<bool> {
         if let Self {
             ext_body: Some(ErrBodyType {
                 payload, ext_shm, ..
@@ -129,7 +178,7 @@ impl MapShm for Err {
             ..
         } = self
         {
-            map_to_shminfo!(payload, ext_shm)
+            map_to_shminfo!(payload, ext_shm, reader)
         } else {
             Ok(false)
         }
@@ -151,21 +200,21 @@ impl MapShm for Err {
 }
 
 // ShmBuf -> ShmInfo
-pub fn map_zmsg_to_shminfo(msg: &mut NetworkMessage) -> ZResult<bool> {
+pub fn map_zmsg_to_shminfo(msg: &mut NetworkMessage, reader: &ShmReaderId) -> ZResult<bool> {
     match &mut msg.body {
         NetworkBody::Push(Push { payload, .. }) => match payload {
-            PushBody::Put(b) => b.map_to_shminfo(),
+            PushBody::Put(b) => b.map_to_shminfo(reader),
             PushBody::Del(_) => Ok(false),
         },
         NetworkBody::Request(Request { payload, .. }) => match payload {
-            RequestBody::Query(b) => b.map_to_shminfo(),
-            RequestBody::Put(b) => b.map_to_shminfo(),
+            RequestBody::Query(b) => b.map_to_shminfo(reader),
+            RequestBody::Put(b) => b.map_to_shminfo(reader),
             RequestBody::Del(_) | RequestBody::Pull(_) => Ok(false),
         },
         NetworkBody::Response(Response { payload, .. }) => match payload {
-            ResponseBody::Reply(b) => b.map_to_shminfo(),
-            ResponseBody::Put(b) => b.map_to_shminfo(),
-            ResponseBody::Err(b) => b.map_to_shminfo(),
+            ResponseBody::Reply(b) => b.map_to_shminfo(reader),
+            ResponseBody::Put(b) => b.map_to_shminfo(reader),
+            ResponseBody::Err(b) => b.map_to_shminfo(reader),
             ResponseBody::Ack(_) => Ok(false),
         },
         NetworkBody::ResponseFinal(_) | NetworkBody::Declare(_) | NetworkBody::OAM(_) => Ok(false),
@@ -173,11 +222,11 @@ pub fn map_zmsg_to_shminfo(msg: &mut NetworkMessage) -> ZResult<bool> {
 }
 
 // Mapping
-pub fn map_zbuf_to_shminfo(zbuf: &mut ZBuf) -> ZResult<bool> {
+pub fn map_zbuf_to_shminfo(zbuf: &mut ZBuf, reader: &ShmReaderId) -> ZResult<bool> {
     let mut res = false;
     for zs in zbuf.zslices_mut() {
         if let Some(shmb) = zs.downcast_ref::<SharedMemoryBuf>() {
-            *zs = map_zslice_to_shminfo(shmb)?;
+            *zs = map_zslice_to_shminfo(shmb, reader)?;
             res = true;
         }
     }
@@ -186,7 +235,7 @@ pub fn map_zbuf_to_shminfo(zbuf: &mut ZBuf) -> ZResult<bool> {
 
 #[cold]
 #[inline(never)]
-pub fn map_zslice_to_shminfo(shmb: &SharedMemoryBuf) -> ZResult<ZSlice> {
+pub fn map_zslice_to_shminfo(shmb: &SharedMemoryBuf, reader: &ShmReaderId) -> ZResult<ZSlice> {
     // Serialize the shmb info
     let codec = Zenoh080::new();
     let mut info = vec![];
@@ -194,8 +243,8 @@ pub fn map_zslice_to_shminfo(shmb: &SharedMemoryBuf) -> ZResult<ZSlice> {
     codec
         .write(&mut writer, &shmb.info)
         .map_err(|e| zerror!("{:?}", e))?;
-    // Increase the reference count so to keep the SharedMemoryBuf valid
-    shmb.inc_ref_count();
+    // Increase the reference count on behalf of the reader so to keep the SharedMemoryBuf valid
+    shmb.lend(reader);
     // Replace the content of the slice
     let mut zslice: ZSlice = info.into();
     zslice.kind = ZSliceKind::ShmPtr;
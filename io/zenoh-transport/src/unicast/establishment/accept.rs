@@ -55,6 +55,7 @@ struct StateTransport {
     #[cfg(feature = "shared-memory")]
     ext_shm: ext::shm::StateAccept,
     ext_lowlatency: ext::lowlatency::StateAccept,
+    ext_patch: ext::patch::StateAccept,
 }
 
 #[cfg(any(feature = "transport_auth", feature = "transport_compression"))]
@@ -130,6 +131,7 @@ struct AcceptLink<'a> {
     #[cfg(feature = "transport_auth")]
     ext_auth: ext::auth::AuthFsm<'a>,
     ext_lowlatency: ext::lowlatency::LowLatencyFsm<'a>,
+    ext_patch: ext::patch::PatchFsm<'a>,
     #[cfg(feature = "transport_compression")]
     ext_compression: ext::compression::CompressionFsm<'a>,
 }
@@ -232,6 +234,12 @@ impl<'a, 'b: 'a> AcceptFsm for &'a mut AcceptLink<'b> {
             .await
             .map_err(|e| (e, Some(close::reason::GENERIC)))?;
 
+        // Extension Patch
+        self.ext_patch
+            .recv_init_syn((&mut state.transport.ext_patch, init_syn.ext_patch))
+            .await
+            .map_err(|e| (e, Some(close::reason::GENERIC)))?;
+
         // Extension Compression
         #[cfg(feature = "transport_compression")]
         self.ext_compression
@@ -301,6 +309,13 @@ impl<'a, 'b: 'a> AcceptFsm for &'a mut AcceptLink<'b> {
             .await
             .map_err(|e| (e, Some(close::reason::GENERIC)))?;
 
+        // Extension Patch
+        let ext_patch = self
+            .ext_patch
+            .send_init_ack(&state.transport.ext_patch)
+            .await
+            .map_err(|e| (e, Some(close::reason::GENERIC)))?;
+
         // Extension MultiLink
         let ext_compression = zcondfeat!(
             "transport_compression",
@@ -327,6 +342,7 @@ impl<'a, 'b: 'a> AcceptFsm for &'a mut AcceptLink<'b> {
             #[cfg(feature = "transport_auth")]
             ext_auth: state.link.ext_auth,
             ext_lowlatency: state.transport.ext_lowlatency,
+            ext_patch: state.transport.ext_patch,
             #[cfg(feature = "transport_compression")]
             ext_compression: state.link.ext_compression,
         };
@@ -360,6 +376,7 @@ impl<'a, 'b: 'a> AcceptFsm for &'a mut AcceptLink<'b> {
             ext_mlink,
             ext_lowlatency,
             ext_compression,
+            ext_patch,
         }
         .into();
 
@@ -444,6 +461,7 @@ impl<'a, 'b: 'a> AcceptFsm for &'a mut AcceptLink<'b> {
                 #[cfg(feature = "shared-memory")]
                 ext_shm: cookie.ext_shm,
                 ext_lowlatency: cookie.ext_lowlatency,
+                ext_patch: cookie.ext_patch,
             },
             #[cfg(any(feature = "transport_auth", feature = "transport_compression"))]
             link: StateLink {
@@ -611,6 +629,7 @@ pub(crate) async fn accept_link(link: LinkUnicast, manager: &TransportManager) -
         #[cfg(feature = "transport_auth")]
         ext_auth: manager.state.unicast.authenticator.fsm(&manager.prng),
         ext_lowlatency: ext::lowlatency::LowLatencyFsm::new(),
+        ext_patch: ext::patch::PatchFsm::new(),
         #[cfg(feature = "transport_compression")]
         ext_compression: ext::compression::CompressionFsm::new(),
     };
@@ -646,6 +665,7 @@ pub(crate) async fn accept_link(link: LinkUnicast, manager: &TransportManager) -
                 ext_lowlatency: ext::lowlatency::StateAccept::new(
                     manager.config.unicast.is_lowlatency,
                 ),
+                ext_patch: ext::patch::StateAccept::new(),
             },
             #[cfg(any(feature = "transport_auth", feature = "transport_compression"))]
             link: StateLink {
@@ -707,7 +727,10 @@ pub(crate) async fn accept_link(link: LinkUnicast, manager: &TransportManager) -
         multilink: state.transport.ext_mlink.multilink(),
         #[cfg(feature = "shared-memory")]
         is_shm: state.transport.ext_shm.is_shm(),
+        #[cfg(feature = "shared-memory")]
+        shm_peer_process: state.transport.ext_shm.peer_process(),
         is_lowlatency: state.transport.ext_lowlatency.is_lowlatency(),
+        patch: state.transport.ext_patch.patch(),
     };
 
     let a_config = TransportLinkUnicastConfig {
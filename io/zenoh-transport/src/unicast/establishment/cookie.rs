@@ -38,6 +38,7 @@ pub(crate) struct Cookie {
     #[cfg(feature = "transport_auth")]
     pub(crate) ext_auth: ext::auth::StateAccept,
     pub(crate) ext_lowlatency: ext::lowlatency::StateAccept,
+    pub(crate) ext_patch: ext::patch::StateAccept,
     #[cfg(feature = "transport_compression")]
     pub(crate) ext_compression: ext::compression::StateAccept,
 }
@@ -64,6 +65,7 @@ where
         #[cfg(feature = "transport_auth")]
         self.write(&mut *writer, &x.ext_auth)?;
         self.write(&mut *writer, &x.ext_lowlatency)?;
+        self.write(&mut *writer, &x.ext_patch)?;
         #[cfg(feature = "transport_compression")]
         self.write(&mut *writer, &x.ext_compression)?;
 
@@ -94,6 +96,7 @@ where
         #[cfg(feature = "transport_auth")]
         let ext_auth: ext::auth::StateAccept = self.read(&mut *reader)?;
         let ext_lowlatency: ext::lowlatency::StateAccept = self.read(&mut *reader)?;
+        let ext_patch: ext::patch::StateAccept = self.read(&mut *reader)?;
         #[cfg(feature = "transport_compression")]
         let ext_compression: ext::compression::StateAccept = self.read(&mut *reader)?;
 
@@ -111,6 +114,7 @@ where
             #[cfg(feature = "transport_auth")]
             ext_auth,
             ext_lowlatency,
+            ext_patch,
             #[cfg(feature = "transport_compression")]
             ext_compression,
         };
@@ -182,6 +186,7 @@ impl Cookie {
             #[cfg(feature = "transport_auth")]
             ext_auth: ext::auth::StateAccept::rand(),
             ext_lowlatency: ext::lowlatency::StateAccept::rand(),
+            ext_patch: ext::patch::StateAccept::rand(),
             #[cfg(feature = "transport_compression")]
             ext_compression: ext::compression::StateAccept::rand(),
         }
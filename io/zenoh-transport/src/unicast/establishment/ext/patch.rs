@@ -0,0 +1,198 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use crate::unicast::establishment::{AcceptFsm, OpenFsm};
+use async_trait::async_trait;
+use core::marker::PhantomData;
+use zenoh_buffers::{
+    reader::{DidntRead, Reader},
+    writer::{DidntWrite, Writer},
+};
+use zenoh_codec::{RCodec, WCodec, Zenoh080};
+use zenoh_protocol::transport::init;
+use zenoh_result::Error as ZError;
+
+// Extension Fsm
+pub(crate) struct PatchFsm<'a> {
+    _a: PhantomData<&'a ()>,
+}
+
+impl<'a> PatchFsm<'a> {
+    pub(crate) const fn new() -> Self {
+        Self { _a: PhantomData }
+    }
+}
+
+// The negotiated revision is the lowest of the two, the nodes without the extension being at revision 0
+fn negotiate(patch: u64, other_ext: Option<init::ext::Patch>) -> u64 {
+    patch.min(other_ext.map_or(0, |ext| ext.value))
+}
+
+/*************************************/
+/*              OPEN                 */
+/*************************************/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct StateOpen {
+    patch: u64,
+}
+
+impl StateOpen {
+    pub(crate) const fn new() -> Self {
+        Self {
+            patch: init::ext::PATCH_CURRENT,
+        }
+    }
+
+    pub(crate) const fn patch(&self) -> u64 {
+        self.patch
+    }
+}
+
+#[async_trait]
+impl<'a> OpenFsm for &'a PatchFsm<'a> {
+    type Error = ZError;
+
+    type SendInitSynIn = &'a StateOpen;
+    type SendInitSynOut = Option<init::ext::Patch>;
+    async fn send_init_syn(
+        self,
+        state: Self::SendInitSynIn,
+    ) -> Result<Self::SendInitSynOut, Self::Error> {
+        Ok(Some(init::ext::Patch::new(state.patch)))
+    }
+
+    type RecvInitAckIn = (&'a mut StateOpen, Option<init::ext::Patch>);
+    type RecvInitAckOut = ();
+    async fn recv_init_ack(
+        self,
+        input: Self::RecvInitAckIn,
+    ) -> Result<Self::RecvInitAckOut, Self::Error> {
+        let (state, other_ext) = input;
+        state.patch = negotiate(state.patch, other_ext);
+        Ok(())
+    }
+
+    type SendOpenSynIn = &'a StateOpen;
+    type SendOpenSynOut = ();
+    async fn send_open_syn(
+        self,
+        _state: Self::SendOpenSynIn,
+    ) -> Result<Self::SendOpenSynOut, Self::Error> {
+        Ok(())
+    }
+
+    type RecvOpenAckIn = &'a mut StateOpen;
+    type RecvOpenAckOut = ();
+    async fn recv_open_ack(
+        self,
+        _state: Self::RecvOpenAckIn,
+    ) -> Result<Self::RecvOpenAckOut, Self::Error> {
+        Ok(())
+    }
+}
+
+/*************************************/
+/*            ACCEPT                 */
+/*************************************/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct StateAccept {
+    patch: u64,
+}
+
+impl StateAccept {
+    pub(crate) const fn new() -> Self {
+        Self {
+            patch: init::ext::PATCH_CURRENT,
+        }
+    }
+
+    pub(crate) const fn patch(&self) -> u64 {
+        self.patch
+    }
+
+    #[cfg(test)]
+    pub(crate) fn rand() -> Self {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        Self {
+            patch: rng.gen_range(0..=init::ext::PATCH_CURRENT),
+        }
+    }
+}
+
+// Codec
+impl<W> WCodec<&StateAccept, &mut W> for Zenoh080
+where
+    W: Writer,
+{
+    type Output = Result<(), DidntWrite>;
+
+    fn write(self, writer: &mut W, x: &StateAccept) -> Self::Output {
+        self.write(&mut *writer, x.patch)
+    }
+}
+
+impl<R> RCodec<StateAccept, &mut R> for Zenoh080
+where
+    R: Reader,
+{
+    type Error = DidntRead;
+
+    fn read(self, reader: &mut R) -> Result<StateAccept, Self::Error> {
+        let patch: u64 = self.read(&mut *reader)?;
+        Ok(StateAccept { patch })
+    }
+}
+
+#[async_trait]
+impl<'a> AcceptFsm for &'a PatchFsm<'a> {
+    type Error = ZError;
+
+    type RecvInitSynIn = (&'a mut StateAccept, Option<init::ext::Patch>);
+    type RecvInitSynOut = ();
+    async fn recv_init_syn(
+        self,
+        input: Self::RecvInitSynIn,
+    ) -> Result<Self::RecvInitSynOut, Self::Error> {
+        let (state, other_ext) = input;
+        state.patch = negotiate(state.patch, other_ext);
+        Ok(())
+    }
+
+    type SendInitAckIn = &'a StateAccept;
+    type SendInitAckOut = Option<init::ext::Patch>;
+    async fn send_init_ack(
+        self,
+        state: Self::SendInitAckIn,
+    ) -> Result<Self::SendInitAckOut, Self::Error> {
+        Ok(Some(init::ext::Patch::new(state.patch)))
+    }
+
+    type RecvOpenSynIn = &'a mut StateAccept;
+    type RecvOpenSynOut = ();
+    async fn recv_open_syn(
+        self,
+        _state: Self::RecvOpenSynIn,
+    ) -> Result<Self::RecvOpenSynOut, Self::Error> {
+        Ok(())
+    }
+
+    type SendOpenAckIn = &'a StateAccept;
+    type SendOpenAckOut = ();
+    async fn send_open_ack(
+        self,
+        _state: Self::SendOpenAckIn,
+    ) -> Result<Self::SendOpenAckOut, Self::Error> {
+        Ok(())
+    }
+}
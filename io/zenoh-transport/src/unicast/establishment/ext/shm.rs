@@ -25,7 +25,7 @@ use zenoh_codec::{RCodec, WCodec, Zenoh080};
 use zenoh_core::zasyncwrite;
 use zenoh_protocol::transport::{init, open};
 use zenoh_result::{zerror, Error as ZError};
-use zenoh_shm::SharedMemoryBufInfo;
+use zenoh_shm::{segment_creator, SharedMemoryBufInfo};
 
 /*************************************/
 /*             InitSyn               */
@@ -139,16 +139,25 @@ impl<'a> ShmFsm<'a> {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) struct StateOpen {
     is_shm: bool,
+    // The pid of the process of the peer, as the creator of the segment of its challenge
+    peer_process: Option<u32>,
 }
 
 impl StateOpen {
     pub(crate) const fn new(is_shm: bool) -> Self {
-        Self { is_shm }
+        Self {
+            is_shm,
+            peer_process: None,
+        }
     }
 
     pub(crate) const fn is_shm(&self) -> bool {
         self.is_shm
     }
+
+    pub(crate) const fn peer_process(&self) -> Option<u32> {
+        self.peer_process
+    }
 }
 
 #[async_trait]
@@ -249,6 +258,7 @@ impl<'a> OpenFsm for &'a ShmFsm<'a> {
             }
         };
         let bob_challenge = u64::from_le_bytes(bytes);
+        state.peer_process = segment_creator(&init_ack.bob_info.shm_manager);
 
         Ok(bob_challenge)
     }
@@ -305,22 +315,34 @@ impl<'a> OpenFsm for &'a ShmFsm<'a> {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) struct StateAccept {
     is_shm: bool,
+    // The pid of the process of the peer, as the creator of the segment of its challenge
+    peer_process: Option<u32>,
 }
 
 impl StateAccept {
     pub(crate) const fn new(is_shm: bool) -> Self {
-        Self { is_shm }
+        Self {
+            is_shm,
+            peer_process: None,
+        }
     }
 
     pub(crate) const fn is_shm(&self) -> bool {
         self.is_shm
     }
 
+    pub(crate) const fn peer_process(&self) -> Option<u32> {
+        self.peer_process
+    }
+
     #[cfg(test)]
     pub(crate) fn rand() -> Self {
         use rand::Rng;
         let mut rng = rand::thread_rng();
-        Self::new(rng.gen_bool(0.5))
+        Self {
+            is_shm: rng.gen_bool(0.5),
+            peer_process: rng.gen_bool(0.5).then(|| rng.gen()),
+        }
     }
 }
 
@@ -334,6 +356,9 @@ where
     fn write(self, writer: &mut W, x: &StateAccept) -> Self::Output {
         let is_shm = u8::from(x.is_shm);
         self.write(&mut *writer, is_shm)?;
+        // 0 if the process of the peer is unknown, its pid + 1 otherwise
+        let peer_process = x.peer_process.map_or(0, |pid| u64::from(pid) + 1);
+        self.write(&mut *writer, peer_process)?;
         Ok(())
     }
 }
@@ -347,7 +372,15 @@ where
     fn read(self, reader: &mut R) -> Result<StateAccept, Self::Error> {
         let is_shm: u8 = self.read(&mut *reader)?;
         let is_shm = is_shm == 1;
-        Ok(StateAccept { is_shm })
+        let peer_process: u64 = self.read(&mut *reader)?;
+        let peer_process = match peer_process {
+            0 => None,
+            pid => Some(u32::try_from(pid - 1).map_err(|_| DidntRead)?),
+        };
+        Ok(StateAccept {
+            is_shm,
+            peer_process,
+        })
     }
 }
 
@@ -402,6 +435,7 @@ impl<'a> AcceptFsm for &'a ShmFsm<'a> {
             }
         };
         let alice_challenge = u64::from_le_bytes(bytes);
+        state.peer_process = segment_creator(&init_syn.alice_info.shm_manager);
 
         Ok(alice_challenge)
     }
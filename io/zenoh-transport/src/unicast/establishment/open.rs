@@ -52,6 +52,7 @@ struct StateTransport {
     #[cfg(feature = "shared-memory")]
     ext_shm: ext::shm::StateOpen,
     ext_lowlatency: ext::lowlatency::StateOpen,
+    ext_patch: ext::patch::StateOpen,
 }
 
 #[cfg(any(feature = "transport_auth", feature = "transport_compression"))]
@@ -114,6 +115,7 @@ struct OpenLink<'a> {
     #[cfg(feature = "transport_auth")]
     ext_auth: ext::auth::AuthFsm<'a>,
     ext_lowlatency: ext::lowlatency::LowLatencyFsm<'a>,
+    ext_patch: ext::patch::PatchFsm<'a>,
     #[cfg(feature = "transport_compression")]
     ext_compression: ext::compression::CompressionFsm<'a>,
 }
@@ -174,6 +176,13 @@ impl<'a, 'b: 'a> OpenFsm for &'a mut OpenLink<'b> {
             .await
             .map_err(|e| (e, Some(close::reason::GENERIC)))?;
 
+        // Extension Patch
+        let ext_patch = self
+            .ext_patch
+            .send_init_syn(&state.transport.ext_patch)
+            .await
+            .map_err(|e| (e, Some(close::reason::GENERIC)))?;
+
         // Extension Compression
         let ext_compression = zcondfeat!(
             "transport_compression",
@@ -196,6 +205,7 @@ impl<'a, 'b: 'a> OpenFsm for &'a mut OpenLink<'b> {
             ext_mlink,
             ext_lowlatency,
             ext_compression,
+            ext_patch,
         }
         .into();
 
@@ -321,6 +331,12 @@ impl<'a, 'b: 'a> OpenFsm for &'a mut OpenLink<'b> {
             .await
             .map_err(|e| (e, Some(close::reason::GENERIC)))?;
 
+        // Extension Patch
+        self.ext_patch
+            .recv_init_ack((&mut state.transport.ext_patch, init_ack.ext_patch))
+            .await
+            .map_err(|e| (e, Some(close::reason::GENERIC)))?;
+
         // Extension Compression
         #[cfg(feature = "transport_compression")]
         self.ext_compression
@@ -535,6 +551,7 @@ pub(crate) async fn open_link(
         #[cfg(feature = "transport_auth")]
         ext_auth: manager.state.unicast.authenticator.fsm(&manager.prng),
         ext_lowlatency: ext::lowlatency::LowLatencyFsm::new(),
+        ext_patch: ext::patch::PatchFsm::new(),
         #[cfg(feature = "transport_compression")]
         ext_compression: ext::compression::CompressionFsm::new(),
     };
@@ -558,6 +575,7 @@ pub(crate) async fn open_link(
             ext_shm: ext::shm::StateOpen::new(manager.config.unicast.is_shm),
 
             ext_lowlatency: ext::lowlatency::StateOpen::new(manager.config.unicast.is_lowlatency),
+            ext_patch: ext::patch::StateOpen::new(),
         },
         #[cfg(any(feature = "transport_auth", feature = "transport_compression"))]
         link: StateLink {
@@ -620,7 +638,10 @@ pub(crate) async fn open_link(
         multilink: state.transport.ext_mlink.multilink(),
         #[cfg(feature = "shared-memory")]
         is_shm: state.transport.ext_shm.is_shm(),
+        #[cfg(feature = "shared-memory")]
+        shm_peer_process: state.transport.ext_shm.peer_process(),
         is_lowlatency: state.transport.ext_lowlatency.is_lowlatency(),
+        patch: state.transport.ext_patch.patch(),
     };
 
     let o_config = TransportLinkUnicastConfig {
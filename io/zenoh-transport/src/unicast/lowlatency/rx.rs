@@ -35,8 +35,11 @@ impl TransportUnicastLowlatency {
         if let Some(callback) = callback.as_ref() {
             #[cfg(feature = "shared-memory")]
             {
-                if self.config.is_shm {
-                    crate::shm::map_zmsg_to_shmbuf(&mut msg, &self.manager.shm().reader)?;
+                if self.config.is_shm
+                    && crate::shm::map_zmsg_to_shmbuf(&mut msg, &self.manager.shm().reader)?
+                {
+                    #[cfg(feature = "stats")]
+                    self.stats.inc_rx_shm_msgs(1);
                 }
             }
             callback.handle_message(msg)
@@ -71,6 +74,9 @@ impl TransportUnicastLowlatency {
 
             match msg.body {
                 zenoh_protocol::transport::TransportBodyLowLatency::Close(_) => {
+                    // The peer closes the transport, it releases the shared memory buffers on its own
+                    #[cfg(feature = "shared-memory")]
+                    self.shm_lender.set_closed();
                     let _ = self.delete().await;
                 }
                 zenoh_protocol::transport::TransportBodyLowLatency::KeepAlive(_) => {}
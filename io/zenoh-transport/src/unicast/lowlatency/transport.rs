@@ -11,6 +11,8 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
+#[cfg(feature = "shared-memory")]
+use crate::shm::ShmLender;
 #[cfg(feature = "stats")]
 use crate::stats::TransportStats;
 use crate::{
@@ -57,6 +59,9 @@ pub(crate) struct TransportUnicastLowlatency {
     // Transport statistics
     #[cfg(feature = "stats")]
     pub(super) stats: Arc<TransportStats>,
+    // The shared memory buffers lent to the peer
+    #[cfg(feature = "shared-memory")]
+    pub(super) shm_lender: Arc<ShmLender>,
 
     // The handles for TX/RX tasks
     pub(crate) token: CancellationToken,
@@ -70,6 +75,8 @@ impl TransportUnicastLowlatency {
     ) -> Arc<dyn TransportUnicastTrait> {
         #[cfg(feature = "stats")]
         let stats = Arc::new(TransportStats::new(Some(manager.get_stats().clone())));
+        #[cfg(feature = "shared-memory")]
+        let shm_lender = Arc::new(ShmLender::new(&config));
         Arc::new(TransportUnicastLowlatency {
             manager,
             config,
@@ -78,6 +85,8 @@ impl TransportUnicastLowlatency {
             alive: Arc::new(AsyncMutex::new(false)),
             #[cfg(feature = "stats")]
             stats,
+            #[cfg(feature = "shared-memory")]
+            shm_lender,
             token: CancellationToken::new(),
             tracker: TaskTracker::new(),
         }) as Arc<dyn TransportUnicastTrait>
@@ -137,6 +146,14 @@ impl TransportUnicastLowlatency {
             let _ = val.close(Some(close::reason::GENERIC)).await;
         }
 
+        // Reclaim the shared memory buffers the peer did not release if it failed
+        #[cfg(feature = "shared-memory")]
+        if self.config.is_shm {
+            let _reclaimed = self.shm_lender.reclaim_on_failure();
+            #[cfg(feature = "stats")]
+            self.stats.inc_shm_reclaimed(_reclaimed);
+        }
+
         // Notify the callback that we have closed the transport
         if let Some(cb) = callback.as_ref() {
             cb.closed();
@@ -264,6 +281,8 @@ impl TransportUnicastTrait for TransportUnicastLowlatency {
     /*************************************/
     async fn close(&self, reason: u8) -> ZResult<()> {
         tracing::trace!("Closing transport with peer: {}", self.config.zid);
+        #[cfg(feature = "shared-memory")]
+        self.shm_lender.set_closed();
         self.finalize(reason).await
     }
 }
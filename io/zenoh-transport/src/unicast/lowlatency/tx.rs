@@ -28,12 +28,15 @@ impl TransportUnicastLowlatency {
         #[cfg(feature = "shared-memory")]
         {
             let res = if self.config.is_shm {
-                crate::shm::map_zmsg_to_shminfo(&mut msg)
+                crate::shm::map_zmsg_to_shminfo(&mut msg, self.shm_lender.reader())
             } else {
                 crate::shm::map_zmsg_to_shmbuf(&mut msg, &self.manager.shm().reader)
             };
-            if let Err(e) = res {
-                bail!("Failed SHM conversion: {}", e);
+            match res {
+                #[cfg(feature = "stats")]
+                Ok(true) if self.config.is_shm => self.stats.inc_tx_shm_msgs(1),
+                Ok(_) => {}
+                Err(e) => bail!("Failed SHM conversion: {}", e),
             }
         }
 
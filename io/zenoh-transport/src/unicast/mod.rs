@@ -55,7 +55,10 @@ pub(crate) struct TransportConfigUnicast {
     pub(crate) multilink: Option<ZPublicKey>,
     #[cfg(feature = "shared-memory")]
     pub(crate) is_shm: bool,
+    #[cfg(feature = "shared-memory")]
+    pub(crate) shm_peer_process: Option<u32>,
     pub(crate) is_lowlatency: bool,
+    pub(crate) patch: u64,
 }
 
 /// [`TransportUnicast`] is the transport handler returned
@@ -90,6 +93,13 @@ impl TransportUnicast {
         Ok(transport.is_shm())
     }
 
+    /// Returns the revision of the protocol negotiated with the peer (see `init::ext::Patch`).
+    #[inline(always)]
+    pub fn get_patch(&self) -> ZResult<u64> {
+        let transport = self.get_inner()?;
+        Ok(transport.get_config().patch)
+    }
+
     #[inline(always)]
     pub fn get_callback(&self) -> ZResult<Option<Arc<dyn TransportPeerEventHandler>>> {
         let transport = self.get_inner()?;
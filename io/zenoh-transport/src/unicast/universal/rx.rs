@@ -42,14 +42,23 @@ impl TransportUnicastUniversal {
     ) -> ZResult<()> {
         #[cfg(feature = "shared-memory")]
         {
-            if self.config.is_shm {
-                crate::shm::map_zmsg_to_shmbuf(&mut msg, &self.manager.state.unicast.shm.reader)?;
+            if self.config.is_shm
+                && crate::shm::map_zmsg_to_shmbuf(&mut msg, &self.manager.state.unicast.shm.reader)?
+            {
+                #[cfg(feature = "stats")]
+                self.stats.inc_rx_shm_msgs(1);
             }
         }
         callback.handle_message(msg)
     }
 
     fn handle_close(&self, link: &Link, _reason: u8, session: bool) -> ZResult<()> {
+        // The peer closes the transport, it releases the shared memory buffers on its own
+        #[cfg(feature = "shared-memory")]
+        if session || zread!(self.links).len() == 1 {
+            self.shm_lender.set_closed();
+        }
+
         // Delete and clean up
         let c_transport = self.clone();
         let c_link = link.clone();
@@ -11,6 +11,8 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
+#[cfg(feature = "shared-memory")]
+use crate::shm::ShmLender;
 #[cfg(feature = "stats")]
 use crate::stats::TransportStats;
 use crate::{
@@ -68,6 +70,9 @@ pub(crate) struct TransportUnicastUniversal {
     // Transport statistics
     #[cfg(feature = "stats")]
     pub(super) stats: Arc<TransportStats>,
+    // The shared memory buffers lent to the peer
+    #[cfg(feature = "shared-memory")]
+    pub(super) shm_lender: Arc<ShmLender>,
 }
 
 impl TransportUnicastUniversal {
@@ -101,6 +106,9 @@ impl TransportUnicastUniversal {
         #[cfg(feature = "stats")]
         let stats = Arc::new(TransportStats::new(Some(manager.get_stats().clone())));
 
+        #[cfg(feature = "shared-memory")]
+        let shm_lender = Arc::new(ShmLender::new(&config));
+
         let t = Arc::new(TransportUnicastUniversal {
             manager,
             config,
@@ -112,6 +120,8 @@ impl TransportUnicastUniversal {
             alive: Arc::new(AsyncMutex::new(false)),
             #[cfg(feature = "stats")]
             stats,
+            #[cfg(feature = "shared-memory")]
+            shm_lender,
         });
 
         Ok(t)
@@ -152,6 +162,14 @@ impl TransportUnicastUniversal {
             let _ = l.close().await;
         }
 
+        // Reclaim the shared memory buffers the peer did not release if it failed
+        #[cfg(feature = "shared-memory")]
+        if self.config.is_shm {
+            let _reclaimed = self.shm_lender.reclaim_on_failure();
+            #[cfg(feature = "stats")]
+            self.stats.inc_shm_reclaimed(_reclaimed);
+        }
+
         // Notify the callback that we have closed the transport
         if let Some(cb) = callback.as_ref() {
             cb.closed();
@@ -352,6 +370,9 @@ impl TransportUnicastTrait for TransportUnicastUniversal {
     async fn close(&self, reason: u8) -> ZResult<()> {
         tracing::trace!("Closing transport with peer: {}", self.config.zid);
 
+        #[cfg(feature = "shared-memory")]
+        self.shm_lender.set_closed();
+
         let mut pipelines = zread!(self.links)
             .iter()
             .map(|sl| sl.pipeline.clone())
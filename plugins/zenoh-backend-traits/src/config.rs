@@ -28,6 +28,7 @@ pub struct PluginConfig {
     #[schemars(with = "Option<bool>")]
     pub required: bool,
     pub backend_search_dirs: Option<Vec<String>>,
+    pub snapshots_dir: Option<String>,
     #[schemars(with = "Map<String, Value>")]
     pub volumes: Vec<VolumeConfig>,
     #[schemars(with = "Map<String, Value>")]
@@ -58,6 +59,9 @@ pub struct StorageConfig {
     pub volume_id: String,
     pub volume_cfg: Value,
     pub garbage_collection_config: GarbageCollectionConfig,
+    // Note: CacheConfig is optional. A cache is maintained only if it is configured
+    pub cache_config: Option<CacheConfig>,
+    pub batch_config: BatchConfig,
     // Note: ReplicaConfig is optional. Alignment will be performed only if it is a replica
     pub replica_config: Option<ReplicaConfig>,
 }
@@ -67,6 +71,24 @@ pub struct ReplicaConfig {
     pub publication_interval: Duration,
     pub propagation_delay: Duration,
     pub delta: Duration,
+    pub alignment: AlignmentStrategy,
+}
+
+// The anti-entropy protocol used by replicas to detect and repair divergences
+#[derive(JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlignmentStrategy {
+    // Digests of the log split into Hot/Warm/Cold eras of fixed time intervals
+    #[default]
+    Era,
+    // Key-space Merkle tree with 16^depth leaves, repaired range by range
+    MerkleTree {
+        depth: usize,
+    },
+}
+
+impl AlignmentStrategy {
+    pub const DEFAULT_MERKLE_DEPTH: usize = 4;
+    pub const MAX_MERKLE_DEPTH: usize = 6;
 }
 
 impl StructVersion for VolumeConfig {
@@ -94,6 +116,8 @@ impl Default for ReplicaConfig {
             // Higher the frequency of updates, lower the delta should be chosen
             // To be efficient, delta should be the time containing no more than 100,000 samples
             delta: Duration::from_millis(1000),
+            // The era based alignment is kept as default for compatibility with existing deployments
+            alignment: AlignmentStrategy::default(),
         }
     }
 }
@@ -117,6 +141,49 @@ impl Default for GarbageCollectionConfig {
     }
 }
 
+// The configuration of the cache of latest values maintained by the storage manager
+// in front of the storages whose backend announces a high `read_cost`
+#[derive(JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub struct CacheConfig {
+    // The cache is enabled if the `read_cost` of the storage is strictly greater than this threshold
+    pub read_cost_threshold: u32,
+    // The maximum number of keys kept in the cache, the least recently used ones are evicted first
+    pub capacity: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            read_cost_threshold: 0,
+            capacity: 1024,
+        }
+    }
+}
+
+// The configuration of the coalescing of incoming samples into batches of writes to the storage
+#[derive(JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub struct BatchConfig {
+    // The maximum number of writes in a batch, the samples are written one by one if set to 1
+    pub max_size: usize,
+    // The maximum duration a sample can wait in a batch before being written to the storage
+    pub max_delay: Duration,
+}
+
+impl BatchConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.max_size > 1
+    }
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 1,
+            max_delay: Duration::from_millis(10),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ConfigDiff {
     DeleteVolume(VolumeConfig),
@@ -170,6 +237,14 @@ impl<S: Into<String> + AsRef<str>, V: AsObject> TryFrom<(S, &V)> for PluginConfi
             None => None,
             _ => bail!("`backend_search_dirs` field of {}'s configuration must be a string or array of strings", name.as_ref())
         };
+        let snapshots_dir = match value.get("snapshots_dir") {
+            Some(serde_json::Value::String(path)) => Some(path.clone()),
+            None => None,
+            _ => bail!(
+                "`snapshots_dir` field of {}'s configuration must be a string",
+                name.as_ref()
+            ),
+        };
         let volumes = match value.get("volumes") {
             Some(configs) => VolumeConfig::try_from(name.as_ref(), configs)?,
             None => Vec::new(),
@@ -196,13 +271,20 @@ impl<S: Into<String> + AsRef<str>, V: AsObject> TryFrom<(S, &V)> for PluginConfi
             name: name.into(),
             required,
             backend_search_dirs,
+            snapshots_dir,
             volumes,
             storages,
             rest: value
                 .into_iter()
                 .filter(|&(k, _v)| {
-                    !["__required__", "backend_search_dirs", "volumes", "storages"]
-                        .contains(&k.as_str())
+                    ![
+                        "__required__",
+                        "backend_search_dirs",
+                        "snapshots_dir",
+                        "volumes",
+                        "storages",
+                    ]
+                    .contains(&k.as_str())
                 })
                 .map(|(k, v)| (k.clone(), v.clone()))
                 .collect(),
@@ -447,6 +529,51 @@ impl StorageConfig {
             }
             None => GarbageCollectionConfig::default(),
         };
+        let cache_config = match config.get("cache") {
+            Some(s) => {
+                let mut cache_config = CacheConfig::default();
+                if let Some(threshold) = s.get("read_cost_threshold") {
+                    let threshold = threshold.to_string().parse::<u32>();
+                    if let Ok(threshold) = threshold {
+                        cache_config.read_cost_threshold = threshold
+                    } else {
+                        bail!("Invalid type for field `read_cost_threshold` in `cache` of storage `{}`. Only integer values are accepted.", plugin_name)
+                    }
+                }
+                if let Some(capacity) = s.get("capacity") {
+                    let capacity = capacity.to_string().parse::<usize>();
+                    if let Ok(capacity) = capacity {
+                        cache_config.capacity = capacity
+                    } else {
+                        bail!("Invalid type for field `capacity` in `cache` of storage `{}`. Only integer values are accepted.", plugin_name)
+                    }
+                }
+                Some(cache_config)
+            }
+            None => None,
+        };
+        let batch_config = match config.get("batch") {
+            Some(s) => {
+                let mut batch_config = BatchConfig::default();
+                if let Some(max_size) = s.get("max_size") {
+                    let max_size = max_size.to_string().parse::<usize>();
+                    match max_size {
+                        Ok(max_size) if max_size > 0 => batch_config.max_size = max_size,
+                        _ => bail!("Invalid value for field `max_size` in `batch` of storage `{}`. Only strictly positive integer values are accepted.", plugin_name),
+                    }
+                }
+                if let Some(max_delay) = s.get("max_delay") {
+                    let max_delay = max_delay.to_string().parse::<u64>();
+                    if let Ok(max_delay) = max_delay {
+                        batch_config.max_delay = Duration::from_millis(max_delay)
+                    } else {
+                        bail!("Invalid type for field `max_delay` in `batch` of storage `{}`. Only integer values are accepted.", plugin_name)
+                    }
+                }
+                batch_config
+            }
+            None => BatchConfig::default(),
+        };
         let replica_config = match config.get("replica_config") {
             Some(s) => {
                 let mut replica_config = ReplicaConfig::default();
@@ -475,6 +602,23 @@ impl StorageConfig {
                         bail!("Invalid type for field `delta` in `replica_config` of storage `{}`. Only integer values are accepted.", plugin_name)
                     }
                 }
+                let depth = match s.get("merkle_depth") {
+                    Some(d) => match d.to_string().parse::<usize>() {
+                        Ok(d) if (1..=AlignmentStrategy::MAX_MERKLE_DEPTH).contains(&d) => d,
+                        _ => bail!("Invalid value for field `merkle_depth` in `replica_config` of storage `{}`. Only integer values between 1 and {} are accepted.", plugin_name, AlignmentStrategy::MAX_MERKLE_DEPTH),
+                    },
+                    None => AlignmentStrategy::DEFAULT_MERKLE_DEPTH,
+                };
+                match s.get("alignment") {
+                    Some(Value::String(a)) if a == "era" => {
+                        replica_config.alignment = AlignmentStrategy::Era
+                    }
+                    Some(Value::String(a)) if a == "merkle_tree" => {
+                        replica_config.alignment = AlignmentStrategy::MerkleTree { depth }
+                    }
+                    Some(_) => bail!("Invalid value for field `alignment` in `replica_config` of storage `{}`. Accepted values: [\"era\", \"merkle_tree\"]", plugin_name),
+                    None => {}
+                }
                 Some(replica_config)
             }
             None => None,
@@ -487,6 +631,8 @@ impl StorageConfig {
             volume_id,
             volume_cfg,
             garbage_collection_config,
+            cache_config,
+            batch_config,
             replica_config,
         })
     }
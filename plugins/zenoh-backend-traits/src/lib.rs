@@ -158,8 +158,9 @@ pub struct Capability {
     pub persistence: Persistence,
     pub history: History,
     /// `read_cost` is a parameter that hels the storage manager take a decision on optimizing database roundtrips
-    /// If the `read_cost` is higher than a given threshold, the storage manger will maintain a cache with the keys present in the database
-    /// This is a placeholder, not actually utilised in the current implementation
+    /// If a `cache` is configured for the storage and the `read_cost` is higher than its `read_cost_threshold` (0 by default),
+    /// the storage manager will maintain a bounded cache of the latest values of the keys present in the database.
+    /// The cache is only used for storages with `History::Latest`.
     pub read_cost: u32,
 }
 
@@ -196,6 +197,21 @@ pub struct StoredData {
     pub timestamp: Timestamp,
 }
 
+/// A write of a batch passed to [`Storage::write_batch`].
+/// As for [`Storage::put`] and [`Storage::delete`], a key can be `None` if it matches the `strip_prefix` exactly.
+#[derive(Debug, Clone)]
+pub enum StorageWrite {
+    Put {
+        key: Option<OwnedKeyExpr>,
+        value: Value,
+        timestamp: Timestamp,
+    },
+    Delete {
+        key: Option<OwnedKeyExpr>,
+        timestamp: Timestamp,
+    },
+}
+
 /// Trait to be implemented by a Backend.
 ///
 #[async_trait]
@@ -266,6 +282,29 @@ pub trait Storage: Send + Sync {
         timestamp: Timestamp,
     ) -> ZResult<StorageInsertionResult>;
 
+    /// Function called for a batch of puts and deletes coalesced by the storage manager,
+    /// in the order they have to be applied. It must return one result per write, in the same order.
+    /// A backend supporting transactions should override it to apply the whole batch in a single transaction.
+    /// The default implementation calls [`Storage::put`] and [`Storage::delete`] for each write.
+    async fn write_batch(
+        &mut self,
+        writes: Vec<StorageWrite>,
+    ) -> Vec<ZResult<StorageInsertionResult>> {
+        let mut results = Vec::with_capacity(writes.len());
+        for write in writes {
+            let result = match write {
+                StorageWrite::Put {
+                    key,
+                    value,
+                    timestamp,
+                } => self.put(key, value, timestamp).await,
+                StorageWrite::Delete { key, timestamp } => self.delete(key, timestamp).await,
+            };
+            results.push(result);
+        }
+        results
+    }
+
     /// Function to retrieve the sample associated with a single key.
     /// A key can be `None` if it matches the `strip_prefix` exactly.
     /// In order to avoid data loss, the storage must retrieve the `value` and `timestamp` associated with the `None` key
@@ -0,0 +1,370 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+// Authentication of the HTTP clients, and enforcement of the `access_control` policy of the router
+// on their requests. An authenticated user is mapped to an ACL subject (i.e. an interface name in the
+// rules of the router) through the `acl_subjects` of the auth config. The requests of the users
+// without subject, or of unauthenticated clients, are only subject to the rules without interfaces.
+// The policy is enforced whether the authentication is enabled or not, as it is on the other interfaces
+// of the router: without authentication, the rules with interfaces never apply to REST requests.
+
+use crate::config::AuthConfig;
+use crate::{openapi, path_to_key_expr, request_mime, response, websocket, Config};
+use base64::{engine::general_purpose::STANDARD as b64_std_engine, Engine};
+use http_types::Method;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tide::{Middleware, Next, Request, Response, StatusCode};
+use zenoh::config::{AclConfig, AclConfigRules, Action, InterceptorFlow, Permission};
+use zenoh::prelude::keyexpr;
+use zenoh::Session;
+use zenoh_result::{zerror, ZResult};
+
+const REALM: &str = "zenoh";
+
+// SHA-256 fingerprint of the certificate presented by an HTTPS client, set by the HTTPS listener
+#[derive(Clone, Debug)]
+pub(crate) struct ClientCertificate(pub(crate) String);
+
+// The rules of the `access_control` config of the router that apply to the ingress flow
+pub(crate) struct AccessPolicy {
+    enabled: bool,
+    default_permission: Permission,
+    rules: Vec<AclConfigRules>,
+}
+
+impl AccessPolicy {
+    pub(crate) fn new(config: &AclConfig) -> Self {
+        let rules = config
+            .rules()
+            .iter()
+            .flatten()
+            .filter(|rule| {
+                rule.flows.as_ref().map_or(true, |flows| {
+                    flows.iter().any(|f| matches!(f, InterceptorFlow::Ingress))
+                })
+            })
+            .cloned()
+            .collect();
+        AccessPolicy {
+            enabled: *config.enabled(),
+            default_permission: *config.default_permission(),
+            rules,
+        }
+    }
+
+    // Same decision as the policy enforcer of the router: a matching deny rule takes precedence,
+    // then the default permission, then the matching allow rules
+    pub(crate) fn permits(
+        &self,
+        subject: Option<&str>,
+        action: Action,
+        key_expr: &keyexpr,
+    ) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let matches = |permission: Permission| {
+            self.rules.iter().any(|rule| {
+                rule.permission == permission
+                    && rule.actions.contains(&action)
+                    && rule.interfaces.as_ref().map_or(true, |interfaces| {
+                        subject.map_or(false, |s| interfaces.iter().any(|i| i == s))
+                    })
+                    && rule.key_exprs.iter().any(|ke| {
+                        keyexpr::new(ke.as_str()).map_or(false, |ke| ke.includes(key_expr))
+                    })
+            })
+        };
+        if matches(Permission::Deny) {
+            false
+        } else if self.default_permission == Permission::Allow {
+            true
+        } else {
+            matches(Permission::Allow)
+        }
+    }
+}
+
+// Permissions of the client of a request, passed to the handlers as an extension of the request
+#[derive(Clone)]
+pub(crate) struct Permissions {
+    policy: Arc<AccessPolicy>,
+    subject: Option<String>,
+}
+
+impl Permissions {
+    // An invalid key expression is denied, whatever the session would do with it
+    pub(crate) fn allows(&self, action: Action, key_expr: &str) -> bool {
+        match keyexpr::new(key_expr) {
+            Ok(key_expr) => self
+                .policy
+                .permits(self.subject.as_deref(), action, key_expr),
+            Err(_) => false,
+        }
+    }
+}
+
+struct Authenticator {
+    // passwords of the users authenticated with Basic authentication, indexed by user
+    passwords: HashMap<String, String>,
+    // users authenticated with Bearer tokens, indexed by token, only looked up in constant time
+    tokens: HashMap<String, String>,
+    certificates: HashMap<String, String>,
+}
+
+impl Authenticator {
+    fn new(config: &AuthConfig, router_dictionary: Option<&str>) -> ZResult<Self> {
+        let passwords = match config.dictionary_file.as_deref().or(router_dictionary) {
+            Some(file) => {
+                let content = std::fs::read_to_string(file).map_err(|e| {
+                    zerror!("Invalid user-password dictionary file '{}': {}", file, e)
+                })?;
+                parse_dictionary(&content)?
+            }
+            None => HashMap::new(),
+        };
+        Ok(Authenticator {
+            passwords,
+            tokens: config.tokens.clone(),
+            certificates: config.certificates.clone(),
+        })
+    }
+
+    // The user authenticated by the client certificate or the `Authorization` header of a request
+    fn authenticate<State>(&self, req: &Request<State>) -> Option<String> {
+        if let Some(ClientCertificate(fingerprint)) = req.ext::<ClientCertificate>() {
+            if let Some(user) = self.certificates.get(fingerprint) {
+                return Some(user.clone());
+            }
+        }
+        let authorization = req.header("authorization")?.as_str();
+        let (scheme, credentials) = authorization.split_once(' ')?;
+        if scheme.eq_ignore_ascii_case("basic") {
+            let credentials = b64_std_engine.decode(credentials.trim()).ok()?;
+            let credentials = String::from_utf8(credentials).ok()?;
+            let (user, password) = credentials.split_once(':')?;
+            match self.passwords.get(user) {
+                Some(p) if constant_time_eq(p.as_bytes(), password.as_bytes()) => {
+                    Some(user.to_string())
+                }
+                _ => None,
+            }
+        } else if scheme.eq_ignore_ascii_case("bearer") {
+            self.token_user(credentials.trim())
+        } else {
+            None
+        }
+    }
+
+    // The user of the given Bearer token, compared with all the tokens not to reveal which one is closest
+    fn token_user(&self, token: &str) -> Option<String> {
+        let mut user = None;
+        for (t, u) in self.tokens.iter() {
+            if constant_time_eq(t.as_bytes(), token.as_bytes()) {
+                user = Some(u);
+            }
+        }
+        user.cloned()
+    }
+
+    fn challenge(&self) -> Response {
+        let scheme = if self.passwords.is_empty() {
+            "Bearer"
+        } else {
+            "Basic"
+        };
+        let mut res = response(StatusCode::Unauthorized, "text/plain", "Unauthorized");
+        res.insert_header("WWW-Authenticate", format!(r#"{scheme} realm="{REALM}""#));
+        res
+    }
+}
+
+// Compares two secrets in a time that does not depend on the position of their first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
+
+// Parses a dictionary with one `<user>:<password>` entry per line, as the usrpwd authentication of the transport
+fn parse_dictionary(content: &str) -> ZResult<HashMap<String, String>> {
+    let mut passwords = HashMap::new();
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let (user, password) = line
+            .split_once(':')
+            .ok_or_else(|| zerror!("Invalid user-password dictionary file: invalid format."))?;
+        passwords.insert(user.trim().to_string(), password.trim().to_string());
+    }
+    Ok(passwords)
+}
+
+// The action of a REST request in the ACL, and the key expression it applies to
+fn request_action<State>(req: &Request<State>, zid: &str) -> Option<(Action, String)> {
+    let action = match req.method() {
+        Method::Get | Method::Post if request_mime(req) == "text/event-stream" => {
+            Action::DeclareSubscriber
+        }
+        Method::Get | Method::Post => Action::Get,
+        Method::Put | Method::Patch | Method::Delete => Action::Put,
+        _ => return None,
+    };
+    let key_expr = path_to_key_expr(req.url().path(), zid).ok()?;
+    Some((action, key_expr.to_string()))
+}
+
+// Middleware authenticating the clients, and checking the permissions of their requests.
+// The messages received on a WebSocket are checked by the WebSocket connection,
+// and the OpenAPI description is available to any authenticated client.
+pub(crate) struct AccessControl {
+    authenticator: Option<Authenticator>,
+    policy: Arc<AccessPolicy>,
+    subjects: HashMap<String, String>,
+}
+
+impl AccessControl {
+    pub(crate) fn new(
+        config: Option<&AuthConfig>,
+        router_dictionary: Option<&str>,
+        acl: &AclConfig,
+    ) -> ZResult<Self> {
+        Ok(AccessControl {
+            authenticator: config
+                .map(|c| Authenticator::new(c, router_dictionary))
+                .transpose()?,
+            policy: Arc::new(AccessPolicy::new(acl)),
+            subjects: config.map(|c| c.acl_subjects.clone()).unwrap_or_default(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware<(Arc<Session>, String, Config)> for AccessControl {
+    async fn handle(
+        &self,
+        mut req: Request<(Arc<Session>, String, Config)>,
+        next: Next<'_, (Arc<Session>, String, Config)>,
+    ) -> tide::Result {
+        let user = match &self.authenticator {
+            Some(authenticator) => match authenticator.authenticate(&req) {
+                Some(user) => Some(user),
+                None => {
+                    tracing::debug!("Unauthenticated REST request: {:?}", req);
+                    return Ok(authenticator.challenge());
+                }
+            },
+            None => None,
+        };
+        let permissions = Permissions {
+            policy: self.policy.clone(),
+            subject: user.and_then(|u| self.subjects.get(&u).cloned()),
+        };
+        let path = req.url().path();
+        if path != websocket::WS_PATH && path != openapi::OPENAPI_PATH {
+            if let Some((action, key_expr)) = request_action(&req, &req.state().1) {
+                if !permissions.allows(action, &key_expr) {
+                    tracing::debug!("REST request denied by ACL: {:?} on {}", action, key_expr);
+                    return Ok(response(StatusCode::Forbidden, "text/plain", "Forbidden"));
+                }
+            }
+        }
+        req.set_ext(permissions);
+        Ok(next.run(req).await)
+    }
+}
+
+#[test]
+fn test_access_policy() {
+    let acl: AclConfig = serde_json::from_str(
+        r#"{
+            "enabled": true,
+            "default_permission": "deny",
+            "rules": [
+                { "interfaces": ["rest-alice"], "key_exprs": ["demo/**"], "actions": ["put", "get"], "permission": "allow" },
+                { "interfaces": ["rest-alice"], "key_exprs": ["demo/secret/**"], "actions": ["get"], "permission": "deny" },
+                { "key_exprs": ["public/**"], "actions": ["get"], "flows": ["ingress"], "permission": "allow" },
+                { "key_exprs": ["egress/**"], "actions": ["get"], "flows": ["egress"], "permission": "allow" }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let policy = AccessPolicy::new(&acl);
+    let ke = |s: &'static str| keyexpr::new(s).unwrap();
+    assert!(policy.permits(Some("rest-alice"), Action::Put, ke("demo/a")));
+    assert!(policy.permits(Some("rest-alice"), Action::Get, ke("demo/a")));
+    assert!(!policy.permits(Some("rest-alice"), Action::Get, ke("demo/secret/a")));
+    assert!(!policy.permits(Some("rest-alice"), Action::DeclareSubscriber, ke("demo/a")));
+    assert!(!policy.permits(None, Action::Put, ke("demo/a")));
+    assert!(!policy.permits(Some("rest-bob"), Action::Put, ke("demo/a")));
+    assert!(policy.permits(None, Action::Get, ke("public/a")));
+    assert!(!policy.permits(None, Action::Get, ke("egress/a")));
+
+    let acl: AclConfig = serde_json::from_str(
+        r#"{ "enabled": false, "default_permission": "deny", "rules": null }"#,
+    )
+    .unwrap();
+    assert!(AccessPolicy::new(&acl).permits(None, Action::Put, ke("demo/a")));
+}
+
+#[test]
+fn test_parse_dictionary() {
+    let passwords = parse_dictionary("alice:secret\n\n bob : pass:word \n").unwrap();
+    assert_eq!(passwords.get("alice").unwrap(), "secret");
+    assert_eq!(passwords.get("bob").unwrap(), "pass:word");
+    assert!(parse_dictionary("alice").is_err());
+}
+
+#[test]
+fn test_permissions() {
+    let acl: AclConfig = serde_json::from_str(
+        r#"{ "enabled": true, "default_permission": "allow", "rules": null }"#,
+    )
+    .unwrap();
+    let permissions = Permissions {
+        policy: Arc::new(AccessPolicy::new(&acl)),
+        subject: None,
+    };
+    assert!(permissions.allows(Action::Put, "demo/a"));
+    assert!(!permissions.allows(Action::Put, "demo//a"));
+    assert!(!permissions.allows(Action::Put, "demo/a/"));
+}
+
+#[test]
+fn test_token_user() {
+    let authenticator = Authenticator {
+        passwords: HashMap::new(),
+        tokens: HashMap::from([
+            ("token-a".to_string(), "alice".to_string()),
+            ("token-b".to_string(), "bob".to_string()),
+        ]),
+        certificates: HashMap::new(),
+    };
+    assert_eq!(
+        authenticator.token_user("token-a").as_deref(),
+        Some("alice")
+    );
+    assert_eq!(authenticator.token_user("token-b").as_deref(), Some("bob"));
+    assert_eq!(authenticator.token_user("token-c"), None);
+    assert_eq!(authenticator.token_user("token"), None);
+}
+
+#[test]
+fn test_constant_time_eq() {
+    assert!(constant_time_eq(b"secret", b"secret"));
+    assert!(!constant_time_eq(b"secret", b"secreT"));
+    assert!(!constant_time_eq(b"secret", b"secrets"));
+    assert!(constant_time_eq(b"", b""));
+}
@@ -14,27 +14,81 @@
 use schemars::JsonSchema;
 use serde::de::{Unexpected, Visitor};
 use serde::{de, Deserialize, Deserializer};
+use std::collections::HashMap;
 use std::fmt;
 
 const DEFAULT_HTTP_INTERFACE: &str = "[::]";
+const DEFAULT_SSE_HEARTBEAT: u64 = 15;
+const DEFAULT_SSE_SEND_TIMEOUT: u64 = 10;
 
 #[derive(JsonSchema, Deserialize, serde::Serialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
     #[serde(deserialize_with = "deserialize_http_port")]
     pub http_port: String,
+    // interval in seconds after which a heartbeat event is sent on an idle SSE stream (0 to disable heartbeats)
+    #[serde(default = "default_sse_heartbeat")]
+    pub sse_heartbeat: u64,
+    // timeout in seconds for sending an event on an SSE stream, after which the stream is closed (null to disable it)
+    #[serde(default = "default_sse_send_timeout")]
+    pub sse_send_timeout: Option<u64>,
+    // serve HTTPS instead of HTTP on `http_port`
+    #[serde(default)]
+    pub https: Option<HttpsConfig>,
+    // require the clients to authenticate (no authentication by default),
+    // the requests are checked against the `access_control` rules of the router in any case
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
     #[serde(default, deserialize_with = "deserialize_path")]
     __path__: Option<Vec<String>>,
     __required__: Option<bool>,
     __config__: Option<String>,
 }
 
+#[derive(JsonSchema, Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HttpsConfig {
+    // path to the PEM file of the certificate chain of the server
+    pub server_certificate: String,
+    // path to the PEM file of the private key of the server
+    pub server_private_key: String,
+    // path to the PEM file of the root certificates the client certificates are verified with (mTLS)
+    #[serde(default)]
+    pub root_ca_certificate: Option<String>,
+}
+
+#[derive(JsonSchema, Deserialize, serde::Serialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    // path to a `<user>:<password>` dictionary for Basic authentication,
+    // the `transport/auth/usrpwd/dictionary_file` of the router is used by default
+    #[serde(default)]
+    pub dictionary_file: Option<String>,
+    // users authenticated by a Bearer token, indexed by token
+    #[serde(default)]
+    pub tokens: HashMap<String, String>,
+    // users authenticated by a client certificate (mTLS), indexed by the SHA-256 fingerprint of the certificate
+    #[serde(default)]
+    pub certificates: HashMap<String, String>,
+    // ACL subject (i.e. interface name in the `access_control` rules of the router) of the users
+    #[serde(default)]
+    pub acl_subjects: HashMap<String, String>,
+}
+
 impl From<&Config> for serde_json::Value {
     fn from(c: &Config) -> Self {
         serde_json::to_value(c).unwrap()
     }
 }
 
+fn default_sse_heartbeat() -> u64 {
+    DEFAULT_SSE_HEARTBEAT
+}
+
+fn default_sse_send_timeout() -> Option<u64> {
+    Some(DEFAULT_SSE_SEND_TIMEOUT)
+}
+
 fn deserialize_http_port<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
     D: Deserializer<'de>,
@@ -215,4 +269,53 @@ mod tests {
         assert_eq!(__path__, None);
         assert_eq!(__required__, None);
     }
+
+    #[test]
+    fn test_sse_fields() {
+        let config = serde_json::from_str::<Config>(r#"{"http_port": 8080}"#).unwrap();
+        assert_eq!(config.sse_heartbeat, super::DEFAULT_SSE_HEARTBEAT);
+        assert_eq!(
+            config.sse_send_timeout,
+            Some(super::DEFAULT_SSE_SEND_TIMEOUT)
+        );
+
+        let config =
+            serde_json::from_str::<Config>(r#"{"http_port": 8080, "sse_send_timeout": null}"#)
+                .unwrap();
+        assert_eq!(config.sse_send_timeout, None);
+
+        let config = serde_json::from_str::<Config>(
+            r#"{"http_port": 8080, "sse_heartbeat": 0, "sse_send_timeout": 5}"#,
+        )
+        .unwrap();
+        assert_eq!(config.sse_heartbeat, 0);
+        assert_eq!(config.sse_send_timeout, Some(5));
+    }
+
+    #[test]
+    fn test_auth_fields() {
+        let config = serde_json::from_str::<Config>(r#"{"http_port": 8080}"#).unwrap();
+        assert!(config.https.is_none());
+        assert!(config.auth.is_none());
+
+        let config = serde_json::from_str::<Config>(
+            r#"{"http_port": 8080,
+                "https": {"server_certificate": "cert.pem", "server_private_key": "key.pem"},
+                "auth": {"tokens": {"secret": "alice"}, "acl_subjects": {"alice": "rest-alice"}}}"#,
+        )
+        .unwrap();
+        let https = config.https.unwrap();
+        assert_eq!(https.server_certificate, "cert.pem");
+        assert_eq!(https.root_ca_certificate, None);
+        let auth = config.auth.unwrap();
+        assert_eq!(auth.dictionary_file, None);
+        assert_eq!(auth.tokens.get("secret").unwrap(), "alice");
+        assert!(auth.certificates.is_empty());
+        assert_eq!(auth.acl_subjects.get("alice").unwrap(), "rest-alice");
+
+        assert!(serde_json::from_str::<Config>(
+            r#"{"http_port": 8080, "https": {"server_certificate": "cert.pem"}}"#
+        )
+        .is_err());
+    }
 }
@@ -0,0 +1,185 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+// HTTPS listener of the REST plugin. The TLS connections are accepted with rustls, and their HTTP requests
+// served by the tide server, with the fingerprint of the client certificate (if any) as a request extension.
+
+use crate::auth::ClientCertificate;
+use crate::config::HttpsConfig;
+use crate::Config;
+use async_std::net::TcpListener;
+use futures::{AsyncRead, AsyncWrite};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::BufReader;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tide::Server;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_util::compat::{Compat, FuturesAsyncReadCompatExt, TokioAsyncReadCompatExt};
+use zenoh::Session;
+use zenoh_result::{bail, zerror, ZResult};
+
+fn load_certificates(path: &str) -> ZResult<Vec<CertificateDer<'static>>> {
+    let mut reader = BufReader::new(
+        File::open(path).map_err(|e| zerror!("Unable to open certificate '{}': {}", path, e))?,
+    );
+    let certs = rustls_pemfile::certs(&mut reader)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| zerror!("Error processing certificate '{}': {}", path, e))?;
+    if certs.is_empty() {
+        bail!("No certificate found in '{}'", path);
+    }
+    Ok(certs)
+}
+
+fn load_private_key(path: &str) -> ZResult<PrivateKeyDer<'static>> {
+    let mut reader = BufReader::new(
+        File::open(path).map_err(|e| zerror!("Unable to open private key '{}': {}", path, e))?,
+    );
+    rustls_pemfile::private_key(&mut reader)
+        .map_err(|e| zerror!("Error processing private key '{}': {}", path, e))?
+        .ok_or_else(|| zerror!("No private key found in '{}'", path).into())
+}
+
+// The TLS config of the server. If root certificates are configured, the client certificates are verified,
+// and clients without certificate are accepted only if `optional_client_auth` (i.e. they can authenticate otherwise).
+pub(crate) fn tls_config(
+    config: &HttpsConfig,
+    optional_client_auth: bool,
+) -> ZResult<Arc<ServerConfig>> {
+    let certs = load_certificates(&config.server_certificate)?;
+    let key = load_private_key(&config.server_private_key)?;
+    let builder = ServerConfig::builder();
+    let builder = match &config.root_ca_certificate {
+        Some(root_ca) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certificates(root_ca)? {
+                roots.add(cert)?;
+            }
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots));
+            let verifier = if optional_client_auth {
+                verifier.allow_unauthenticated().build()?
+            } else {
+                verifier.build()?
+            };
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| zerror!("Invalid server certificate or private key: {}", e))?;
+    Ok(Arc::new(config))
+}
+
+// Hex encoded SHA-256 digest of a DER certificate, as displayed by `openssl x509 -fingerprint -sha256` without colons
+pub(crate) fn fingerprint(cert: &[u8]) -> String {
+    Sha256::digest(cert)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+type TlsConnectionStream = Compat<TlsStream<Compat<async_std::net::TcpStream>>>;
+
+// A TLS connection shared by the reader and the writer of the HTTP server
+#[derive(Clone)]
+struct TlsConnection(Arc<Mutex<TlsConnectionStream>>);
+
+impl AsyncRead for TlsConnection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut *self.0.lock().unwrap()).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TlsConnection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut *self.0.lock().unwrap()).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut *self.0.lock().unwrap()).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut *self.0.lock().unwrap()).poll_close(cx)
+    }
+}
+
+pub(crate) async fn listen(
+    app: Server<(Arc<Session>, String, Config)>,
+    addr: &str,
+    config: Arc<ServerConfig>,
+) -> ZResult<()> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!(
+        "REST server listening on https://{}",
+        listener.local_addr()?
+    );
+    let acceptor = TlsAcceptor::from(config);
+    loop {
+        let (stream, peer_addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::warn!("Error accepting an HTTPS connection: {}", e);
+                continue;
+            }
+        };
+        let local_addr = stream.local_addr().ok();
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        async_std::task::spawn(async move {
+            let stream = match acceptor.accept(stream.compat()).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    tracing::debug!("TLS handshake with {} failed: {}", peer_addr, e);
+                    return;
+                }
+            };
+            let certificate = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .map(|cert| ClientCertificate(fingerprint(cert)));
+            let connection = TlsConnection(Arc::new(Mutex::new(stream.compat())));
+            let result = async_h1::accept(connection, |mut req| async {
+                req.set_peer_addr(Some(peer_addr));
+                req.set_local_addr(local_addr);
+                if let Some(certificate) = &certificate {
+                    req.ext_mut().insert(certificate.clone());
+                }
+                app.respond(req).await
+            })
+            .await;
+            if let Err(e) = result {
+                tracing::debug!("HTTPS connection with {} closed: {}", peer_addr, e);
+            }
+        });
+    }
+}
@@ -32,19 +32,28 @@ use zenoh::prelude::r#async::*;
 use zenoh::properties::Properties;
 use zenoh::query::{QueryConsolidation, Reply};
 use zenoh::runtime::Runtime;
-use zenoh::selector::TIME_RANGE_KEY;
+use zenoh::selector::{TimeBound, TimeRange, TIME_RANGE_KEY};
+use zenoh::time::Timestamp;
 use zenoh::Session;
+use zenoh_ext::SubscriberBuilderExt;
 use zenoh_plugin_trait::{plugin_long_version, plugin_version, Plugin, PluginControl};
 use zenoh_result::{bail, zerror, ZResult};
 
+mod auth;
 mod config;
 pub use config::Config;
+mod https;
+mod openapi;
+mod websocket;
 
 const GIT_VERSION: &str = git_version::git_version!(prefix = "v", cargo_prefix = "v");
 lazy_static::lazy_static! {
     static ref LONG_VERSION: String = format!("{} built with {}", GIT_VERSION, env!("RUSTC_VERSION"));
 }
 const RAW_KEY: &str = "_raw";
+const ENCODING_KEY: &str = "_encoding";
+const LAST_EVENT_ID: &str = "Last-Event-ID";
+const SSE_HEARTBEAT: &str = "heartbeat";
 
 fn value_to_json(value: Value) -> String {
     // @TODO: transcode to JSON when implemented in Value
@@ -170,6 +179,65 @@ async fn to_raw_response(results: flume::Receiver<Reply>) -> Response {
     }
 }
 
+enum SseEvent {
+    Sample(Box<Sample>),
+    Heartbeat,
+}
+
+// Waits for the next sample, or for a heartbeat if no sample is received within `heartbeat` seconds.
+// Returns None if the subscriber is closed.
+async fn sse_next_event(sub: &flume::Receiver<Sample>, heartbeat: u64) -> Option<SseEvent> {
+    let sample = async {
+        sub.recv_async()
+            .await
+            .ok()
+            .map(|s| SseEvent::Sample(Box::new(s)))
+    };
+    if heartbeat == 0 {
+        return sample.await;
+    }
+    sample
+        .race(async {
+            async_std::task::sleep(std::time::Duration::from_secs(heartbeat)).await;
+            Some(SseEvent::Heartbeat)
+        })
+        .await
+}
+
+// The encodings listed in the `_encoding` parameter of the selector of an SSE stream, separated by commas
+fn sse_encodings(parameters: &str) -> Vec<Encoding> {
+    parameters
+        .decode()
+        .filter(|(k, _)| k.as_ref() == ENCODING_KEY)
+        .flat_map(|(_, v)| {
+            v.split(',')
+                .filter(|e| !e.is_empty())
+                .map(|e| Encoding::from(e.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn sse_encoding_matches(encodings: &[Encoding], encoding: &Encoding) -> bool {
+    encodings.is_empty() || encodings.iter().any(|e| encoding.starts_with(e.clone()))
+}
+
+// The first MIME type accepted by the client of a request
+fn request_mime<State>(req: &Request<State>) -> String {
+    match req.header("accept") {
+        Some(accept) => accept[0]
+            .to_string()
+            .split(';')
+            .next()
+            .unwrap()
+            .split(',')
+            .next()
+            .unwrap()
+            .to_string(),
+        None => "application/json".to_string(),
+    }
+}
+
 fn method_to_kind(method: Method) -> SampleKind {
     match method {
         Method::Put => SampleKind::Put,
@@ -256,12 +324,34 @@ impl RunningPluginTrait for RunningPlugin {
             {
                 responses.push(zenoh::plugins::Response::new(
                     port_key.clone(),
-                    (&self.0).into(),
+                    self.0.http_port.clone().into(),
                 ))
             }
         });
         Ok(responses)
     }
+
+    fn adminspace_schemas(&self) -> Vec<zenoh::plugins::Response> {
+        vec![
+            zenoh::plugins::Response::new(
+                "version".to_string(),
+                serde_json::json!({
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "title": "Version",
+                    "type": "string"
+                }),
+            ),
+            zenoh::plugins::Response::new(
+                "port".to_string(),
+                serde_json::json!({
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "title": "Port",
+                    "description": "The `<interface>:<port>` the REST server listens on",
+                    "type": "string"
+                }),
+            ),
+        ]
+    }
 }
 
 fn with_extended_string<R, F: FnMut(&mut String) -> R>(
@@ -278,25 +368,14 @@ fn with_extended_string<R, F: FnMut(&mut String) -> R>(
     result
 }
 
-async fn query(mut req: Request<(Arc<Session>, String)>) -> tide::Result<Response> {
+async fn query(mut req: Request<(Arc<Session>, String, Config)>) -> tide::Result<Response> {
     tracing::trace!("Incoming GET request: {:?}", req);
 
-    let first_accept = match req.header("accept") {
-        Some(accept) => accept[0]
-            .to_string()
-            .split(';')
-            .next()
-            .unwrap()
-            .split(',')
-            .next()
-            .unwrap()
-            .to_string(),
-        None => "application/json".to_string(),
-    };
+    let first_accept = request_mime(&req);
     if first_accept == "text/event-stream" {
         Ok(tide::sse::upgrade(
             req,
-            move |req: Request<(Arc<Session>, String)>, sender: Sender| async move {
+            move |req: Request<(Arc<Session>, String, Config)>, sender: Sender| async move {
                 let key_expr = match path_to_key_expr(req.url().path(), &req.state().1) {
                     Ok(ke) => ke.into_owned(),
                     Err(e) => {
@@ -306,27 +385,104 @@ async fn query(mut req: Request<(Arc<Session>, String)>) -> tide::Result<Respons
                         ))
                     }
                 };
+                let encodings = match req.url().query() {
+                    Some(q) => sse_encodings(q),
+                    None => Vec::new(),
+                };
+                let last_event_id = match req.header(LAST_EVENT_ID) {
+                    Some(id) => match Timestamp::from_str(id.as_str()) {
+                        Ok(ts) => Some(ts),
+                        Err(e) => {
+                            return Err(tide::Error::new(
+                                tide::StatusCode::BadRequest,
+                                anyhow::anyhow!("Invalid {} header: {:?}", LAST_EVENT_ID, e),
+                            ))
+                        }
+                    },
+                    None => None,
+                };
                 async_std::task::spawn(async move {
                     tracing::debug!(
                         "Subscribe to {} for SSE stream (task {})",
                         key_expr,
                         async_std::task::current().id()
                     );
-                    let sender = &sender;
-                    let sub = req
-                        .state()
-                        .0
-                        .declare_subscriber(&key_expr)
-                        .res()
-                        .await
-                        .unwrap();
+                    let session = &req.state().0;
+                    let conf = &req.state().2;
+                    let live_sub;
+                    let replay_sub;
+                    // on a reconnection, the samples published since the last received event are fetched from storages
+                    let sub = match last_event_id {
+                        Some(last_event_id) => {
+                            let mut selector = Selector::from(&key_expr);
+                            selector.with_time_range(TimeRange(
+                                TimeBound::Inclusive(
+                                    last_event_id.get_time().to_system_time().into(),
+                                ),
+                                TimeBound::Unbounded,
+                            ));
+                            tracing::debug!(
+                                "Resume SSE stream after event {} querying {}",
+                                last_event_id,
+                                selector
+                            );
+                            match session
+                                .declare_subscriber(&key_expr)
+                                .querying()
+                                .query_selector(selector)
+                                .res()
+                                .await
+                            {
+                                Ok(sub) => {
+                                    replay_sub = sub;
+                                    &*replay_sub
+                                }
+                                Err(e) => {
+                                    tracing::error!("Error declaring querying subscriber: {}", e);
+                                    return;
+                                }
+                            }
+                        }
+                        None => match session.declare_subscriber(&key_expr).res().await {
+                            Ok(sub) => {
+                                live_sub = sub;
+                                &*live_sub
+                            }
+                            Err(e) => {
+                                tracing::error!("Error declaring subscriber: {}", e);
+                                return;
+                            }
+                        },
+                    };
                     loop {
-                        let sample = sub.recv_async().await.unwrap();
-                        match sender
-                            .send(&sample.kind.to_string(), sample_to_json(sample), None)
-                            .timeout(std::time::Duration::new(10, 0))
-                            .await
-                        {
+                        let (name, id, data) = match sse_next_event(sub, conf.sse_heartbeat).await {
+                            Some(SseEvent::Sample(sample)) => {
+                                // skip the samples already sent before the reconnection
+                                if matches!((last_event_id, sample.timestamp), (Some(last), Some(ts)) if ts <= last)
+                                    || !sse_encoding_matches(&encodings, &sample.value.encoding)
+                                {
+                                    continue;
+                                }
+                                (
+                                    sample.kind.to_string(),
+                                    sample.timestamp.map(|ts| ts.to_string()),
+                                    sample_to_json(*sample),
+                                )
+                            }
+                            Some(SseEvent::Heartbeat) => {
+                                (SSE_HEARTBEAT.to_string(), None, "{}".to_string())
+                            }
+                            None => break,
+                        };
+                        let event = sender.send(&name, data, id.as_deref());
+                        let result = match conf.sse_send_timeout {
+                            Some(timeout) => event
+                                .timeout(std::time::Duration::from_secs(timeout))
+                                .await
+                                .map_err(|_| "timeout".to_string()),
+                            None => Ok(event.await),
+                        };
+                        match result {
                             Ok(Ok(_)) => {}
                             Ok(Err(e)) => {
                                 tracing::debug!(
@@ -334,9 +490,6 @@ async fn query(mut req: Request<(Arc<Session>, String)>) -> tide::Result<Respons
                                     e,
                                     async_std::task::current().id()
                                 );
-                                if let Err(e) = sub.undeclare().res().await {
-                                    tracing::error!("Error undeclaring subscriber: {}", e);
-                                }
                                 break;
                             }
                             Err(_) => {
@@ -344,13 +497,11 @@ async fn query(mut req: Request<(Arc<Session>, String)>) -> tide::Result<Respons
                                     "SSE timeout! Unsubscribe and terminate (task {})",
                                     async_std::task::current().id()
                                 );
-                                if let Err(e) = sub.undeclare().res().await {
-                                    tracing::error!("Error undeclaring subscriber: {}", e);
-                                }
                                 break;
                             }
                         }
                     }
+                    // the subscriber is undeclared when dropped
                 });
                 Ok(())
             },
@@ -407,7 +558,7 @@ async fn query(mut req: Request<(Arc<Session>, String)>) -> tide::Result<Respons
     }
 }
 
-async fn write(mut req: Request<(Arc<Session>, String)>) -> tide::Result<Response> {
+async fn write(mut req: Request<(Arc<Session>, String, Config)>) -> tide::Result<Response> {
     tracing::trace!("Incoming PUT request: {:?}", req);
     match req.body_bytes().await {
         Ok(bytes) => {
@@ -459,9 +610,26 @@ pub async fn run(runtime: Runtime, conf: Config) -> ZResult<()> {
     zenoh_util::try_init_log_from_env();
 
     let zid = runtime.zid().to_string();
+    let access_control = {
+        let runtime_conf = runtime.config().lock();
+        auth::AccessControl::new(
+            conf.auth.as_ref(),
+            runtime_conf
+                .transport()
+                .auth()
+                .usrpwd()
+                .dictionary_file()
+                .as_deref(),
+            runtime_conf.access_control(),
+        )?
+    };
+    let tls_config = match &conf.https {
+        Some(https) => Some(https::tls_config(https, conf.auth.is_some())?),
+        None => None,
+    };
     let session = zenoh::init(runtime).res().await.unwrap();
 
-    let mut app = Server::with_state((Arc::new(session), zid));
+    let mut app = Server::with_state((Arc::new(session), zid, conf.clone()));
     app.with(
         tide::security::CorsMiddleware::new()
             .allow_methods(
@@ -472,7 +640,10 @@ pub async fn run(runtime: Runtime, conf: Config) -> ZResult<()> {
             .allow_origin(tide::security::Origin::from("*"))
             .allow_credentials(false),
     );
+    app.with(access_control);
 
+    app.at(websocket::WS_PATH).get(websocket::upgrade);
+    app.at(openapi::OPENAPI_PATH).get(openapi::serve);
     app.at("/")
         .get(query)
         .post(query)
@@ -486,9 +657,13 @@ pub async fn run(runtime: Runtime, conf: Config) -> ZResult<()> {
         .patch(write)
         .delete(write);
 
-    if let Err(e) = app.listen(conf.http_port).await {
+    let result = match tls_config {
+        Some(tls_config) => https::listen(app, &conf.http_port, tls_config).await,
+        None => app.listen(conf.http_port).await.map_err(|e| e.into()),
+    };
+    if let Err(e) = result {
         tracing::error!("Unable to start http server for REST: {:?}", e);
-        return Err(e.into());
+        return Err(e);
     }
     Ok(())
 }
@@ -503,3 +678,30 @@ fn path_to_key_expr<'a>(path: &'a str, zid: &str) -> ZResult<KeyExpr<'a>> {
         KeyExpr::try_from(path)
     }
 }
+
+#[test]
+fn test_adminspace_port() {
+    let config = serde_json::from_str::<Config>(
+        r#"{"http_port": 8080, "auth": {"tokens": {"secret": "alice"}}}"#,
+    )
+    .unwrap();
+    let plugin = RunningPlugin(config);
+    let selector = Selector::try_from("@/router/zid/status/plugins/rest/**").unwrap();
+    let responses = plugin
+        .adminspace_getter(&selector, "@/router/zid/status/plugins/rest")
+        .unwrap();
+    let port = responses
+        .iter()
+        .find(|response| response.key.ends_with("/port"))
+        .unwrap();
+    assert_eq!(port.value, serde_json::json!("[::]:8080"));
+    let schema = plugin
+        .adminspace_schemas()
+        .into_iter()
+        .find(|schema| schema.key == "port")
+        .unwrap();
+    assert_eq!(schema.value["type"], "string");
+    assert!(responses
+        .iter()
+        .all(|response| !response.value.to_string().contains("secret")));
+}
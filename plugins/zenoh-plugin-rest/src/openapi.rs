@@ -0,0 +1,264 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+// OpenAPI description of the routes of the REST plugin, and of the selector parameters they honour.
+// The security schemes depend on the `auth` and `https` configuration of the plugin.
+
+use crate::websocket::WS_PATH;
+use crate::{response, Config, ENCODING_KEY, LAST_EVENT_ID, RAW_KEY};
+use serde_json::json;
+use std::str::FromStr;
+use std::sync::Arc;
+use tide::http::Mime;
+use tide::{Request, Response, StatusCode};
+use zenoh::selector::TIME_RANGE_KEY;
+use zenoh::Session;
+
+pub(crate) const OPENAPI_PATH: &str = "/@openapi";
+
+pub(crate) async fn serve(req: Request<(Arc<Session>, String, Config)>) -> tide::Result<Response> {
+    tracing::trace!("Incoming OpenAPI request: {:?}", req);
+    Ok(response(
+        StatusCode::Ok,
+        Mime::from_str("application/json").unwrap(),
+        &openapi(&req.state().2).to_string(),
+    ))
+}
+
+fn error_responses() -> serde_json::Value {
+    json!({
+        "400": { "description": "Invalid key expression or selector" },
+        "401": { "description": "The client is not authenticated" },
+        "403": { "description": "The request is denied by the access control rules of the router" },
+        "500": { "description": "The operation failed" }
+    })
+}
+
+fn with_errors(mut responses: serde_json::Value) -> serde_json::Value {
+    if let (Some(responses), serde_json::Value::Object(errors)) =
+        (responses.as_object_mut(), error_responses())
+    {
+        responses.extend(errors);
+    }
+    responses
+}
+
+pub(crate) fn openapi(conf: &Config) -> serde_json::Value {
+    let query_responses = with_errors(json!({
+        "200": {
+            "description": "The replies to the query, or the stream of the samples published on the key expression \
+                if `text/event-stream` is accepted. With the `_raw` parameter, the payload of the first reply.",
+            "content": {
+                "application/json": {
+                    "schema": { "type": "array", "items": { "$ref": "#/components/schemas/Reply" } }
+                },
+                "text/html": { "schema": { "type": "string" } },
+                "text/event-stream": { "schema": { "type": "string" } }
+            }
+        }
+    }));
+    let write_responses = with_errors(json!({
+        "200": { "description": "The sample is published" }
+    }));
+    let query = |summary: &str| {
+        json!({
+            "summary": summary,
+            "parameters": [
+                {
+                    "name": "Accept",
+                    "in": "header",
+                    "description": "`application/json` (default), `text/html`, or `text/event-stream` to subscribe",
+                    "schema": { "type": "string" }
+                },
+                {
+                    "name": LAST_EVENT_ID,
+                    "in": "header",
+                    "description": "Resume a Server-Sent Events stream after the event with this id (a timestamp), \
+                        the samples published in the meantime are retrieved from the storages",
+                    "schema": { "type": "string" }
+                }
+            ],
+            "responses": query_responses
+        })
+    };
+    let mut query_with_value =
+        query("Query the key expression, with the body as the value of the query");
+    query_with_value["requestBody"] = json!({
+        "required": false,
+        "content": { "*/*": { "schema": {} } }
+    });
+    let write = |summary: &str| {
+        json!({
+            "summary": summary,
+            "requestBody": { "content": { "*/*": { "schema": {} } } },
+            "responses": write_responses
+        })
+    };
+
+    let mut doc = json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Zenoh REST API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Publications, queries and subscriptions on zenoh key expressions over HTTP. \
+                The path of a request is a key expression, possibly with `*` and `**` chunks. \
+                The `@/router/local` prefix designates the admin space of the router serving the request."
+        },
+        "paths": {
+            "/{key_expr}": {
+                "parameters": [
+                    {
+                        "name": "key_expr",
+                        "in": "path",
+                        "required": true,
+                        "description": "The key expression (its `/` separators are not escaped)",
+                        "schema": { "type": "string" }
+                    },
+                    {
+                        "name": TIME_RANGE_KEY,
+                        "in": "query",
+                        "description": "Time range of the samples to retrieve from the storages, e.g. `[now(-1h)..]`",
+                        "schema": { "type": "string" }
+                    },
+                    {
+                        "name": RAW_KEY,
+                        "in": "query",
+                        "description": "Reply with the payload of the first reply, with its encoding as content type",
+                        "allowEmptyValue": true,
+                        "schema": { "type": "string" }
+                    },
+                    {
+                        "name": ENCODING_KEY,
+                        "in": "query",
+                        "description": "Comma separated encodings of the samples sent on a Server-Sent Events stream",
+                        "schema": { "type": "string" }
+                    }
+                ],
+                "get": query("Query the key expression, or subscribe to it with Server-Sent Events"),
+                "post": query_with_value,
+                "put": write("Put the body on the key expression, with its content type as encoding"),
+                "patch": write("Put the body on the key expression, with its content type as encoding"),
+                "delete": without_body(write("Delete the key expression"))
+            },
+            WS_PATH: {
+                "get": {
+                    "summary": "Open a WebSocket to subscribe, publish, query and declare queryables \
+                        with JSON messages tagged by their `op` field",
+                    "responses": {
+                        "101": { "description": "The connection is upgraded to a WebSocket" },
+                        "400": { "description": "The request is not a WebSocket upgrade" },
+                        "401": { "description": "The client is not authenticated" }
+                    }
+                }
+            },
+            OPENAPI_PATH: {
+                "get": {
+                    "summary": "This OpenAPI description",
+                    "responses": {
+                        "200": {
+                            "description": "The OpenAPI description",
+                            "content": { "application/json": { "schema": { "type": "object" } } }
+                        },
+                        "401": { "description": "The client is not authenticated" }
+                    }
+                }
+            }
+        },
+        "components": {
+            "schemas": {
+                "Sample": {
+                    "type": "object",
+                    "required": ["key", "value", "encoding", "time"],
+                    "properties": {
+                        "key": { "type": "string" },
+                        "value": { "description": "The value, as JSON if its encoding allows it, as a base64 string otherwise" },
+                        "encoding": { "type": "string" },
+                        "time": { "type": "string", "description": "The timestamp of the sample, or `None`" }
+                    }
+                },
+                "ReplyError": {
+                    "type": "object",
+                    "required": ["key", "value", "encoding"],
+                    "properties": {
+                        "key": { "const": "ERROR" },
+                        "value": {},
+                        "encoding": { "type": "string" }
+                    }
+                },
+                "Reply": {
+                    "oneOf": [
+                        { "$ref": "#/components/schemas/Sample" },
+                        { "$ref": "#/components/schemas/ReplyError" }
+                    ]
+                }
+            }
+        }
+    });
+
+    if let Some(auth) = &conf.auth {
+        let mut schemes = serde_json::Map::new();
+        schemes.insert(
+            "basicAuth".into(),
+            json!({ "type": "http", "scheme": "basic" }),
+        );
+        if !auth.tokens.is_empty() {
+            schemes.insert(
+                "bearerAuth".into(),
+                json!({ "type": "http", "scheme": "bearer" }),
+            );
+        }
+        if conf
+            .https
+            .as_ref()
+            .map_or(false, |https| https.root_ca_certificate.is_some())
+            && !auth.certificates.is_empty()
+        {
+            schemes.insert("mutualTLS".into(), json!({ "type": "mutualTLS" }));
+        }
+        // any of the schemes authenticates the client
+        doc["security"] = schemes.keys().map(|name| json!({ name: [] })).collect();
+        doc["components"]["securitySchemes"] = schemes.into();
+    }
+    doc
+}
+
+fn without_body(mut operation: serde_json::Value) -> serde_json::Value {
+    if let Some(operation) = operation.as_object_mut() {
+        operation.remove("requestBody");
+    }
+    operation
+}
+
+#[test]
+fn test_openapi() {
+    let conf: Config = serde_json::from_str(r#"{"http_port": 8080}"#).unwrap();
+    let doc = openapi(&conf);
+    assert_eq!(doc["openapi"], "3.1.0");
+    for method in ["get", "post", "put", "patch", "delete"] {
+        assert!(doc["paths"]["/{key_expr}"][method]["responses"]["200"].is_object());
+    }
+    assert!(doc["paths"]["/{key_expr}"]["delete"]["requestBody"].is_null());
+    assert!(doc["paths"][OPENAPI_PATH]["get"].is_object());
+    assert!(doc.get("security").is_none());
+
+    let conf: Config =
+        serde_json::from_str(r#"{"http_port": 8080, "auth": {"tokens": {"secret": "alice"}}}"#)
+            .unwrap();
+    let doc = openapi(&conf);
+    assert_eq!(doc["security"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        doc["components"]["securitySchemes"]["bearerAuth"]["scheme"],
+        "bearer"
+    );
+}
@@ -0,0 +1,603 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+// WebSocket endpoint multiplexing subscriptions, publications, queries and queryables
+// of a client over a single connection, with JSON text messages tagged by their `op` field.
+//
+// Client to server:
+//   { "op": "subscribe", "id": <id>, "key_expr": <key_expr> }
+//   { "op": "queryable", "id": <id>, "key_expr": <key_expr>, "complete": <bool> }
+//   { "op": "undeclare", "id": <id> }
+//   { "op": "put", "key_expr": <key_expr>, "value": <value>, "encoding": <encoding>, "base64": <bool> }
+//   { "op": "delete", "key_expr": <key_expr> }
+//   { "op": "get", "id": <id>, "selector": <selector>, "value": <value>, "encoding": <encoding>, "base64": <bool> }
+//   { "op": "reply", "query_id": <query_id>, "key_expr": <key_expr>, "value": <value>, "encoding": <encoding>, "base64": <bool> }
+//   { "op": "reply_error", "query_id": <query_id>, "value": <value>, "encoding": <encoding>, "base64": <bool> }
+//   { "op": "reply_final", "query_id": <query_id> }
+// Server to client:
+//   { "op": "ok", "id": <id> }
+//   { "op": "error", "id": <id>, "error": <message> }
+//   { "op": "sample", "id": <subscription id>, "key": ..., "value": ..., "encoding": ..., "time": ..., "kind": ... }
+//   { "op": "reply", "id": <get id>, "key": ..., "value": ..., "encoding": ..., "time": ... }
+//   { "op": "reply_error", "id": <get id>, "value": ..., "encoding": ... }
+//   { "op": "reply_final", "id": <get id> }
+//   { "op": "query", "id": <queryable id>, "query_id": <query_id>, "selector": ..., "value": ..., "encoding": ... }
+//
+// The `id` of the declarations and gets are chosen by the client, the `query_id` of the queries by the server.
+// A string `value` is sent as is (or decoded from base64 if `base64` is true), any other JSON value is sent
+// as JSON. Received values are converted as in the replies to GET requests. A query is answered when the
+// client sends `reply_final`, when it times out, or when the connection is closed.
+// The ids of the subscriptions and queryables must be unique among the live declarations of the connection.
+// The messages to the client are queued up to a limit: when a slow client lets the queue fill up, the samples
+// and queries are dropped, and the other messages wait for the queue to drain.
+// The declarations, puts, deletes and gets are checked against the ACL subject of the authenticated client,
+// a denied operation is answered with an `error` message.
+
+use crate::auth::Permissions;
+use crate::{response, value_to_json, Config};
+use base64::{engine::general_purpose::STANDARD as b64_std_engine, Engine};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tide::{Request, Response, StatusCode};
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+use zenoh::config::Action;
+use zenoh::prelude::r#async::*;
+use zenoh::queryable::{Query, Queryable};
+use zenoh::subscriber::Subscriber;
+use zenoh::Session;
+use zenoh_result::{zerror, ZResult};
+
+pub(crate) const WS_PATH: &str = "/@ws";
+// Maximum number of messages queued for the client
+const WS_QUEUE_SIZE: usize = 1024;
+// Time for the client to answer a query with `reply_final`, after which the query is finalized
+const WS_QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum ClientMessage {
+    Subscribe {
+        id: u64,
+        key_expr: String,
+    },
+    Queryable {
+        id: u64,
+        key_expr: String,
+        #[serde(default)]
+        complete: bool,
+    },
+    Undeclare {
+        id: u64,
+    },
+    Put {
+        key_expr: String,
+        #[serde(flatten)]
+        value: ClientValue,
+    },
+    Delete {
+        key_expr: String,
+    },
+    Get {
+        id: u64,
+        selector: String,
+        #[serde(flatten)]
+        value: Option<ClientValue>,
+    },
+    Reply {
+        query_id: u64,
+        key_expr: String,
+        #[serde(flatten)]
+        value: ClientValue,
+    },
+    ReplyError {
+        query_id: u64,
+        #[serde(flatten)]
+        value: ClientValue,
+    },
+    ReplyFinal {
+        query_id: u64,
+    },
+}
+
+impl ClientMessage {
+    // The action of the message in the ACL, and the key expression it applies to
+    fn access(&self) -> Option<(Option<u64>, Action, &str)> {
+        match self {
+            ClientMessage::Subscribe { id, key_expr } => {
+                Some((Some(*id), Action::DeclareSubscriber, key_expr))
+            }
+            ClientMessage::Queryable { id, key_expr, .. } => {
+                Some((Some(*id), Action::DeclareQueryable, key_expr))
+            }
+            ClientMessage::Put { key_expr, .. } | ClientMessage::Delete { key_expr } => {
+                Some((None, Action::Put, key_expr))
+            }
+            ClientMessage::Get { id, selector, .. } => Some((
+                Some(*id),
+                Action::Get,
+                selector.split('?').next().unwrap_or_default(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ClientValue {
+    value: serde_json::Value,
+    encoding: Option<String>,
+    #[serde(default)]
+    base64: bool,
+}
+
+impl ClientValue {
+    fn into_value(self) -> ZResult<Value> {
+        let (payload, default_encoding) = match self.value {
+            serde_json::Value::String(s) if self.base64 => (
+                b64_std_engine
+                    .decode(s)
+                    .map_err(|e| zerror!("Invalid base64 value: {}", e))?,
+                KnownEncoding::AppOctetStream,
+            ),
+            serde_json::Value::String(s) => (s.into_bytes(), KnownEncoding::TextPlain),
+            value => (value.to_string().into_bytes(), KnownEncoding::AppJson),
+        };
+        let encoding = match self.encoding {
+            Some(encoding) => Encoding::from(encoding),
+            None => default_encoding.into(),
+        };
+        Ok(Value::from(payload).encoding(encoding))
+    }
+}
+
+// Upgrades a request on the WebSocket path, and serves the connection in a new task
+pub(crate) async fn upgrade(
+    req: Request<(Arc<Session>, String, Config)>,
+) -> tide::Result<Response> {
+    tracing::trace!("Incoming WebSocket request: {:?}", req);
+    let is_upgrade = req
+        .header("upgrade")
+        .map(|h| h.as_str().eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false);
+    let accept_key = match req.header("sec-websocket-key") {
+        Some(key) if is_upgrade => derive_accept_key(key.as_str().as_bytes()),
+        _ => {
+            return Ok(response(
+                StatusCode::BadRequest,
+                "text/plain",
+                "Expected a WebSocket upgrade request",
+            ))
+        }
+    };
+    let mut res = Response::new(StatusCode::SwitchingProtocols);
+    res.insert_header("upgrade", "websocket");
+    res.insert_header("connection", "Upgrade");
+    res.insert_header("sec-websocket-accept", accept_key);
+    let http_res: &mut http_types::Response = res.as_mut();
+    let upgrade_receiver = http_res.recv_upgrade().await;
+    let session = req.state().0.clone();
+    let permissions = req.ext::<Permissions>().cloned();
+    async_std::task::spawn(async move {
+        if let Some(connection) = upgrade_receiver.await {
+            let ws =
+                WebSocketStream::from_raw_socket(connection.compat(), Role::Server, None).await;
+            WsConnection::new(session, permissions).run(ws).await;
+        }
+    });
+    Ok(res)
+}
+
+struct WsConnection {
+    session: Arc<Session>,
+    permissions: Option<Permissions>,
+    // messages to be sent to the client
+    tx: flume::Sender<String>,
+    rx: flume::Receiver<String>,
+    subscribers: HashMap<u64, Subscriber<'static, ()>>,
+    queryables: HashMap<u64, Queryable<'static, ()>>,
+    // queries received by the queryables of the client, until it sends `reply_final`
+    queries: Arc<Mutex<HashMap<u64, Query>>>,
+    next_query_id: Arc<AtomicU64>,
+}
+
+impl WsConnection {
+    fn new(session: Arc<Session>, permissions: Option<Permissions>) -> Self {
+        let (tx, rx) = flume::bounded(WS_QUEUE_SIZE);
+        WsConnection {
+            session,
+            permissions,
+            tx,
+            rx,
+            subscribers: HashMap::new(),
+            queryables: HashMap::new(),
+            queries: Arc::new(Mutex::new(HashMap::new())),
+            next_query_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    async fn run<S>(mut self, ws: WebSocketStream<S>)
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        let (mut sink, mut stream) = ws.split();
+        let rx = self.rx.clone();
+        let writer = async_std::task::spawn(async move {
+            while let Ok(msg) = rx.recv_async().await {
+                if let Err(e) = sink.send(Message::Text(msg)).await {
+                    tracing::debug!("WebSocket error sending a message: {}", e);
+                    break;
+                }
+            }
+            let _ = sink.close().await;
+        });
+        tracing::debug!("WebSocket connection opened");
+        while let Some(msg) = stream.next().await {
+            match msg {
+                Ok(Message::Text(text)) => match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(msg) => self.handle(msg).await,
+                    Err(e) => self.send_error(None, format!("Invalid message: {e}")).await,
+                },
+                Ok(Message::Close(_)) => break,
+                Ok(Message::Binary(_)) => {
+                    self.send_error(None, "Binary messages are not supported".to_string())
+                        .await
+                }
+                // pings are answered by the WebSocket stream itself
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::debug!("WebSocket error: {}", e);
+                    break;
+                }
+            }
+        }
+        tracing::debug!("WebSocket connection closed");
+        // undeclare the subscribers and queryables, and terminate the pending queries
+        self.subscribers.clear();
+        self.queryables.clear();
+        self.queries.lock().unwrap().clear();
+        writer.cancel().await;
+    }
+
+    async fn handle(&mut self, msg: ClientMessage) {
+        tracing::trace!("WebSocket message: {:?}", msg);
+        if let Some((id, action, key_expr)) = msg.access() {
+            if !self.allows(action, key_expr) {
+                return self
+                    .send_error(id, format!("Access denied: {action:?} on {key_expr}"))
+                    .await;
+            }
+        }
+        match msg {
+            ClientMessage::Subscribe { id, .. } | ClientMessage::Queryable { id, .. }
+                if self.subscribers.contains_key(&id) || self.queryables.contains_key(&id) =>
+            {
+                self.send_error(Some(id), format!("Declaration {id} already exists"))
+                    .await
+            }
+            ClientMessage::Subscribe { id, key_expr } => {
+                let tx = self.tx.clone();
+                let result = self
+                    .session
+                    .declare_subscriber(key_expr)
+                    .callback(move |sample| {
+                        if let Err(flume::TrySendError::Full(_)) =
+                            tx.try_send(sample_message("sample", id, sample))
+                        {
+                            tracing::debug!("WebSocket queue full: sample dropped");
+                        }
+                    })
+                    .res()
+                    .await;
+                match result {
+                    Ok(subscriber) => {
+                        self.subscribers.insert(id, subscriber);
+                        self.send_ok(id).await;
+                    }
+                    Err(e) => self.send_error(Some(id), e.to_string()).await,
+                }
+            }
+            ClientMessage::Queryable {
+                id,
+                key_expr,
+                complete,
+            } => {
+                let tx = self.tx.clone();
+                let queries = self.queries.clone();
+                let next_query_id = self.next_query_id.clone();
+                let result = self
+                    .session
+                    .declare_queryable(key_expr)
+                    .complete(complete)
+                    .callback(move |query| {
+                        let query_id = next_query_id.fetch_add(1, Ordering::Relaxed);
+                        let mut msg = json!({
+                            "op": "query",
+                            "id": id,
+                            "query_id": query_id,
+                            "selector": query.selector().to_string(),
+                        });
+                        if let Some(value) = query.value() {
+                            msg["value"] = json_value(value.clone());
+                            msg["encoding"] = json!(value.encoding.to_string());
+                        }
+                        // a query not forwarded to the client is finalized right away
+                        if let Err(flume::TrySendError::Full(_)) = tx.try_send(msg.to_string()) {
+                            tracing::debug!("WebSocket queue full: query dropped");
+                            return;
+                        }
+                        queries.lock().unwrap().insert(query_id, query);
+                        let queries = queries.clone();
+                        async_std::task::spawn(async move {
+                            async_std::task::sleep(WS_QUERY_TIMEOUT).await;
+                            if queries.lock().unwrap().remove(&query_id).is_some() {
+                                tracing::debug!("WebSocket query {} timed out", query_id);
+                            }
+                        });
+                    })
+                    .res()
+                    .await;
+                match result {
+                    Ok(queryable) => {
+                        self.queryables.insert(id, queryable);
+                        self.send_ok(id).await;
+                    }
+                    Err(e) => self.send_error(Some(id), e.to_string()).await,
+                }
+            }
+            ClientMessage::Undeclare { id } => {
+                let subscriber = self.subscribers.remove(&id);
+                let queryable = self.queryables.remove(&id);
+                if subscriber.is_some() || queryable.is_some() {
+                    self.send_ok(id).await;
+                } else {
+                    self.send_error(Some(id), format!("Unknown declaration {id}"))
+                        .await;
+                }
+            }
+            ClientMessage::Put { key_expr, value } => {
+                let result = match value.into_value() {
+                    Ok(value) => self.session.put(key_expr, value).res().await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    self.send_error(None, e.to_string()).await;
+                }
+            }
+            ClientMessage::Delete { key_expr } => {
+                if let Err(e) = self.session.delete(key_expr).res().await {
+                    self.send_error(None, e.to_string()).await;
+                }
+            }
+            ClientMessage::Get {
+                id,
+                selector,
+                value,
+            } => {
+                let value = match value.map(ClientValue::into_value).transpose() {
+                    Ok(value) => value,
+                    Err(e) => return self.send_error(Some(id), e.to_string()).await,
+                };
+                let session = self.session.clone();
+                let tx = self.tx.clone();
+                // the replies are forwarded in a separate task, not to block the other messages
+                async_std::task::spawn(async move {
+                    let mut get = session.get(selector);
+                    if let Some(value) = value {
+                        get = get.with_value(value);
+                    }
+                    match get.res().await {
+                        Ok(replies) => {
+                            while let Ok(reply) = replies.recv_async().await {
+                                let msg = match reply.sample {
+                                    Ok(sample) => sample_message("reply", id, sample),
+                                    Err(value) => json!({
+                                        "op": "reply_error",
+                                        "id": id,
+                                        "encoding": value.encoding.to_string(),
+                                        "value": json_value(value),
+                                    })
+                                    .to_string(),
+                                };
+                                let _ = tx.send_async(msg).await;
+                            }
+                            let _ = tx
+                                .send_async(json!({ "op": "reply_final", "id": id }).to_string())
+                                .await;
+                        }
+                        Err(e) => {
+                            let _ = tx.send_async(error_message(Some(id), e.to_string())).await;
+                        }
+                    }
+                });
+            }
+            ClientMessage::Reply {
+                query_id,
+                key_expr,
+                value,
+            } => {
+                let result = match (self.get_query(query_id), value.into_value()) {
+                    (Ok(query), Ok(value)) => match KeyExpr::try_from(key_expr) {
+                        Ok(key_expr) => query.reply(Ok(Sample::new(key_expr, value))).res().await,
+                        Err(e) => Err(e),
+                    },
+                    (Err(e), _) | (_, Err(e)) => Err(e),
+                };
+                if let Err(e) = result {
+                    self.send_error(None, e.to_string()).await;
+                }
+            }
+            ClientMessage::ReplyError { query_id, value } => {
+                let result = match (self.get_query(query_id), value.into_value()) {
+                    (Ok(query), Ok(value)) => query.reply(Err(value)).res().await,
+                    (Err(e), _) | (_, Err(e)) => Err(e),
+                };
+                if let Err(e) = result {
+                    self.send_error(None, e.to_string()).await;
+                }
+            }
+            ClientMessage::ReplyFinal { query_id } => {
+                // the query is finalized when it is dropped
+                let query = self.queries.lock().unwrap().remove(&query_id);
+                if query.is_none() {
+                    self.send_error(None, format!("Unknown query {query_id}"))
+                        .await;
+                }
+            }
+        }
+    }
+
+    fn allows(&self, action: Action, key_expr: &str) -> bool {
+        self.permissions
+            .as_ref()
+            .map_or(true, |p| p.allows(action, key_expr))
+    }
+
+    fn get_query(&self, query_id: u64) -> ZResult<Query> {
+        self.queries
+            .lock()
+            .unwrap()
+            .get(&query_id)
+            .cloned()
+            .ok_or_else(|| zerror!("Unknown query {}", query_id).into())
+    }
+
+    async fn send_ok(&self, id: u64) {
+        let _ = self
+            .tx
+            .send_async(json!({ "op": "ok", "id": id }).to_string())
+            .await;
+    }
+
+    async fn send_error(&self, id: Option<u64>, error: String) {
+        tracing::debug!("WebSocket error: {}", error);
+        let _ = self.tx.send_async(error_message(id, error)).await;
+    }
+}
+
+fn json_value(value: Value) -> serde_json::Value {
+    let json = value_to_json(value);
+    serde_json::from_str(&json).unwrap_or(serde_json::Value::String(json))
+}
+
+fn sample_message(op: &str, id: u64, sample: Sample) -> String {
+    json!({
+        "op": op,
+        "id": id,
+        "key": sample.key_expr.as_str(),
+        "encoding": sample.value.encoding.to_string(),
+        "time": sample.timestamp.map(|ts| ts.to_string()),
+        "kind": sample.kind.to_string(),
+        "value": json_value(sample.value),
+    })
+    .to_string()
+}
+
+fn error_message(id: Option<u64>, error: String) -> String {
+    json!({ "op": "error", "id": id, "error": error }).to_string()
+}
+
+#[test]
+fn test_client_messages() {
+    let msg: ClientMessage =
+        serde_json::from_str(r#"{"op": "subscribe", "id": 1, "key_expr": "demo/**"}"#).unwrap();
+    assert!(matches!(msg, ClientMessage::Subscribe { id: 1, .. }));
+
+    let msg: ClientMessage =
+        serde_json::from_str(r#"{"op": "put", "key_expr": "demo/a", "value": {"x": 1}}"#).unwrap();
+    let ClientMessage::Put { value, .. } = msg else {
+        panic!("expected a put")
+    };
+    let value = value.into_value().unwrap();
+    assert_eq!(value.encoding, KnownEncoding::AppJson.into());
+    assert_eq!(value.to_string(), r#"{"x":1}"#);
+
+    let msg: ClientMessage =
+        serde_json::from_str(r#"{"op": "get", "id": 2, "selector": "demo/**?x=1"}"#).unwrap();
+    assert!(matches!(msg, ClientMessage::Get { value: None, .. }));
+    assert!(matches!(
+        msg.access(),
+        Some((Some(2), Action::Get, "demo/**"))
+    ));
+
+    let msg: ClientMessage = serde_json::from_str(
+        r#"{"op": "reply", "query_id": 3, "key_expr": "demo/a", "value": "AAH/", "base64": true}"#,
+    )
+    .unwrap();
+    assert!(msg.access().is_none());
+    let ClientMessage::Reply { value, .. } = msg else {
+        panic!("expected a reply")
+    };
+    let value = value.into_value().unwrap();
+    assert_eq!(value.encoding, KnownEncoding::AppOctetStream.into());
+    assert_eq!(value.payload.contiguous().as_ref(), &[0u8, 1, 255]);
+}
+
+#[async_std::test]
+async fn test_websocket_connection() {
+    use async_std::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::client_async;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    let mut config = zenoh::config::peer();
+    config.scouting.multicast.set_enabled(Some(false)).unwrap();
+    let session = Arc::new(zenoh::open(config).res().await.unwrap());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let conf: Config = serde_json::from_value(json!({ "http_port": address.to_string() })).unwrap();
+    let mut app = tide::Server::with_state((session, "zid".to_string(), conf));
+    app.at(WS_PATH).get(upgrade);
+    async_std::task::spawn(app.listen(listener));
+
+    let stream = TcpStream::connect(address).await.unwrap();
+    let (mut ws, _) = client_async(format!("ws://{address}{WS_PATH}"), stream.compat())
+        .await
+        .unwrap();
+    for msg in [
+        json!({ "op": "subscribe", "id": 1, "key_expr": "test/ws/**" }),
+        json!({ "op": "subscribe", "id": 1, "key_expr": "test/ws/a" }),
+        json!({ "op": "put", "key_expr": "test/ws/a", "value": "hello" }),
+    ] {
+        ws.send(Message::Text(msg.to_string())).await.unwrap();
+    }
+
+    let mut received = Vec::new();
+    while received.len() < 3 {
+        let msg = async_std::future::timeout(TIMEOUT, ws.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        received
+            .push(serde_json::from_str::<serde_json::Value>(&msg.into_text().unwrap()).unwrap());
+    }
+    assert_eq!(received[0], json!({ "op": "ok", "id": 1 }));
+    // the id of a live declaration cannot be reused
+    assert_eq!(received[1]["op"], "error");
+    assert_eq!(received[1]["id"], 1);
+    // the put of the client is delivered to its subscription
+    assert_eq!(received[2]["op"], "sample");
+    assert_eq!(received[2]["id"], 1);
+    assert_eq!(received[2]["key"], "test/ws/a");
+    assert_eq!(received[2]["value"], "hello");
+}
@@ -13,6 +13,7 @@
 //
 use super::storages_mgt::*;
 use flume::Sender;
+use std::path::PathBuf;
 use std::sync::Arc;
 use zenoh::prelude::r#async::*;
 use zenoh::Session;
@@ -33,6 +34,7 @@ pub(crate) async fn create_and_start_storage(
     backend: &VolumeInstance,
     in_interceptor: Option<Arc<dyn Fn(Sample) -> Sample + Send + Sync>>,
     out_interceptor: Option<Arc<dyn Fn(Sample) -> Sample + Send + Sync>>,
+    snapshots_dir: Option<PathBuf>,
     zenoh: Arc<Session>,
 ) -> ZResult<Sender<StorageMessage>> {
     tracing::trace!("Create storage '{}'", &admin_key);
@@ -45,5 +47,5 @@ pub(crate) async fn create_and_start_storage(
         out_interceptor,
     };
 
-    start_storage(store_intercept, config, admin_key, zenoh).await
+    start_storage(store_intercept, config, admin_key, snapshots_dir, zenoh).await
 }
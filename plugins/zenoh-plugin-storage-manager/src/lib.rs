@@ -24,6 +24,7 @@ use flume::Sender;
 use memory_backend::MemoryBackend;
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 use std::sync::Mutex;
 use storages_mgt::StorageMessage;
@@ -43,13 +44,14 @@ use zenoh_plugin_trait::Plugin;
 use zenoh_plugin_trait::PluginControl;
 use zenoh_plugin_trait::PluginReport;
 use zenoh_plugin_trait::PluginStatusRec;
-use zenoh_result::ZResult;
+use zenoh_result::{bail, zerror, ZResult};
 use zenoh_util::LibLoader;
 
 mod backends_mgt;
 use backends_mgt::*;
 mod memory_backend;
 mod replica;
+mod snapshot;
 mod storages_mgt;
 
 #[cfg(feature = "dynamic_plugin")]
@@ -85,6 +87,7 @@ struct StorageRuntimeInner {
     runtime: Runtime,
     session: Arc<Session>,
     storages: HashMap<String, HashMap<String, Sender<StorageMessage>>>,
+    snapshots_dir: Option<PathBuf>,
     plugins_manager: PluginsManager,
 }
 impl StorageRuntimeInner {
@@ -103,6 +106,7 @@ impl StorageRuntimeInner {
         let PluginConfig {
             name,
             backend_search_dirs,
+            snapshots_dir,
             volumes,
             storages,
             ..
@@ -123,6 +127,7 @@ impl StorageRuntimeInner {
             runtime,
             session,
             storages: Default::default(),
+            snapshots_dir: snapshots_dir.map(PathBuf::from),
             plugins_manager,
         };
         new_self
@@ -247,6 +252,7 @@ impl StorageRuntimeInner {
             backend.instance(),
             in_interceptor,
             out_interceptor,
+            self.snapshots_dir.clone(),
             self.session.clone(),
         ))?;
         self.storages
@@ -365,11 +371,159 @@ impl RunningPluginTrait for StorageRuntime {
         });
         Ok(responses)
     }
+
+    fn adminspace_schemas(&self) -> Vec<zenoh::plugins::Response> {
+        vec![
+            zenoh::plugins::Response::new(
+                "version".to_string(),
+                serde_json::json!({
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "title": "Version",
+                    "type": "string"
+                }),
+            ),
+            zenoh::plugins::Response::new(
+                "volumes".to_string(),
+                serde_json::json!({
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "title": "Volume",
+                    "description": "The status of a volume on `volumes/<name>`, specific to its backend, whose library path is on `volumes/<name>/__path__`",
+                    "type": "object"
+                }),
+            ),
+            zenoh::plugins::Response::new("storages".to_string(), storage_status_schema()),
+        ]
+    }
+}
+
+// The status of a storage on `storages/<name>`: the status of its backend, with the statistics of its cache
+// and the state of its replication if any
+fn storage_status_schema() -> serde_json::Value {
+    serde_json::json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "Storage",
+        "description": "The status of a storage on `storages/<name>`, specific to its backend, along with the statistics of its cache and the state of its replication if any",
+        "type": "object",
+        "properties": {
+            "cache": {
+                "type": "object",
+                "required": ["capacity", "size", "hits", "misses"],
+                "properties": {
+                    "capacity": { "type": "integer", "minimum": 0 },
+                    "size": { "type": "integer", "minimum": 0 },
+                    "hits": { "type": "integer", "minimum": 0 },
+                    "misses": { "type": "integer", "minimum": 0 }
+                }
+            },
+            "replication": {
+                "type": "object",
+                "required": ["alignment", "checksum", "converged", "last_alignment", "peers"],
+                "properties": {
+                    "alignment": {},
+                    "checksum": { "type": "integer" },
+                    "converged": { "type": "boolean" },
+                    "last_alignment": { "type": ["string", "null"] },
+                    "peers": {
+                        "type": "object",
+                        "additionalProperties": {
+                            "type": "object",
+                            "required": ["last_digest", "checksum", "converged", "last_alignment", "keys_fetched", "alignment_rounds", "alignment_errors"],
+                            "properties": {
+                                "last_digest": { "type": "string" },
+                                "checksum": { "type": "integer" },
+                                "converged": { "type": "boolean" },
+                                "last_alignment": { "type": ["string", "null"] },
+                                "keys_fetched": { "type": "integer", "minimum": 0 },
+                                "alignment_rounds": { "type": "integer", "minimum": 0 },
+                                "alignment_errors": { "type": "integer", "minimum": 0 }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
 }
 
 const BACKEND_LIB_PREFIX: &str = "zenoh_backend_";
 const MEMORY_BACKEND_NAME: &str = "memory";
 
+// Resolves the `path` of a snapshot operation within the configured `snapshots_dir`.
+// The path must be relative and cannot leave this directory, even through symbolic links.
+fn snapshot_path(snapshots_dir: Option<&Path>, path: &str) -> ZResult<PathBuf> {
+    let Some(snapshots_dir) = snapshots_dir else {
+        bail!("Snapshots are disabled: no `snapshots_dir` is configured")
+    };
+    let relative = Path::new(path);
+    if relative.file_name().is_none()
+        || !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        bail!(
+            "Invalid snapshot path '{}': it must be a relative path without '..'",
+            path
+        )
+    }
+    let snapshots_dir = snapshots_dir
+        .canonicalize()
+        .map_err(|e| zerror!("Invalid snapshots_dir '{}': {}", snapshots_dir.display(), e))?;
+    let path = snapshots_dir.join(relative);
+    // the file itself doesn't exist yet for an export, but its directory must
+    let parent = path
+        .parent()
+        .and_then(|parent| parent.canonicalize().ok())
+        .ok_or_else(|| {
+            zerror!(
+                "Invalid snapshot path '{}': no such directory",
+                relative.display()
+            )
+        })?;
+    // a dangling symbolic link cannot be resolved, and could still be written through
+    let path = if path.symlink_metadata().is_ok() {
+        path.canonicalize()
+            .map_err(|e| zerror!("Invalid snapshot path '{}': {}", relative.display(), e))?
+    } else {
+        parent.join(relative.file_name().unwrap_or_default())
+    };
+    if !parent.starts_with(&snapshots_dir) || !path.starts_with(&snapshots_dir) {
+        bail!(
+            "Invalid snapshot path '{}': it is outside of snapshots_dir",
+            relative.display()
+        )
+    }
+    Ok(path)
+}
+
+#[test]
+fn test_snapshot_path() {
+    let dir = std::env::temp_dir().join(format!("zenoh_snapshots_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    let canonical = dir.canonicalize().unwrap();
+
+    assert!(snapshot_path(None, "snapshot.json").is_err());
+    assert_eq!(
+        snapshot_path(Some(&dir), "snapshot.json").unwrap(),
+        canonical.join("snapshot.json")
+    );
+    assert_eq!(
+        snapshot_path(Some(&dir), "sub/snapshot.json").unwrap(),
+        canonical.join("sub/snapshot.json")
+    );
+    assert!(snapshot_path(Some(&dir), "/etc/passwd").is_err());
+    assert!(snapshot_path(Some(&dir), "../snapshot.json").is_err());
+    assert!(snapshot_path(Some(&dir), "sub/../../snapshot.json").is_err());
+    assert!(snapshot_path(Some(&dir), "missing/snapshot.json").is_err());
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(std::env::temp_dir(), dir.join("link")).unwrap();
+        assert!(snapshot_path(Some(&dir), "link/snapshot.json").is_err());
+        std::os::unix::fs::symlink("/nonexistent/file", dir.join("dangling")).unwrap();
+        assert!(snapshot_path(Some(&dir), "dangling").is_err());
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
 fn with_extended_string<R, F: FnMut(&mut String) -> R>(
     prefix: &mut String,
     suffixes: &[&str],
@@ -383,3 +537,21 @@ fn with_extended_string<R, F: FnMut(&mut String) -> R>(
     prefix.truncate(prefix_len);
     result
 }
+
+#[test]
+fn test_storage_status_schema() {
+    use zenoh_backend_traits::config::AlignmentStrategy;
+    let schema = storage_status_schema();
+    let statuses = [
+        ("cache", replica::cache::LatestValueCache::new(1).to_json()),
+        (
+            "replication",
+            replica::status::ReplicaStatus::new(AlignmentStrategy::Era).to_json(),
+        ),
+    ];
+    for (name, status) in statuses {
+        for property in schema["properties"][name]["required"].as_array().unwrap() {
+            assert!(status.get(property.as_str().unwrap()).is_some());
+        }
+    }
+}
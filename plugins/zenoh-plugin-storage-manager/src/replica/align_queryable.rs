@@ -213,54 +213,7 @@ impl AlignQueryable {
 // replying queries
 impl AlignQueryable {
     async fn get_entry(&self, logentry: &LogEntry) -> Option<Sample> {
-        // get corresponding key from log
-        let replies = self.session.get(&logentry.key).res().await.unwrap();
-        if let Ok(reply) = replies.recv_async().await {
-            match reply.sample {
-                Ok(sample) => {
-                    tracing::trace!(
-                        "[ALIGN QUERYABLE] Received ('{}': '{}' @ {:?})",
-                        sample.key_expr.as_str(),
-                        sample.value,
-                        sample.timestamp
-                    );
-                    if let Some(timestamp) = sample.timestamp {
-                        match timestamp.cmp(&logentry.timestamp) {
-                            Ordering::Greater => {
-                                tracing::error!(
-                                    "[ALIGN QUERYABLE] Data in the storage is newer than requested."
-                                );
-                                return None;
-                            }
-                            Ordering::Less => {
-                                tracing::error!(
-                                    "[ALIGN QUERYABLE] Data in the storage is older than requested."
-                                );
-                                return None;
-                            }
-                            Ordering::Equal => {
-                                tracing::debug!(
-                                    "[ALIGN QUERYABLE] Data in the storage has a good timestamp."
-                                );
-                                return Some(sample);
-                            }
-                        }
-                    } else {
-                        tracing::error!(
-                            "[ALIGN QUERYABLE] No timestamp on log entry sample from storage."
-                        );
-                    }
-                }
-                Err(err) => {
-                    tracing::error!(
-                        "[ALIGN QUERYABLE] Error when requesting storage: {:?}.",
-                        err
-                    );
-                    return None;
-                }
-            }
-        }
-        None
+        get_entry(&self.session, logentry).await
     }
 
     async fn get_intervals(&self, era: &EraType) -> HashMap<u64, u64> {
@@ -282,3 +235,55 @@ impl AlignQueryable {
         digest.get_subinterval_content(subintervals)
     }
 }
+
+// Get the sample of a log entry from the storage, if the stored timestamp matches
+pub(super) async fn get_entry(session: &Session, logentry: &LogEntry) -> Option<Sample> {
+    // get corresponding key from log
+    let replies = session.get(&logentry.key).res().await.unwrap();
+    if let Ok(reply) = replies.recv_async().await {
+        match reply.sample {
+            Ok(sample) => {
+                tracing::trace!(
+                    "[ALIGN QUERYABLE] Received ('{}': '{}' @ {:?})",
+                    sample.key_expr.as_str(),
+                    sample.value,
+                    sample.timestamp
+                );
+                if let Some(timestamp) = sample.timestamp {
+                    match timestamp.cmp(&logentry.timestamp) {
+                        Ordering::Greater => {
+                            tracing::error!(
+                                "[ALIGN QUERYABLE] Data in the storage is newer than requested."
+                            );
+                            return None;
+                        }
+                        Ordering::Less => {
+                            tracing::error!(
+                                "[ALIGN QUERYABLE] Data in the storage is older than requested."
+                            );
+                            return None;
+                        }
+                        Ordering::Equal => {
+                            tracing::debug!(
+                                "[ALIGN QUERYABLE] Data in the storage has a good timestamp."
+                            );
+                            return Some(sample);
+                        }
+                    }
+                } else {
+                    tracing::error!(
+                        "[ALIGN QUERYABLE] No timestamp on log entry sample from storage."
+                    );
+                }
+            }
+            Err(err) => {
+                tracing::error!(
+                    "[ALIGN QUERYABLE] Error when requesting storage: {:?}.",
+                    err
+                );
+                return None;
+            }
+        }
+    }
+    None
+}
@@ -12,10 +12,11 @@
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
 
-use super::{Digest, EraType, LogEntry, Snapshotter};
+use super::{Digest, EraType, LogEntry, ReplicaStatus, Snapshotter};
 use super::{CONTENTS, ERA, INTERVALS, SUBINTERVALS};
 use async_std::sync::{Arc, RwLock};
 use flume::{Receiver, Sender};
+use futures::select;
 use std::collections::{HashMap, HashSet};
 use std::str;
 use zenoh::key_expr::{KeyExpr, OwnedKeyExpr};
@@ -28,8 +29,12 @@ pub struct Aligner {
     digest_key: OwnedKeyExpr,
     snapshotter: Arc<Snapshotter>,
     rx_digest: Receiver<(String, Digest)>,
+    rx_align: Receiver<async_std::channel::Sender<serde_json::Value>>,
     tx_sample: Sender<Sample>,
     digests_processed: RwLock<HashSet<u64>>,
+    // latest digest received from each replica, realigned on request
+    latest_digests: RwLock<HashMap<String, Digest>>,
+    status: Arc<RwLock<ReplicaStatus>>,
 }
 
 impl Aligner {
@@ -37,43 +42,80 @@ impl Aligner {
         session: Arc<Session>,
         digest_key: OwnedKeyExpr,
         rx_digest: Receiver<(String, Digest)>,
+        rx_align: Receiver<async_std::channel::Sender<serde_json::Value>>,
         tx_sample: Sender<Sample>,
         snapshotter: Arc<Snapshotter>,
+        status: Arc<RwLock<ReplicaStatus>>,
     ) {
         let aligner = Aligner {
             session,
             digest_key,
             snapshotter,
             rx_digest,
+            rx_align,
             tx_sample,
             digests_processed: RwLock::new(HashSet::new()),
+            latest_digests: RwLock::new(HashMap::new()),
+            status,
         };
         aligner.start().await;
     }
 
     pub async fn start(&self) {
-        while let Ok((from, incoming_digest)) = self.rx_digest.recv_async().await {
-            if self.in_processed(incoming_digest.checksum).await {
-                tracing::trace!(
-                    "[ALIGNER]Skipping already processed digest: {}",
-                    incoming_digest.checksum
-                );
-                continue;
-            } else if self.snapshotter.get_digest().await.checksum == incoming_digest.checksum {
-                tracing::trace!(
-                    "[ALIGNER]Skipping matching digest: {}",
-                    incoming_digest.checksum
-                );
-                continue;
-            } else {
-                // process this digest
-                tracing::debug!(
-                    "[ALIGNER]Processing digest: {:?} from {}",
-                    incoming_digest,
-                    from
-                );
-                self.process_incoming_digest(incoming_digest, &from).await;
-            }
+        loop {
+            select!(
+                digest = self.rx_digest.recv_async() => {
+                    let Ok((from, incoming_digest)) = digest else {
+                        return;
+                    };
+                    self.latest_digests
+                        .write()
+                        .await
+                        .insert(from.clone(), incoming_digest.clone());
+                    self.align_with(incoming_digest, &from).await;
+                },
+                // on alignment request, process again the latest digests of all replicas
+                request = self.rx_align.recv_async() => {
+                    let Ok(reply) = request else {
+                        return;
+                    };
+                    let latest_digests = self.latest_digests.read().await.clone();
+                    tracing::debug!(
+                        "[ALIGNER] Alignment requested with {} replicas",
+                        latest_digests.len()
+                    );
+                    for (from, digest) in latest_digests {
+                        if self.snapshotter.get_digest().await.checksum != digest.checksum {
+                            self.process_incoming_digest(digest, &from).await;
+                        }
+                    }
+                    // the outcome of the round is reported by the status of the replica
+                    let status = self.status.read().await.to_json();
+                    std::mem::drop(reply.send(status).await);
+                },
+            );
+        }
+    }
+
+    async fn align_with(&self, incoming_digest: Digest, from: &str) {
+        if self.in_processed(incoming_digest.checksum).await {
+            tracing::trace!(
+                "[ALIGNER]Skipping already processed digest: {}",
+                incoming_digest.checksum
+            );
+        } else if self.snapshotter.get_digest().await.checksum == incoming_digest.checksum {
+            tracing::trace!(
+                "[ALIGNER]Skipping matching digest: {}",
+                incoming_digest.checksum
+            );
+        } else {
+            // process this digest
+            tracing::debug!(
+                "[ALIGNER]Processing digest: {:?} from {}",
+                incoming_digest,
+                from
+            );
+            self.process_incoming_digest(incoming_digest, from).await;
         }
     }
 
@@ -94,7 +136,10 @@ impl Aligner {
 
         // If missing content is not identified, it showcases some problem
         // The problem will be addressed in the future rounds, hence will not count as processed
-        if !missing_content.is_empty() {
+        // nor as a successful alignment
+        if missing_content.is_empty() {
+            self.status.write().await.alignment_done(from, 0, false);
+        } else {
             let (missing_data, no_data_err) = self
                 .get_missing_data(&missing_content, timestamp, from)
                 .await;
@@ -102,6 +147,11 @@ impl Aligner {
             // Missing data might be empty since some samples in digest might be outdated
             tracing::debug!("[ALIGNER] Received {} queried samples", missing_data.len());
             tracing::trace!("[ALIGNER] Received queried samples: {missing_data:?}");
+            self.status.write().await.alignment_done(
+                from,
+                missing_data.len(),
+                no_content_err && no_data_err,
+            );
 
             for (key, (ts, value)) in missing_data {
                 let sample = Sample::new(key, value).with_timestamp(ts);
@@ -310,51 +360,58 @@ impl Aligner {
     }
 
     async fn perform_query(&self, from: &str, properties: String) -> (Vec<Sample>, bool) {
-        let mut no_err = true;
-        let selector = KeyExpr::from(&self.digest_key)
-            .join(&from)
-            .unwrap()
-            .with_parameters(&properties);
-        tracing::trace!("[ALIGNER] Sending Query '{}'...", selector);
-        let mut return_val = Vec::new();
-        match self
-            .session
-            .get(&selector)
-            .consolidation(zenoh::query::ConsolidationMode::None)
-            .accept_replies(zenoh::query::ReplyKeyExpr::Any)
-            .res()
-            .await
-        {
-            Ok(replies) => {
-                while let Ok(reply) = replies.recv_async().await {
-                    match reply.sample {
-                        Ok(sample) => {
-                            tracing::trace!(
-                                "[ALIGNER] Received ('{}': '{}')",
-                                sample.key_expr.as_str(),
-                                sample.value
-                            );
-                            return_val.push(sample);
-                        }
-                        Err(err) => {
-                            tracing::error!(
-                                "[ALIGNER] Received error for query on selector {} :{}",
-                                selector,
-                                err
-                            );
-                            no_err = false;
-                        }
+        query_replica(&self.session, &self.digest_key, from, properties).await
+    }
+}
+
+// Query the align queryable of another replica
+pub(super) async fn query_replica(
+    session: &Session,
+    digest_key: &OwnedKeyExpr,
+    from: &str,
+    properties: String,
+) -> (Vec<Sample>, bool) {
+    let mut no_err = true;
+    let selector = KeyExpr::from(digest_key)
+        .join(&from)
+        .unwrap()
+        .with_parameters(&properties);
+    tracing::trace!("[ALIGNER] Sending Query '{}'...", selector);
+    let mut return_val = Vec::new();
+    match session
+        .get(&selector)
+        .consolidation(zenoh::query::ConsolidationMode::None)
+        .accept_replies(zenoh::query::ReplyKeyExpr::Any)
+        .res()
+        .await
+    {
+        Ok(replies) => {
+            while let Ok(reply) = replies.recv_async().await {
+                match reply.sample {
+                    Ok(sample) => {
+                        tracing::trace!(
+                            "[ALIGNER] Received ('{}': '{}')",
+                            sample.key_expr.as_str(),
+                            sample.value
+                        );
+                        return_val.push(sample);
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            "[ALIGNER] Received error for query on selector {} :{}",
+                            selector,
+                            err
+                        );
+                        no_err = false;
                     }
                 }
             }
-            Err(err) => {
-                tracing::error!("[ALIGNER] Query failed on selector `{}`: {}", selector, err);
-                no_err = false;
-            }
-        };
-        tracing::trace!(
-            "[ALIGNER] On Query '{selector}' received: {return_val:?} (no_err:{no_err})"
-        );
-        (return_val, no_err)
-    }
+        }
+        Err(err) => {
+            tracing::error!("[ALIGNER] Query failed on selector `{}`: {}", selector, err);
+            no_err = false;
+        }
+    };
+    tracing::trace!("[ALIGNER] On Query '{selector}' received: {return_val:?} (no_err:{no_err})");
+    (return_val, no_err)
 }
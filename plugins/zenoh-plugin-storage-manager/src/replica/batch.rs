@@ -0,0 +1,109 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+// Coalescing of the writes of a storage into batches, flushed by the storage service
+// when they reach `max_size` writes, or when the oldest write has waited for `max_delay`.
+
+use std::collections::HashSet;
+use std::time::Instant;
+use zenoh::prelude::SampleKind;
+use zenoh_backend_traits::config::BatchConfig;
+use zenoh_backend_traits::StoredData;
+use zenoh_keyexpr::key_expr::OwnedKeyExpr;
+
+#[derive(Debug, Clone)]
+pub struct PendingWrite {
+    // key of the write, including the strip_prefix of the storage
+    pub key: OwnedKeyExpr,
+    pub kind: SampleKind,
+    pub data: StoredData,
+}
+
+pub struct WriteBatch {
+    config: BatchConfig,
+    writes: Vec<PendingWrite>,
+    keys: HashSet<OwnedKeyExpr>,
+    // time before which the batch must be flushed, if it is not empty
+    deadline: Option<Instant>,
+}
+
+impl WriteBatch {
+    pub fn new(config: BatchConfig) -> Self {
+        WriteBatch {
+            config,
+            writes: Vec::new(),
+            keys: HashSet::new(),
+            deadline: None,
+        }
+    }
+
+    pub fn contains(&self, key: &OwnedKeyExpr) -> bool {
+        self.keys.contains(key)
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    // Adds a write to the batch, returns true if the batch is full and must be flushed
+    pub fn push(&mut self, write: PendingWrite) -> bool {
+        if self.writes.is_empty() {
+            self.deadline = Some(Instant::now() + self.config.max_delay);
+        }
+        self.keys.insert(write.key.clone());
+        self.writes.push(write);
+        self.writes.len() >= self.config.max_size
+    }
+
+    // Empties the batch, returning its writes in their order of arrival
+    pub fn take(&mut self) -> Vec<PendingWrite> {
+        self.keys.clear();
+        self.deadline = None;
+        std::mem::take(&mut self.writes)
+    }
+}
+
+#[test]
+fn test_write_batch() {
+    use std::str::FromStr;
+    use std::time::Duration;
+    use zenoh::prelude::Value;
+    use zenoh::time::Timestamp;
+
+    let timestamp = Timestamp::from_str("2022-12-21T15:00:00.000000000Z/1").unwrap();
+    let get_write = |key: &str| PendingWrite {
+        key: OwnedKeyExpr::from_str(key).unwrap(),
+        kind: SampleKind::Put,
+        data: StoredData {
+            value: Value::from(key),
+            timestamp,
+        },
+    };
+    let mut batch = WriteBatch::new(BatchConfig {
+        max_size: 2,
+        max_delay: Duration::from_secs(1),
+    });
+    assert!(batch.deadline().is_none());
+    assert!(!batch.push(get_write("demo/a")));
+    assert!(batch.deadline().is_some());
+    assert!(batch.contains(&OwnedKeyExpr::from_str("demo/a").unwrap()));
+    assert!(batch.push(get_write("demo/b")));
+
+    let writes = batch.take();
+    assert_eq!(writes.len(), 2);
+    assert_eq!(writes[0].key.as_str(), "demo/a");
+    assert_eq!(writes[1].key.as_str(), "demo/b");
+    assert!(batch.deadline().is_none());
+    assert!(!batch.contains(&writes[0].key));
+}
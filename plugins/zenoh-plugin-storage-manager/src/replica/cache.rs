@@ -0,0 +1,156 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+// Bounded cache of the latest values of a storage, maintained in front of backends with a high `read_cost`.
+// The cache is written through by the storage service on every put, and invalidated on every delete,
+// so that it never holds a value older than the one in the backend.
+
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use zenoh::time::Timestamp;
+use zenoh_backend_traits::StoredData;
+use zenoh_keyexpr::key_expr::OwnedKeyExpr;
+
+pub struct LatestValueCache {
+    capacity: usize,
+    // latest value of each cached key, with the tick of its latest access
+    entries: HashMap<OwnedKeyExpr, (StoredData, u64)>,
+    // cached keys ordered by their latest access, the first one is the least recently used
+    accesses: BTreeMap<u64, OwnedKeyExpr>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl LatestValueCache {
+    pub fn new(capacity: usize) -> Self {
+        LatestValueCache {
+            capacity,
+            entries: HashMap::new(),
+            accesses: BTreeMap::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &OwnedKeyExpr) -> Option<StoredData> {
+        let tick = self.next_tick();
+        match self.entries.get_mut(key) {
+            Some((data, last_access)) => {
+                self.accesses.remove(last_access);
+                self.accesses.insert(tick, key.clone());
+                *last_access = tick;
+                self.hits += 1;
+                Some(data.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    // Caches the value of a key, unless the cache already holds a newer one
+    pub fn put(&mut self, key: OwnedKeyExpr, data: StoredData) {
+        if self.capacity == 0 {
+            return;
+        }
+        let tick = self.next_tick();
+        if let Some((cached, last_access)) = self.entries.get(&key) {
+            if cached.timestamp > data.timestamp {
+                return;
+            }
+            self.accesses.remove(last_access);
+        }
+        self.accesses.insert(tick, key.clone());
+        self.entries.insert(key, (data, tick));
+        while self.entries.len() > self.capacity {
+            match self.accesses.pop_first() {
+                Some((_, lru)) => {
+                    self.entries.remove(&lru);
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn invalidate(&mut self, key: &OwnedKeyExpr) {
+        if let Some((_, last_access)) = self.entries.remove(key) {
+            self.accesses.remove(&last_access);
+        }
+    }
+
+    // Timestamp of the cached value of a key, without counting it as an access
+    pub fn timestamp(&self, key: &OwnedKeyExpr) -> Option<Timestamp> {
+        self.entries.get(key).map(|(data, _)| data.timestamp)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "capacity": self.capacity,
+            "size": self.entries.len(),
+            "hits": self.hits,
+            "misses": self.misses,
+        })
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+fn get_data(value: &str, second: u64) -> StoredData {
+    use std::str::FromStr;
+    use zenoh::prelude::Value;
+    StoredData {
+        value: Value::from(value),
+        timestamp: Timestamp::from_str(&format!("2022-12-21T15:00:{:02}.000000000Z/1", second))
+            .unwrap(),
+    }
+}
+
+#[test]
+fn test_latest_value_cache() {
+    use std::str::FromStr;
+    let a = OwnedKeyExpr::from_str("demo/a").unwrap();
+    let b = OwnedKeyExpr::from_str("demo/b").unwrap();
+    let c = OwnedKeyExpr::from_str("demo/c").unwrap();
+    let mut cache = LatestValueCache::new(2);
+    assert!(cache.get(&a).is_none());
+
+    cache.put(a.clone(), get_data("a1", 10));
+    cache.put(b.clone(), get_data("b1", 10));
+    // an older value does not override a newer one
+    cache.put(a.clone(), get_data("a0", 5));
+    assert_eq!(
+        cache.get(&a).unwrap().timestamp,
+        get_data("a1", 10).timestamp
+    );
+
+    // b is the least recently used key
+    cache.put(c.clone(), get_data("c1", 10));
+    assert!(cache.timestamp(&b).is_none());
+    assert!(cache.timestamp(&a).is_some());
+    assert!(cache.timestamp(&c).is_some());
+
+    cache.invalidate(&a);
+    assert!(cache.get(&a).is_none());
+    let status = cache.to_json();
+    assert_eq!(status["size"], 1);
+    assert_eq!(status["hits"], 1);
+    assert_eq!(status["misses"], 2);
+}
@@ -0,0 +1,339 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+// A Merkle tree over the key space of a storage, used as an alternative to the era digest.
+// Every key is hashed into one of the 16^depth leaves (buckets) of the tree.
+// The hash of a leaf is the XOR of the hashes of its (key, timestamp) entries, so that it can be
+// updated incrementally, and the hash of an inner node is the checksum of the hashes of its children.
+// Two replicas holding the same entries have the same root hash, regardless of when the entries were received.
+// Nodes and leaves are stored sparsely: a node that is not stored is the root of an empty subtree and hashes to 0,
+// so that the memory used by the tree depends on the number of keys rather than on its depth.
+
+use super::LogEntry;
+use async_std::sync::{RwLock, RwLockReadGuard};
+use crc::{Crc, CRC_64_ECMA_182};
+use flume::Receiver;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use zenoh::key_expr::OwnedKeyExpr;
+use zenoh::time::Timestamp;
+
+pub const MERKLE_FANOUT: usize = 16;
+
+const CRC64: Crc<u64> = Crc::<u64>::new(&CRC_64_ECMA_182);
+
+#[derive(Eq, PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct MerkleDigest {
+    pub timestamp: Timestamp,
+    pub depth: usize,
+    pub checksum: u64,
+}
+
+pub struct MerkleTree {
+    depth: usize,
+    // hashes of the non-empty nodes per level: levels[0] contains the root, levels[depth] contains the leaves
+    levels: Vec<HashMap<usize, u64>>,
+    // latest timestamp of each key, per non-empty leaf
+    leaves: HashMap<usize, HashMap<OwnedKeyExpr, Timestamp>>,
+}
+
+impl MerkleTree {
+    pub fn new(depth: usize) -> Self {
+        MerkleTree {
+            depth,
+            levels: (0..=depth).map(|_| HashMap::new()).collect(),
+            leaves: HashMap::new(),
+        }
+    }
+
+    // Creates a tree from the log of a storage when initializing the replica
+    pub fn from_log(depth: usize, log: &[(OwnedKeyExpr, Timestamp)]) -> Self {
+        let mut tree = MerkleTree::new(depth);
+        for (key, timestamp) in log {
+            tree.update(key.clone(), *timestamp);
+        }
+        tree
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn checksum(&self) -> u64 {
+        self.get_hash(0, 0)
+    }
+
+    // Updates the timestamp of a key, returns false if the tree already has a newer one
+    pub fn update(&mut self, key: OwnedKeyExpr, timestamp: Timestamp) -> bool {
+        let leaf = self.get_leaf(&key);
+        let mut hash = self.get_hash(self.depth, leaf);
+        let entries = self.leaves.entry(leaf).or_default();
+        match entries.get(&key) {
+            Some(old) if *old >= timestamp => return false,
+            Some(old) => hash ^= MerkleTree::get_entry_hash(&key, old),
+            None => {}
+        }
+        hash ^= MerkleTree::get_entry_hash(&key, &timestamp);
+        entries.insert(key, timestamp);
+        self.set_hash(self.depth, leaf, hash);
+        self.propagate(leaf);
+        true
+    }
+
+    // Index of the leaf a key belongs to
+    pub fn get_leaf(&self, key: &OwnedKeyExpr) -> usize {
+        (CRC64.checksum(key.as_bytes()) % self.get_width(self.depth) as u64) as usize
+    }
+
+    // Hashes of the children of a node, if the node is not a leaf
+    pub fn get_children(&self, level: usize, index: usize) -> Option<Vec<u64>> {
+        if level >= self.depth || index >= self.get_width(level) {
+            return None;
+        }
+        let first = index * MERKLE_FANOUT;
+        Some(
+            (first..first + MERKLE_FANOUT)
+                .map(|child| self.get_hash(level + 1, child))
+                .collect(),
+        )
+    }
+
+    // Entries of a leaf
+    pub fn get_leaf_content(&self, index: usize) -> Option<Vec<LogEntry>> {
+        if index >= self.get_width(self.depth) {
+            return None;
+        }
+        Some(
+            self.leaves
+                .get(&index)
+                .map(|entries| {
+                    entries
+                        .iter()
+                        .map(|(key, timestamp)| LogEntry {
+                            timestamp: *timestamp,
+                            key: key.clone(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        )
+    }
+
+    // Indexes (at level + 1) of the children of a node whose hashes differ from the given ones
+    pub fn get_children_diff(&self, level: usize, index: usize, other: &[u64]) -> Vec<usize> {
+        match self.get_children(level, index) {
+            Some(children) if children.len() == other.len() => children
+                .iter()
+                .zip(other)
+                .enumerate()
+                .filter(|(_, (this, other))| this != other)
+                .map(|(i, _)| index * MERKLE_FANOUT + i)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // Entries of another replica that are missing or newer than the ones in this tree
+    pub fn get_content_diff(&self, other: Vec<LogEntry>) -> Vec<LogEntry> {
+        other
+            .into_iter()
+            .filter(|entry| {
+                let leaf = self.get_leaf(&entry.key);
+                match self.leaves.get(&leaf).and_then(|e| e.get(&entry.key)) {
+                    Some(timestamp) => *timestamp < entry.timestamp,
+                    None => true,
+                }
+            })
+            .collect()
+    }
+
+    // recompute the hashes of the ancestors of a leaf
+    fn propagate(&mut self, leaf: usize) {
+        let mut index = leaf;
+        for level in (0..self.depth).rev() {
+            index /= MERKLE_FANOUT;
+            let first = index * MERKLE_FANOUT;
+            let children: Vec<u64> = (first..first + MERKLE_FANOUT)
+                .map(|child| self.get_hash(level + 1, child))
+                .collect();
+            self.set_hash(level, index, MerkleTree::get_node_hash(&children));
+        }
+    }
+
+    // number of nodes at a level
+    fn get_width(&self, level: usize) -> usize {
+        MERKLE_FANOUT.pow(level as u32)
+    }
+
+    fn get_hash(&self, level: usize, index: usize) -> u64 {
+        self.levels[level].get(&index).copied().unwrap_or(0)
+    }
+
+    // nodes hashing to 0 are the roots of empty subtrees and are not stored
+    fn set_hash(&mut self, level: usize, index: usize, hash: u64) {
+        if hash == 0 {
+            self.levels[level].remove(&index);
+        } else {
+            self.levels[level].insert(index, hash);
+        }
+    }
+
+    fn get_entry_hash(key: &OwnedKeyExpr, timestamp: &Timestamp) -> u64 {
+        CRC64.checksum(format!("{}-{}", timestamp, key).as_bytes())
+    }
+
+    // an empty subtree hashes to 0, so that empty trees of any depth have the same checksum
+    fn get_node_hash(children: &[u64]) -> u64 {
+        if children.iter().all(|c| *c == 0) {
+            return 0;
+        }
+        let mut hasher = CRC64.digest();
+        for c in children {
+            hasher.update(&c.to_le_bytes());
+        }
+        hasher.finalize()
+    }
+}
+
+// Maintains the Merkle tree of a replica up to date with the updates of the storage
+pub struct MerkleLog {
+    // channel to get updates from the storage
+    storage_update: Receiver<(OwnedKeyExpr, Timestamp)>,
+    tree: RwLock<MerkleTree>,
+}
+
+impl MerkleLog {
+    pub fn new(
+        rx_sample: Receiver<(OwnedKeyExpr, Timestamp)>,
+        initial_entries: &[(OwnedKeyExpr, Timestamp)],
+        depth: usize,
+    ) -> Self {
+        MerkleLog {
+            storage_update: rx_sample,
+            tree: RwLock::new(MerkleTree::from_log(depth, initial_entries)),
+        }
+    }
+
+    // Listen to storage updates
+    pub async fn start(&self) {
+        while let Ok((key, timestamp)) = self.storage_update.recv_async().await {
+            self.tree.write().await.update(key, timestamp);
+        }
+    }
+
+    pub async fn get_digest(&self) -> MerkleDigest {
+        let tree = self.tree.read().await;
+        MerkleDigest {
+            timestamp: zenoh::time::new_reception_timestamp(),
+            depth: tree.depth(),
+            checksum: tree.checksum(),
+        }
+    }
+
+    pub async fn tree(&self) -> RwLockReadGuard<'_, MerkleTree> {
+        self.tree.read().await
+    }
+}
+
+#[cfg(test)]
+fn get_entry(key: &str, second: u64) -> (OwnedKeyExpr, Timestamp) {
+    use std::str::FromStr;
+    (
+        OwnedKeyExpr::from_str(key).unwrap(),
+        Timestamp::from_str(&format!("2022-12-21T15:00:{:02}.000000000Z/1", second)).unwrap(),
+    )
+}
+
+#[test]
+fn test_merkle_tree_empty() {
+    let tree = MerkleTree::new(2);
+    assert_eq!(tree.checksum(), 0);
+    assert_eq!(MerkleTree::new(3).checksum(), tree.checksum());
+    assert_eq!(tree.get_children(0, 0).unwrap(), vec![0; MERKLE_FANOUT]);
+    assert!(tree.get_children(2, 0).is_none());
+    assert_eq!(tree.get_leaf_content(0).unwrap().len(), 0);
+    assert!(tree
+        .get_leaf_content(MERKLE_FANOUT * MERKLE_FANOUT)
+        .is_none());
+}
+
+#[test]
+fn test_merkle_tree_sparse() {
+    let depth = zenoh_backend_traits::config::AlignmentStrategy::MAX_MERKLE_DEPTH;
+    let tree = MerkleTree::from_log(depth, &[get_entry("demo/a", 10)]);
+    assert_eq!(tree.leaves.len(), 1);
+    assert!(tree.levels.iter().all(|level| level.len() == 1));
+    assert_eq!(
+        tree.get_leaf_content(tree.get_leaf(&get_entry("demo/a", 10).0))
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_merkle_tree_order_independent() {
+    let log = vec![
+        get_entry("demo/a", 10),
+        get_entry("demo/b", 20),
+        get_entry("demo/c", 30),
+    ];
+    let tree = MerkleTree::from_log(2, &log);
+    let mut reversed = log.clone();
+    reversed.reverse();
+    let other = MerkleTree::from_log(2, &reversed);
+    assert_ne!(tree.checksum(), 0);
+    assert_eq!(tree.checksum(), other.checksum());
+}
+
+#[test]
+fn test_merkle_tree_update() {
+    let mut tree = MerkleTree::from_log(2, &[get_entry("demo/a", 10)]);
+    let checksum = tree.checksum();
+    let (key, ts) = get_entry("demo/a", 5);
+    assert!(!tree.update(key, ts));
+    assert_eq!(tree.checksum(), checksum);
+    let (key, ts) = get_entry("demo/a", 20);
+    assert!(tree.update(key, ts));
+    assert_ne!(tree.checksum(), checksum);
+    assert_eq!(
+        tree.checksum(),
+        MerkleTree::from_log(2, &[get_entry("demo/a", 20)]).checksum()
+    );
+}
+
+#[test]
+fn test_merkle_tree_diff() {
+    let common = vec![get_entry("demo/a", 10), get_entry("demo/b", 20)];
+    let this = MerkleTree::from_log(2, &common);
+    let mut log = common.clone();
+    log.push(get_entry("demo/c", 30));
+    let other = MerkleTree::from_log(2, &log);
+
+    // descend to the differing leaf
+    let mut diff = vec![0];
+    for level in 0..2 {
+        diff = diff
+            .into_iter()
+            .flat_map(|i| this.get_children_diff(level, i, &other.get_children(level, i).unwrap()))
+            .collect();
+        assert_eq!(diff.len(), 1);
+    }
+    let (key, _) = get_entry("demo/c", 30);
+    assert_eq!(diff, vec![this.get_leaf(&key)]);
+
+    let missing = this.get_content_diff(other.get_leaf_content(diff[0]).unwrap());
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].key, key);
+}
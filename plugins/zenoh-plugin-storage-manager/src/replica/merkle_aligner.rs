@@ -0,0 +1,389 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+// Alignment of replicas using the Merkle tree of their key space
+// On a mismatching root, the aligner descends the tree of the other replica level by level,
+// only querying the children of the nodes whose hashes differ, down to the leaves.
+// The entries of the mismatching leaves are then compared and the missing samples fetched.
+
+use super::align_queryable::get_entry;
+use super::aligner::query_replica;
+use super::{LogEntry, MerkleDigest, MerkleLog, ReplicaStatus};
+use super::{CONTENTS, MERKLE_LEAVES, MERKLE_LEVEL, MERKLE_NODES};
+use async_std::sync::{Arc, RwLock};
+use flume::{Receiver, Sender};
+use futures::select;
+use std::collections::{HashMap, HashSet};
+use std::str;
+use zenoh::key_expr::OwnedKeyExpr;
+use zenoh::prelude::r#async::*;
+use zenoh::Session;
+
+// maximum number of nodes or entries requested in a single query
+const MERKLE_QUERY_CHUNK: usize = 256;
+
+pub struct MerkleAligner {
+    session: Arc<Session>,
+    digest_key: OwnedKeyExpr,
+    merkle_log: Arc<MerkleLog>,
+    rx_digest: Receiver<(String, MerkleDigest)>,
+    rx_align: Receiver<async_std::channel::Sender<serde_json::Value>>,
+    tx_sample: Sender<Sample>,
+    digests_processed: RwLock<HashSet<u64>>,
+    // latest digest received from each replica, realigned on request
+    latest_digests: RwLock<HashMap<String, MerkleDigest>>,
+    status: Arc<RwLock<ReplicaStatus>>,
+}
+
+impl MerkleAligner {
+    pub async fn start_aligner(
+        session: Arc<Session>,
+        digest_key: OwnedKeyExpr,
+        rx_digest: Receiver<(String, MerkleDigest)>,
+        rx_align: Receiver<async_std::channel::Sender<serde_json::Value>>,
+        tx_sample: Sender<Sample>,
+        merkle_log: Arc<MerkleLog>,
+        status: Arc<RwLock<ReplicaStatus>>,
+    ) {
+        let aligner = MerkleAligner {
+            session,
+            digest_key,
+            merkle_log,
+            rx_digest,
+            rx_align,
+            tx_sample,
+            digests_processed: RwLock::new(HashSet::new()),
+            latest_digests: RwLock::new(HashMap::new()),
+            status,
+        };
+        aligner.start().await;
+    }
+
+    pub async fn start(&self) {
+        loop {
+            select!(
+                digest = self.rx_digest.recv_async() => {
+                    let Ok((from, incoming_digest)) = digest else {
+                        return;
+                    };
+                    self.latest_digests
+                        .write()
+                        .await
+                        .insert(from.clone(), incoming_digest.clone());
+                    self.align_with(incoming_digest, &from).await;
+                },
+                // on alignment request, process again the latest digests of all replicas
+                request = self.rx_align.recv_async() => {
+                    let Ok(reply) = request else {
+                        return;
+                    };
+                    let latest_digests = self.latest_digests.read().await.clone();
+                    tracing::debug!(
+                        "[MERKLE ALIGNER] Alignment requested with {} replicas",
+                        latest_digests.len()
+                    );
+                    for (from, digest) in latest_digests {
+                        // the tree of the other replica is queried again, so its latest digest is enough
+                        self.process_incoming_digest(digest, &from).await;
+                    }
+                    // the outcome of the round is reported by the status of the replica
+                    let status = self.status.read().await.to_json();
+                    std::mem::drop(reply.send(status).await);
+                },
+            );
+        }
+    }
+
+    async fn align_with(&self, incoming_digest: MerkleDigest, from: &str) {
+        if self
+            .digests_processed
+            .read()
+            .await
+            .contains(&incoming_digest.checksum)
+        {
+            tracing::trace!(
+                "[MERKLE ALIGNER] Skipping already processed digest: {}",
+                incoming_digest.checksum
+            );
+        } else if self.merkle_log.get_digest().await.checksum == incoming_digest.checksum {
+            tracing::trace!(
+                "[MERKLE ALIGNER] Skipping matching digest: {}",
+                incoming_digest.checksum
+            );
+        } else {
+            tracing::debug!(
+                "[MERKLE ALIGNER] Processing digest: {:?} from {}",
+                incoming_digest,
+                from
+            );
+            self.process_incoming_digest(incoming_digest, from).await;
+        }
+    }
+
+    async fn process_incoming_digest(&self, other: MerkleDigest, from: &str) {
+        let (missing_content, no_content_err) = self.get_missing_content(&other, from).await;
+        tracing::debug!(
+            "[MERKLE ALIGNER] Missing {} entries; query corresponding samples",
+            missing_content.len()
+        );
+        let mut no_data_err = true;
+        let mut keys_fetched = 0;
+        for chunk in missing_content.chunks(MERKLE_QUERY_CHUNK) {
+            let properties = format!(
+                "timestamp={}&{}={}",
+                other.timestamp,
+                CONTENTS,
+                serde_json::to_string(chunk).unwrap()
+            );
+            let (replies, no_err) = self.perform_query(from, properties).await;
+            no_data_err &= no_err;
+            // Replies might miss some samples since some entries might be outdated
+            tracing::debug!(
+                "[MERKLE ALIGNER] Received {} queried samples",
+                replies.len()
+            );
+            keys_fetched += replies.len();
+            for sample in replies {
+                tracing::debug!("[MERKLE ALIGNER] Adding {:?} to storage", sample);
+                self.tx_sample.send_async(sample).await.unwrap_or_else(|e| {
+                    tracing::error!("[MERKLE ALIGNER] Error adding sample to storage: {}", e)
+                });
+            }
+        }
+        self.status
+            .write()
+            .await
+            .alignment_done(from, keys_fetched, no_content_err && no_data_err);
+        // In case of errors, the digest will be processed again in the future rounds
+        if no_content_err && no_data_err {
+            self.digests_processed.write().await.insert(other.checksum);
+        }
+    }
+
+    // descend the tree of the other replica to find the entries we are missing
+    async fn get_missing_content(&self, other: &MerkleDigest, from: &str) -> (Vec<LogEntry>, bool) {
+        let depth = self.merkle_log.tree().await.depth();
+        if other.depth != depth {
+            tracing::error!(
+                "[MERKLE ALIGNER] Mismatching tree depth with {}: {} != {}",
+                from,
+                other.depth,
+                depth
+            );
+            return (Vec::new(), false);
+        }
+        let mut no_err = true;
+        let mut diff_nodes = vec![0];
+        for level in 0..depth {
+            if diff_nodes.is_empty() {
+                break;
+            }
+            let mut other_children: Vec<(usize, Vec<u64>)> = Vec::new();
+            for chunk in diff_nodes.chunks(MERKLE_QUERY_CHUNK) {
+                let properties = format!(
+                    "timestamp={}&{}={}&{}={}",
+                    other.timestamp,
+                    MERKLE_LEVEL,
+                    level,
+                    MERKLE_NODES,
+                    serde_json::to_string(chunk).unwrap()
+                );
+                let (replies, no_query_err) = self.perform_query(from, properties).await;
+                no_err &= no_query_err;
+                other_children.extend(self.decode_replies(replies, &mut no_err));
+            }
+            let tree = self.merkle_log.tree().await;
+            diff_nodes = other_children
+                .into_iter()
+                .flat_map(|(i, hashes)| tree.get_children_diff(level, i, &hashes))
+                .collect();
+            tracing::trace!(
+                "[MERKLE ALIGNER] Mismatching nodes with {} at level {}: {:?}",
+                from,
+                level + 1,
+                diff_nodes
+            );
+        }
+
+        let mut other_content: Vec<LogEntry> = Vec::new();
+        for chunk in diff_nodes.chunks(MERKLE_QUERY_CHUNK) {
+            let properties = format!(
+                "timestamp={}&{}={}",
+                other.timestamp,
+                MERKLE_LEAVES,
+                serde_json::to_string(chunk).unwrap()
+            );
+            let (replies, no_query_err) = self.perform_query(from, properties).await;
+            no_err &= no_query_err;
+            for (_, content) in self.decode_replies::<Vec<LogEntry>>(replies, &mut no_err) {
+                other_content.extend(content);
+            }
+        }
+        let tree = self.merkle_log.tree().await;
+        (tree.get_content_diff(other_content), no_err)
+    }
+
+    fn decode_replies<T: serde::de::DeserializeOwned>(
+        &self,
+        replies: Vec<Sample>,
+        no_err: &mut bool,
+    ) -> Vec<(usize, T)> {
+        let mut result = Vec::new();
+        for each in replies {
+            match serde_json::from_str(&each.value.to_string()) {
+                Ok(decoded) => result.push(decoded),
+                Err(e) => {
+                    tracing::error!("[MERKLE ALIGNER] Error decoding reply: {}", e);
+                    *no_err = false;
+                }
+            }
+        }
+        result
+    }
+
+    async fn perform_query(&self, from: &str, properties: String) -> (Vec<Sample>, bool) {
+        query_replica(&self.session, &self.digest_key, from, properties).await
+    }
+}
+
+#[derive(Debug)]
+enum MerkleComponent {
+    Nodes(usize, Vec<usize>),
+    Leaves(Vec<usize>),
+    Contents(Vec<LogEntry>),
+}
+
+pub struct MerkleAlignQueryable {
+    session: Arc<Session>,
+    digest_key: OwnedKeyExpr,
+    merkle_log: Arc<MerkleLog>,
+}
+
+impl MerkleAlignQueryable {
+    pub async fn start_align_queryable(
+        session: Arc<Session>,
+        digest_key: OwnedKeyExpr,
+        replica_name: &str,
+        merkle_log: Arc<MerkleLog>,
+    ) {
+        let digest_key = digest_key.join(replica_name).unwrap().join("**").unwrap();
+
+        let align_queryable = MerkleAlignQueryable {
+            session,
+            digest_key,
+            merkle_log,
+        };
+
+        align_queryable.start().await;
+    }
+
+    async fn start(&self) {
+        tracing::debug!(
+            "[MERKLE ALIGN QUERYABLE] Declaring Queryable on '{}'...",
+            self.digest_key
+        );
+        let queryable = self
+            .session
+            .declare_queryable(&self.digest_key)
+            .complete(true) // This queryable is meant to have all the history
+            .res()
+            .await
+            .unwrap();
+
+        while let Ok(query) = queryable.recv_async().await {
+            tracing::trace!(
+                "[MERKLE ALIGN QUERYABLE] Received Query '{}'",
+                query.selector()
+            );
+            let diff_required = match self.parse_selector(query.selector()) {
+                Some(diff_required) => diff_required,
+                None => {
+                    tracing::error!(
+                        "[MERKLE ALIGN QUERYABLE] Invalid query '{}'",
+                        query.selector()
+                    );
+                    continue;
+                }
+            };
+            for sample in self.get_value(query.key_expr(), diff_required).await {
+                if let Err(e) = query.reply(Ok(sample)).res().await {
+                    tracing::error!("[MERKLE ALIGN QUERYABLE] Error replying query: {}", e);
+                }
+            }
+        }
+    }
+
+    async fn get_value(
+        &self,
+        key_expr: &KeyExpr<'static>,
+        diff_required: MerkleComponent,
+    ) -> Vec<Sample> {
+        let mut result = Vec::new();
+        match diff_required {
+            MerkleComponent::Nodes(level, nodes) => {
+                let tree = self.merkle_log.tree().await;
+                for i in nodes {
+                    if let Some(children) = tree.get_children(level, i) {
+                        result.push(Sample::new(
+                            key_expr.clone(),
+                            serde_json::to_string(&(i, children)).unwrap(),
+                        ));
+                    }
+                }
+            }
+            MerkleComponent::Leaves(leaves) => {
+                let tree = self.merkle_log.tree().await;
+                for i in leaves {
+                    if let Some(content) = tree.get_leaf_content(i) {
+                        result.push(Sample::new(
+                            key_expr.clone(),
+                            serde_json::to_string(&(i, content)).unwrap(),
+                        ));
+                    }
+                }
+            }
+            MerkleComponent::Contents(contents) => {
+                for each in contents {
+                    if let Some(entry) = get_entry(&self.session, &each).await {
+                        result.push(
+                            Sample::new(OwnedKeyExpr::from(entry.key_expr), entry.value)
+                                .with_timestamp(each.timestamp),
+                        );
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn parse_selector(&self, selector: Selector) -> Option<MerkleComponent> {
+        let properties = selector.parameters_stringmap().ok()?;
+        tracing::trace!("[MERKLE ALIGN QUERYABLE] Properties are: {:?}", properties);
+        if let Some(nodes) = properties.get(MERKLE_NODES) {
+            let level = properties.get(MERKLE_LEVEL)?.parse::<usize>().ok()?;
+            Some(MerkleComponent::Nodes(
+                level,
+                serde_json::from_str(nodes).ok()?,
+            ))
+        } else if let Some(leaves) = properties.get(MERKLE_LEAVES) {
+            Some(MerkleComponent::Leaves(serde_json::from_str(leaves).ok()?))
+        } else if let Some(contents) = properties.get(CONTENTS) {
+            Some(MerkleComponent::Contents(
+                serde_json::from_str(contents).ok()?,
+            ))
+        } else {
+            None
+        }
+    }
+}
@@ -20,7 +20,9 @@ use async_std::stream::{interval, StreamExt};
 use async_std::sync::Arc;
 use async_std::sync::RwLock;
 use flume::{Receiver, Sender};
-use futures::{pin_mut, select, FutureExt};
+use futures::{pin_mut, select, Future, FutureExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
 use std::str;
 use std::str::FromStr;
@@ -29,24 +31,36 @@ use urlencoding::encode;
 use zenoh::prelude::r#async::*;
 use zenoh::time::Timestamp;
 use zenoh::Session;
-use zenoh_backend_traits::config::{ReplicaConfig, StorageConfig};
+use zenoh_backend_traits::config::{AlignmentStrategy, ReplicaConfig, StorageConfig};
 
 pub mod align_queryable;
 pub mod aligner;
+pub mod batch;
+pub mod cache;
 pub mod digest;
+pub mod merkle;
+pub mod merkle_aligner;
 pub mod snapshotter;
+pub mod status;
 pub mod storage;
 
 pub use align_queryable::AlignQueryable;
 pub use aligner::Aligner;
+pub use cache::LatestValueCache;
 pub use digest::{Digest, DigestConfig, EraType, LogEntry};
+pub use merkle::{MerkleDigest, MerkleLog, MerkleTree};
+pub use merkle_aligner::{MerkleAlignQueryable, MerkleAligner};
 pub use snapshotter::Snapshotter;
+pub use status::ReplicaStatus;
 pub use storage::{ReplicationService, StorageService};
 
 const ERA: &str = "era";
 const INTERVALS: &str = "intervals";
 const SUBINTERVALS: &str = "subintervals";
 const CONTENTS: &str = "contents";
+const MERKLE_LEVEL: &str = "merkle_level";
+const MERKLE_NODES: &str = "merkle_nodes";
+const MERKLE_LEAVES: &str = "merkle_leaves";
 pub const EPOCH_START: SystemTime = SystemTime::UNIX_EPOCH;
 
 pub const ALIGN_PREFIX: &str = "@-digest";
@@ -61,6 +75,9 @@ pub const SUBINTERVAL_CHUNKS: usize = 10;
 // The `Aligner` identifies mismatches in the contents of the storage with respect to the other storage
 // `Aligner` generates a list of missing updates that is then send to the `StorageService`
 // When a `StorageService` receives an update, it sends a log to the `Snapshotter`
+// Alternatively, with the `MerkleTree` alignment strategy, the `Snapshotter` is replaced by a `MerkleLog`
+// and the `Digest` by the root of a Merkle tree over the key space, see `merkle` module
+// All the components report the progress of the alignment in a shared `ReplicaStatus`, exposed in the admin space
 
 pub struct Replica {
     // TODO: Discuss if we need to add -<storage_type> for uniqueness
@@ -69,6 +86,7 @@ pub struct Replica {
     key_expr: OwnedKeyExpr,
     replica_config: ReplicaConfig,
     digests_published: RwLock<HashSet<u64>>, // checksum of all digests generated and published by this replica
+    status: Arc<RwLock<ReplicaStatus>>,
 }
 
 impl Replica {
@@ -109,14 +127,45 @@ impl Replica {
             }
         };
 
+        let replica_config = storage_config.replica_config.clone().unwrap();
         let replica = Replica {
             name: name.to_string(),
             session,
             key_expr: storage_config.key_expr.clone(),
-            replica_config: storage_config.replica_config.clone().unwrap(),
+            status: Arc::new(RwLock::new(ReplicaStatus::new(replica_config.alignment))),
+            replica_config,
             digests_published: RwLock::new(HashSet::new()),
         };
 
+        match replica.replica_config.alignment {
+            AlignmentStrategy::Era => {
+                replica
+                    .start_era_alignment(store_intercept, storage_config, rx, startup_entries)
+                    .await
+            }
+            AlignmentStrategy::MerkleTree { depth } => {
+                replica
+                    .start_merkle_alignment(
+                        store_intercept,
+                        storage_config,
+                        rx,
+                        startup_entries,
+                        depth,
+                    )
+                    .await
+            }
+        }
+    }
+
+    // Start the components of a replica aligning through era digests
+    async fn start_era_alignment(
+        &self,
+        store_intercept: StoreIntercept,
+        storage_config: StorageConfig,
+        rx: Receiver<StorageMessage>,
+        startup_entries: Vec<(OwnedKeyExpr, Timestamp)>,
+    ) {
+        let replica = self;
         // Create channels for communication between components
         // channel to queue digests to be aligned
         let (tx_digest, rx_digest) = flume::unbounded();
@@ -124,6 +173,8 @@ impl Replica {
         let (tx_sample, rx_sample) = flume::unbounded();
         // channel for storage to send logging information back
         let (tx_log, rx_log) = flume::unbounded();
+        // channel for storage to request an immediate alignment
+        let (tx_align, rx_align) = flume::bounded(1);
 
         let config = replica.replica_config.clone();
         // snapshotter
@@ -144,12 +195,16 @@ impl Replica {
             replica.session.clone(),
             digest_key,
             rx_digest,
+            rx_align,
             tx_sample,
             snapshotter.clone(),
+            replica.status.clone(),
         )
         .fuse();
         // digest pub
-        let digest_pub = replica.start_digest_pub(snapshotter.clone()).fuse();
+        let digest_pub = replica
+            .start_digest_pub(|| async { snapshotter.get_digest().await.compress() })
+            .fuse();
 
         //updating snapshot time
         let snapshot_task = snapshotter.start().fuse();
@@ -159,6 +214,8 @@ impl Replica {
             empty_start: startup_entries.is_empty(),
             aligner_updates: rx_sample,
             log_propagation: tx_log,
+            align_trigger: tx_align,
+            status: replica.status.clone(),
         };
         // channel to pipe the receiver to storage
         let storage_task = StorageService::start(
@@ -190,9 +247,93 @@ impl Replica {
         )
     }
 
+    // Start the components of a replica aligning through a Merkle tree of the key space
+    async fn start_merkle_alignment(
+        &self,
+        store_intercept: StoreIntercept,
+        storage_config: StorageConfig,
+        rx: Receiver<StorageMessage>,
+        startup_entries: Vec<(OwnedKeyExpr, Timestamp)>,
+        depth: usize,
+    ) {
+        let replica = self;
+        // channel to queue digests to be aligned
+        let (tx_digest, rx_digest) = flume::unbounded();
+        // channel for aligner to send missing samples to storage
+        let (tx_sample, rx_sample) = flume::unbounded();
+        // channel for storage to send logging information back
+        let (tx_log, rx_log) = flume::unbounded();
+        // channel for storage to request an immediate alignment
+        let (tx_align, rx_align) = flume::bounded(1);
+
+        let merkle_log = Arc::new(MerkleLog::new(rx_log, &startup_entries, depth));
+        // digest sub
+        let digest_sub = replica.start_digest_sub(tx_digest).fuse();
+        // queryable for alignment
+        let digest_key = Replica::get_digest_key(&replica.key_expr, ALIGN_PREFIX);
+        let align_q = MerkleAlignQueryable::start_align_queryable(
+            replica.session.clone(),
+            digest_key.clone(),
+            &replica.name,
+            merkle_log.clone(),
+        )
+        .fuse();
+        // aligner
+        let aligner = MerkleAligner::start_aligner(
+            replica.session.clone(),
+            digest_key,
+            rx_digest,
+            rx_align,
+            tx_sample,
+            merkle_log.clone(),
+            replica.status.clone(),
+        )
+        .fuse();
+        // digest pub
+        let digest_pub = replica.start_digest_pub(|| merkle_log.get_digest()).fuse();
+        // updating the tree
+        let merkle_task = merkle_log.start().fuse();
+
+        //actual storage
+        let replication = ReplicationService {
+            empty_start: startup_entries.is_empty(),
+            aligner_updates: rx_sample,
+            log_propagation: tx_log,
+            align_trigger: tx_align,
+            status: replica.status.clone(),
+        };
+        let storage_task = StorageService::start(
+            replica.session.clone(),
+            storage_config,
+            &replica.name,
+            store_intercept,
+            rx,
+            Some(replication),
+        )
+        .fuse();
+
+        pin_mut!(
+            digest_sub,
+            align_q,
+            aligner,
+            digest_pub,
+            merkle_task,
+            storage_task
+        );
+
+        select!(
+            () = digest_sub => tracing::trace!("[REPLICA] Exiting digest subscriber"),
+            () = align_q => tracing::trace!("[REPLICA] Exiting align queryable"),
+            () = aligner => tracing::trace!("[REPLICA] Exiting aligner"),
+            () = digest_pub => tracing::trace!("[REPLICA] Exiting digest publisher"),
+            () = merkle_task => tracing::trace!("[REPLICA] Exiting merkle task"),
+            () = storage_task => tracing::trace!("[REPLICA] Exiting storage task"),
+        )
+    }
+
     // Create a subscriber to get digests of remote replicas
     // Subscribe on <align_prefix>/<encoded_key_expr>/**
-    pub async fn start_digest_sub(&self, tx: Sender<(String, Digest)>) {
+    pub async fn start_digest_sub<D: AlignmentDigest>(&self, tx: Sender<(String, D)>) {
         let mut received = HashMap::<String, Timestamp>::new();
 
         let digest_key = Replica::get_digest_key(&self.key_expr, ALIGN_PREFIX)
@@ -228,25 +369,23 @@ impl Replica {
                 sample.key_expr.as_str(),
                 sample.value
             );
-            let digest: Digest = match serde_json::from_str(&format!("{}", sample.value)) {
+            let digest: D = match serde_json::from_str(&format!("{}", sample.value)) {
                 Ok(digest) => digest,
                 Err(e) => {
                     tracing::error!("[DIGEST_SUB] Error in decoding the digest: {}", e);
                     continue;
                 }
             };
-            let ts = digest.timestamp;
+            let ts = digest.timestamp();
+            self.status
+                .write()
+                .await
+                .digest_received(from, ts, digest.checksum());
             let to_be_processed = self
-                .processing_needed(
-                    from,
-                    digest.timestamp,
-                    digest.checksum,
-                    received.clone(),
-                    digest.config.clone(),
-                )
+                .processing_needed(from, &digest, received.clone())
                 .await;
             if to_be_processed {
-                tracing::trace!("[DIGEST_SUB] sending {} to aligner", digest.checksum);
+                tracing::trace!("[DIGEST_SUB] sending {} to aligner", digest.checksum());
                 match tx.send_async((from.to_string(), digest)).await {
                     Ok(()) => {}
                     Err(e) => {
@@ -260,7 +399,12 @@ impl Replica {
 
     // Create a publisher to periodically publish digests from the snapshotter
     // Publish on <align_prefix>/<encoded_key_expr>/<replica_name>
-    pub async fn start_digest_pub(&self, snapshotter: Arc<Snapshotter>) {
+    pub async fn start_digest_pub<D, F, Fut>(&self, get_digest: F)
+    where
+        D: AlignmentDigest,
+        F: Fn() -> Fut,
+        Fut: Future<Output = D>,
+    {
         let digest_key = Replica::get_digest_key(&self.key_expr, ALIGN_PREFIX)
             .join(&self.name)
             .unwrap();
@@ -279,12 +423,15 @@ impl Replica {
         loop {
             let _ = interval.next().await;
 
-            let digest = snapshotter.get_digest().await;
-            let digest = digest.compress();
+            let digest = get_digest().await;
             let digest_json = serde_json::to_string(&digest).unwrap();
             let mut digests_published = self.digests_published.write().await;
-            digests_published.insert(digest.checksum);
+            digests_published.insert(digest.checksum());
             drop(digests_published);
+            self.status
+                .write()
+                .await
+                .digest_published(digest.checksum());
             drop(digest);
 
             tracing::trace!("[DIGEST_PUB] Putting Digest: {} ...", digest_json);
@@ -295,14 +442,14 @@ impl Replica {
         }
     }
 
-    async fn processing_needed(
+    async fn processing_needed<D: AlignmentDigest>(
         &self,
         from: &str,
-        ts: Timestamp,
-        checksum: u64,
+        digest: &D,
         received: HashMap<String, Timestamp>,
-        config: DigestConfig,
     ) -> bool {
+        let ts = digest.timestamp();
+        let checksum = digest.checksum();
         if checksum == 0 {
             // no values to align
             return false;
@@ -319,13 +466,7 @@ impl Replica {
             return false;
         }
         // TODO: test this part
-        if config.delta != self.replica_config.delta
-            || config.hot
-                != Replica::get_hot_interval_number(
-                    self.replica_config.publication_interval,
-                    self.replica_config.delta,
-                )
-        {
+        if !digest.is_compatible(&self.replica_config) {
             tracing::error!("[DIGEST_SUB] Mismatching digest configs, cannot be aligned");
             return false;
         }
@@ -348,3 +489,44 @@ impl Replica {
         Replica::get_hot_interval_number(publication_interval, delta) * 5
     }
 }
+
+// The digests exchanged by replicas, depending on their alignment strategy
+pub trait AlignmentDigest: Serialize + DeserializeOwned {
+    fn timestamp(&self) -> Timestamp;
+    fn checksum(&self) -> u64;
+    // whether a digest can be aligned with a replica having the given config
+    fn is_compatible(&self, replica_config: &ReplicaConfig) -> bool;
+}
+
+impl AlignmentDigest for Digest {
+    fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    fn checksum(&self) -> u64 {
+        self.checksum
+    }
+
+    fn is_compatible(&self, replica_config: &ReplicaConfig) -> bool {
+        self.config.delta == replica_config.delta
+            && self.config.hot
+                == Replica::get_hot_interval_number(
+                    replica_config.publication_interval,
+                    replica_config.delta,
+                )
+    }
+}
+
+impl AlignmentDigest for MerkleDigest {
+    fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    fn checksum(&self) -> u64 {
+        self.checksum
+    }
+
+    fn is_compatible(&self, replica_config: &ReplicaConfig) -> bool {
+        replica_config.alignment == AlignmentStrategy::MerkleTree { depth: self.depth }
+    }
+}
@@ -0,0 +1,162 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use serde_json::json;
+use std::collections::HashMap;
+use zenoh::time::Timestamp;
+use zenoh_backend_traits::config::AlignmentStrategy;
+
+// Status of the alignment of a replica with the other replicas of the same key_expr
+// It is updated by the digest subscriber, the digest publisher and the aligner,
+// and exposed in the admin space by the storage service
+#[derive(Debug, Clone)]
+pub struct ReplicaStatus {
+    alignment: AlignmentStrategy,
+    // checksum of the latest digest published by this replica
+    checksum: u64,
+    // time of the latest alignment round that completed without error, with any replica
+    last_alignment: Option<Timestamp>,
+    peers: HashMap<String, PeerStatus>,
+}
+
+#[derive(Debug, Clone)]
+struct PeerStatus {
+    // timestamp and checksum of the latest digest received from the replica
+    last_digest: Timestamp,
+    checksum: u64,
+    // time of the latest alignment round with the replica that completed without error
+    last_alignment: Option<Timestamp>,
+    // number of keys fetched from the replica in the latest alignment round
+    keys_fetched: usize,
+    // number of alignment rounds with the replica, including failed ones
+    alignment_rounds: u64,
+    // number of alignment rounds with the replica that raised errors
+    alignment_errors: u64,
+}
+
+impl ReplicaStatus {
+    pub fn new(alignment: AlignmentStrategy) -> Self {
+        ReplicaStatus {
+            alignment,
+            checksum: 0,
+            last_alignment: None,
+            peers: HashMap::new(),
+        }
+    }
+
+    pub fn digest_published(&mut self, checksum: u64) {
+        self.checksum = checksum;
+    }
+
+    pub fn digest_received(&mut self, from: &str, timestamp: Timestamp, checksum: u64) {
+        match self.peers.get_mut(from) {
+            Some(peer) => {
+                peer.last_digest = timestamp;
+                peer.checksum = checksum;
+            }
+            None => {
+                self.peers.insert(
+                    from.to_string(),
+                    PeerStatus {
+                        last_digest: timestamp,
+                        checksum,
+                        last_alignment: None,
+                        keys_fetched: 0,
+                        alignment_rounds: 0,
+                        alignment_errors: 0,
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn alignment_done(&mut self, from: &str, keys_fetched: usize, no_err: bool) {
+        let now = zenoh::time::new_reception_timestamp();
+        if let Some(peer) = self.peers.get_mut(from) {
+            peer.keys_fetched = keys_fetched;
+            peer.alignment_rounds += 1;
+            if no_err {
+                peer.last_alignment = Some(now);
+            } else {
+                peer.alignment_errors += 1;
+            }
+        }
+        if no_err {
+            self.last_alignment = Some(now);
+        }
+    }
+
+    pub fn peers(&self) -> Vec<String> {
+        self.peers.keys().cloned().collect()
+    }
+
+    // The replica is converged when the latest digests of all the known replicas match its own
+    pub fn is_converged(&self) -> bool {
+        self.peers
+            .values()
+            .all(|peer| peer.checksum == self.checksum)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let peers: serde_json::Map<String, serde_json::Value> = self
+            .peers
+            .iter()
+            .map(|(name, peer)| {
+                (
+                    name.clone(),
+                    json!({
+                        "last_digest": peer.last_digest.to_string(),
+                        "checksum": peer.checksum,
+                        "converged": peer.checksum == self.checksum,
+                        "last_alignment": peer.last_alignment.map(|ts| ts.to_string()),
+                        "keys_fetched": peer.keys_fetched,
+                        "alignment_rounds": peer.alignment_rounds,
+                        "alignment_errors": peer.alignment_errors,
+                    }),
+                )
+            })
+            .collect();
+        json!({
+            "alignment": match self.alignment {
+                AlignmentStrategy::Era => json!("era"),
+                AlignmentStrategy::MerkleTree { depth } => json!({ "merkle_tree": { "depth": depth } }),
+            },
+            "checksum": self.checksum,
+            "converged": self.is_converged(),
+            "last_alignment": self.last_alignment.map(|ts| ts.to_string()),
+            "peers": peers,
+        })
+    }
+}
+
+#[test]
+fn test_replica_status_convergence() {
+    use std::str::FromStr;
+    let ts = Timestamp::from_str("2022-12-21T15:00:00.000000000Z/1").unwrap();
+    let mut status = ReplicaStatus::new(AlignmentStrategy::Era);
+    status.digest_published(42);
+    assert!(status.is_converged());
+
+    status.digest_received("other", ts, 7);
+    assert!(!status.is_converged());
+    status.alignment_done("other", 3, true);
+    let json = status.to_json();
+    assert_eq!(json["converged"], false);
+    assert_eq!(json["peers"]["other"]["keys_fetched"], 3);
+    assert_eq!(json["peers"]["other"]["alignment_rounds"], 1);
+    assert!(json["last_alignment"].is_string());
+
+    status.digest_received("other", ts, 42);
+    assert!(status.is_converged());
+    assert_eq!(status.to_json()["peers"]["other"]["converged"], true);
+}
@@ -11,32 +11,49 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
+use super::batch::{PendingWrite, WriteBatch};
+use super::{LatestValueCache, ReplicaStatus};
 use crate::backends_mgt::StoreIntercept;
+use crate::snapshot::{
+    SnapshotEntry, SnapshotReader, SnapshotRecord, SnapshotTombstone, SnapshotWriter,
+};
 use crate::storages_mgt::StorageMessage;
 use async_std::sync::Arc;
 use async_std::sync::{Mutex, RwLock};
 use async_trait::async_trait;
 use flume::{Receiver, Sender};
-use futures::select;
+use futures::{select, FutureExt};
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::str::{self, FromStr};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use zenoh::buffers::ZBuf;
 use zenoh::prelude::r#async::*;
 use zenoh::query::ConsolidationMode;
 use zenoh::time::{Timestamp, NTP64};
 use zenoh::{Result as ZResult, Session};
 use zenoh_backend_traits::config::{GarbageCollectionConfig, StorageConfig};
-use zenoh_backend_traits::{Capability, History, Persistence, StorageInsertionResult, StoredData};
+use zenoh_backend_traits::{
+    Capability, History, Persistence, Storage, StorageInsertionResult, StorageWrite, StoredData,
+};
 use zenoh_keyexpr::key_expr::OwnedKeyExpr;
 use zenoh_keyexpr::keyexpr_tree::impls::KeyedSetProvider;
 use zenoh_keyexpr::keyexpr_tree::{support::NonWild, support::UnknownWildness, KeBoxTree};
 use zenoh_keyexpr::keyexpr_tree::{IKeyExprTree, IKeyExprTreeMut};
-use zenoh_result::bail;
+use zenoh_result::{bail, zerror};
 use zenoh_util::{zenoh_home, Timed, TimedEvent, Timer};
 
 pub const WILDCARD_UPDATES_FILENAME: &str = "wildcard_updates";
 pub const TOMBSTONE_FILENAME: &str = "tombstones";
+// samples of a snapshot waiting to be processed by the storage service
+const SNAPSHOT_IMPORT_QUEUE_SIZE: usize = 256;
+
+// Samples of a snapshot, imported by a dedicated task into the loop of the storage service
+enum SnapshotImport {
+    Sample(Box<Sample>),
+    // Acknowledged once the previous samples are written to the storage
+    Done(async_std::channel::Sender<()>),
+}
 
 #[derive(Clone)]
 struct Update {
@@ -48,6 +65,8 @@ pub struct ReplicationService {
     pub empty_start: bool,
     pub aligner_updates: Receiver<Sample>,
     pub log_propagation: Sender<(OwnedKeyExpr, Timestamp)>,
+    pub align_trigger: Sender<async_std::channel::Sender<serde_json::Value>>,
+    pub status: Arc<RwLock<ReplicaStatus>>,
 }
 
 pub struct StorageService {
@@ -56,13 +75,18 @@ pub struct StorageService {
     complete: bool,
     name: String,
     strip_prefix: Option<OwnedKeyExpr>,
-    storage: Mutex<Box<dyn zenoh_backend_traits::Storage>>,
+    storage: Arc<Mutex<Box<dyn zenoh_backend_traits::Storage>>>,
     capability: Capability,
+    // cache of the latest values, only if configured for a storage keeping the latest value with a read_cost above its threshold
+    cache: Option<Mutex<LatestValueCache>>,
+    // writes waiting to be flushed to the storage, only if batching is enabled
+    batch: Option<Mutex<WriteBatch>>,
     tombstones: Arc<RwLock<KeBoxTree<Timestamp, NonWild, KeyedSetProvider>>>,
     wildcard_updates: Arc<RwLock<KeBoxTree<Update, UnknownWildness, KeyedSetProvider>>>,
     in_interceptor: Option<Arc<dyn Fn(Sample) -> Sample + Send + Sync>>,
     out_interceptor: Option<Arc<dyn Fn(Sample) -> Sample + Send + Sync>>,
     replication: Option<ReplicationService>,
+    snapshot_imports: (Sender<SnapshotImport>, Receiver<SnapshotImport>),
 }
 
 impl StorageService {
@@ -74,20 +98,41 @@ impl StorageService {
         rx: Receiver<StorageMessage>,
         replication: Option<ReplicationService>,
     ) {
-        // @TODO: optimization: if read_cost is high for the storage, initialize a cache for the latest value
+        let cache = match &config.cache_config {
+            Some(cache_config)
+                if store_intercept.capability.history.eq(&History::Latest)
+                    && store_intercept.capability.read_cost > cache_config.read_cost_threshold =>
+            {
+                tracing::debug!(
+                    "[STORAGE] Storage '{}' has a read_cost of {}, enabling a cache of {} latest values",
+                    name,
+                    store_intercept.capability.read_cost,
+                    cache_config.capacity
+                );
+                Some(Mutex::new(LatestValueCache::new(cache_config.capacity)))
+            }
+            _ => None,
+        };
         let mut storage_service = StorageService {
             session,
             key_expr: config.key_expr,
             complete: config.complete,
             name: name.to_string(),
             strip_prefix: config.strip_prefix,
-            storage: Mutex::new(store_intercept.storage),
+            storage: Arc::new(Mutex::new(store_intercept.storage)),
             capability: store_intercept.capability,
+            cache,
+            batch: if config.batch_config.is_enabled() {
+                Some(Mutex::new(WriteBatch::new(config.batch_config)))
+            } else {
+                None
+            },
             tombstones: Arc::new(RwLock::new(KeBoxTree::default())),
             wildcard_updates: Arc::new(RwLock::new(KeBoxTree::default())),
             in_interceptor: store_intercept.in_interceptor,
             out_interceptor: store_intercept.out_interceptor,
             replication,
+            snapshot_imports: flume::bounded(SNAPSHOT_IMPORT_QUEUE_SIZE),
         };
         if storage_service
             .capability
@@ -165,7 +210,8 @@ impl StorageService {
         };
 
         if self.replication.is_some() {
-            let aligner_updates = &self.replication.as_ref().unwrap().aligner_updates;
+            let replication = self.replication.as_ref().unwrap();
+            let aligner_updates = &replication.aligner_updates;
             loop {
                 select!(
                     // on sample for key_expr
@@ -190,6 +236,10 @@ impl StorageService {
                     query = storage_queryable.recv_async() => {
                         self.reply_query(query).await;
                     },
+                    // on deadline of the pending writes
+                    _ = self.batch_deadline().fuse() => {
+                        self.flush_batch().await;
+                    },
                     // on aligner update
                     update = aligner_updates.recv_async() => {
                         match update {
@@ -199,17 +249,48 @@ impl StorageService {
                             }
                         }
                     },
+                    // on sample imported from a snapshot
+                    import = self.snapshot_imports.1.recv_async() => {
+                        if let Ok(import) = import {
+                            self.process_snapshot_import(import).await;
+                        }
+                    },
                     // on storage handle drop
                     message = rx.recv_async() => {
                         match message {
                             Ok(StorageMessage::Stop) => {
                                 tracing::trace!("Dropping storage '{}'", self.name);
+                                self.flush_batch().await;
                                 return
                             },
                             Ok(StorageMessage::GetStatus(tx)) => {
                                 let storage = self.storage.lock().await;
-                                std::mem::drop(tx.send(storage.get_admin_status()).await);
+                                let mut status = storage.get_admin_status();
                                 drop(storage);
+                                self.add_cache_status(&mut status).await;
+                                if let serde_json::Value::Object(status) = &mut status {
+                                    status.insert(
+                                        "replication".into(),
+                                        replication.status.read().await.to_json(),
+                                    );
+                                }
+                                std::mem::drop(tx.send(status).await);
+                            }
+                            Ok(StorageMessage::Align(tx)) => {
+                                let peers = replication.status.read().await.peers();
+                                tracing::debug!("Alignment of storage '{}' requested with {:?}", self.name, peers);
+                                // the aligner replies once the alignment round completes
+                                if let Err(e) = replication.align_trigger.try_send(tx) {
+                                    let error = format!("An alignment of storage '{}' is already pending", self.name);
+                                    std::mem::drop(e.into_inner().send(serde_json::json!({ "error": error })).await);
+                                }
+                            }
+                            Ok(StorageMessage::Export(path, tx)) => {
+                                self.flush_batch().await;
+                                self.spawn_export(path, tx);
+                            }
+                            Ok(StorageMessage::Import(path, tx)) => {
+                                self.spawn_import(path, tx);
                             }
                             Err(e) => {
                                 tracing::error!("Storage Message Channel Error: {}", e);
@@ -237,17 +318,41 @@ impl StorageService {
                     query = storage_queryable.recv_async() => {
                         self.reply_query(query).await;
                     },
+                    // on deadline of the pending writes
+                    _ = self.batch_deadline().fuse() => {
+                        self.flush_batch().await;
+                    },
+                    // on sample imported from a snapshot
+                    import = self.snapshot_imports.1.recv_async() => {
+                        if let Ok(import) = import {
+                            self.process_snapshot_import(import).await;
+                        }
+                    },
                     // on storage handle drop
                     message = rx.recv_async() => {
                         match message {
                             Ok(StorageMessage::Stop) => {
                                 tracing::trace!("Dropping storage '{}'", self.name);
+                                self.flush_batch().await;
                                 return
                             },
                             Ok(StorageMessage::GetStatus(tx)) => {
                                 let storage = self.storage.lock().await;
-                                std::mem::drop(tx.send(storage.get_admin_status()).await);
+                                let mut status = storage.get_admin_status();
                                 drop(storage);
+                                self.add_cache_status(&mut status).await;
+                                std::mem::drop(tx.send(status).await);
+                            }
+                            Ok(StorageMessage::Align(tx)) => {
+                                let error = format!("Storage '{}' is not a replica, it cannot be aligned", self.name);
+                                std::mem::drop(tx.send(serde_json::json!({ "error": error })).await);
+                            }
+                            Ok(StorageMessage::Export(path, tx)) => {
+                                self.flush_batch().await;
+                                self.spawn_export(path, tx);
+                            }
+                            Ok(StorageMessage::Import(path, tx)) => {
+                                self.spawn_import(path, tx);
                             }
                             Err(e) => {
                                 tracing::error!("Storage Message Channel Error: {}", e);
@@ -271,8 +376,10 @@ impl StorageService {
         };
 
         // if wildcard, update wildcard_updates
+        // and flush the pending writes, so that the wildcard update applies to their keys
         if sample.key_expr.is_wild() {
             self.register_wildcard_update(sample.clone()).await;
+            self.flush_batch().await;
         }
 
         let matching_keys = if sample.key_expr.is_wild() {
@@ -287,6 +394,13 @@ impl StorageService {
         );
 
         for k in matching_keys {
+            // the sample must be compared with the pending write on the same key, once stored
+            if let Some(batch) = &self.batch {
+                let pending = batch.lock().await.contains(&k);
+                if pending {
+                    self.flush_batch().await;
+                }
+            }
             if !self
                 .is_deleted(&k.clone(), sample.get_timestamp().unwrap())
                 .await
@@ -321,43 +435,117 @@ impl StorageService {
                     }
                 };
 
-                let stripped_key = match self.strip_prefix(&sample_to_store.key_expr) {
-                    Ok(stripped) => stripped,
-                    Err(e) => {
-                        tracing::error!("{}", e);
-                        return;
-                    }
+                let write = PendingWrite {
+                    key: k,
+                    kind: sample.kind,
+                    data: StoredData {
+                        value: sample_to_store.value,
+                        timestamp: sample_to_store.timestamp.unwrap(),
+                    },
                 };
-                let mut storage = self.storage.lock().await;
-                let result = if sample.kind == SampleKind::Put {
-                    storage
-                        .put(
-                            stripped_key,
-                            sample_to_store.value.clone(),
-                            sample_to_store.timestamp.unwrap(),
-                        )
-                        .await
-                } else if sample.kind == SampleKind::Delete {
+                match &self.batch {
+                    Some(batch) => {
+                        let full = batch.lock().await.push(write);
+                        if full {
+                            self.flush_batch().await;
+                        }
+                    }
+                    None => self.write_to_storage(vec![write]).await,
+                }
+            }
+        }
+    }
+
+    // Writes all the pending writes of the batch (if any) to the storage
+    async fn flush_batch(&self) {
+        if let Some(batch) = &self.batch {
+            let writes = batch.lock().await.take();
+            if !writes.is_empty() {
+                tracing::trace!(
+                    "[STORAGE] Flushing a batch of {} writes to storage '{}'",
+                    writes.len(),
+                    self.name
+                );
+                self.write_to_storage(writes).await;
+            }
+        }
+    }
+
+    // Resolves when the pending writes of the batch must be flushed, never if there are none
+    async fn batch_deadline(&self) {
+        let deadline = match &self.batch {
+            Some(batch) => batch.lock().await.deadline(),
+            None => None,
+        };
+        match deadline {
+            Some(deadline) => {
+                async_std::task::sleep(deadline.saturating_duration_since(Instant::now())).await
+            }
+            None => futures::future::pending().await,
+        }
+    }
+
+    async fn write_to_storage(&self, writes: Vec<PendingWrite>) {
+        let mut storage = self.storage.lock().await;
+        let mut stored_writes = Vec::with_capacity(writes.len());
+        let mut storage_writes = Vec::with_capacity(writes.len());
+        for write in writes {
+            let stripped_key = match self.strip_prefix(&write.key.clone().into()) {
+                Ok(stripped) => stripped,
+                Err(e) => {
+                    tracing::error!("{}", e);
+                    continue;
+                }
+            };
+            storage_writes.push(match write.kind {
+                SampleKind::Put => StorageWrite::Put {
+                    key: stripped_key,
+                    value: write.data.value.clone(),
+                    timestamp: write.data.timestamp,
+                },
+                SampleKind::Delete => {
                     // register a tombstone
-                    self.mark_tombstone(&k, sample_to_store.timestamp.unwrap())
-                        .await;
-                    storage
-                        .delete(stripped_key, sample_to_store.timestamp.unwrap())
-                        .await
-                } else {
-                    Err("sample kind not implemented".into())
-                };
-                drop(storage);
-                if self.replication.is_some()
-                    && result.is_ok()
-                    && !matches!(result.unwrap(), StorageInsertionResult::Outdated)
+                    self.mark_tombstone(&write.key, write.data.timestamp).await;
+                    StorageWrite::Delete {
+                        key: stripped_key,
+                        timestamp: write.data.timestamp,
+                    }
+                }
+            });
+            stored_writes.push(write);
+        }
+        let results = storage.write_batch(storage_writes).await;
+        if results.len() != stored_writes.len() {
+            tracing::error!(
+                "Storage '{}' returned {} results for a batch of {} writes",
+                self.name,
+                results.len(),
+                stored_writes.len()
+            );
+        }
+        // write the new values through the cache, or invalidate them if they were not stored
+        if let Some(cache) = &self.cache {
+            let mut cache = cache.lock().await;
+            for (index, write) in stored_writes.iter().enumerate() {
+                match results.get(index) {
+                    Some(Ok(StorageInsertionResult::Inserted))
+                    | Some(Ok(StorageInsertionResult::Replaced))
+                        if write.kind == SampleKind::Put =>
+                    {
+                        cache.put(write.key.clone(), write.data.clone())
+                    }
+                    _ => cache.invalidate(&write.key),
+                }
+            }
+        }
+        drop(storage);
+        if let Some(replication) = &self.replication {
+            for (write, result) in stored_writes.iter().zip(results) {
+                if matches!(result, Ok(result) if !matches!(result, StorageInsertionResult::Outdated))
                 {
-                    let sending = self
-                        .replication
-                        .as_ref()
-                        .unwrap()
+                    let sending = replication
                         .log_propagation
-                        .send((k.clone(), *sample_to_store.get_timestamp().unwrap()));
+                        .send((write.key.clone(), write.data.timestamp));
                     match sending {
                         Ok(_) => (),
                         Err(e) => {
@@ -471,7 +659,12 @@ impl StorageService {
     }
 
     async fn is_latest(&self, key_expr: &OwnedKeyExpr, timestamp: &Timestamp) -> bool {
-        // @TODO: if cache exists, read from there
+        if let Some(cache) = &self.cache {
+            // the cache is written through, a cached value is the latest one in the storage
+            if let Some(cached) = cache.lock().await.timestamp(key_expr) {
+                return cached <= *timestamp;
+            }
+        }
         let mut storage = self.storage.lock().await;
         let stripped_key = match self.strip_prefix(&key_expr.into()) {
             Ok(stripped) => stripped,
@@ -480,6 +673,7 @@ impl StorageService {
                 return false;
             }
         };
+        // the backend is read directly, so that the lookups of the writes are not accounted in the cache statistics
         if let Ok(stored_data) = storage.get(stripped_key, "").await {
             for entry in stored_data {
                 if entry.timestamp > *timestamp {
@@ -499,6 +693,8 @@ impl StorageService {
             }
         };
         tracing::trace!("[STORAGE] Processing query on key_expr: {}", q.key_expr());
+        // the reply must include the pending writes
+        self.flush_batch().await;
         if q.key_expr().is_wild() {
             // resolve key expr into individual keys
             let matching_keys = self.get_matching_keys(q.key_expr()).await;
@@ -512,7 +708,10 @@ impl StorageService {
                         return;
                     }
                 };
-                match storage.get(stripped_key, q.parameters()).await {
+                match self
+                    .get_stored_data(&mut storage, &key, stripped_key, q.parameters())
+                    .await
+                {
                     Ok(stored_data) => {
                         for entry in stored_data {
                             let sample = Sample::new(key.clone(), entry.value)
@@ -547,8 +746,12 @@ impl StorageService {
                     return;
                 }
             };
+            let key: OwnedKeyExpr = q.key_expr().clone().into();
             let mut storage = self.storage.lock().await;
-            match storage.get(stripped_key, q.parameters()).await {
+            match self
+                .get_stored_data(&mut storage, &key, stripped_key, q.parameters())
+                .await
+            {
                 Ok(stored_data) => {
                     for entry in stored_data {
                         let sample = Sample::new(q.key_expr().clone(), entry.value)
@@ -575,6 +778,35 @@ impl StorageService {
         }
     }
 
+    // Gets the stored data of a key, through the cache for the queries on the latest value.
+    // Must be called with the lock on the storage, so that the cache is filled consistently with the storage.
+    async fn get_stored_data(
+        &self,
+        storage: &mut Box<dyn Storage>,
+        key: &OwnedKeyExpr,
+        stripped_key: Option<OwnedKeyExpr>,
+        parameters: &str,
+    ) -> ZResult<Vec<StoredData>> {
+        let cache = match &self.cache {
+            Some(cache) if parameters.is_empty() => cache,
+            _ => return storage.get(stripped_key, parameters).await,
+        };
+        if let Some(cached) = cache.lock().await.get(key) {
+            return Ok(vec![cached]);
+        }
+        let stored_data = storage.get(stripped_key, parameters).await?;
+        if let Some(latest) = stored_data.iter().max_by_key(|entry| entry.timestamp) {
+            cache.lock().await.put(key.clone(), latest.clone());
+        }
+        Ok(stored_data)
+    }
+
+    async fn add_cache_status(&self, status: &mut serde_json::Value) {
+        if let (Some(cache), serde_json::Value::Object(status)) = (&self.cache, status) {
+            status.insert("cache".into(), cache.lock().await.to_json());
+        }
+    }
+
     async fn get_matching_keys(&self, key_expr: &KeyExpr<'_>) -> Vec<OwnedKeyExpr> {
         let mut result = Vec::new();
         // @TODO: if cache exists, use that to get the list
@@ -636,6 +868,52 @@ impl StorageService {
         }
     }
 
+    // Export the content of the storage and its tombstones to a snapshot file, from a dedicated task
+    // so that the storage keeps processing samples and queries meanwhile
+    fn spawn_export(&self, path: PathBuf, tx: async_std::channel::Sender<serde_json::Value>) {
+        let name = self.name.clone();
+        let key_expr = self.key_expr.clone();
+        let strip_prefix = self.strip_prefix.clone();
+        let storage = self.storage.clone();
+        let tombstones = self.tombstones.clone();
+        let history = self.capability.history.clone();
+        async_std::task::spawn(async move {
+            let result = export_snapshot(
+                &name,
+                &key_expr,
+                &strip_prefix,
+                history,
+                &storage,
+                &tombstones,
+                &path,
+            )
+            .await;
+            std::mem::drop(tx.send(operation_result(result)).await);
+        });
+    }
+
+    // Import a snapshot file into the storage, from a dedicated task reading it incrementally.
+    // The entries are processed by the storage service as incoming samples, hence newer data in the storage is preserved
+    fn spawn_import(&self, path: PathBuf, tx: async_std::channel::Sender<serde_json::Value>) {
+        let name = self.name.clone();
+        let key_expr = self.key_expr.clone();
+        let imports = self.snapshot_imports.0.clone();
+        async_std::task::spawn(async move {
+            let result = import_snapshot(&name, &key_expr, &imports, &path).await;
+            std::mem::drop(tx.send(operation_result(result)).await);
+        });
+    }
+
+    async fn process_snapshot_import(&self, import: SnapshotImport) {
+        match import {
+            SnapshotImport::Sample(sample) => self.process_sample(*sample).await,
+            SnapshotImport::Done(tx) => {
+                self.flush_batch().await;
+                let _ = tx.send(()).await;
+            }
+        }
+    }
+
     async fn initialize_if_empty(&mut self) {
         if self.replication.is_some() && self.replication.as_ref().unwrap().empty_start {
             // align with other storages, querying them on key_expr,
@@ -670,6 +948,147 @@ impl StorageService {
     }
 }
 
+// The keys and the tombstones exported are the ones of the storage when the export starts.
+// The storage is only locked to read each key: the history of a time-series is exported up to that point,
+// while a key keeping only its latest value is exported with the value it has when read.
+async fn export_snapshot(
+    name: &str,
+    key_expr: &OwnedKeyExpr,
+    strip_prefix: &Option<OwnedKeyExpr>,
+    history: History,
+    storage: &Mutex<Box<dyn Storage>>,
+    tombstones: &RwLock<KeBoxTree<Timestamp, NonWild, KeyedSetProvider>>,
+    path: &Path,
+) -> ZResult<serde_json::Value> {
+    // time-series storages export their whole history
+    let parameters = if history.eq(&History::All) {
+        "_time=[..]"
+    } else {
+        ""
+    };
+    let entries = storage.lock().await.get_all_entries().await?;
+    let tombstones: Vec<(OwnedKeyExpr, Timestamp)> = tombstones
+        .read()
+        .await
+        .key_value_pairs()
+        .map(|(key, timestamp)| (key, *timestamp))
+        .collect();
+
+    let mut writer = SnapshotWriter::create(path, key_expr).await?;
+    let mut exported_entries = 0;
+    for (key, latest) in entries {
+        let full_key = match &key {
+            Some(key) => StorageService::get_prefixed(strip_prefix, &key.into()),
+            None => match strip_prefix {
+                Some(prefix) => prefix.clone(),
+                None => {
+                    tracing::error!("Storage '{}' returned an empty key", name);
+                    continue;
+                }
+            },
+        };
+        let result = storage.lock().await.get(key, parameters).await;
+        match result {
+            Ok(stored_data) => {
+                for data in stored_data {
+                    if history.eq(&History::All) && data.timestamp > latest {
+                        continue;
+                    }
+                    let record = SnapshotRecord::Entry(SnapshotEntry::new(&full_key, &data));
+                    writer.write(&record).await?;
+                    exported_entries += 1;
+                }
+            }
+            Err(e) => tracing::warn!(
+                "Storage '{}' raised an error exporting key {}: {}",
+                name,
+                full_key,
+                e
+            ),
+        }
+    }
+    for (key, timestamp) in &tombstones {
+        let record = SnapshotRecord::Tombstone(SnapshotTombstone::new(key, timestamp));
+        writer.write(&record).await?;
+    }
+    writer.finish().await?;
+    tracing::info!(
+        "Storage '{}' exported {} entries and {} tombstones to {}",
+        name,
+        exported_entries,
+        tombstones.len(),
+        path.display()
+    );
+    Ok(serde_json::json!({
+        "path": path.display().to_string(),
+        "entries": exported_entries,
+        "tombstones": tombstones.len(),
+    }))
+}
+
+async fn import_snapshot(
+    name: &str,
+    key_expr: &OwnedKeyExpr,
+    imports: &Sender<SnapshotImport>,
+    path: &Path,
+) -> ZResult<serde_json::Value> {
+    let mut reader = SnapshotReader::open(path).await?;
+    let (mut imported, mut skipped) = (0, 0);
+    while let Some(record) = reader.next().await? {
+        let sample = match &record {
+            SnapshotRecord::Entry(entry) => entry.to_sample(),
+            SnapshotRecord::Tombstone(tombstone) => tombstone.to_sample(),
+            SnapshotRecord::Header { .. } => Err(zerror!("Unexpected snapshot header").into()),
+        };
+        match sample {
+            Ok(sample) if key_expr.includes(&sample.key_expr) => {
+                if imports
+                    .send_async(SnapshotImport::Sample(Box::new(sample)))
+                    .await
+                    .is_err()
+                {
+                    bail!("Storage '{}' stopped during the import", name);
+                }
+                imported += 1;
+            }
+            Ok(sample) => {
+                tracing::debug!(
+                    "Storage '{}' skipped key {} not matching '{}'",
+                    name,
+                    sample.key_expr,
+                    key_expr
+                );
+                skipped += 1;
+            }
+            Err(e) => {
+                tracing::warn!("Storage '{}' skipped a snapshot entry: {}", name, e);
+                skipped += 1;
+            }
+        }
+    }
+    // the import completes once its samples are written to the storage
+    let (tx, rx) = async_std::channel::bounded(1);
+    if imports.send_async(SnapshotImport::Done(tx)).await.is_err() || rx.recv().await.is_err() {
+        bail!("Storage '{}' stopped during the import", name);
+    }
+    tracing::info!(
+        "Storage '{}' imported {} entries from {} ({} skipped)",
+        name,
+        imported,
+        path.display(),
+        skipped
+    );
+    Ok(serde_json::json!({
+        "path": path.display().to_string(),
+        "imported": imported,
+        "skipped": skipped,
+    }))
+}
+
+fn operation_result(result: ZResult<serde_json::Value>) -> serde_json::Value {
+    result.unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() }))
+}
+
 fn serialize_update(update: &Update) -> String {
     let result = (
         update.kind.to_string(),
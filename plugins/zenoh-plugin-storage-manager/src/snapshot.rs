@@ -0,0 +1,252 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+// Portable snapshot of the content of a storage, independent of its backend.
+// Keys are saved with the `strip_prefix` of the storage, so that a snapshot can be imported
+// into any storage whose key_expr covers them, whatever its backend and its `strip_prefix`.
+// A snapshot file is line-delimited JSON: a header followed by one record per entry or tombstone,
+// so that it is written and read incrementally, whatever the size of the storage.
+
+use async_std::fs::File;
+use async_std::io::prelude::{BufReadExt, WriteExt};
+use async_std::io::{BufReader, BufWriter};
+use base64::{engine::general_purpose::STANDARD as b64_std_engine, Engine};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use zenoh::buffers::ZBuf;
+use zenoh::prelude::*;
+use zenoh::time::Timestamp;
+use zenoh_backend_traits::StoredData;
+use zenoh_result::{zerror, ZResult};
+
+pub const SNAPSHOT_VERSION: u64 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotRecord {
+    Header {
+        version: u64,
+        // key_expr of the exported storage
+        key_expr: String,
+    },
+    Entry(SnapshotEntry),
+    Tombstone(SnapshotTombstone),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub key: String,
+    pub timestamp: String,
+    pub encoding: String,
+    // payload encoded in base64
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotTombstone {
+    pub key: String,
+    pub timestamp: String,
+}
+
+// Writes the records of a snapshot to a file, one per line
+pub struct SnapshotWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+}
+
+impl SnapshotWriter {
+    pub async fn create(path: &Path, key_expr: &OwnedKeyExpr) -> ZResult<Self> {
+        let file = File::create(path)
+            .await
+            .map_err(|e| zerror!("Error creating snapshot {}: {}", path.display(), e))?;
+        let mut writer = SnapshotWriter {
+            path: path.to_path_buf(),
+            file: BufWriter::new(file),
+        };
+        writer
+            .write(&SnapshotRecord::Header {
+                version: SNAPSHOT_VERSION,
+                key_expr: key_expr.to_string(),
+            })
+            .await?;
+        Ok(writer)
+    }
+
+    pub async fn write(&mut self, record: &SnapshotRecord) -> ZResult<()> {
+        let mut line = serde_json::to_vec(record)
+            .map_err(|e| zerror!("Error serializing snapshot record: {}", e))?;
+        line.push(b'\n');
+        self.file
+            .write_all(&line)
+            .await
+            .map_err(|e| zerror!("Error writing snapshot to {}: {}", self.path.display(), e).into())
+    }
+
+    pub async fn finish(mut self) -> ZResult<()> {
+        self.file
+            .flush()
+            .await
+            .map_err(|e| zerror!("Error writing snapshot to {}: {}", self.path.display(), e).into())
+    }
+}
+
+// Reads the records of a snapshot from a file, one per line
+pub struct SnapshotReader {
+    path: PathBuf,
+    file: BufReader<File>,
+    line: String,
+}
+
+impl SnapshotReader {
+    // Opens a snapshot and checks its header
+    pub async fn open(path: &Path) -> ZResult<Self> {
+        let file = File::open(path)
+            .await
+            .map_err(|e| zerror!("Error reading snapshot from {}: {}", path.display(), e))?;
+        let mut reader = SnapshotReader {
+            path: path.to_path_buf(),
+            file: BufReader::new(file),
+            line: String::new(),
+        };
+        match reader.next().await? {
+            Some(SnapshotRecord::Header { version, .. }) if version == SNAPSHOT_VERSION => {
+                Ok(reader)
+            }
+            Some(SnapshotRecord::Header { version, .. }) => Err(zerror!(
+                "Unsupported snapshot version {} in {} (expected {})",
+                version,
+                path.display(),
+                SNAPSHOT_VERSION
+            )
+            .into()),
+            _ => Err(zerror!("Missing snapshot header in {}", path.display()).into()),
+        }
+    }
+
+    // Returns the next record, or None at the end of the snapshot
+    pub async fn next(&mut self) -> ZResult<Option<SnapshotRecord>> {
+        loop {
+            self.line.clear();
+            let read = self.file.read_line(&mut self.line).await.map_err(|e| {
+                zerror!("Error reading snapshot from {}: {}", self.path.display(), e)
+            })?;
+            if read == 0 {
+                return Ok(None);
+            }
+            if self.line.trim().is_empty() {
+                continue;
+            }
+            return serde_json::from_str(&self.line).map(Some).map_err(|e| {
+                zerror!("Error decoding snapshot {}: {}", self.path.display(), e).into()
+            });
+        }
+    }
+}
+
+impl SnapshotEntry {
+    pub fn new(key: &OwnedKeyExpr, data: &StoredData) -> Self {
+        SnapshotEntry {
+            key: key.to_string(),
+            timestamp: data.timestamp.to_string(),
+            encoding: data.value.encoding.to_string(),
+            value: b64_std_engine.encode(data.value.payload.contiguous()),
+        }
+    }
+
+    pub fn to_sample(&self) -> ZResult<Sample> {
+        let key = OwnedKeyExpr::from_str(&self.key)?;
+        let timestamp = parse_timestamp(&self.timestamp)?;
+        let payload = b64_std_engine
+            .decode(&self.value)
+            .map_err(|e| zerror!("Invalid value for key {} in snapshot: {}", self.key, e))?;
+        let value = Value::new(ZBuf::from(payload)).encoding(Encoding::from(self.encoding.clone()));
+        Ok(Sample::new(key, value).with_timestamp(timestamp))
+    }
+}
+
+impl SnapshotTombstone {
+    pub fn new(key: &OwnedKeyExpr, timestamp: &Timestamp) -> Self {
+        SnapshotTombstone {
+            key: key.to_string(),
+            timestamp: timestamp.to_string(),
+        }
+    }
+
+    pub fn to_sample(&self) -> ZResult<Sample> {
+        let key = OwnedKeyExpr::from_str(&self.key)?;
+        let timestamp = parse_timestamp(&self.timestamp)?;
+        let mut sample = Sample::new(key, Value::empty()).with_timestamp(timestamp);
+        sample.kind = SampleKind::Delete;
+        Ok(sample)
+    }
+}
+
+fn parse_timestamp(s: &str) -> ZResult<Timestamp> {
+    Timestamp::from_str(s)
+        .map_err(|e| zerror!("Invalid timestamp {} in snapshot: {:?}", s, e).into())
+}
+
+#[test]
+fn test_snapshot_roundtrip() {
+    let key = OwnedKeyExpr::from_str("demo/example/a").unwrap();
+    let timestamp = Timestamp::from_str("2022-12-21T15:00:00.000000000Z/1").unwrap();
+    let data = StoredData {
+        value: Value::from(vec![0u8, 1, 2, 255]).encoding(KnownEncoding::AppOctetStream.into()),
+        timestamp,
+    };
+    let records = vec![
+        SnapshotRecord::Entry(SnapshotEntry::new(&key, &data)),
+        SnapshotRecord::Tombstone(SnapshotTombstone::new(&key, &timestamp)),
+    ];
+
+    let path = std::env::temp_dir().join("zenoh_storage_snapshot_test.jsonl");
+    let loaded = async_std::task::block_on(async {
+        let key_expr = OwnedKeyExpr::from_str("demo/example/**").unwrap();
+        let mut writer = SnapshotWriter::create(&path, &key_expr).await.unwrap();
+        for record in &records {
+            writer.write(record).await.unwrap();
+        }
+        writer.finish().await.unwrap();
+
+        let mut reader = SnapshotReader::open(&path).await.unwrap();
+        let mut loaded = Vec::new();
+        while let Some(record) = reader.next().await.unwrap() {
+            loaded.push(record);
+        }
+        loaded
+    });
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, records);
+
+    let SnapshotRecord::Entry(entry) = &loaded[0] else {
+        panic!("Expected an entry, got {:?}", loaded[0]);
+    };
+    let sample = entry.to_sample().unwrap();
+    assert_eq!(sample.key_expr.as_str(), "demo/example/a");
+    assert_eq!(sample.kind, SampleKind::Put);
+    assert_eq!(sample.timestamp, Some(timestamp));
+    assert_eq!(sample.value.encoding, data.value.encoding);
+    assert_eq!(
+        sample.value.payload.contiguous(),
+        data.value.payload.contiguous()
+    );
+
+    let SnapshotRecord::Tombstone(tombstone) = &loaded[1] else {
+        panic!("Expected a tombstone, got {:?}", loaded[1]);
+    };
+    let sample = tombstone.to_sample().unwrap();
+    assert_eq!(sample.kind, SampleKind::Delete);
+    assert_eq!(sample.timestamp, Some(timestamp));
+}
@@ -12,6 +12,9 @@
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
 use async_std::sync::Arc;
+use std::path::PathBuf;
+use zenoh::prelude::r#async::*;
+use zenoh::queryable::Query;
 use zenoh::Session;
 use zenoh_backend_traits::config::StorageConfig;
 use zenoh_result::ZResult;
@@ -21,12 +24,26 @@ pub use super::replica::{Replica, StorageService};
 pub enum StorageMessage {
     Stop,
     GetStatus(async_std::channel::Sender<serde_json::Value>),
+    // Request an immediate alignment of a replica with the other replicas
+    Align(async_std::channel::Sender<serde_json::Value>),
+    // Export the content of the storage to a snapshot file at the given path,
+    // resolved within the `snapshots_dir` of the plugin
+    Export(
+        std::path::PathBuf,
+        async_std::channel::Sender<serde_json::Value>,
+    ),
+    // Import the snapshot file at the given path into the storage
+    Import(
+        std::path::PathBuf,
+        async_std::channel::Sender<serde_json::Value>,
+    ),
 }
 
 pub(crate) async fn start_storage(
     store_intercept: super::StoreIntercept,
     config: StorageConfig,
     admin_key: String,
+    snapshots_dir: Option<PathBuf>,
     zenoh: Arc<Session>,
 ) -> ZResult<flume::Sender<StorageMessage>> {
     // Ex: @/router/390CEC11A1E34977A1C609A35BC015E6/status/plugins/storage_manager/storages/demo1 -> 390CEC11A1E34977A1C609A35BC015E6/demo1 (/<type> needed????)
@@ -39,6 +56,20 @@ pub(crate) async fn start_storage(
 
     let (tx, rx) = flume::bounded(1);
 
+    // The operations on the storage (alignment of a replica, export and import of a snapshot) are requested
+    // by a query on `<admin_key>/<operation>`, and replied with their result once the storage completes them
+    let operations_queryable = {
+        let handle = tx.clone();
+        let prefix = format!("{admin_key}/");
+        zenoh
+            .declare_queryable(format!("{admin_key}/*"))
+            .callback(move |query| {
+                run_storage_operation(&handle, &prefix, snapshots_dir.as_deref(), query)
+            })
+            .res_async()
+            .await?
+    };
+
     async_std::task::spawn(async move {
         // If a configuration for replica is present, we initialize a replica, else only a storage service
         // A replica contains a storage service and all metadata required for anti-entropy
@@ -47,7 +78,62 @@ pub(crate) async fn start_storage(
         } else {
             StorageService::start(zenoh.clone(), config, &name, store_intercept, rx, None).await;
         }
+        drop(operations_queryable);
     });
 
     Ok(tx)
 }
+
+// Only a query on the exact key of an operation triggers it, wildcard queries on the admin space don't
+fn run_storage_operation(
+    handle: &flume::Sender<StorageMessage>,
+    prefix: &str,
+    snapshots_dir: Option<&std::path::Path>,
+    query: Query,
+) {
+    let Some(operation) = query.key_expr().as_str().strip_prefix(prefix) else {
+        return;
+    };
+    if !matches!(operation, "align" | "export" | "import") {
+        return;
+    }
+    let (tx, rx) = async_std::channel::bounded(1);
+    let path = query
+        .selector()
+        .parameters_stringmap()
+        .ok()
+        .and_then(|mut parameters| parameters.remove("path"));
+    let message = match path.map(|path| super::snapshot_path(snapshots_dir, &path)) {
+        _ if operation == "align" => Ok(StorageMessage::Align(tx)),
+        None => Err(format!(
+            "Operation `{operation}` requires a `path` parameter"
+        )),
+        Some(Ok(path)) if operation == "export" => Ok(StorageMessage::Export(path, tx)),
+        Some(Ok(path)) => Ok(StorageMessage::Import(path, tx)),
+        Some(Err(e)) => {
+            tracing::warn!("Rejected storage operation `{}`: {}", operation, e);
+            Err(e.to_string())
+        }
+    };
+    let handle = handle.clone();
+    // the operation is awaited off the session callback, as it can last long
+    async_std::task::spawn(async move {
+        let result = match message {
+            Ok(message) => match handle.send_async(message).await {
+                Ok(()) => rx.recv().await.ok(),
+                Err(_) => None,
+            },
+            Err(error) => Some(serde_json::json!({ "error": error })),
+        };
+        if let Some(result) = result {
+            let sample = Sample::new(query.key_expr().clone(), Value::from(result));
+            if let Err(e) = query.reply(Ok(sample)).res_async().await {
+                tracing::warn!(
+                    "Error replying to storage operation `{}`: {}",
+                    query.key_expr(),
+                    e
+                );
+            }
+        }
+    });
+}
@@ -0,0 +1,122 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+// Test batched writes -
+// 1. the pending writes are visible to queries before the batch is full
+// 2. updates of a key pending in a batch, and wildcard deletes, are applied in order
+
+use std::thread::sleep;
+
+use async_std::task;
+use zenoh::prelude::r#async::*;
+use zenoh::prelude::Config;
+use zenoh::query::Reply;
+use zenoh_core::zasync_executor_init;
+use zenoh_plugin_trait::Plugin;
+
+async fn put_data(session: &zenoh::Session, key_expr: &str, value: &str) {
+    println!("Putting Data ('{key_expr}': '{value}')...");
+    session.put(key_expr, value).res().await.unwrap();
+}
+
+async fn delete_data(session: &zenoh::Session, key_expr: &str) {
+    println!("Deleting Data '{key_expr}'...");
+    session.delete(key_expr).res().await.unwrap();
+}
+
+async fn get_data(session: &zenoh::Session, key_expr: &str) -> Vec<Sample> {
+    let replies: Vec<Reply> = session
+        .get(key_expr)
+        .res()
+        .await
+        .unwrap()
+        .into_iter()
+        .collect();
+    println!("Getting replies on '{key_expr}': '{replies:?}'...");
+    let mut samples = Vec::new();
+    for reply in replies {
+        if let Ok(sample) = reply.sample {
+            samples.push(sample);
+        }
+    }
+    println!("Getting Data on '{key_expr}': '{samples:?}'...");
+    samples
+}
+
+async fn test_batched_updates() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+    let mut config = Config::default();
+    config
+        .insert_json5(
+            "plugins/storage-manager",
+            r#"{
+                    storages: {
+                        batch_test: {
+                            key_expr: "batch/test/**",
+                            volume: {
+                                id: "memory"
+                            },
+                            batch: {
+                                max_size: 100,
+                                max_delay: 60000
+                            }
+                        }
+                    }
+                }"#,
+        )
+        .unwrap();
+
+    let runtime = zenoh::runtime::RuntimeBuilder::new(config)
+        .build()
+        .await
+        .unwrap();
+    let storage =
+        zenoh_plugin_storage_manager::StoragesPlugin::start("storage-manager", &runtime).unwrap();
+
+    let session = zenoh::init(runtime).res().await.unwrap();
+
+    sleep(std::time::Duration::from_secs(1));
+
+    put_data(&session, "batch/test/a", "1").await;
+    put_data(&session, "batch/test/b", "2").await;
+    put_data(&session, "batch/test/a", "3").await;
+
+    sleep(std::time::Duration::from_millis(10));
+
+    // the batch is neither full nor expired, but the query flushes it
+    let data = get_data(&session, "batch/test/a").await;
+    assert_eq!(data.len(), 1);
+    assert_eq!(format!("{}", data[0].value), "3");
+
+    let data = get_data(&session, "batch/test/**").await;
+    assert_eq!(data.len(), 2);
+
+    put_data(&session, "batch/test/c", "4").await;
+    delete_data(&session, "batch/test/*").await;
+
+    sleep(std::time::Duration::from_millis(10));
+
+    // expects zero sample
+    let data = get_data(&session, "batch/test/**").await;
+    assert_eq!(data.len(), 0);
+
+    drop(storage);
+}
+
+#[test]
+fn batch_test() {
+    task::block_on(async { test_batched_updates().await });
+}
@@ -0,0 +1,155 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+// Test the snapshot operations on storages -
+// 1. an export and an import are triggered by a query on the key of the operation, and replied with their result
+// 2. wildcard queries on the admin space don't trigger any operation
+// 3. the alignment of a storage that is not a replica is replied with an error
+
+use std::thread::sleep;
+
+use async_std::task;
+use zenoh::prelude::r#async::*;
+use zenoh::prelude::Config;
+use zenoh_core::zasync_executor_init;
+use zenoh_plugin_trait::Plugin;
+
+async fn run_operation(session: &zenoh::Session, selector: &str) -> Vec<serde_json::Value> {
+    let replies = session.get(selector).res().await.unwrap();
+    let mut results = Vec::new();
+    while let Ok(reply) = replies.recv_async().await {
+        if let Ok(sample) = reply.sample {
+            println!("Operation '{selector}' replied: {}", sample.value);
+            results.push(serde_json::from_str(&sample.value.to_string()).unwrap());
+        }
+    }
+    results
+}
+
+async fn test_snapshot_operations() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+    let dir = std::env::temp_dir().join(format!("zenoh_snapshot_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut config = Config::default();
+    config
+        .insert_json5(
+            "plugins/storage-manager",
+            &format!(
+                r#"{{
+                    snapshots_dir: {:?},
+                    storages: {{
+                        source: {{
+                            key_expr: "snapshot/source/**",
+                            volume: {{
+                                id: "memory"
+                            }}
+                        }},
+                        target: {{
+                            key_expr: "snapshot/target/**",
+                            volume: {{
+                                id: "memory"
+                            }}
+                        }}
+                    }}
+                }}"#,
+                dir.display().to_string()
+            ),
+        )
+        .unwrap();
+
+    let runtime = zenoh::runtime::RuntimeBuilder::new(config)
+        .build()
+        .await
+        .unwrap();
+    let storage =
+        zenoh_plugin_storage_manager::StoragesPlugin::start("storage-manager", &runtime).unwrap();
+
+    let session = zenoh::init(runtime).res().await.unwrap();
+    let storages = format!(
+        "@/router/{}/status/plugins/storage-manager/storages",
+        session.zid()
+    );
+
+    sleep(std::time::Duration::from_secs(1));
+
+    session.put("snapshot/source/a", "1").res().await.unwrap();
+    session.put("snapshot/source/b", "2").res().await.unwrap();
+
+    sleep(std::time::Duration::from_millis(10));
+
+    // wildcard queries don't trigger any export
+    run_operation(&session, &format!("{storages}/**?path=source.json")).await;
+    assert!(!dir.join("source.json").exists());
+
+    let results = run_operation(
+        &session,
+        &format!("{storages}/source/export?path=source.json"),
+    )
+    .await;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["entries"], 2);
+    assert!(dir.join("source.json").exists());
+
+    // the snapshot is imported back, the storage still holds the same entries
+    let results = run_operation(
+        &session,
+        &format!("{storages}/source/import?path=source.json"),
+    )
+    .await;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["imported"], 2);
+    assert_eq!(results[0]["skipped"], 0);
+    let replies = session.get("snapshot/source/**").res().await.unwrap();
+    let mut count = 0;
+    while replies.recv_async().await.is_ok() {
+        count += 1;
+    }
+    assert_eq!(count, 2);
+
+    // the keys of the snapshot don't match the key_expr of the target storage
+    let results = run_operation(
+        &session,
+        &format!("{storages}/target/import?path=source.json"),
+    )
+    .await;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["imported"], 0);
+    assert_eq!(results[0]["skipped"], 2);
+
+    let results = run_operation(&session, &format!("{storages}/source/export")).await;
+    assert_eq!(results.len(), 1);
+    assert!(results[0]["error"].is_string());
+
+    let results = run_operation(
+        &session,
+        &format!("{storages}/source/export?path=../source.json"),
+    )
+    .await;
+    assert_eq!(results.len(), 1);
+    assert!(results[0]["error"].is_string());
+
+    let results = run_operation(&session, &format!("{storages}/source/align")).await;
+    assert_eq!(results.len(), 1);
+    assert!(results[0]["error"].is_string());
+
+    drop(storage);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn snapshot_test() {
+    task::block_on(async { test_snapshot_operations().await });
+}
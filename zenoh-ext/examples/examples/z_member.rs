@@ -38,9 +38,12 @@ async fn main() {
             v.iter()
                 .fold(String::from("\n"), |a, b| format!("\t{a} \n\t{b:?}")),
         );
-        println!(">>>>>>> Eventual Leader <<<<<<<<<");
-        let m = group.leader().await;
+        println!(">>>>>>> Elected Leader <<<<<<<<<");
+        let m = group.elected_leader().await;
         println!("Leader mid = {m:?}");
+        if let GroupEvent::NewLeader(e) = &evt {
+            println!("Leader term = {}", e.term);
+        }
         println!(">>>>>>><<<<<<<<<");
     }
 }
@@ -23,7 +23,8 @@ async fn main() {
     // Initiate logging
     zenoh_util::try_init_log_from_env();
 
-    let (config, key_expr, value, history, prefix, complete) = parse_args();
+    let (config, args) = parse_args();
+    let key_expr = args.key;
 
     println!("Opening session...");
     let session = zenoh::open(config).res().await.unwrap();
@@ -31,17 +32,24 @@ async fn main() {
     println!("Declaring PublicationCache on {}", &key_expr);
     let mut publication_cache_builder = session
         .declare_publication_cache(&key_expr)
-        .history(history)
-        .queryable_complete(complete);
-    if let Some(prefix) = prefix {
+        .history(args.history)
+        .queryable_complete(args.complete);
+    if let Some(prefix) = args.prefix {
         publication_cache_builder = publication_cache_builder.queryable_prefix(prefix);
     }
+    if let Some(retention) = args.retention {
+        publication_cache_builder =
+            publication_cache_builder.retention(Duration::from_secs(retention));
+    }
+    if let Some(persistence) = args.persistence {
+        publication_cache_builder = publication_cache_builder.persistence(persistence);
+    }
     let _publication_cache = publication_cache_builder.res().await.unwrap();
 
     println!("Press CTRL-C to quit...");
     for idx in 0..u32::MAX {
         tokio::time::sleep(Duration::from_secs(1)).await;
-        let buf = format!("[{idx:4}] {value}");
+        let buf = format!("[{idx:4}] {}", args.value);
         println!("Put Data ('{}': '{}')", &key_expr, buf);
         session.put(&key_expr, buf).res().await.unwrap();
     }
@@ -64,30 +72,22 @@ struct Args {
     #[arg(short = 'x', long)]
     /// An optional queryable prefix.
     prefix: Option<String>,
+    #[arg(short, long)]
+    /// An optional retention of the publications in cache, in seconds.
+    retention: Option<u64>,
+    #[arg(short = 'f', long)]
+    /// An optional file persisting the cache across restarts.
+    persistence: Option<String>,
     #[command(flatten)]
     common: CommonArgs,
 }
 
-fn parse_args() -> (
-    Config,
-    KeyExpr<'static>,
-    String,
-    usize,
-    Option<String>,
-    bool,
-) {
+fn parse_args() -> (Config, Args) {
     let args = Args::parse();
-    let mut config: Config = args.common.into();
+    let mut config: Config = args.common.clone().into();
     config
         .timestamping
         .set_enabled(Some(ModeDependentValue::Unique(true)))
         .unwrap();
-    (
-        config,
-        args.key,
-        args.value,
-        args.history,
-        args.prefix,
-        args.complete,
-    )
+    (config, args)
 }
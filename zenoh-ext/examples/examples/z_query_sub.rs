@@ -24,10 +24,10 @@ async fn main() {
     // Initiate logging
     zenoh_util::try_init_log_from_env();
 
-    let (config, key_expr, query) = parse_args();
+    let (config, key_expr, query, recovery) = parse_args();
 
     println!("Opening session...");
-    let session = zenoh::open(config).res().await.unwrap();
+    let session = zenoh::open(config).res().await.unwrap().into_arc();
 
     println!(
         "Declaring QueryingSubscriber on {} with an initial query on {}",
@@ -40,6 +40,7 @@ async fn main() {
             .querying()
             .query_selector(&selector)
             .query_accept_replies(ReplyKeyExpr::Any)
+            .recovery(recovery)
             .res()
             .await
             .unwrap()
@@ -47,6 +48,7 @@ async fn main() {
         session
             .declare_subscriber(key_expr)
             .querying()
+            .recovery(recovery)
             .res()
             .await
             .unwrap()
@@ -71,11 +73,14 @@ struct Args {
     #[arg(short, long)]
     /// The selector to use for queries (by default it's same as 'key' option)
     query: Option<String>,
+    #[arg(short, long)]
+    /// Query again the publications missed on reconnection or sequence gaps.
+    recovery: bool,
     #[command(flatten)]
     common: CommonArgs,
 }
 
-fn parse_args() -> (Config, String, Option<String>) {
+fn parse_args() -> (Config, String, Option<String>, bool) {
     let args = Args::parse();
-    (args.common.into(), args.key, args.query)
+    (args.common.into(), args.key, args.query, args.recovery)
 }
@@ -18,7 +18,7 @@ use flume::{Receiver, Sender};
 use futures::prelude::*;
 use futures::select;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::ops::Add;
 use std::sync::Arc;
@@ -36,6 +36,7 @@ use zenoh_task::TaskController;
 
 const GROUP_PREFIX: &str = "zenoh/ext/net/group";
 const EVENT_POSTFIX: &str = "evt";
+const ELECTION_POSTFIX: &str = "election";
 const VIEW_REFRESH_LEASE_RATIO: f32 = 0.75f32;
 const DEFAULT_LEASE: Duration = Duration::from_secs(18);
 const DEFAULT_PRIORITY: Priority = Priority::DataHigh;
@@ -58,6 +59,8 @@ pub struct LeaveEvent {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct NewLeaderEvent {
     pub mid: OwnedKeyExpr,
+    // The term of the election, i.e. the fencing token of the leader
+    pub term: u64,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -72,6 +75,62 @@ enum GroupNetEvent {
     KeepAlive(KeepAliveEvent),
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+struct TermEvent {
+    pub mid: OwnedKeyExpr,
+    pub term: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct VoteRequestEvent {
+    pub mid: OwnedKeyExpr,
+    pub term: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct VoteEvent {
+    pub mid: OwnedKeyExpr,
+    pub candidate: OwnedKeyExpr,
+    pub term: u64,
+    pub granted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct LeaderEvent {
+    pub mid: OwnedKeyExpr,
+    pub term: u64,
+}
+
+// The events of the election of the leader, published apart from the GroupNetEvents
+// so that the members not taking part in the elections never receive them
+#[derive(Serialize, Deserialize, Debug)]
+enum ElectionEvent {
+    Term(TermEvent),
+    VoteRequest(VoteRequestEvent),
+    Vote(VoteEvent),
+    Leader(LeaderEvent),
+}
+
+impl ElectionEvent {
+    fn mid(&self) -> &OwnedKeyExpr {
+        match self {
+            ElectionEvent::Term(TermEvent { mid, .. })
+            | ElectionEvent::VoteRequest(VoteRequestEvent { mid, .. })
+            | ElectionEvent::Vote(VoteEvent { mid, .. })
+            | ElectionEvent::Leader(LeaderEvent { mid, .. }) => mid,
+        }
+    }
+
+    fn term(&self) -> u64 {
+        match self {
+            ElectionEvent::Term(TermEvent { term, .. })
+            | ElectionEvent::VoteRequest(VoteRequestEvent { term, .. })
+            | ElectionEvent::Vote(VoteEvent { term, .. })
+            | ElectionEvent::Leader(LeaderEvent { term, .. }) => *term,
+        }
+    }
+}
+
 /// Events exposed to the user to be informed for relevant
 /// changes in the group.
 #[derive(Serialize, Deserialize, Debug)]
@@ -97,6 +156,8 @@ pub struct Member {
     refresh_ratio: f32,
     #[serde(skip)]
     priority: Priority,
+    #[serde(skip)]
+    group_size: Option<usize>,
 }
 
 impl Member {
@@ -116,6 +177,7 @@ impl Member {
             lease: DEFAULT_LEASE,
             refresh_ratio: VIEW_REFRESH_LEASE_RATIO,
             priority: DEFAULT_PRIORITY,
+            group_size: None,
         })
     }
 
@@ -150,6 +212,44 @@ impl Member {
         self.priority = p;
         self
     }
+
+    /// Sets the expected number of members of the group taking part in the elections, so that a leader is
+    /// elected by a majority of it.
+    ///
+    /// By default a majority of the members currently taking part in the elections is enough: during a
+    /// partition, a leader may then be elected on each side.
+    pub fn group_size(mut self, n: usize) -> Self {
+        self.group_size = Some(n);
+        self
+    }
+}
+
+// The election of the leader of the group, as seen by the local member
+#[derive(Default)]
+struct Election {
+    // The highest term seen in the group
+    term: u64,
+    // The member voted for in the current term
+    voted_for: Option<OwnedKeyExpr>,
+    // The elected leader and the term of its election
+    leader: Option<(OwnedKeyExpr, u64)>,
+    // The start of the candidacy of the local member in the current term and the votes it received
+    candidacy: Option<(Instant, HashSet<OwnedKeyExpr>)>,
+    // The last announcement of the term of the local member, or of its leadership
+    announced: Option<Instant>,
+    // The other members taking part in the elections, until the expiration of their lease
+    participants: HashMap<OwnedKeyExpr, Instant>,
+}
+
+impl Election {
+    // Follows a later term seen in the group, giving up the vote and candidacy of the current one
+    fn observe(&mut self, term: u64) {
+        if term > self.term {
+            self.term = term;
+            self.voted_for = None;
+            self.candidacy = None;
+        }
+    }
 }
 
 struct GroupState {
@@ -157,8 +257,29 @@ struct GroupState {
     local_member: Member,
     members: Mutex<HashMap<OwnedKeyExpr, (Member, Instant)>>,
     group_publisher: Publisher<'static>,
+    election_publisher: Publisher<'static>,
     user_events_tx: Mutex<Option<Sender<GroupEvent>>>,
     cond: Condition,
+    election: Mutex<Election>,
+    joined: Instant,
+}
+
+impl GroupState {
+    // The number of members needed to elect a leader, given the number of members taking part in the elections
+    fn quorum(&self, participants: usize) -> usize {
+        self.local_member.group_size.unwrap_or(participants) / 2 + 1
+    }
+
+    async fn publish(&self, evt: &ElectionEvent) {
+        let buf = bincode::serialize(evt).unwrap();
+        let _ = self.election_publisher.put(buf).res().await;
+    }
+
+    async fn notify(&self, evt: GroupEvent) {
+        if let Some(tx) = &*self.user_events_tx.lock().await {
+            let _ = tx.send(evt);
+        }
+    }
 }
 
 pub struct Group {
@@ -217,6 +338,96 @@ async fn watchdog_task(s: Arc<GroupState>, period: Duration) {
     }
 }
 
+async fn election_task(s: Arc<GroupState>, period: Duration) {
+    let local = &s.local_member.mid;
+    let lease = s.local_member.lease;
+    let heartbeat = lease.mul_f32(s.local_member.refresh_ratio);
+    loop {
+        tokio::time::sleep(period).await;
+        let now = Instant::now();
+        let ms = s.members.lock().await;
+        let mut e = s.election.lock().await;
+        e.participants.retain(|_, expiry| *expiry > now);
+        let quorum = s.quorum(e.participants.len() + 1);
+        let mut announce = None;
+        match e.leader.clone() {
+            Some((mid, _)) if &mid == local => {
+                if e.participants.len() + 1 < quorum {
+                    tracing::warn!("Leader {} lost the majority: stepping down", local);
+                    e.leader = None;
+                }
+            }
+            Some((mid, _)) if !ms.contains_key(&mid) || !e.participants.contains_key(&mid) => {
+                tracing::debug!("Leader {} left the group", mid);
+                e.leader = None;
+            }
+            _ => {}
+        }
+        drop(ms);
+        // The member with the highest id runs for election, once it had the time to learn about
+        // the current leader and the other participants after joining and its previous candidacy timed out
+        let preferred = e
+            .participants
+            .keys()
+            .all(|mid| mid.as_str() < local.as_str());
+        let mut elected = None;
+        if e.leader.is_none()
+            && preferred
+            && s.joined.elapsed() >= lease
+            && e.candidacy
+                .as_ref()
+                .map_or(true, |(t, _)| t.elapsed() >= lease)
+        {
+            e.term += 1;
+            let term = e.term;
+            tracing::debug!("Running for election in term {}", term);
+            e.voted_for = Some(local.clone());
+            e.candidacy = Some((now, HashSet::from([local.clone()])));
+            if quorum <= 1 {
+                e.candidacy = None;
+                e.leader = Some((local.clone(), term));
+                elected = Some(term);
+                announce = Some(ElectionEvent::Leader(LeaderEvent {
+                    mid: local.clone(),
+                    term,
+                }));
+            } else {
+                announce = Some(ElectionEvent::VoteRequest(VoteRequestEvent {
+                    mid: local.clone(),
+                    term,
+                }));
+            }
+        }
+        // The participation of the local member is announced periodically, along with its term
+        if announce.is_none() && e.announced.map_or(true, |t| t.elapsed() >= heartbeat) {
+            announce = Some(match &e.leader {
+                Some((mid, term)) if mid == local => ElectionEvent::Leader(LeaderEvent {
+                    mid: local.clone(),
+                    term: *term,
+                }),
+                _ => ElectionEvent::Term(TermEvent {
+                    mid: local.clone(),
+                    term: e.term,
+                }),
+            });
+        }
+        if announce.is_some() {
+            e.announced = Some(now);
+        }
+        drop(e);
+        if let Some(evt) = announce {
+            s.publish(&evt).await;
+        }
+        if let Some(term) = elected {
+            s.notify(GroupEvent::NewLeader(NewLeaderEvent {
+                mid: local.clone(),
+                term,
+            }))
+            .await;
+        }
+    }
+}
+
 async fn query_handler(z: Arc<Session>, state: Arc<GroupState>) {
     let qres: KeyExpr = format!(
         "{}/{}/{}",
@@ -354,6 +565,133 @@ async fn net_event_handler(z: Arc<Session>, state: Arc<GroupState>) {
     }
 }
 
+async fn election_event_handler(z: Arc<Session>, state: Arc<GroupState>) {
+    let sub = z
+        .declare_subscriber(state.election_publisher.key_expr())
+        .res()
+        .await
+        .unwrap();
+    while let Ok(s) = sub.recv_async().await {
+        match bincode::deserialize::<ElectionEvent>(&(s.value.payload.contiguous())) {
+            Ok(evt) => {
+                if evt.mid().eq(&state.local_member.mid) {
+                    continue;
+                }
+                let lease = match state.members.lock().await.get(evt.mid()) {
+                    Some((m, _)) => m.lease,
+                    None => state.local_member.lease,
+                };
+                let mut e = state.election.lock().await;
+                e.participants
+                    .insert(evt.mid().clone(), Instant::now().add(lease));
+                // A candidate learns the later terms from any member, and runs again in a later one
+                e.observe(evt.term());
+                drop(e);
+                match evt {
+                    ElectionEvent::Term(_) => {}
+                    ElectionEvent::VoteRequest(vre) => on_vote_request(&state, vre).await,
+                    ElectionEvent::Vote(ve) => {
+                        if ve.candidate.eq(&state.local_member.mid) {
+                            on_vote(&state, ve).await;
+                        }
+                    }
+                    ElectionEvent::Leader(le) => on_leader(&state, le).await,
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Failed decoding election event due to: {:?}", e);
+            }
+        }
+    }
+}
+
+async fn on_vote_request(state: &GroupState, vre: VoteRequestEvent) {
+    let ms = state.members.lock().await;
+    let mut e = state.election.lock().await;
+    // A member keeps following a live leader, so that it is not disrupted by members joining or coming back
+    let granted = match &e.leader {
+        Some((leader, _)) if leader == &state.local_member.mid || ms.contains_key(leader) => {
+            tracing::debug!(
+                "Vote request from {} refused: {} is leader",
+                vre.mid,
+                leader
+            );
+            false
+        }
+        _ => vre.term == e.term && e.voted_for.as_ref().map_or(true, |mid| mid == &vre.mid),
+    };
+    drop(ms);
+    if granted {
+        tracing::debug!("Voting for {} in term {}", vre.mid, vre.term);
+        e.voted_for = Some(vre.mid.clone());
+    }
+    // A refusal carries the term of the local member, so that a candidate in an earlier one learns about it
+    let term = e.term;
+    drop(e);
+    state
+        .publish(&ElectionEvent::Vote(VoteEvent {
+            mid: state.local_member.mid.clone(),
+            candidate: vre.mid,
+            term,
+            granted,
+        }))
+        .await;
+}
+
+async fn on_vote(state: &GroupState, ve: VoteEvent) {
+    let mut e = state.election.lock().await;
+    let quorum = state.quorum(e.participants.len() + 1);
+    if !ve.granted || ve.term != e.term || e.leader.is_some() {
+        return;
+    }
+    let Some((_, votes)) = &mut e.candidacy else {
+        return;
+    };
+    votes.insert(ve.mid);
+    if votes.len() < quorum {
+        return;
+    }
+    tracing::debug!("Elected leader in term {}", ve.term);
+    let mid = state.local_member.mid.clone();
+    e.candidacy = None;
+    e.leader = Some((mid.clone(), ve.term));
+    e.announced = Some(Instant::now());
+    drop(e);
+    state
+        .publish(&ElectionEvent::Leader(LeaderEvent {
+            mid: mid.clone(),
+            term: ve.term,
+        }))
+        .await;
+    state
+        .notify(GroupEvent::NewLeader(NewLeaderEvent { mid, term: ve.term }))
+        .await;
+}
+
+async fn on_leader(state: &GroupState, le: LeaderEvent) {
+    let mut e = state.election.lock().await;
+    // A leader elected in a later term takes over, the highest id wins between leaders of the same term
+    let accept = match &e.leader {
+        Some((mid, term)) => {
+            le.term > *term || (le.term == *term && le.mid.as_str() > mid.as_str())
+        }
+        None => le.term == e.term,
+    };
+    if !accept {
+        return;
+    }
+    tracing::debug!("New leader {} in term {}", le.mid, le.term);
+    e.candidacy = None;
+    e.leader = Some((le.mid.clone(), le.term));
+    drop(e);
+    state
+        .notify(GroupEvent::NewLeader(NewLeaderEvent {
+            mid: le.mid,
+            term: le.term,
+        }))
+        .await;
+}
+
 impl Group {
     pub async fn join<T>(z: Arc<Session>, group: T, with: Member) -> ZResult<Group>
     where
@@ -372,13 +710,23 @@ impl Group {
             .res()
             .await
             .unwrap();
+        let election_expr = format!("{GROUP_PREFIX}/{group}/{ELECTION_POSTFIX}");
+        let election_publisher = z
+            .declare_publisher(election_expr)
+            .priority(with.priority)
+            .res()
+            .await
+            .unwrap();
         let state = Arc::new(GroupState {
             gid: String::from(group),
             local_member: with.clone(),
             members: Mutex::new(Default::default()),
             group_publisher: publisher,
+            election_publisher,
             user_events_tx: Mutex::new(Default::default()),
             cond: Condition::new(),
+            election: Mutex::new(Default::default()),
+            joined: Instant::now(),
         });
         let is_auto_liveliness = matches!(with.liveliness, MemberLiveliness::Auto);
 
@@ -394,8 +742,10 @@ impl Group {
             task_controller.spawn_abortable(keep_alive_task(state.clone()));
         }
         task_controller.spawn_abortable(net_event_handler(z.clone(), state.clone()));
+        task_controller.spawn_abortable(election_event_handler(z.clone(), state.clone()));
         task_controller.spawn_abortable(query_handler(z.clone(), state.clone()));
         task_controller.spawn_abortable(watchdog_task(state.clone(), Duration::from_secs(1)));
+        task_controller.spawn_abortable(election_task(state.clone(), Duration::from_secs(1)));
         Ok(Group {
             state,
             task_controller,
@@ -469,6 +819,9 @@ impl Group {
 
     /// Returns the evental leader for this group. Notice that a view change may cause
     /// a change on leader. Thus it is wise to always get the leader after a view change.
+    ///
+    /// It is the member with the highest id in the view, whether it was elected or not:
+    /// see [`Group::elected_leader`].
     pub async fn leader(&self) -> Member {
         use std::cmp::Ordering;
         let group = self.view().await;
@@ -480,4 +833,41 @@ impl Group {
         }
         leader
     }
+
+    /// Returns the leader elected in this group, if any.
+    ///
+    /// The member with the highest id among the ones taking part in the elections runs for election when
+    /// there is no leader, and is elected when a majority of them (see [`Member::group_size`]) votes for it.
+    /// A [`GroupEvent::NewLeader`] is notified on each election.
+    pub async fn elected_leader(&self) -> Option<Member> {
+        let (mid, _) = self.state.election.lock().await.leader.clone()?;
+        if mid == self.state.local_member.mid {
+            Some(self.state.local_member.clone())
+        } else {
+            self.state
+                .members
+                .lock()
+                .await
+                .get(&mid)
+                .map(|(m, _)| m.clone())
+        }
+    }
+
+    /// Returns true if the local member is the leader of this group.
+    pub async fn is_leader(&self) -> bool {
+        self.fencing_token().await.is_some()
+    }
+
+    /// Returns the fencing token of the local member if it is the leader of this group, in other terms
+    /// the term of its election.
+    ///
+    /// It increases with each election: the resources accessed by the leader should reject the operations
+    /// with a lower token than the highest one they have seen, so that a former leader that does not know
+    /// yet that it was replaced cannot corrupt them.
+    pub async fn fencing_token(&self) -> Option<u64> {
+        match &self.state.election.lock().await.leader {
+            Some((mid, term)) if mid == &self.state.local_member.mid => Some(*term),
+            _ => None,
+        }
+    }
 }
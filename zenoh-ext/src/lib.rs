@@ -12,15 +12,26 @@
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
 pub mod group;
+pub mod lock;
 mod publication_cache;
 mod querying_subscriber;
+pub mod rpc;
+mod sequenced_publisher;
+mod sequenced_subscriber;
+mod sequencing;
 mod session_ext;
 mod subscriber_ext;
 pub use publication_cache::{PublicationCache, PublicationCacheBuilder};
 pub use querying_subscriber::{
     FetchingSubscriber, FetchingSubscriberBuilder, QueryingSubscriberBuilder,
 };
-pub use session_ext::SessionExt;
+pub use sequenced_publisher::{
+    SequencedPublication, SequencedPublisher, SequencedPublisherBuilder,
+};
+pub use sequenced_subscriber::{
+    SequenceEvent, SequenceStats, SequencedSubscriber, SequencedSubscriberBuilder,
+};
+pub use session_ext::{SequencingSessionExt, SessionExt};
 pub use subscriber_ext::SubscriberBuilderExt;
 pub use subscriber_ext::SubscriberForward;
 
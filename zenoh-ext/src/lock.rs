@@ -0,0 +1,328 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+//! To manage distributed locks
+//!
+//! A [`DistributedLock`] is held by at most one of its instances across the zenoh network at a time, as long as
+//! they all see each other. It is bound to the liveliness of the session of its holder: it is released when the
+//! session is closed or loses its connectivity, allowing another instance to acquire it.
+//!
+//! Each holder is given a fencing token, greater than the ones of the previous holders: the resources protected
+//! by the lock should reject the operations with a lower token than the highest one they have seen, so that a
+//! former holder that does not know yet that it lost the lock cannot corrupt them. The tokens are made monotonic
+//! by every instance remembering the highest token it has ever seen on the fence queries, so that they survive the
+//! loss of the holder that issued them.
+//!
+//! The mutual exclusion relies on a timing assumption: the liveliness token of a contender must reach the other
+//! contenders within a settle delay of 200ms. A contender that is not seen in time, e.g. across a slow or partitioned
+//! network, may acquire the lock concurrently with another one, which the fencing tokens allow the protected
+//! resources to detect.
+
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use zenoh::liveliness::LivelinessToken;
+use zenoh::prelude::r#async::*;
+use zenoh::queryable::Queryable;
+use zenoh::subscriber::FlumeSubscriber;
+use zenoh::Error as ZError;
+use zenoh::Result as ZResult;
+use zenoh::Session;
+use zenoh_core::zlock;
+use zenoh_result::bail;
+
+const LOCK_PREFIX: &str = "zenoh/ext/lock";
+const HOLDER_POSTFIX: &str = "holder";
+const FENCE_POSTFIX: &str = "fence";
+// The time for the liveliness token of a contender to reach the other instances,
+// which the mutual exclusion relies on (see the module documentation)
+const SETTLE_DELAY: Duration = Duration::from_millis(200);
+// The period of the acquisition attempts, in case the release of the lock is missed
+const RETRY_PERIOD: Duration = Duration::from_millis(500);
+
+// The state of an instance, as replied to the contenders
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct FenceInfo {
+    // True if the instance holds the lock
+    holding: bool,
+    // The highest fencing token the instance has seen
+    token: u64,
+}
+
+#[derive(Default)]
+struct LockState {
+    // The highest fencing token seen, issued by this instance or observed on the fence queries
+    last_token: u64,
+    held: Option<u64>,
+}
+
+/// A lock held by at most one of its instances at a time, see the [module](self) documentation.
+///
+/// # Examples
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::time::Duration;
+/// use zenoh::prelude::r#async::*;
+/// use zenoh_ext::lock::DistributedLock;
+///
+/// let session = zenoh::open(config::peer()).res().await.unwrap().into_arc();
+/// let lock = DistributedLock::new(session, "my/lock").await.unwrap();
+/// let guard = lock.acquire(Duration::from_secs(10)).await.unwrap();
+/// println!("Acquired with fencing token {}", guard.fencing_token());
+/// drop(guard);
+/// # }
+/// ```
+pub struct DistributedLock {
+    session: Arc<Session>,
+    name: OwnedKeyExpr,
+    id: OwnedKeyExpr,
+    state: Arc<Mutex<LockState>>,
+    attempt: tokio::sync::Mutex<()>,
+    releases: FlumeSubscriber<'static>,
+    _queryable: Queryable<'static, ()>,
+}
+
+impl DistributedLock {
+    /// Creates an instance of the lock with the given name.
+    pub async fn new<T>(z: Arc<Session>, name: T) -> ZResult<DistributedLock>
+    where
+        T: TryInto<OwnedKeyExpr>,
+        <T as TryInto<OwnedKeyExpr>>::Error: Into<ZError>,
+    {
+        let name: OwnedKeyExpr = name.try_into().map_err(|e| e.into())?;
+        if name.is_wild() {
+            bail!("Lock name is not allowed to contain wildcards: {}", name);
+        }
+        let id = ZenohId::rand().into_keyexpr();
+        let state = Arc::new(Mutex::new(LockState::default()));
+
+        let releases = z
+            .liveliness()
+            .declare_subscriber(format!("{LOCK_PREFIX}/{name}/{HOLDER_POSTFIX}/*"))
+            .res()
+            .await?;
+
+        let fence_key: OwnedKeyExpr = format!("{LOCK_PREFIX}/{name}/{FENCE_POSTFIX}/{id}")
+            .try_into()
+            .unwrap();
+        let queryable = z
+            .declare_queryable(fence_key.clone())
+            .callback({
+                let state = state.clone();
+                move |query| {
+                    let info = {
+                        let state = zlock!(state);
+                        FenceInfo {
+                            holding: state.held.is_some(),
+                            token: state.last_token,
+                        }
+                    };
+                    let buf = bincode::serialize(&info).unwrap();
+                    if let Err(e) = zenoh_core::SyncResolve::res_sync(
+                        query.reply(Ok(Sample::new(fence_key.clone(), buf))),
+                    ) {
+                        tracing::warn!("Error replying to lock query: {}", e);
+                    }
+                }
+            })
+            .res()
+            .await?;
+
+        Ok(DistributedLock {
+            session: z,
+            name,
+            id,
+            state,
+            attempt: tokio::sync::Mutex::new(()),
+            releases,
+            _queryable: queryable,
+        })
+    }
+
+    /// Returns the name of the lock.
+    pub fn name(&self) -> &keyexpr {
+        &self.name
+    }
+
+    /// Returns true if this instance holds the lock.
+    pub fn is_held(&self) -> bool {
+        zlock!(self.state).held.is_some()
+    }
+
+    /// Acquires the lock, waiting for its release by its holder for at most the given timeout.
+    pub async fn acquire(&self, timeout: Duration) -> ZResult<LockGuard> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(guard) = self.try_acquire().await? {
+                return Ok(guard);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                bail!("Timeout acquiring lock {}", self.name);
+            }
+            // spread the attempts of the contenders
+            let jitter = Duration::from_millis(u64::from(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .subsec_millis()
+                    % 100,
+            ));
+            let wait = (deadline - now).min(RETRY_PERIOD + jitter);
+            let _ = tokio::time::timeout(wait, self.wait_release()).await;
+        }
+    }
+
+    /// Acquires the lock if it is not held, returns `None` otherwise.
+    pub async fn try_acquire(&self) -> ZResult<Option<LockGuard>> {
+        let _attempt = self.attempt.lock().await;
+        if self.is_held() {
+            return Ok(None);
+        }
+        if !self.contenders().await?.is_empty() {
+            // keep track of the token of the holder, in case it is lost before being released
+            self.fences().await?;
+            return Ok(None);
+        }
+        let token = self
+            .session
+            .liveliness()
+            .declare_token(format!(
+                "{LOCK_PREFIX}/{}/{HOLDER_POSTFIX}/{}",
+                self.name, self.id
+            ))
+            .res()
+            .await?;
+        tokio::time::sleep(SETTLE_DELAY).await;
+
+        // the contender with the lowest id wins among the ones trying concurrently,
+        // unless the lock was acquired meanwhile by a contender that did not see them
+        if self
+            .contenders()
+            .await?
+            .iter()
+            .any(|id| id.as_str() < self.id.as_str())
+        {
+            tracing::debug!("Lock {} contended: backing off", self.name);
+            return Ok(None);
+        }
+        if self.fences().await? {
+            tracing::debug!("Lock {} acquired by another contender", self.name);
+            return Ok(None);
+        }
+
+        // the token is greater than any token seen, the clock only makes it meaningful across restarts
+        let now = self
+            .session
+            .hlc()
+            .map(|hlc| hlc.new_timestamp().get_time().as_u64())
+            .unwrap_or_default();
+        let mut state = zlock!(self.state);
+        let fencing_token = (state.last_token + 1).max(now);
+        state.last_token = fencing_token;
+        state.held = Some(fencing_token);
+        tracing::debug!(
+            "Lock {} acquired with fencing token {}",
+            self.name,
+            fencing_token
+        );
+        Ok(Some(LockGuard {
+            state: self.state.clone(),
+            fencing_token,
+            _token: token,
+        }))
+    }
+
+    // Returns the ids of the other instances trying to acquire or holding the lock
+    async fn contenders(&self) -> ZResult<Vec<OwnedKeyExpr>> {
+        let replies = self
+            .session
+            .liveliness()
+            .get(format!("{LOCK_PREFIX}/{}/{HOLDER_POSTFIX}/*", self.name))
+            .res()
+            .await?;
+        let mut contenders = vec![];
+        while let Ok(reply) = replies.recv_async().await {
+            if let Ok(sample) = reply.sample {
+                if let Some(id) = sample.key_expr.as_str().rsplit('/').next() {
+                    if id != self.id.as_str() {
+                        contenders.push(OwnedKeyExpr::try_from(id)?);
+                    }
+                }
+            }
+        }
+        Ok(contenders)
+    }
+
+    // Returns true if another instance holds the lock, and records the highest fencing token seen by the instances
+    async fn fences(&self) -> ZResult<bool> {
+        let replies = self
+            .session
+            .get(format!("{LOCK_PREFIX}/{}/{FENCE_POSTFIX}/*", self.name))
+            .consolidation(ConsolidationMode::None)
+            .res()
+            .await?;
+        let (mut holding, mut token) = (false, 0);
+        while let Ok(reply) = replies.recv_async().await {
+            match reply.sample {
+                Ok(sample) => match bincode::deserialize::<FenceInfo>(&sample.payload.contiguous())
+                {
+                    Ok(info) => {
+                        holding |= info.holding;
+                        token = token.max(info.token);
+                    }
+                    Err(e) => tracing::warn!("Unable to deserialize the lock info: {}", e),
+                },
+                Err(e) => tracing::warn!("Error received: {}", e),
+            }
+        }
+        let mut state = zlock!(self.state);
+        state.last_token = state.last_token.max(token);
+        Ok(holding)
+    }
+
+    // Waits for another instance to stop trying to acquire or holding the lock
+    async fn wait_release(&self) {
+        while let Ok(sample) = self.releases.recv_async().await {
+            if sample.kind == SampleKind::Delete
+                && !sample.key_expr.as_str().ends_with(self.id.as_str())
+            {
+                return;
+            }
+        }
+    }
+}
+
+/// The proof that a [`DistributedLock`] is held, it is released when dropped.
+pub struct LockGuard {
+    state: Arc<Mutex<LockState>>,
+    fencing_token: u64,
+    _token: LivelinessToken<'static>,
+}
+
+impl LockGuard {
+    /// Returns the fencing token of the holder, greater than the ones of the previous holders.
+    pub fn fencing_token(&self) -> u64 {
+        self.fencing_token
+    }
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        // the lock is released before the liveliness token is undeclared, so that it is not reported as held anymore
+        zlock!(self.state).held = None;
+    }
+}
@@ -11,16 +11,24 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
-use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::TryInto;
+use std::fs::{File, OpenOptions};
 use std::future::Ready;
-use std::time::Duration;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use zenoh::buffers::ZBuf;
 use zenoh::prelude::r#async::*;
 use zenoh::queryable::{Query, Queryable};
 use zenoh::subscriber::FlumeSubscriber;
+use zenoh::time::Timestamp;
 use zenoh::SessionRef;
 use zenoh_core::{AsyncResolve, Resolvable, SyncResolve};
-use zenoh_result::{bail, ZResult};
+use zenoh_result::{bail, zerror, ZResult};
 use zenoh_task::TerminatableTask;
 use zenoh_util::core::ResolveFuture;
 
@@ -34,8 +42,14 @@ pub struct PublicationCacheBuilder<'a, 'b, 'c> {
     complete: Option<bool>,
     history: usize,
     resources_limit: Option<usize>,
+    retention: Option<Duration>,
+    persistence: Option<PathBuf>,
+    persistence_max_size: usize,
 }
 
+// The default maximum size of the file persisting the cache
+const DEFAULT_PERSISTENCE_MAX_SIZE: usize = 16 * 1024 * 1024;
+
 impl<'a, 'b, 'c> PublicationCacheBuilder<'a, 'b, 'c> {
     pub(crate) fn new(
         session: SessionRef<'a>,
@@ -49,6 +63,9 @@ impl<'a, 'b, 'c> PublicationCacheBuilder<'a, 'b, 'c> {
             complete: None,
             history: 1,
             resources_limit: None,
+            retention: None,
+            persistence: None,
+            persistence_max_size: DEFAULT_PERSISTENCE_MAX_SIZE,
         }
     }
 
@@ -88,6 +105,32 @@ impl<'a, 'b, 'c> PublicationCacheBuilder<'a, 'b, 'c> {
         self.resources_limit = Some(limit);
         self
     }
+
+    /// Keep the samples only for the given duration after their timestamp, in addition to the history size.
+    ///
+    /// Use it with a `history` of `usize::MAX` to keep all the samples of the last `retention` period.
+    pub fn retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    /// Persist the cache in the given file, so that it is restored when a [`PublicationCache`] is declared
+    /// again with the same file, e.g. after a restart of the process.
+    ///
+    /// The samples are appended to the file as they are published. When it exceeds its maximum size
+    /// (see [`persistence_max_size`](Self::persistence_max_size)), it is rewritten with the content of
+    /// the cache, dropping the oldest samples first if they do not fit. The newest sample of each key is always
+    /// kept, even if the file then exceeds its maximum size.
+    pub fn persistence<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.persistence = Some(path.into());
+        self
+    }
+
+    /// Change the maximum size in bytes of the file persisting the cache, 16 MiB by default.
+    pub fn persistence_max_size(mut self, max_size: usize) -> Self {
+        self.persistence_max_size = max_size;
+        self
+    }
 }
 
 impl<'a> Resolvable for PublicationCacheBuilder<'a, '_, '_> {
@@ -112,6 +155,7 @@ pub struct PublicationCache<'a> {
     local_sub: FlumeSubscriber<'a>,
     _queryable: Queryable<'a, flume::Receiver<Query>>,
     task: TerminatableTask,
+    persistence: Option<PathBuf>,
 }
 
 impl<'a> PublicationCache<'a> {
@@ -128,10 +172,12 @@ impl<'a> PublicationCache<'a> {
                 Some(Err(e)) => bail!("Invalid key expression for queryable_prefix: {}", e),
             };
         tracing::debug!(
-            "Create PublicationCache on {} with history={} resource_limit={:?}",
+            "Create PublicationCache on {} with history={} resource_limit={:?} retention={:?} persistence={:?}",
             &key_expr,
             conf.history,
-            conf.resources_limit
+            conf.resources_limit,
+            conf.retention,
+            conf.persistence
         );
 
         if conf.session.hlc().is_none() {
@@ -142,6 +188,27 @@ impl<'a> PublicationCache<'a> {
             )
         }
 
+        // restore the cache from its file, if persisted
+        let mut cache = Cache {
+            samples: HashMap::with_capacity(conf.resources_limit.unwrap_or(32)),
+            queryable_prefix,
+            history: conf.history,
+            resources_limit: conf.resources_limit.unwrap_or(usize::MAX),
+            retention: conf.retention,
+            log: None,
+        };
+        if let Some(path) = conf.persistence.clone() {
+            let (log, samples) = CacheLog::open(path, conf.persistence_max_size)?;
+            for sample in samples {
+                if key_expr.intersects(&sample.key_expr) {
+                    cache.insert(sample);
+                }
+            }
+            cache.evict_expired();
+            cache.log = Some(log);
+            cache.compact_log();
+        }
+
         // declare the local subscriber that will store the local publications
         let local_sub = conf
             .session
@@ -163,8 +230,6 @@ impl<'a> PublicationCache<'a> {
         let sub_recv = local_sub.receiver.clone();
         let quer_recv = queryable.receiver.clone();
         let pub_key_expr = key_expr.into_owned();
-        let resources_limit = conf.resources_limit;
-        let history = conf.history;
 
         // TODO(yuyuan): use CancellationToken to manage it
         let token = TerminatableTask::create_cancellation_token();
@@ -172,67 +237,27 @@ impl<'a> PublicationCache<'a> {
         let task = TerminatableTask::spawn(
             zenoh_runtime::ZRuntime::Application,
             async move {
-                let mut cache: HashMap<OwnedKeyExpr, VecDeque<Sample>> =
-                    HashMap::with_capacity(resources_limit.unwrap_or(32));
-                let limit = resources_limit.unwrap_or(usize::MAX);
                 loop {
                     tokio::select! {
                         // on publication received by the local subscriber, store it
                         sample = sub_recv.recv_async() => {
-                            if let Ok(sample) = sample {
-                                let queryable_key_expr: KeyExpr<'_> = if let Some(prefix) = &queryable_prefix {
-                                    prefix.join(&sample.key_expr).unwrap().into()
-                                } else {
-                                    sample.key_expr.clone()
-                                };
-
-                                if let Some(queue) = cache.get_mut(queryable_key_expr.as_keyexpr()) {
-                                    if queue.len() >= history {
-                                        queue.pop_front();
-                                    }
-                                    queue.push_back(sample);
-                                } else if cache.len() >= limit {
-                                    tracing::error!("PublicationCache on {}: resource_limit exceeded - can't cache publication for a new resource",
-                                    pub_key_expr);
-                                } else {
-                                    let mut queue: VecDeque<Sample> = VecDeque::new();
-                                    queue.push_back(sample);
-                                    cache.insert(queryable_key_expr.into(), queue);
-                                }
+                            // the subscriber is undeclared: the cache is dropped without being closed
+                            let Ok(sample) = sample else { return };
+                            if cache.insert(sample.clone()) {
+                                cache.persist(&sample);
+                            } else {
+                                tracing::error!("PublicationCache on {}: resource_limit exceeded - can't cache publication for a new resource",
+                                pub_key_expr);
                             }
                         },
 
                         // on query, reply with cach content
                         query = quer_recv.recv_async() => {
-                            if let Ok(query) = query {
-                                if !query.selector().key_expr.as_str().contains('*') {
-                                    if let Some(queue) = cache.get(query.selector().key_expr.as_keyexpr()) {
-                                        for sample in queue {
-                                            if let (Ok(Some(time_range)), Some(timestamp)) = (query.selector().time_range(), sample.timestamp) {
-                                                if !time_range.contains(timestamp.get_time().to_system_time()){
-                                                    continue;
-                                                }
-                                            }
-                                            if let Err(e) = query.reply(Ok(sample.clone())).res_async().await {
-                                                tracing::warn!("Error replying to query: {}", e);
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    for (key_expr, queue) in cache.iter() {
-                                        if query.selector().key_expr.intersects(unsafe{ keyexpr::from_str_unchecked(key_expr) }) {
-                                            for sample in queue {
-                                                if let (Ok(Some(time_range)), Some(timestamp)) = (query.selector().time_range(), sample.timestamp) {
-                                                    if !time_range.contains(timestamp.get_time().to_system_time()){
-                                                        continue;
-                                                    }
-                                                }
-                                                if let Err(e) = query.reply(Ok(sample.clone())).res_async().await {
-                                                    tracing::warn!("Error replying to query: {}", e);
-                                                }
-                                            }
-                                        }
-                                    }
+                            let Ok(query) = query else { return };
+                            cache.evict_expired();
+                            for sample in cache.replies(&query) {
+                                if let Err(e) = query.reply(Ok(sample)).res_async().await {
+                                    tracing::warn!("Error replying to query: {}", e);
                                 }
                             }
                         },
@@ -247,6 +272,7 @@ impl<'a> PublicationCache<'a> {
             local_sub,
             _queryable: queryable,
             task,
+            persistence: conf.persistence,
         })
     }
 
@@ -258,10 +284,15 @@ impl<'a> PublicationCache<'a> {
                 _queryable,
                 local_sub,
                 task,
+                persistence,
             } = self;
             _queryable.undeclare().res_async().await?;
             local_sub.undeclare().res_async().await?;
             task.terminate(Duration::from_secs(10));
+            // the cache is closed once its pending writes are persisted
+            if let Some(path) = persistence {
+                CacheLog::wait_stopped(&path).await;
+            }
             Ok(())
         })
     }
@@ -270,3 +301,347 @@ impl<'a> PublicationCache<'a> {
         self.local_sub.key_expr()
     }
 }
+
+// The content of a PublicationCache, per key of its queryable
+struct Cache {
+    samples: HashMap<OwnedKeyExpr, VecDeque<Sample>>,
+    queryable_prefix: Option<OwnedKeyExpr>,
+    history: usize,
+    resources_limit: usize,
+    retention: Option<Duration>,
+    log: Option<CacheLog>,
+}
+
+impl Cache {
+    // Returns false if the sample is for a new resource and the resources limit is reached
+    fn insert(&mut self, sample: Sample) -> bool {
+        let queryable_key_expr: OwnedKeyExpr = match &self.queryable_prefix {
+            Some(prefix) => prefix.join(&sample.key_expr).unwrap(),
+            None => sample.key_expr.clone().into(),
+        };
+        let expiration = self.expiration();
+        if let Some(queue) = self.samples.get_mut(&queryable_key_expr) {
+            if queue.len() >= self.history {
+                queue.pop_front();
+            }
+            queue.push_back(sample);
+            if let Some(expiration) = expiration {
+                while queue.front().is_some_and(|s| is_expired(s, expiration)) {
+                    queue.pop_front();
+                }
+            }
+        } else if self.samples.len() >= self.resources_limit {
+            return false;
+        } else {
+            let mut queue: VecDeque<Sample> = VecDeque::new();
+            queue.push_back(sample);
+            self.samples.insert(queryable_key_expr, queue);
+        }
+        true
+    }
+
+    // The time before which the samples are expired
+    fn expiration(&self) -> Option<SystemTime> {
+        self.retention
+            .and_then(|retention| SystemTime::now().checked_sub(retention))
+    }
+
+    fn evict_expired(&mut self) {
+        if let Some(expiration) = self.expiration() {
+            for queue in self.samples.values_mut() {
+                queue.retain(|s| !is_expired(s, expiration));
+            }
+            self.samples.retain(|_, queue| !queue.is_empty());
+        }
+    }
+
+    fn replies(&self, query: &Query) -> Vec<Sample> {
+        let selector = query.selector();
+        let time_range = selector.time_range().ok().flatten();
+        let in_time_range = |sample: &&Sample| match (&time_range, sample.timestamp) {
+            (Some(time_range), Some(timestamp)) => {
+                time_range.contains(timestamp.get_time().to_system_time())
+            }
+            _ => true,
+        };
+        if !selector.key_expr.as_str().contains('*') {
+            self.samples
+                .get(selector.key_expr.as_keyexpr())
+                .into_iter()
+                .flatten()
+                .filter(in_time_range)
+                .cloned()
+                .collect()
+        } else {
+            self.samples
+                .iter()
+                .filter(|(key_expr, _)| selector.key_expr.intersects(key_expr))
+                .flat_map(|(_, queue)| queue)
+                .filter(in_time_range)
+                .cloned()
+                .collect()
+        }
+    }
+
+    fn persist(&mut self, sample: &Sample) {
+        if self.log.as_mut().is_some_and(|log| log.append(sample)) {
+            self.compact_log();
+        }
+    }
+
+    fn compact_log(&mut self) {
+        if let Some(log) = self.log.as_mut() {
+            let mut samples: Vec<&Sample> = self.samples.values().flatten().collect();
+            samples.sort_by_key(|s| s.timestamp);
+            log.rewrite(&samples);
+        }
+    }
+}
+
+fn is_expired(sample: &Sample, expiration: SystemTime) -> bool {
+    sample
+        .timestamp
+        .is_some_and(|t| t.get_time().to_system_time() < expiration)
+}
+
+// The writers of the dropped CacheLogs per file, disconnected once their pending writes are completed
+static STOPPING_WRITERS: Mutex<Vec<(PathBuf, flume::Receiver<()>)>> = Mutex::new(Vec::new());
+
+// The file persisting a PublicationCache: a sequence of records, each preceded by its length.
+// It is written by a dedicated thread, not to block the task of the cache.
+struct CacheLog {
+    path: PathBuf,
+    size: usize,
+    max_size: usize,
+    // The size above which the file is rewritten: the maximum size, unless the newest sample of each key
+    // does not fit in half of it
+    threshold: usize,
+    writer: Option<flume::Sender<LogWrite>>,
+    // Disconnected when the writer thread ends
+    stopped: flume::Receiver<()>,
+}
+
+enum LogWrite {
+    Append(Vec<u8>),
+    Rewrite(Vec<Vec<u8>>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheRecord {
+    key_expr: String,
+    kind: u64,
+    encoding: String,
+    timestamp: Option<String>,
+    payload: Vec<u8>,
+}
+
+impl CacheRecord {
+    fn encode(sample: &Sample) -> Vec<u8> {
+        let record = CacheRecord {
+            key_expr: sample.key_expr.to_string(),
+            kind: sample.kind as u64,
+            encoding: sample.value.encoding.to_string(),
+            timestamp: sample.timestamp.map(|t| t.to_string()),
+            payload: sample.value.payload.contiguous().into_owned(),
+        };
+        let record = bincode::serialize(&record).unwrap();
+        let mut buf = Vec::with_capacity(4 + record.len());
+        buf.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&record);
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> ZResult<Sample> {
+        let record: CacheRecord = bincode::deserialize(buf).map_err(|e| zerror!("{}", e))?;
+        let key_expr = OwnedKeyExpr::from_str(&record.key_expr)?;
+        let value =
+            Value::new(ZBuf::from(record.payload)).encoding(Encoding::from(record.encoding));
+        let mut sample = Sample::new(key_expr, value);
+        sample.kind =
+            SampleKind::try_from(record.kind).map_err(|k| zerror!("Invalid sample kind {}", k))?;
+        if let Some(timestamp) = record.timestamp {
+            sample.timestamp = Some(
+                Timestamp::from_str(&timestamp)
+                    .map_err(|e| zerror!("Invalid timestamp {}: {:?}", timestamp, e))?,
+            );
+        }
+        Ok(sample)
+    }
+}
+
+impl CacheLog {
+    // Opens the file, creating it if needed, and returns the samples it contains
+    fn open(path: PathBuf, max_size: usize) -> ZResult<(CacheLog, Vec<Sample>)> {
+        // the pending writes of a previous CacheLog of the file are completed before it is read
+        for stopped in Self::stopping(&path) {
+            let _ = stopped.recv();
+        }
+
+        let mut content = vec![];
+        match File::open(&path) {
+            Ok(mut file) => {
+                file.read_to_end(&mut content)
+                    .map_err(|e| zerror!("Error reading {}: {}", path.display(), e))?;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => bail!("Error opening {}: {}", path.display(), e),
+        }
+
+        // a record truncated by a crash while it was written ends the file
+        let mut samples = vec![];
+        let mut rest = content.as_slice();
+        while rest.len() >= 4 {
+            let len = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let Some(record) = rest.get(4..4 + len) else {
+                break;
+            };
+            match CacheRecord::decode(record) {
+                Ok(sample) => samples.push(sample),
+                Err(e) => tracing::warn!("Skipping invalid record in {}: {}", path.display(), e),
+            }
+            rest = &rest[4 + len..];
+        }
+        if !rest.is_empty() {
+            tracing::warn!("Ignoring truncated record at the end of {}", path.display());
+        }
+
+        let file = Self::open_append(&path)?;
+        let (writer, receiver) = flume::unbounded();
+        let (stopping, stopped) = flume::bounded(0);
+        std::thread::Builder::new()
+            .name(format!("pub-cache-log-{}", path.display()))
+            .spawn({
+                let path = path.clone();
+                move || {
+                    Self::write(path, file, receiver);
+                    drop(stopping);
+                }
+            })
+            .map_err(|e| zerror!("Error starting the writer of {}: {}", path.display(), e))?;
+        let log = CacheLog {
+            path,
+            size: content.len() - rest.len(),
+            max_size,
+            threshold: max_size,
+            writer: Some(writer),
+            stopped,
+        };
+        Ok((log, samples))
+    }
+
+    fn open_append(path: &PathBuf) -> ZResult<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| zerror!("Error opening {}: {}", path.display(), e).into())
+    }
+
+    // Returns true if the file exceeds its maximum size and must be rewritten
+    fn append(&mut self, sample: &Sample) -> bool {
+        let record = CacheRecord::encode(sample);
+        self.size += record.len();
+        self.send(LogWrite::Append(record));
+        self.size > self.threshold
+    }
+
+    // Replaces the content of the file with the given samples, sorted from the oldest: the newest ones are kept
+    // within half of the maximum size, to leave room for the next appends, and the newest one of each key in any case
+    fn rewrite(&mut self, samples: &[&Sample]) {
+        let mut records: Vec<Vec<u8>> = vec![];
+        let mut keys = HashSet::new();
+        let mut size = 0;
+        let mut full = false;
+        for sample in samples.iter().rev() {
+            let newest = keys.insert(&sample.key_expr);
+            let record = CacheRecord::encode(sample);
+            full = full || size + record.len() > self.max_size / 2;
+            if full && !newest {
+                continue;
+            }
+            size += record.len();
+            records.push(record);
+        }
+        records.reverse();
+        if size > self.max_size / 2 {
+            tracing::warn!(
+                "The newest samples persisted in {} take {} bytes, more than half of its maximum size {}",
+                self.path.display(),
+                size,
+                self.max_size
+            );
+        }
+        self.size = size;
+        self.threshold = self.max_size.max(2 * size);
+        self.send(LogWrite::Rewrite(records));
+    }
+
+    fn send(&self, write: LogWrite) {
+        if let Some(writer) = self.writer.as_ref() {
+            if writer.send(write).is_err() {
+                tracing::warn!("The writer of {} has stopped", self.path.display());
+            }
+        }
+    }
+
+    // The loop of the writer thread, until the CacheLog is dropped
+    fn write(path: PathBuf, mut file: File, receiver: flume::Receiver<LogWrite>) {
+        while let Ok(write) = receiver.recv() {
+            match write {
+                LogWrite::Append(record) => {
+                    if let Err(e) = file.write_all(&record) {
+                        tracing::warn!("Error persisting publication in {}: {}", path.display(), e);
+                    }
+                }
+                LogWrite::Rewrite(records) => match Self::replace(&path, &records) {
+                    Ok(new_file) => file = new_file,
+                    Err(e) => tracing::warn!("Error compacting {}: {}", path.display(), e),
+                },
+            }
+        }
+    }
+
+    // Atomically replaces the content of the file, returns it opened for the next appends
+    fn replace(path: &PathBuf, records: &[Vec<u8>]) -> ZResult<File> {
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let mut file = File::create(&tmp)?;
+        for record in records {
+            file.write_all(record)?;
+        }
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)?;
+        Self::open_append(path)
+    }
+
+    // Returns the writers of the dropped CacheLogs of the file that are still running
+    fn stopping(path: &PathBuf) -> Vec<flume::Receiver<()>> {
+        let mut writers = STOPPING_WRITERS.lock().unwrap();
+        writers.retain(|(_, stopped)| !stopped.is_disconnected());
+        writers
+            .iter()
+            .filter(|(p, _)| p == path)
+            .map(|(_, stopped)| stopped.clone())
+            .collect()
+    }
+
+    // Waits for the writers of the dropped CacheLogs of the file to complete their pending writes
+    async fn wait_stopped(path: &PathBuf) {
+        for stopped in Self::stopping(path) {
+            let _ = stopped.recv_async().await;
+        }
+    }
+}
+
+impl Drop for CacheLog {
+    fn drop(&mut self) {
+        // the writer completes the pending writes on its own, without blocking the task dropping the cache,
+        // and is waited for before the file is opened again
+        drop(self.writer.take());
+        STOPPING_WRITERS
+            .lock()
+            .unwrap()
+            .push((self.path.clone(), self.stopped.clone()));
+    }
+}
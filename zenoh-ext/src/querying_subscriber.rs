@@ -11,20 +11,31 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
-use std::collections::{btree_map, BTreeMap, VecDeque};
+use crate::sequencing::{sequence_number, SOURCE_EXPIRATION};
+use std::collections::{btree_map, BTreeMap, HashMap, HashSet, VecDeque};
 use std::convert::TryInto;
 use std::future::Ready;
 use std::mem::swap;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use zenoh::handlers::{locked, DefaultHandler};
 use zenoh::prelude::r#async::*;
-use zenoh::query::{QueryConsolidation, QueryTarget, ReplyKeyExpr};
+use zenoh::query::{QueryConsolidation, QueryTarget, Reply, ReplyKeyExpr};
+use zenoh::selector::{TimeBound, TimeRange};
 use zenoh::subscriber::{Reliability, Subscriber};
-use zenoh::time::Timestamp;
+use zenoh::time::{Timestamp, TimestampId};
 use zenoh::Result as ZResult;
 use zenoh::SessionRef;
 use zenoh_core::{zlock, AsyncResolve, Resolvable, SyncResolve};
+use zenoh_util::core::ResolveFuture;
+
+// Time given to a router or peer the transport was opened again with to declare its queryables,
+// before querying it for the publications missed while disconnected
+const RECONNECTION_DELAY: Duration = Duration::from_secs(1);
+
+// Fetches the publications since the given time, if any, to recover the ones missed by a FetchingSubscriber
+pub(crate) type RecoveryFetch =
+    Arc<dyn Fn(Option<Timestamp>, Box<dyn Fn(Reply) + Send + Sync>) -> ZResult<()> + Send + Sync>;
 
 /// The builder of [`FetchingSubscriber`], allowing to configure it.
 #[must_use = "Resolvables do nothing unless you resolve them using the `res` method from either `SyncResolve` or `AsyncResolve`"]
@@ -39,6 +50,7 @@ pub struct QueryingSubscriberBuilder<'a, 'b, KeySpace, Handler> {
     pub(crate) query_consolidation: QueryConsolidation,
     pub(crate) query_accept_replies: ReplyKeyExpr,
     pub(crate) query_timeout: Duration,
+    pub(crate) recovery: Option<SessionRef<'static>>,
     pub(crate) handler: Handler,
 }
 
@@ -63,6 +75,7 @@ impl<'a, 'b, KeySpace> QueryingSubscriberBuilder<'a, 'b, KeySpace, DefaultHandle
             query_consolidation,
             query_accept_replies,
             query_timeout,
+            recovery,
             handler: _,
         } = self;
         QueryingSubscriberBuilder {
@@ -76,6 +89,7 @@ impl<'a, 'b, KeySpace> QueryingSubscriberBuilder<'a, 'b, KeySpace, DefaultHandle
             query_consolidation,
             query_accept_replies,
             query_timeout,
+            recovery,
             handler: callback,
         }
     }
@@ -116,6 +130,7 @@ impl<'a, 'b, KeySpace> QueryingSubscriberBuilder<'a, 'b, KeySpace, DefaultHandle
             query_consolidation,
             query_accept_replies,
             query_timeout,
+            recovery,
             handler: _,
         } = self;
         QueryingSubscriberBuilder {
@@ -129,6 +144,7 @@ impl<'a, 'b, KeySpace> QueryingSubscriberBuilder<'a, 'b, KeySpace, DefaultHandle
             query_consolidation,
             query_accept_replies,
             query_timeout,
+            recovery,
             handler,
         }
     }
@@ -201,6 +217,45 @@ impl<'a, 'b, Handler> QueryingSubscriberBuilder<'a, 'b, crate::UserSpace, Handle
     }
 }
 
+impl<'b, Handler> QueryingSubscriberBuilder<'static, 'b, crate::UserSpace, Handler> {
+    /// Enable the recovery of the publications missed after the initial query.
+    ///
+    /// The query is issued again, restricted to the publications since the last delivered sample, whenever
+    /// the transport with a router or peer is opened again after it was closed, or a gap is detected in the sequence numbers of a
+    /// [`SequencedPublisher`](crate::SequencedPublisher).
+    /// The recovered samples are merged with the received publications: they are made available in timestamp
+    /// order, without the ones already delivered, as identified by their sequence numbers or else by their timestamps.
+    ///
+    /// The queried [`PublicationCache`](crate::PublicationCache)s or storages should support time ranges.
+    /// Only available on a session shared by an [`Arc`], as the queries are issued after the declaration.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use zenoh::prelude::r#async::*;
+    /// use zenoh_ext::*;
+    ///
+    /// let session = zenoh::open(config::peer()).res().await.unwrap().into_arc();
+    /// let subscriber = session
+    ///     .declare_subscriber("key/expr")
+    ///     .querying()
+    ///     .recovery(true)
+    ///     .res()
+    ///     .await
+    ///     .unwrap();
+    /// while let Ok(sample) = subscriber.recv_async().await {
+    ///     println!("Received: {:?}", sample);
+    /// }
+    /// # }
+    /// ```
+    #[inline]
+    pub fn recovery(mut self, enabled: bool) -> Self {
+        self.recovery = enabled.then(|| self.session.clone());
+        self
+    }
+}
+
 impl<'a, 'b, KeySpace, Handler> QueryingSubscriberBuilder<'a, 'b, KeySpace, Handler> {
     /// Change the timeout to be used for queries.
     #[inline]
@@ -236,6 +291,34 @@ where
         let query_consolidation = self.query_consolidation;
         let query_accept_replies = self.query_accept_replies;
         let query_timeout = self.query_timeout;
+        let recovery = match (self.recovery, &key_space) {
+            (Some(session), crate::KeySpace::User) => {
+                let selector = match &query_selector {
+                    Some(s) => s.clone().into_owned(),
+                    None => key_expr.clone().into_owned().into(),
+                };
+                Some(Arc::new(
+                    move |since: Option<Timestamp>, cb: Box<dyn Fn(Reply) + Send + Sync>| {
+                        let mut selector = selector.clone();
+                        if let Some(since) = since {
+                            let since = since.get_time().to_system_time();
+                            selector.with_time_range(
+                                TimeRange(TimeBound::Inclusive(since), TimeBound::Unbounded).into(),
+                            );
+                        }
+                        session
+                            .get(selector)
+                            .callback(cb)
+                            .target(query_target)
+                            .consolidation(query_consolidation)
+                            .accept_replies(query_accept_replies)
+                            .timeout(query_timeout)
+                            .res_sync()
+                    },
+                ) as RecoveryFetch)
+            }
+            _ => None,
+        };
         FetchingSubscriberBuilder {
             session: self.session,
             key_expr: Ok(key_expr.clone()),
@@ -260,6 +343,7 @@ where
                     .timeout(query_timeout)
                     .res_sync(),
             },
+            recovery,
             handler: self.handler,
             phantom: std::marker::PhantomData,
         }
@@ -338,6 +422,149 @@ impl Iterator for MergeQueueValues {
 struct InnerState {
     pending_fetches: u64,
     merge_queue: MergeQueue,
+    recovery: Option<RecoveryState>,
+}
+
+impl InnerState {
+    // Propagates the sample, unless it was already propagated
+    fn deliver(&mut self, callback: &(dyn Fn(Sample) + Send + Sync), sample: Sample) {
+        if self.recovery.as_mut().map_or(true, |r| r.accept(&sample)) {
+            callback(sample);
+        }
+    }
+
+    fn drain(&mut self, callback: &(dyn Fn(Sample) + Send + Sync)) {
+        for s in self.merge_queue.drain() {
+            self.deliver(callback, s);
+        }
+    }
+}
+
+// The progress of the publishers identified by a sequence number
+struct SourceState {
+    // The highest sequence number received from the publications
+    received: Option<u64>,
+    // The sequence number and timestamp of the last propagated sample
+    delivered: Option<u64>,
+    delivered_timestamp: Option<Timestamp>,
+    // When a publication of the source was last received
+    last_seen: Instant,
+}
+
+impl SourceState {
+    fn new(now: Instant) -> Self {
+        SourceState {
+            received: None,
+            delivered: None,
+            delivered_timestamp: None,
+            last_seen: now,
+        }
+    }
+}
+
+// The progress of a FetchingSubscriber recovering the missed publications
+struct RecoveryState {
+    fetch: RecoveryFetch,
+    // The timestamp of the last propagated sample
+    last_timestamp: Option<Timestamp>,
+    sources: HashMap<ZenohId, SourceState>,
+    // The timestamp of the last propagated sample per clock, for the samples without sequence number,
+    // and when it was propagated
+    clocks: HashMap<TimestampId, (Timestamp, Instant)>,
+    // When the sources and clocks not heard of since SOURCE_EXPIRATION are next forgotten
+    next_expiration: Instant,
+}
+
+impl RecoveryState {
+    fn new(fetch: RecoveryFetch) -> Self {
+        RecoveryState {
+            fetch,
+            last_timestamp: None,
+            sources: HashMap::new(),
+            clocks: HashMap::new(),
+            next_expiration: Instant::now() + SOURCE_EXPIRATION,
+        }
+    }
+
+    // Forgets the sources and clocks not heard of since SOURCE_EXPIRATION, at most once per SOURCE_EXPIRATION
+    fn expire(&mut self, now: Instant) {
+        if now < self.next_expiration {
+            return;
+        }
+        self.next_expiration = now + SOURCE_EXPIRATION;
+        self.sources
+            .retain(|_, source| now.duration_since(source.last_seen) < SOURCE_EXPIRATION);
+        self.clocks
+            .retain(|_, (_, last_seen)| now.duration_since(*last_seen) < SOURCE_EXPIRATION);
+    }
+
+    // Returns the time since which the publications must be recovered if the received publication reveals a gap
+    fn detect_gap(&mut self, sample: &Sample) -> Option<Option<Timestamp>> {
+        let (id, sn) = sequence_number(sample)?;
+        let now = Instant::now();
+        self.expire(now);
+        let source = self
+            .sources
+            .entry(id)
+            .or_insert_with(|| SourceState::new(now));
+        source.last_seen = now;
+        let gap = source.received.is_some_and(|r| sn > r + 1);
+        if source.received.map_or(true, |r| sn > r) {
+            source.received = Some(sn);
+        }
+        if gap {
+            tracing::debug!(
+                "Gap detected in the publications of {} before sequence number {}",
+                id,
+                sn
+            );
+            Some(source.delivered_timestamp.or(self.last_timestamp))
+        } else {
+            None
+        }
+    }
+
+    // Returns false if the sample was already propagated
+    fn accept(&mut self, sample: &Sample) -> bool {
+        let timestamp = sample.timestamp;
+        let now = Instant::now();
+        self.expire(now);
+        if let Some((id, sn)) = sequence_number(sample) {
+            let source = self
+                .sources
+                .entry(id)
+                .or_insert_with(|| SourceState::new(now));
+            source.last_seen = now;
+            if source.delivered.is_some_and(|d| sn <= d) {
+                tracing::trace!("Duplicate sample {} from {} dropped", sn, id);
+                return false;
+            }
+            source.delivered = Some(sn);
+            if source.received.map_or(true, |r| sn > r) {
+                source.received = Some(sn);
+            }
+            if timestamp.is_some() {
+                source.delivered_timestamp = timestamp;
+            }
+        } else if let Some(ts) = timestamp {
+            match self.clocks.get_mut(ts.get_id()) {
+                Some((last, _)) if ts <= *last => {
+                    tracing::trace!("Duplicate sample {} dropped", ts);
+                    return false;
+                }
+                Some(last) => *last = (ts, now),
+                None => {
+                    self.clocks.insert(*ts.get_id(), (ts, now));
+                }
+            }
+        }
+        if let Some(ts) = timestamp {
+            if self.last_timestamp.map_or(true, |last| ts > last) {
+                self.last_timestamp = Some(ts);
+            }
+        }
+        true
+    }
 }
 
 /// The builder of [`FetchingSubscriber`], allowing to configure it.
@@ -359,6 +586,7 @@ pub struct FetchingSubscriberBuilder<
     pub(crate) reliability: Reliability,
     pub(crate) origin: Locality,
     pub(crate) fetch: Fetch,
+    pub(crate) recovery: Option<RecoveryFetch>,
     pub(crate) handler: Handler,
     pub(crate) phantom: std::marker::PhantomData<TryIntoSample>,
 }
@@ -385,6 +613,7 @@ where
             reliability: self.reliability,
             origin: self.origin,
             fetch: self.fetch,
+            recovery: self.recovery,
             handler: self.handler,
             phantom: std::marker::PhantomData,
         }
@@ -418,6 +647,7 @@ where
             reliability,
             origin,
             fetch,
+            recovery,
             handler: _,
             phantom,
         } = self;
@@ -428,6 +658,7 @@ where
             reliability,
             origin,
             fetch,
+            recovery,
             handler: callback,
             phantom,
         }
@@ -472,6 +703,7 @@ where
             reliability,
             origin,
             fetch,
+            recovery,
             handler: _,
             phantom,
         } = self;
@@ -482,6 +714,7 @@ where
             reliability,
             origin,
             fetch,
+            recovery,
             handler,
             phantom,
         }
@@ -620,6 +853,7 @@ where
 /// ```
 pub struct FetchingSubscriber<'a, Receiver> {
     subscriber: Subscriber<'a, ()>,
+    reconnections: Option<Subscriber<'a, ()>>,
     callback: Arc<dyn Fn(Sample) + Send + Sync + 'static>,
     state: Arc<Mutex<InnerState>>,
     receiver: Receiver,
@@ -656,6 +890,7 @@ impl<'a, Receiver> FetchingSubscriber<'a, Receiver> {
         let state = Arc::new(Mutex::new(InnerState {
             pending_fetches: 0,
             merge_queue: MergeQueue::new(),
+            recovery: conf.recovery.map(RecoveryState::new),
         }));
         let (callback, receiver) = conf.handler.into_cb_receiver_pair();
 
@@ -663,9 +898,10 @@ impl<'a, Receiver> FetchingSubscriber<'a, Receiver> {
             let state = state.clone();
             let callback = callback.clone();
             move |mut s| {
-                let state = &mut zlock!(state);
-                if state.pending_fetches == 0 {
-                    callback(s);
+                let mut guard = zlock!(state);
+                let gap = guard.recovery.as_mut().and_then(|r| r.detect_gap(&s));
+                if guard.pending_fetches == 0 && gap.is_none() {
+                    guard.deliver(callback.as_ref(), s);
                 } else {
                     tracing::trace!(
                         "Sample received while fetch in progress: push it to merge_queue"
@@ -673,7 +909,10 @@ impl<'a, Receiver> FetchingSubscriber<'a, Receiver> {
                     // ensure the sample has a timestamp, thus it will always be sorted into the MergeQueue
                     // after any timestamped Sample possibly coming from a fetch reply.
                     s.ensure_timestamp();
-                    state.merge_queue.push(s);
+                    guard.merge_queue.push(s);
+                }
+                if let Some(since) = gap {
+                    recover(&state, guard, &callback, since);
                 }
             }
         };
@@ -699,8 +938,50 @@ impl<'a, Receiver> FetchingSubscriber<'a, Receiver> {
                 .res_sync()?,
         };
 
+        // recover the publications missed while disconnected when reconnecting to a router or peer
+        let reconnections = if zlock!(state).recovery.is_some() {
+            let key_expr = format!("@/session/{}/transport/unicast/*", conf.session.zid());
+            let state = state.clone();
+            let callback = callback.clone();
+            // the routers and peers whose transport was closed, until it is opened again
+            let disconnected = Mutex::new(HashSet::new());
+            let reconnections = conf
+                .session
+                .declare_subscriber(key_expr)
+                .callback(move |s| {
+                    let peer = s.key_expr.as_str().rsplit('/').next().unwrap_or_default();
+                    let reconnected = match s.kind {
+                        SampleKind::Put => zlock!(disconnected).remove(peer),
+                        SampleKind::Delete => {
+                            zlock!(disconnected).insert(peer.to_string());
+                            false
+                        }
+                    };
+                    if reconnected {
+                        tracing::debug!(
+                            "Transport with {} opened again: recover missed publications",
+                            peer
+                        );
+                        let state = state.clone();
+                        let callback = callback.clone();
+                        zenoh_runtime::ZRuntime::Application.spawn(async move {
+                            tokio::time::sleep(RECONNECTION_DELAY).await;
+                            let guard = zlock!(state);
+                            let since = guard.recovery.as_ref().and_then(|r| r.last_timestamp);
+                            recover(&state, guard, &callback, since);
+                        });
+                    }
+                })
+                .allowed_origin(Locality::SessionLocal)
+                .res_sync()?;
+            Some(reconnections)
+        } else {
+            None
+        };
+
         let fetch_subscriber = FetchingSubscriber {
             subscriber,
+            reconnections,
             callback,
             state,
             receiver,
@@ -715,7 +996,17 @@ impl<'a, Receiver> FetchingSubscriber<'a, Receiver> {
     /// Close this FetchingSubscriber
     #[inline]
     pub fn close(self) -> impl Resolve<ZResult<()>> + 'a {
-        self.subscriber.undeclare()
+        let FetchingSubscriber {
+            subscriber,
+            reconnections,
+            ..
+        } = self;
+        ResolveFuture::new(async move {
+            if let Some(reconnections) = reconnections {
+                reconnections.undeclare().res_async().await?;
+            }
+            subscriber.undeclare().res_async().await
+        })
     }
 
     /// Return the key expression of this FetchingSubscriber
@@ -803,9 +1094,7 @@ impl Drop for RepliesHandler {
                 "All fetches done. Replies and live publications merged - {} samples to propagate",
                 state.merge_queue.len()
             );
-            for s in state.merge_queue.drain() {
-                (self.callback)(s);
-            }
+            state.drain(self.callback.as_ref());
         }
     }
 }
@@ -903,6 +1192,29 @@ fn register_handler(
     RepliesHandler { state, callback }
 }
 
+// Runs the recovery fetch of the publications since the given time, once the state is unlocked
+fn recover(
+    state: &Arc<Mutex<InnerState>>,
+    mut guard: std::sync::MutexGuard<InnerState>,
+    callback: &Arc<dyn Fn(Sample) + Send + Sync>,
+    since: Option<Timestamp>,
+) {
+    let Some(fetch) = guard.recovery.as_ref().map(|r| r.fetch.clone()) else {
+        return;
+    };
+    // pending fetches will be decremented in RepliesHandler drop()
+    guard.pending_fetches += 1;
+    drop(guard);
+    let handler = RepliesHandler {
+        state: state.clone(),
+        callback: callback.clone(),
+    };
+    tracing::debug!("Recover publications since {:?}", since);
+    if let Err(e) = run_fetch(|cb| fetch(since, cb), handler) {
+        tracing::warn!("Unable to recover missed publications: {}", e);
+    }
+}
+
 fn run_fetch<
     Fetch: FnOnce(Box<dyn Fn(TryIntoSample) + Send + Sync>) -> ZResult<()>,
     TryIntoSample,
@@ -924,3 +1236,80 @@ where
         Err(e) => tracing::debug!("Received error fetching data: {}", e.into()),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequencing::sequence_attachment;
+    use zenoh::time::NTP64;
+
+    fn recovery_state() -> RecoveryState {
+        RecoveryState::new(Arc::new(|_, _| Ok(())))
+    }
+
+    fn sample(id: Option<&ZenohId>, sn: u64, time: u64) -> Sample {
+        let clock = TimestampId::try_from([1]).unwrap();
+        let sample = Sample::new(keyexpr::new("test/recovery").unwrap(), sn.to_string())
+            .with_timestamp(Timestamp::new(NTP64(time), clock));
+        match id {
+            Some(id) => sample.with_attachment(sequence_attachment(id, sn)),
+            None => sample,
+        }
+    }
+
+    #[test]
+    fn gap_detection() {
+        let id = ZenohId::rand();
+        let mut state = recovery_state();
+        assert_eq!(state.detect_gap(&sample(Some(&id), 0, 10)), None);
+        assert!(state.accept(&sample(Some(&id), 0, 10)));
+        assert_eq!(state.detect_gap(&sample(Some(&id), 1, 20)), None);
+        assert!(state.accept(&sample(Some(&id), 1, 20)));
+
+        // the recovery starts from the last sample delivered from the source
+        let gap = state.detect_gap(&sample(Some(&id), 4, 50));
+        assert_eq!(gap.unwrap().unwrap().get_time(), &NTP64(20));
+        // the samples received late do not reveal any gap
+        assert_eq!(state.detect_gap(&sample(Some(&id), 2, 30)), None);
+        assert_eq!(state.detect_gap(&sample(Some(&id), 5, 60)), None);
+
+        // the samples without sequence number are not checked
+        assert_eq!(state.detect_gap(&sample(None, 0, 70)), None);
+    }
+
+    #[test]
+    fn deduplication() {
+        let id = ZenohId::rand();
+        let mut state = recovery_state();
+        assert!(state.accept(&sample(Some(&id), 0, 10)));
+        assert!(state.accept(&sample(Some(&id), 1, 20)));
+        // the samples from a source are deduplicated by sequence number
+        assert!(!state.accept(&sample(Some(&id), 1, 20)));
+        assert!(!state.accept(&sample(Some(&id), 0, 10)));
+        assert!(state.accept(&sample(Some(&id), 2, 30)));
+
+        // and the other samples by timestamp
+        assert!(state.accept(&sample(None, 0, 40)));
+        assert!(!state.accept(&sample(None, 0, 40)));
+        assert!(!state.accept(&sample(None, 0, 35)));
+        assert!(state.accept(&sample(None, 0, 50)));
+        assert_eq!(state.last_timestamp.unwrap().get_time(), &NTP64(50));
+    }
+
+    #[test]
+    fn expiration() {
+        let id = ZenohId::rand();
+        let mut state = recovery_state();
+        assert!(state.accept(&sample(Some(&id), 0, 10)));
+        assert!(state.accept(&sample(None, 0, 20)));
+
+        let now = Instant::now();
+        state.expire(now);
+        assert_eq!((state.sources.len(), state.clocks.len()), (1, 1));
+
+        // the sources and clocks not heard of since SOURCE_EXPIRATION are forgotten
+        state.expire(now + SOURCE_EXPIRATION * 2);
+        assert!(state.sources.is_empty());
+        assert!(state.clocks.is_empty());
+    }
+}
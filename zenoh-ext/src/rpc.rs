@@ -0,0 +1,537 @@
+//
+// Copyright (c) 2024 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+//! To build typed request/response services over queryables
+//!
+//! An [`RpcServer`] serves the methods of a service, each one bound to the key expression
+//! `<service>/<method>/<instance>`. The requests, the responses and the errors returned by the methods are
+//! serialized with [`Bincode`]. The instances of a service are tracked by the [`RpcClient`]s with liveliness
+//! tokens, and the calls are balanced across them in a round-robin fashion.
+//!
+//! A call fails with [`RpcError::Timeout`] if no reply is received before its deadline: the query is then
+//! cancelled, and the method stops being processed by the server.
+//!
+//! The [`service`] attribute generates the client stubs and the server bindings of a trait.
+//!
+//! # Examples
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() {
+//! use serde::{Deserialize, Serialize};
+//! use zenoh::prelude::r#async::*;
+//! use zenoh_ext::rpc::RpcError;
+//!
+//! #[derive(Serialize, Deserialize, Debug)]
+//! enum CalcError {
+//!     DivisionByZero,
+//! }
+//!
+//! #[zenoh_ext::rpc::service]
+//! trait Calculator {
+//!     /// Divides `a` by `b`.
+//!     async fn div(&self, a: i64, b: i64) -> Result<i64, CalcError>;
+//! }
+//!
+//! struct Calc;
+//!
+//! #[zenoh_ext::rpc::async_trait]
+//! impl Calculator for Calc {
+//!     async fn div(&self, a: i64, b: i64) -> Result<i64, CalcError> {
+//!         a.checked_div(b).ok_or(CalcError::DivisionByZero)
+//!     }
+//! }
+//!
+//! let session = zenoh::open(config::peer()).res().await.unwrap().into_arc();
+//! let _server = Calc
+//!     .into_rpc_server(session.clone(), "calc")
+//!     .declare()
+//!     .await
+//!     .unwrap();
+//! let client = CalculatorClient::new(session, "calc").await.unwrap();
+//! assert_eq!(client.div(6, 3).await.unwrap(), 2);
+//! assert!(matches!(
+//!     client.div(1, 0).await,
+//!     Err(RpcError::Service(CalcError::DivisionByZero))
+//! ));
+//! # }
+//! ```
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use zenoh::liveliness::LivelinessToken;
+use zenoh::prelude::r#async::*;
+use zenoh::queryable::{Query, Queryable};
+use zenoh::serialization::{Bincode, Serializer};
+use zenoh::subscriber::Subscriber;
+use zenoh::Error as ZError;
+use zenoh::Result as ZResult;
+use zenoh::Session;
+use zenoh_core::zlock;
+use zenoh_result::{bail, zerror};
+use zenoh_task::TaskController;
+
+/// Generates the client stubs and the server bindings of a service from a trait.
+///
+/// The methods of the trait must be `async`, take `&self` and arguments by value, and return a
+/// `Result<T, E>`: the arguments, `T` and `E` must be serializable with `serde`.
+/// The trait is made an [`async_trait`] with the `Send + Sync + 'static` supertraits, and given an
+/// `into_rpc_server` method returning the [`RpcServerBuilder`] of an implementation.
+/// A `<Trait>Client` stub is generated, whose methods return a `Result<T, RpcError<E>>`.
+///
+/// See the [module](self) documentation for an example.
+pub use zenoh_macros::rpc_service as service;
+
+/// Re-export of [`async_trait`](https://docs.rs/async-trait), to implement the traits of the services.
+pub use async_trait::async_trait;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use std::sync::Arc;
+    pub use zenoh::prelude::OwnedKeyExpr;
+    pub use zenoh::Error as ZError;
+    pub use zenoh::Result as ZResult;
+    pub use zenoh::Session;
+}
+
+const RPC_PREFIX: &str = "zenoh/ext/rpc";
+
+// The time for the cancellation of a call to be sent once its deadline expired
+const QUERY_TIMEOUT_MARGIN: Duration = Duration::from_secs(1);
+
+/// The default deadline of the calls of an [`RpcClient`].
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The default maximum number of calls processed concurrently by an [`RpcServer`].
+pub const DEFAULT_MAX_CONCURRENT_CALLS: usize = 256;
+
+type MethodHandler =
+    Arc<dyn Fn(Option<Value>) -> BoxFuture<'static, Result<Value, Value>> + Send + Sync>;
+
+/// The error of a call to the method of a service.
+#[derive(Debug)]
+pub enum RpcError<E> {
+    /// The error returned by the method.
+    Service(E),
+    /// No instance of the service is available.
+    Unavailable,
+    /// No reply was received before the deadline of the call.
+    Timeout,
+    /// The call failed for another reason, e.g. an unknown method or a request that cannot be deserialized.
+    Failed(ZError),
+}
+
+impl<E: fmt::Display> fmt::Display for RpcError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpcError::Service(e) => write!(f, "{e}"),
+            RpcError::Unavailable => f.write_str("No instance of the service is available"),
+            RpcError::Timeout => f.write_str("Timeout waiting for the reply"),
+            RpcError::Failed(e) => write!(f, "Call failed: {e}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for RpcError<E> {}
+
+impl<E> From<ZError> for RpcError<E> {
+    fn from(e: ZError) -> Self {
+        RpcError::Failed(e)
+    }
+}
+
+// The reply to a call that failed before or outside of the method
+fn failure<D: fmt::Display>(e: D) -> Value {
+    Value::from(e.to_string())
+}
+
+/// A builder for initializing an [`RpcServer`].
+pub struct RpcServerBuilder {
+    session: Arc<Session>,
+    service: ZResult<OwnedKeyExpr>,
+    methods: HashMap<String, MethodHandler>,
+    max_concurrent_calls: usize,
+}
+
+impl RpcServerBuilder {
+    /// Creates a builder for a server of the given service.
+    pub fn new<K>(session: Arc<Session>, service: K) -> Self
+    where
+        K: TryInto<OwnedKeyExpr>,
+        <K as TryInto<OwnedKeyExpr>>::Error: Into<ZError>,
+    {
+        RpcServerBuilder {
+            session,
+            service: service.try_into().map_err(|e| e.into()),
+            methods: HashMap::new(),
+            max_concurrent_calls: DEFAULT_MAX_CONCURRENT_CALLS,
+        }
+    }
+
+    /// Sets the maximum number of calls processed concurrently, the calls beyond it fail immediately.
+    pub fn max_concurrent_calls(mut self, max_concurrent_calls: usize) -> Self {
+        self.max_concurrent_calls = max_concurrent_calls;
+        self
+    }
+
+    /// Adds a method, served by the given async function of the request.
+    pub fn method<Req, Resp, Err, F, Fut>(mut self, name: &str, f: F) -> Self
+    where
+        Req: DeserializeOwned + Send + 'static,
+        Resp: Serialize,
+        Err: Serialize,
+        F: Fn(Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp, Err>> + Send + 'static,
+    {
+        let handler: MethodHandler = Arc::new(move |value: Option<Value>| {
+            let request: ZResult<Req> = match value {
+                Some(value) => value.deserialize(&Bincode),
+                None => Bincode.deserialize_bytes(&[]),
+            };
+            match request {
+                Ok(request) => f(request)
+                    .map(|result| match result {
+                        Ok(response) => Value::serialize(&Bincode, &response).map_err(failure),
+                        Err(e) => Err(Value::serialize(&Bincode, &e).unwrap_or_else(failure)),
+                    })
+                    .boxed(),
+                Err(e) => {
+                    futures::future::ready(Err(failure(format!("Invalid request: {e}")))).boxed()
+                }
+            }
+        });
+        self.methods.insert(name.to_string(), handler);
+        self
+    }
+
+    /// Declares the server.
+    pub async fn declare(self) -> ZResult<RpcServer> {
+        let service = self.service?;
+        if service.is_wild() {
+            bail!(
+                "Service name is not allowed to contain wildcards: {}",
+                service
+            );
+        }
+        for name in self.methods.keys() {
+            check_method(name)?;
+        }
+        let id = ZenohId::rand().into_keyexpr();
+        let methods = Arc::new(self.methods);
+        let task_controller = TaskController::default();
+        let calls = Arc::new(Semaphore::new(self.max_concurrent_calls));
+
+        let queryable = self
+            .session
+            .declare_queryable(format!("{service}/*/{id}"))
+            .callback({
+                let service = service.clone();
+                let id = id.clone();
+                let task_controller = task_controller.clone();
+                move |query: Query| {
+                    let Ok(permit) = calls.clone().try_acquire_owned() else {
+                        let busy = failure("Too many calls in progress");
+                        if let Err(e) = zenoh_core::SyncResolve::res_sync(query.reply(Err(busy))) {
+                            tracing::warn!("Error replying to call: {}", e);
+                        }
+                        return;
+                    };
+                    let method = query
+                        .key_expr()
+                        .as_str()
+                        .strip_prefix(service.as_str())
+                        .and_then(|rest| rest.strip_prefix('/'))
+                        .and_then(|rest| rest.split_once('/'))
+                        .map(|(method, _)| method.to_string());
+                    let handler = method.as_ref().and_then(|m| methods.get(m)).cloned();
+                    let reply_key: ZResult<OwnedKeyExpr> = method
+                        .as_ref()
+                        .ok_or_else(|| zerror!("No method in {}", query.key_expr()).into())
+                        .and_then(|method| format!("{service}/{method}/{id}").try_into());
+                    task_controller.spawn_abortable_with_rt(
+                        zenoh_runtime::ZRuntime::Application,
+                        async move {
+                            let result = match (handler, reply_key) {
+                                (Some(handler), Ok(reply_key)) => {
+                                    let call = handler(query.value().cloned());
+                                    tokio::select! {
+                                        result = call => result.map(|value| Sample::new(reply_key, value)),
+                                        _ = query.cancelled() => {
+                                            tracing::debug!("Call to {} cancelled", query.key_expr());
+                                            return;
+                                        }
+                                    }
+                                }
+                                _ => Err(failure(format!("Unknown method: {}", query.key_expr()))),
+                            };
+                            if let Err(e) = query.reply(result).res().await {
+                                tracing::warn!("Error replying to call: {}", e);
+                            }
+                            drop(permit);
+                        },
+                    );
+                }
+            })
+            .res()
+            .await?;
+        let token = self
+            .session
+            .liveliness()
+            .declare_token(format!("{RPC_PREFIX}/{service}/{id}"))
+            .res()
+            .await?;
+
+        Ok(RpcServer {
+            service,
+            id,
+            _queryable: queryable,
+            _token: token,
+            task_controller,
+        })
+    }
+}
+
+// A method name must be a single chunk without wildcards
+fn check_method(name: &str) -> ZResult<()> {
+    match keyexpr::new(name) {
+        Ok(ke) if !ke.is_wild() && !name.contains('/') => Ok(()),
+        _ => bail!("Invalid method name: '{}'", name),
+    }
+}
+
+/// A server of the methods of a service, see the [module](self) documentation.
+pub struct RpcServer {
+    service: OwnedKeyExpr,
+    id: OwnedKeyExpr,
+    _queryable: Queryable<'static, ()>,
+    _token: LivelinessToken<'static>,
+    task_controller: TaskController,
+}
+
+impl RpcServer {
+    /// Returns the name of the service.
+    pub fn service(&self) -> &keyexpr {
+        &self.service
+    }
+
+    /// Returns the identifier of this instance of the service.
+    pub fn id(&self) -> &keyexpr {
+        &self.id
+    }
+}
+
+impl Drop for RpcServer {
+    fn drop(&mut self) {
+        // abort the calls in progress
+        self.task_controller.terminate_all(Duration::from_secs(10));
+    }
+}
+
+struct ClientState {
+    session: Arc<Session>,
+    service: OwnedKeyExpr,
+    instances: Arc<Mutex<Vec<OwnedKeyExpr>>>,
+    next: AtomicUsize,
+    _liveliness: Subscriber<'static, ()>,
+}
+
+/// A client of a service, balancing its calls across the instances of the service.
+///
+/// The clones of a client share their view of the instances of the service.
+#[derive(Clone)]
+pub struct RpcClient {
+    state: Arc<ClientState>,
+    timeout: Duration,
+}
+
+impl RpcClient {
+    /// Creates a client of the given service.
+    pub async fn new<K>(session: Arc<Session>, service: K) -> ZResult<RpcClient>
+    where
+        K: TryInto<OwnedKeyExpr>,
+        <K as TryInto<OwnedKeyExpr>>::Error: Into<ZError>,
+    {
+        let service: OwnedKeyExpr = service.try_into().map_err(|e| e.into())?;
+        if service.is_wild() {
+            bail!(
+                "Service name is not allowed to contain wildcards: {}",
+                service
+            );
+        }
+        let instances = Arc::new(Mutex::new(vec![]));
+        let tokens = format!("{RPC_PREFIX}/{service}/*");
+
+        let liveliness = session
+            .liveliness()
+            .declare_subscriber(tokens.clone())
+            .callback({
+                let instances = instances.clone();
+                move |sample: Sample| {
+                    if let Some(id) = instance_id(&sample.key_expr) {
+                        let mut instances = zlock!(instances);
+                        match sample.kind {
+                            SampleKind::Put => {
+                                if !instances.contains(&id) {
+                                    instances.push(id);
+                                }
+                            }
+                            SampleKind::Delete => instances.retain(|i| *i != id),
+                        }
+                    }
+                }
+            })
+            .res()
+            .await?;
+        let replies = session.liveliness().get(tokens).res().await?;
+        while let Ok(reply) = replies.recv_async().await {
+            if let Ok(sample) = reply.sample {
+                if let Some(id) = instance_id(&sample.key_expr) {
+                    let mut instances = zlock!(instances);
+                    if !instances.contains(&id) {
+                        instances.push(id);
+                    }
+                }
+            }
+        }
+
+        Ok(RpcClient {
+            state: Arc::new(ClientState {
+                session,
+                service,
+                instances,
+                next: AtomicUsize::new(0),
+                _liveliness: liveliness,
+            }),
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// Sets the deadline of the calls, [`DEFAULT_TIMEOUT`] by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns the name of the service.
+    pub fn service(&self) -> &keyexpr {
+        &self.state.service
+    }
+
+    /// Returns the deadline of the calls.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the identifiers of the known instances of the service.
+    pub fn instances(&self) -> Vec<OwnedKeyExpr> {
+        zlock!(self.state.instances).clone()
+    }
+
+    /// Calls a method of the service.
+    pub async fn call<Req, Resp, E>(&self, method: &str, request: &Req) -> Result<Resp, RpcError<E>>
+    where
+        Req: Serialize + ?Sized,
+        Resp: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        self.call_with_timeout(method, request, self.timeout).await
+    }
+
+    /// Calls a method of the service, with the given deadline.
+    pub async fn call_with_timeout<Req, Resp, E>(
+        &self,
+        method: &str,
+        request: &Req,
+        timeout: Duration,
+    ) -> Result<Resp, RpcError<E>>
+    where
+        Req: Serialize + ?Sized,
+        Resp: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        check_method(method)?;
+        let instance = self.next_instance().ok_or(RpcError::Unavailable)?;
+        let value = Value::serialize(&Bincode, request)?;
+        let (replies, cancellation) = self
+            .state
+            .session
+            .get(format!("{}/{method}/{instance}", self.state.service))
+            .with_value(value)
+            // the query outlives the deadline, to be cancelled when it expires
+            .timeout(timeout + QUERY_TIMEOUT_MARGIN)
+            .cancellable()
+            .res()
+            .await?;
+
+        match tokio::time::timeout(timeout, replies.recv_async()).await {
+            Ok(Ok(reply)) => match reply.sample {
+                Ok(sample) => Ok(sample.value.deserialize(&Bincode)?),
+                Err(value) => Err(service_error(value)),
+            },
+            Ok(Err(_)) => Err(RpcError::Failed(
+                zerror!(
+                    "No reply from instance {} of service {}",
+                    instance,
+                    self.state.service
+                )
+                .into(),
+            )),
+            Err(_) => {
+                if let Err(e) = cancellation.cancel().res().await {
+                    tracing::warn!("Error cancelling call: {}", e);
+                }
+                Err(RpcError::Timeout)
+            }
+        }
+    }
+
+    // Returns the next instance to call, in a round-robin fashion
+    fn next_instance(&self) -> Option<OwnedKeyExpr> {
+        let instances = zlock!(self.state.instances);
+        if instances.is_empty() {
+            return None;
+        }
+        let next = self.state.next.fetch_add(1, Ordering::Relaxed);
+        Some(instances[next % instances.len()].clone())
+    }
+}
+
+// Returns the identifier of an instance from its liveliness token
+fn instance_id(token: &keyexpr) -> Option<OwnedKeyExpr> {
+    token
+        .as_str()
+        .rsplit('/')
+        .next()
+        .and_then(|id| OwnedKeyExpr::try_from(id).ok())
+}
+
+// The service errors are serialized, the failures of the calls are plain text
+fn service_error<E: DeserializeOwned>(value: Value) -> RpcError<E> {
+    if Bincode.accepts(&value.encoding) {
+        match value.deserialize(&Bincode) {
+            Ok(e) => RpcError::Service(e),
+            Err(e) => RpcError::Failed(e),
+        }
+    } else {
+        RpcError::Failed(zerror!("{}", String::from_utf8_lossy(&value.payload.contiguous())).into())
+    }
+}
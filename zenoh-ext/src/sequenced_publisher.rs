@@ -0,0 +1,213 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use crate::sequencing::sequence_attachment;
+use std::future::Ready;
+use std::sync::atomic::{AtomicU64, Ordering};
+use zenoh::prelude::r#async::*;
+use zenoh::publication::{Publication, Publisher, PublisherBuilder};
+use zenoh::sample::Attachment;
+use zenoh_core::{AsyncResolve, Resolvable, SyncResolve};
+use zenoh_result::ZResult;
+
+/// The builder of [`SequencedPublisher`], allowing to configure it.
+#[must_use = "Resolvables do nothing unless you resolve them using the `res` method from either `SyncResolve` or `AsyncResolve`"]
+pub struct SequencedPublisherBuilder<'a, 'b: 'a> {
+    publisher: PublisherBuilder<'a, 'b>,
+}
+
+impl<'a, 'b> SequencedPublisherBuilder<'a, 'b> {
+    pub(crate) fn new(publisher: PublisherBuilder<'a, 'b>) -> SequencedPublisherBuilder<'a, 'b> {
+        SequencedPublisherBuilder { publisher }
+    }
+
+    /// Change the `congestion_control` to apply when routing the data.
+    #[inline]
+    pub fn congestion_control(mut self, congestion_control: CongestionControl) -> Self {
+        self.publisher = self.publisher.congestion_control(congestion_control);
+        self
+    }
+
+    /// Change the priority of the written data.
+    #[inline]
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.publisher = self.publisher.priority(priority);
+        self
+    }
+
+    /// Restrict the matching subscribers that will receive the published data
+    /// to the ones that have the given [`Locality`](zenoh::prelude::Locality).
+    #[zenoh_macros::unstable]
+    #[inline]
+    pub fn allowed_destination(mut self, destination: Locality) -> Self {
+        self.publisher = self.publisher.allowed_destination(destination);
+        self
+    }
+}
+
+impl<'a> Resolvable for SequencedPublisherBuilder<'a, '_> {
+    type To = ZResult<SequencedPublisher<'a>>;
+}
+
+impl SyncResolve for SequencedPublisherBuilder<'_, '_> {
+    fn res_sync(self) -> <Self as Resolvable>::To {
+        Ok(SequencedPublisher {
+            publisher: self.publisher.res_sync()?,
+            id: ZenohId::rand(),
+            next_sn: AtomicU64::new(0),
+        })
+    }
+}
+
+impl AsyncResolve for SequencedPublisherBuilder<'_, '_> {
+    type Future = Ready<Self::To>;
+
+    fn res_async(self) -> Self::Future {
+        std::future::ready(self.res_sync())
+    }
+}
+
+/// A [`Publisher`] stamping its samples with its identifier and monotonically increasing sequence numbers.
+///
+/// They are carried in dedicated entries of the attachment of the samples, so that the subscribers can detect the lost
+/// samples: see [`SequencedSubscriber`](crate::SequencedSubscriber), or the recovery of the
+/// [`FetchingSubscriber`](crate::FetchingSubscriber)s.
+///
+/// # Examples
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use zenoh::prelude::r#async::*;
+/// use zenoh_ext::*;
+///
+/// let session = zenoh::open(config::peer()).res().await.unwrap();
+/// let publisher = session
+///     .declare_sequenced_publisher("key/expr")
+///     .res()
+///     .await
+///     .unwrap();
+/// publisher.put("value").res().await.unwrap();
+/// # }
+/// ```
+pub struct SequencedPublisher<'a> {
+    publisher: Publisher<'a>,
+    id: ZenohId,
+    next_sn: AtomicU64,
+}
+
+impl<'a> SequencedPublisher<'a> {
+    /// Return the identifier of this SequencedPublisher, distinct from the one of its session.
+    #[inline]
+    pub fn id(&self) -> ZenohId {
+        self.id
+    }
+
+    /// Return the key expression of this SequencedPublisher.
+    #[inline]
+    pub fn key_expr(&self) -> &KeyExpr<'a> {
+        self.publisher.key_expr()
+    }
+
+    /// Return the sequence number of the next published sample.
+    #[inline]
+    pub fn next_sn(&self) -> u64 {
+        self.next_sn.load(Ordering::Relaxed)
+    }
+
+    /// Put data with the next sequence number.
+    #[inline]
+    pub fn put<IntoValue>(&self, value: IntoValue) -> SequencedPublication
+    where
+        IntoValue: Into<Value>,
+    {
+        SequencedPublication {
+            publication: self.publisher.put(value),
+            id: self.id,
+            next_sn: &self.next_sn,
+            attachment: None,
+        }
+    }
+
+    /// Delete data with the next sequence number.
+    #[inline]
+    pub fn delete(&self) -> SequencedPublication {
+        SequencedPublication {
+            publication: self.publisher.delete(),
+            id: self.id,
+            next_sn: &self.next_sn,
+            attachment: None,
+        }
+    }
+
+    /// Undeclare this SequencedPublisher.
+    #[inline]
+    pub fn undeclare(self) -> impl Resolve<ZResult<()>> + 'a {
+        self.publisher.undeclare()
+    }
+}
+
+/// A [`Resolvable`] returned by [`SequencedPublisher::put()`](SequencedPublisher::put) and
+/// [`SequencedPublisher::delete()`](SequencedPublisher::delete).
+///
+/// The sequence number of the sample is only allocated when it is resolved, so that a dropped publication
+/// is not seen as a lost sample by the subscribers.
+#[must_use = "Resolvables do nothing unless you resolve them using the `res` method from either `SyncResolve` or `AsyncResolve`"]
+pub struct SequencedPublication<'a> {
+    publication: Publication<'a>,
+    id: ZenohId,
+    next_sn: &'a AtomicU64,
+    attachment: Option<Attachment>,
+}
+
+impl SequencedPublication<'_> {
+    /// Attach the given entries to the published sample, along with the ones carrying its sequence number.
+    pub fn with_attachment(mut self, attachment: Attachment) -> Self {
+        match self.attachment.as_mut() {
+            Some(entries) => {
+                entries.extend(attachment);
+            }
+            None => self.attachment = Some(attachment),
+        }
+        self
+    }
+}
+
+impl Resolvable for SequencedPublication<'_> {
+    type To = ZResult<()>;
+}
+
+impl SyncResolve for SequencedPublication<'_> {
+    fn res_sync(self) -> <Self as Resolvable>::To {
+        let sn = self.next_sn.fetch_add(1, Ordering::Relaxed);
+        let mut attachment = sequence_attachment(&self.id, sn);
+        if let Some(entries) = self.attachment {
+            attachment.extend(entries);
+        }
+        let res = self.publication.with_attachment(attachment).res_sync();
+        if res.is_err() {
+            // the sequence number is released if no other sample was published since
+            let _ = self
+                .next_sn
+                .compare_exchange(sn + 1, sn, Ordering::Relaxed, Ordering::Relaxed);
+        }
+        res
+    }
+}
+
+impl AsyncResolve for SequencedPublication<'_> {
+    type Future = Ready<Self::To>;
+
+    fn res_async(self) -> Self::Future {
+        std::future::ready(self.res_sync())
+    }
+}
@@ -0,0 +1,540 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use crate::sequencing::{sequence_number, SOURCE_EXPIRATION};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Ready;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use zenoh::handlers::{locked, DefaultHandler};
+use zenoh::prelude::r#async::*;
+use zenoh::subscriber::{Reliability, Subscriber};
+use zenoh::SessionRef;
+use zenoh_core::{zlock, AsyncResolve, Resolvable, SyncResolve};
+use zenoh_result::ZResult;
+
+// The maximum number of ranges of missing sequence numbers remembered per publisher,
+// the oldest ones are forgotten first: their samples are then reported as duplicates if received late
+const MAX_MISSING_RANGES: usize = 1024;
+
+/// An event on the sequence of the samples of a publisher, reported by a [`SequencedSubscriber`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceEvent {
+    /// The samples with the sequence numbers from `first` to `last` included were not received.
+    Gap {
+        source: ZenohId,
+        first: u64,
+        last: u64,
+    },
+    /// The sample with the given sequence number was already received.
+    Duplicate { source: ZenohId, sn: u64 },
+    /// The sample with the given sequence number was received after a sample with a higher one,
+    /// it was previously reported in a [`Gap`](SequenceEvent::Gap).
+    Reordered { source: ZenohId, sn: u64 },
+}
+
+/// The statistics of a [`SequencedSubscriber`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SequenceStats {
+    /// The number of publishers the samples were received from, not counting the ones forgotten after
+    /// not being heard of for a while.
+    pub sources: usize,
+    /// The number of received samples with a sequence number.
+    pub received: u64,
+    /// The number of received samples without sequence number.
+    pub unsequenced: u64,
+    /// The number of samples reported in a gap and never received since.
+    pub lost: u64,
+    /// The number of samples received more than once.
+    pub duplicates: u64,
+    /// The number of samples received after a sample with a higher sequence number.
+    pub reordered: u64,
+}
+
+/// The builder of [`SequencedSubscriber`], allowing to configure it.
+#[must_use = "Resolvables do nothing unless you resolve them using the `res` method from either `SyncResolve` or `AsyncResolve`"]
+pub struct SequencedSubscriberBuilder<'a, 'b, Handler> {
+    session: SessionRef<'a>,
+    key_expr: ZResult<KeyExpr<'b>>,
+    reliability: Reliability,
+    origin: Locality,
+    on_event: Option<Arc<dyn Fn(SequenceEvent) + Send + Sync>>,
+    handler: Handler,
+}
+
+impl<'a, 'b> SequencedSubscriberBuilder<'a, 'b, DefaultHandler> {
+    pub(crate) fn new(
+        session: SessionRef<'a>,
+        key_expr: ZResult<KeyExpr<'b>>,
+    ) -> SequencedSubscriberBuilder<'a, 'b, DefaultHandler> {
+        SequencedSubscriberBuilder {
+            session,
+            key_expr,
+            reliability: Reliability::default(),
+            origin: Locality::default(),
+            on_event: None,
+            handler: DefaultHandler,
+        }
+    }
+
+    /// Add callback to [`SequencedSubscriber`].
+    #[inline]
+    pub fn callback<Callback>(
+        self,
+        callback: Callback,
+    ) -> SequencedSubscriberBuilder<'a, 'b, Callback>
+    where
+        Callback: Fn(Sample) + Send + Sync + 'static,
+    {
+        self.with(callback)
+    }
+
+    /// Add callback to [`SequencedSubscriber`].
+    ///
+    /// Using this guarantees that your callback will never be called concurrently.
+    /// If your callback is also accepted by the [`callback`](SequencedSubscriberBuilder::callback)
+    /// method, we suggest you use it instead of `callback_mut`
+    #[inline]
+    pub fn callback_mut<CallbackMut>(
+        self,
+        callback: CallbackMut,
+    ) -> SequencedSubscriberBuilder<'a, 'b, impl Fn(Sample) + Send + Sync + 'static>
+    where
+        CallbackMut: FnMut(Sample) + Send + Sync + 'static,
+    {
+        self.callback(locked(callback))
+    }
+
+    /// Use the given handler to receive Samples.
+    #[inline]
+    pub fn with<Handler>(self, handler: Handler) -> SequencedSubscriberBuilder<'a, 'b, Handler>
+    where
+        Handler: IntoCallbackReceiverPair<'static, Sample>,
+    {
+        let SequencedSubscriberBuilder {
+            session,
+            key_expr,
+            reliability,
+            origin,
+            on_event,
+            handler: _,
+        } = self;
+        SequencedSubscriberBuilder {
+            session,
+            key_expr,
+            reliability,
+            origin,
+            on_event,
+            handler,
+        }
+    }
+}
+
+impl<'a, 'b, Handler> SequencedSubscriberBuilder<'a, 'b, Handler> {
+    /// Change the subscription reliability.
+    #[inline]
+    pub fn reliability(mut self, reliability: Reliability) -> Self {
+        self.reliability = reliability;
+        self
+    }
+
+    /// Change the subscription reliability to Reliable.
+    #[inline]
+    pub fn reliable(mut self) -> Self {
+        self.reliability = Reliability::Reliable;
+        self
+    }
+
+    /// Change the subscription reliability to BestEffort.
+    #[inline]
+    pub fn best_effort(mut self) -> Self {
+        self.reliability = Reliability::BestEffort;
+        self
+    }
+
+    /// Restrict the matching publications that will be receive by this [`SequencedSubscriber`]
+    /// to the ones that have the given [`Locality`](zenoh::prelude::Locality).
+    #[zenoh_macros::unstable]
+    #[inline]
+    pub fn allowed_origin(mut self, origin: Locality) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Call the given callback on the gaps, duplicates and reordering detected in the samples of a publisher.
+    ///
+    /// It is called before the concerned sample is propagated.
+    #[inline]
+    pub fn on_event<OnEvent>(mut self, on_event: OnEvent) -> Self
+    where
+        OnEvent: Fn(SequenceEvent) + Send + Sync + 'static,
+    {
+        self.on_event = Some(Arc::new(on_event));
+        self
+    }
+}
+
+impl<'a, Handler> Resolvable for SequencedSubscriberBuilder<'a, '_, Handler>
+where
+    Handler: IntoCallbackReceiverPair<'static, Sample>,
+    Handler::Receiver: Send,
+{
+    type To = ZResult<SequencedSubscriber<'a, Handler::Receiver>>;
+}
+
+impl<Handler> SyncResolve for SequencedSubscriberBuilder<'_, '_, Handler>
+where
+    Handler: IntoCallbackReceiverPair<'static, Sample> + Send,
+    Handler::Receiver: Send,
+{
+    fn res_sync(self) -> <Self as Resolvable>::To {
+        SequencedSubscriber::new(self)
+    }
+}
+
+impl<Handler> AsyncResolve for SequencedSubscriberBuilder<'_, '_, Handler>
+where
+    Handler: IntoCallbackReceiverPair<'static, Sample> + Send,
+    Handler::Receiver: Send,
+{
+    type Future = Ready<Self::To>;
+
+    fn res_async(self) -> Self::Future {
+        std::future::ready(self.res_sync())
+    }
+}
+
+// The sequence numbers received from a publisher
+struct SourceState {
+    // The sequence number following the highest received one
+    next: u64,
+    // The ranges of missing sequence numbers, as first -> last included
+    missing: BTreeMap<u64, u64>,
+    // When the last sample of the publisher was received
+    last_seen: Instant,
+}
+
+impl SourceState {
+    // Removes the sequence number from the missing ones, returns false if it was not missing
+    fn recover(&mut self, sn: u64) -> bool {
+        let Some((&first, &last)) = self.missing.range(..=sn).next_back() else {
+            return false;
+        };
+        if sn > last {
+            return false;
+        }
+        self.missing.remove(&first);
+        if first < sn {
+            self.missing.insert(first, sn - 1);
+        }
+        if sn < last {
+            self.missing.insert(sn + 1, last);
+        }
+        true
+    }
+}
+
+struct SequenceState {
+    sources: HashMap<ZenohId, SourceState>,
+    stats: SequenceStats,
+    // When the sources not heard of since SOURCE_EXPIRATION are next forgotten
+    next_expiration: Instant,
+}
+
+impl SequenceState {
+    fn new() -> Self {
+        SequenceState {
+            sources: HashMap::new(),
+            stats: SequenceStats::default(),
+            next_expiration: Instant::now() + SOURCE_EXPIRATION,
+        }
+    }
+
+    // Forgets the sources not heard of since SOURCE_EXPIRATION, at most once per SOURCE_EXPIRATION
+    fn expire(&mut self, now: Instant) {
+        if now < self.next_expiration {
+            return;
+        }
+        self.next_expiration = now + SOURCE_EXPIRATION;
+        self.sources
+            .retain(|_, source| now.duration_since(source.last_seen) < SOURCE_EXPIRATION);
+        self.stats.sources = self.sources.len();
+    }
+
+    fn check(&mut self, sample: &Sample) -> Option<SequenceEvent> {
+        let Some((source, sn)) = sequence_number(sample) else {
+            self.stats.unsequenced += 1;
+            return None;
+        };
+        self.stats.received += 1;
+        let now = Instant::now();
+        self.expire(now);
+        let Some(state) = self.sources.get_mut(&source) else {
+            // the samples published before the subscription are not reported
+            self.sources.insert(
+                source,
+                SourceState {
+                    next: sn + 1,
+                    missing: BTreeMap::new(),
+                    last_seen: now,
+                },
+            );
+            self.stats.sources = self.sources.len();
+            return None;
+        };
+        state.last_seen = now;
+        if sn == state.next {
+            state.next += 1;
+            None
+        } else if sn > state.next {
+            let (first, last) = (state.next, sn - 1);
+            state.missing.insert(first, last);
+            if state.missing.len() > MAX_MISSING_RANGES {
+                state.missing.pop_first();
+            }
+            state.next = sn + 1;
+            self.stats.lost += last - first + 1;
+            Some(SequenceEvent::Gap {
+                source,
+                first,
+                last,
+            })
+        } else if state.recover(sn) {
+            self.stats.lost -= 1;
+            self.stats.reordered += 1;
+            Some(SequenceEvent::Reordered { source, sn })
+        } else {
+            self.stats.duplicates += 1;
+            Some(SequenceEvent::Duplicate { source, sn })
+        }
+    }
+}
+
+/// A Subscriber detecting the samples lost, duplicated or reordered between a
+/// [`SequencedPublisher`](crate::SequencedPublisher) and itself.
+///
+/// All the samples are propagated. The detected anomalies are reported through the callback given with
+/// [`on_event`](SequencedSubscriberBuilder::on_event) and accounted in the [`stats`](SequencedSubscriber::stats).
+/// The samples published before the first one received from a publisher are not reported as lost. A publisher
+/// not heard of for 10 minutes is forgotten, its next samples being checked as the ones of a new publisher.
+///
+/// # Examples
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use zenoh::prelude::r#async::*;
+/// use zenoh_ext::*;
+///
+/// let session = zenoh::open(config::peer()).res().await.unwrap();
+/// let subscriber = session
+///     .declare_sequenced_subscriber("key/expr")
+///     .on_event(|event| println!("{:?}", event))
+///     .res()
+///     .await
+///     .unwrap();
+/// while let Ok(sample) = subscriber.recv_async().await {
+///     println!("Received: {:?}", sample);
+/// }
+/// println!("Lost samples: {}", subscriber.stats().lost);
+/// # }
+/// ```
+pub struct SequencedSubscriber<'a, Receiver> {
+    subscriber: Subscriber<'a, ()>,
+    state: Arc<Mutex<SequenceState>>,
+    receiver: Receiver,
+}
+
+impl<Receiver> std::ops::Deref for SequencedSubscriber<'_, Receiver> {
+    type Target = Receiver;
+    fn deref(&self) -> &Self::Target {
+        &self.receiver
+    }
+}
+
+impl<Receiver> std::ops::DerefMut for SequencedSubscriber<'_, Receiver> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.receiver
+    }
+}
+
+impl<'a, Receiver> SequencedSubscriber<'a, Receiver> {
+    fn new<Handler>(conf: SequencedSubscriberBuilder<'a, '_, Handler>) -> ZResult<Self>
+    where
+        Handler: IntoCallbackReceiverPair<'static, Sample, Receiver = Receiver> + Send,
+    {
+        let state = Arc::new(Mutex::new(SequenceState::new()));
+        let (callback, receiver) = conf.handler.into_cb_receiver_pair();
+        let on_event = conf.on_event;
+
+        let sub_callback = {
+            let state = state.clone();
+            move |s: Sample| {
+                let event = zlock!(state).check(&s);
+                if let Some(event) = event {
+                    tracing::debug!("{:?} on {}", event, s.key_expr);
+                    if let Some(on_event) = &on_event {
+                        on_event(event);
+                    }
+                }
+                callback(s);
+            }
+        };
+
+        let subscriber = conf
+            .session
+            .declare_subscriber(conf.key_expr?)
+            .callback(sub_callback)
+            .reliability(conf.reliability)
+            .allowed_origin(conf.origin)
+            .res_sync()?;
+
+        Ok(SequencedSubscriber {
+            subscriber,
+            state,
+            receiver,
+        })
+    }
+
+    /// Close this SequencedSubscriber
+    #[inline]
+    pub fn close(self) -> impl Resolve<ZResult<()>> + 'a {
+        self.subscriber.undeclare()
+    }
+
+    /// Return the key expression of this SequencedSubscriber
+    #[inline]
+    pub fn key_expr(&self) -> &KeyExpr<'static> {
+        self.subscriber.key_expr()
+    }
+
+    /// Return the statistics of the samples received by this SequencedSubscriber.
+    pub fn stats(&self) -> SequenceStats {
+        zlock!(self.state).stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequencing::sequence_attachment;
+
+    fn sample(source: &ZenohId, sn: u64) -> Sample {
+        Sample::new(KeyExpr::try_from("test/sequencing").unwrap(), "value")
+            .with_attachment(sequence_attachment(source, sn))
+    }
+
+    #[test]
+    fn recover() {
+        let mut state = SourceState {
+            next: 10,
+            missing: BTreeMap::from([(2, 4), (6, 6)]),
+            last_seen: Instant::now(),
+        };
+        assert!(!state.recover(1));
+        assert!(!state.recover(5));
+        assert!(state.recover(3));
+        assert_eq!(state.missing, BTreeMap::from([(2, 2), (4, 4), (6, 6)]));
+        assert!(state.recover(2));
+        assert!(state.recover(6));
+        assert!(!state.recover(6));
+        assert_eq!(state.missing, BTreeMap::from([(4, 4)]));
+    }
+
+    #[test]
+    fn check() {
+        let (a, b) = (ZenohId::rand(), ZenohId::rand());
+        let mut state = SequenceState::new();
+        // the samples published before the first received one are not reported
+        assert_eq!(state.check(&sample(&a, 5)), None);
+        assert_eq!(state.check(&sample(&b, 0)), None);
+        assert_eq!(state.check(&sample(&a, 6)), None);
+        assert_eq!(
+            state.check(&sample(&a, 9)),
+            Some(SequenceEvent::Gap {
+                source: a,
+                first: 7,
+                last: 8
+            })
+        );
+        assert_eq!(
+            state.check(&sample(&a, 8)),
+            Some(SequenceEvent::Reordered { source: a, sn: 8 })
+        );
+        assert_eq!(
+            state.check(&sample(&a, 8)),
+            Some(SequenceEvent::Duplicate { source: a, sn: 8 })
+        );
+        assert_eq!(
+            state.check(&sample(&a, 6)),
+            Some(SequenceEvent::Duplicate { source: a, sn: 6 })
+        );
+        assert_eq!(state.check(&sample(&b, 1)), None);
+        assert_eq!(
+            state.check(&Sample::new(
+                KeyExpr::try_from("test/sequencing").unwrap(),
+                "value"
+            )),
+            None
+        );
+        assert_eq!(
+            state.stats,
+            SequenceStats {
+                sources: 2,
+                received: 8,
+                unsequenced: 1,
+                lost: 1,
+                duplicates: 2,
+                reordered: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn expiration() {
+        let (a, b) = (ZenohId::rand(), ZenohId::rand());
+        let mut state = SequenceState::new();
+        state.check(&sample(&a, 0));
+        state.check(&sample(&b, 0));
+        let now = Instant::now();
+        state.expire(now);
+        assert_eq!((state.sources.len(), state.stats.sources), (2, 2));
+
+        // the sources not heard of since SOURCE_EXPIRATION are forgotten
+        state.expire(now + SOURCE_EXPIRATION * 2);
+        assert!(state.sources.is_empty());
+        assert_eq!(state.stats.sources, 0);
+
+        // the next samples of a forgotten source are not reported as lost
+        assert_eq!(state.check(&sample(&a, 5)), None);
+        assert_eq!((state.stats.sources, state.stats.lost), (1, 0));
+    }
+
+    #[test]
+    fn forget_oldest_missing_ranges() {
+        let source = ZenohId::rand();
+        let mut state = SequenceState::new();
+        state.check(&sample(&source, 0));
+        for i in 0..=MAX_MISSING_RANGES as u64 {
+            state.check(&sample(&source, 2 * i + 2));
+        }
+        // the first gap was forgotten: its sample is reported as a duplicate
+        assert_eq!(
+            state.check(&sample(&source, 1)),
+            Some(SequenceEvent::Duplicate { source, sn: 1 })
+        );
+        assert_eq!(
+            state.check(&sample(&source, 3)),
+            Some(SequenceEvent::Reordered { source, sn: 3 })
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use std::convert::TryInto;
+use std::time::Duration;
+use zenoh::prelude::{Sample, ZenohId};
+use zenoh::sample::Attachment;
+
+/// Time after which a publisher that was not heard of anymore is forgotten by the subscribers tracking its
+/// sequence numbers, its next publications being considered as coming from a new publisher.
+pub(crate) const SOURCE_EXPIRATION: Duration = Duration::from_secs(600);
+
+/// The attachment entry carrying the identifier of the publisher of a sequence-numbered sample.
+pub(crate) const SOURCE_ID_ATTACHMENT: &str = "zenoh-ext/source_id";
+/// The attachment entry carrying the sequence number of a sample in its publisher, little-endian encoded.
+pub(crate) const SOURCE_SN_ATTACHMENT: &str = "zenoh-ext/source_sn";
+
+/// Returns the identifier of the publisher of the given sample and its sequence number, if any.
+///
+/// They are taken from the [`SourceInfo`](zenoh::sample::SourceInfo) of the sample when set,
+/// or else from its attachment.
+pub(crate) fn sequence_number(sample: &Sample) -> Option<(ZenohId, u64)> {
+    if let (Some(id), Some(sn)) = (sample.source_info.source_id, sample.source_info.source_sn) {
+        return Some((id, sn));
+    }
+    let attachment = sample.attachment()?;
+    let id = attachment.get(&SOURCE_ID_ATTACHMENT)?;
+    let sn = attachment.get(&SOURCE_SN_ATTACHMENT)?;
+    let id = ZenohId::try_from(id.as_slice()).ok()?;
+    let sn = u64::from_le_bytes(sn.as_slice().try_into().ok()?);
+    Some((id, sn))
+}
+
+/// Returns the attachment stamping a sample with the identifier of its publisher and its sequence number.
+pub(crate) fn sequence_attachment(id: &ZenohId, sn: u64) -> Attachment {
+    let mut attachment = Attachment::new();
+    attachment.insert(SOURCE_ID_ATTACHMENT, &id.to_le_bytes()[..id.size()]);
+    attachment.insert(SOURCE_SN_ATTACHMENT, &sn.to_le_bytes());
+    attachment
+}
@@ -11,10 +11,11 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
-use super::PublicationCacheBuilder;
+use super::{PublicationCacheBuilder, SequencedPublisherBuilder, SequencedSubscriberBuilder};
 use std::convert::TryInto;
 use std::sync::Arc;
-use zenoh::prelude::KeyExpr;
+use zenoh::handlers::DefaultHandler;
+use zenoh::prelude::{KeyExpr, SessionDeclarations};
 use zenoh::{Session, SessionRef};
 
 /// Some extensions to the [`zenoh::Session`](zenoh::Session)
@@ -83,3 +84,98 @@ impl<'s> SessionExt<'s, 'static> for Arc<Session> {
         SessionRef::Shared(self.clone()).declare_publication_cache(pub_key_expr)
     }
 }
+
+/// Some extensions to the [`zenoh::Session`](zenoh::Session) to declare sequence-numbered publishers and the
+/// subscribers detecting their lost samples
+pub trait SequencingSessionExt<'s, 'a> {
+    /// Create a [`SequencedPublisher`](super::SequencedPublisher) stamping its samples with sequence numbers.
+    fn declare_sequenced_publisher<'b, TryIntoKeyExpr>(
+        &'s self,
+        key_expr: TryIntoKeyExpr,
+    ) -> SequencedPublisherBuilder<'a, 'b>
+    where
+        TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
+        <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>;
+
+    /// Create a [`SequencedSubscriber`](super::SequencedSubscriber) detecting the samples lost, duplicated or
+    /// reordered from the [`SequencedPublisher`](super::SequencedPublisher)s.
+    fn declare_sequenced_subscriber<'b, TryIntoKeyExpr>(
+        &'s self,
+        key_expr: TryIntoKeyExpr,
+    ) -> SequencedSubscriberBuilder<'a, 'b, DefaultHandler>
+    where
+        TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
+        <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>;
+}
+
+impl<'s, 'a> SequencingSessionExt<'s, 'a> for SessionRef<'a> {
+    fn declare_sequenced_publisher<'b, TryIntoKeyExpr>(
+        &'s self,
+        key_expr: TryIntoKeyExpr,
+    ) -> SequencedPublisherBuilder<'a, 'b>
+    where
+        TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
+        <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>,
+    {
+        SequencedPublisherBuilder::new(self.declare_publisher(key_expr))
+    }
+
+    fn declare_sequenced_subscriber<'b, TryIntoKeyExpr>(
+        &'s self,
+        key_expr: TryIntoKeyExpr,
+    ) -> SequencedSubscriberBuilder<'a, 'b, DefaultHandler>
+    where
+        TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
+        <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>,
+    {
+        SequencedSubscriberBuilder::new(self.clone(), key_expr.try_into().map_err(Into::into))
+    }
+}
+
+impl<'a> SequencingSessionExt<'a, 'a> for Session {
+    fn declare_sequenced_publisher<'b, TryIntoKeyExpr>(
+        &'a self,
+        key_expr: TryIntoKeyExpr,
+    ) -> SequencedPublisherBuilder<'a, 'b>
+    where
+        TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
+        <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>,
+    {
+        SessionRef::Borrow(self).declare_sequenced_publisher(key_expr)
+    }
+
+    fn declare_sequenced_subscriber<'b, TryIntoKeyExpr>(
+        &'a self,
+        key_expr: TryIntoKeyExpr,
+    ) -> SequencedSubscriberBuilder<'a, 'b, DefaultHandler>
+    where
+        TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
+        <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>,
+    {
+        SessionRef::Borrow(self).declare_sequenced_subscriber(key_expr)
+    }
+}
+
+impl<'s> SequencingSessionExt<'s, 'static> for Arc<Session> {
+    fn declare_sequenced_publisher<'b, TryIntoKeyExpr>(
+        &'s self,
+        key_expr: TryIntoKeyExpr,
+    ) -> SequencedPublisherBuilder<'static, 'b>
+    where
+        TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
+        <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>,
+    {
+        SessionRef::Shared(self.clone()).declare_sequenced_publisher(key_expr)
+    }
+
+    fn declare_sequenced_subscriber<'b, TryIntoKeyExpr>(
+        &'s self,
+        key_expr: TryIntoKeyExpr,
+    ) -> SequencedSubscriberBuilder<'static, 'b, DefaultHandler>
+    where
+        TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
+        <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>,
+    {
+        SessionRef::Shared(self.clone()).declare_sequenced_subscriber(key_expr)
+    }
+}
@@ -182,6 +182,7 @@ impl<'a, 'b, Handler> SubscriberBuilderExt<'a, 'b, Handler>
             reliability: self.reliability,
             origin: self.origin,
             fetch,
+            recovery: None,
             handler: self.handler,
             phantom: std::marker::PhantomData,
         }
@@ -232,6 +233,7 @@ impl<'a, 'b, Handler> SubscriberBuilderExt<'a, 'b, Handler>
             query_consolidation: QueryConsolidation::from(zenoh::query::ConsolidationMode::None),
             query_accept_replies: ReplyKeyExpr::default(),
             query_timeout: Duration::from_secs(10),
+            recovery: None,
             handler: self.handler,
         }
     }
@@ -298,6 +300,7 @@ impl<'a, 'b, Handler> SubscriberBuilderExt<'a, 'b, Handler>
             reliability: Reliability::default(),
             origin: Locality::default(),
             fetch,
+            recovery: None,
             handler: self.handler,
             phantom: std::marker::PhantomData,
         }
@@ -347,6 +350,7 @@ impl<'a, 'b, Handler> SubscriberBuilderExt<'a, 'b, Handler>
             query_consolidation: QueryConsolidation::default(),
             query_accept_replies: ReplyKeyExpr::MatchingQuery,
             query_timeout: Duration::from_secs(10),
+            recovery: None,
             handler: self.handler,
         }
     }
@@ -0,0 +1,101 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use std::sync::Arc;
+use std::time::Duration;
+use zenoh::prelude::r#async::*;
+use zenoh::Session;
+use zenoh_core::ztimeout;
+use zenoh_ext::group::{Group, GroupEvent, Member};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+const SLEEP: Duration = Duration::from_millis(500);
+const LEASE: Duration = Duration::from_secs(2);
+
+async fn join(session: &Arc<Session>, mid: &str) -> Group {
+    let member = Member::new(mid).unwrap().lease(LEASE);
+    ztimeout!(Group::join(session.clone(), "test/election", member)).unwrap()
+}
+
+// Waits for the election of the given leader to be notified to a member, returns the term of the latest one
+async fn notified_term(events: &flume::Receiver<GroupEvent>, leader: &str) -> u64 {
+    ztimeout!(async {
+        let mut term = None;
+        loop {
+            for event in events.try_iter() {
+                if let GroupEvent::NewLeader(e) = event {
+                    if e.mid.as_str() == leader {
+                        term = Some(e.term);
+                    }
+                }
+            }
+            if let Some(term) = term {
+                break term;
+            }
+            tokio::time::sleep(SLEEP).await;
+        }
+    })
+}
+
+// Waits for all the given members to follow the given leader
+async fn wait_leader(groups: &[&Group], leader: &str) {
+    ztimeout!(async {
+        loop {
+            let mut elected = true;
+            for group in groups {
+                let member = group.elected_leader().await;
+                elected &= member.is_some_and(|m| m.id().as_str() == leader);
+            }
+            if elected {
+                break;
+            }
+            tokio::time::sleep(SLEEP).await;
+        }
+    });
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn group_election() {
+    let mut config = config::peer();
+    config.scouting.multicast.set_enabled(Some(false)).unwrap();
+    let session = ztimeout!(zenoh::open(config).res_async())
+        .unwrap()
+        .into_arc();
+    let a = join(&session, "a").await;
+    let events = a.subscribe().await;
+    let b = join(&session, "b").await;
+    let c = join(&session, "c").await;
+
+    // the member with the highest id is elected
+    wait_leader(&[&a, &b, &c], "c").await;
+    let term = c.fencing_token().await.unwrap();
+    assert!(!a.is_leader().await && !b.is_leader().await);
+    assert_eq!(c.leader().await.id().as_str(), "c");
+    assert_eq!(notified_term(&events, "c").await, term);
+
+    // its lease expires: a new leader is elected in a later term
+    drop(c);
+    wait_leader(&[&a, &b], "b").await;
+    assert!(b.fencing_token().await.unwrap() > term);
+    assert_eq!(
+        Some(notified_term(&events, "b").await),
+        b.fencing_token().await
+    );
+    assert!(a.fencing_token().await.is_none());
+
+    // a member with a higher id joining does not disrupt the live leader
+    let d = join(&session, "d").await;
+    tokio::time::sleep(LEASE * 3).await;
+    wait_leader(&[&a, &b, &d], "b").await;
+    assert!(!d.is_leader().await);
+}
@@ -0,0 +1,114 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use std::sync::Arc;
+use std::time::Duration;
+use zenoh::prelude::r#async::*;
+use zenoh::Session;
+use zenoh_core::ztimeout;
+use zenoh_ext::lock::DistributedLock;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+const SLEEP: Duration = Duration::from_secs(1);
+const ACQUIRE: Duration = Duration::from_secs(10);
+
+async fn open_sessions(port: u16) -> (Arc<Session>, Arc<Session>) {
+    let endpoint = format!("tcp/localhost:{port}");
+    let mut c1 = config::peer();
+    c1.listen
+        .set_endpoints(vec![endpoint.parse().unwrap()])
+        .unwrap();
+    c1.scouting.multicast.set_enabled(Some(false)).unwrap();
+    let session1 = ztimeout!(zenoh::open(c1).res_async()).unwrap().into_arc();
+    let mut c2 = config::peer();
+    c2.connect
+        .set_endpoints(vec![endpoint.parse().unwrap()])
+        .unwrap();
+    c2.scouting.multicast.set_enabled(Some(false)).unwrap();
+    let session2 = ztimeout!(zenoh::open(c2).res_async()).unwrap().into_arc();
+    tokio::time::sleep(SLEEP).await;
+    (session1, session2)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn lock_mutual_exclusion() {
+    let (session1, session2) = open_sessions(47460).await;
+    let lock1 = ztimeout!(DistributedLock::new(session1, "test/lock/exclusion")).unwrap();
+    let lock2 = ztimeout!(DistributedLock::new(session2, "test/lock/exclusion")).unwrap();
+    tokio::time::sleep(SLEEP).await;
+
+    let guard1 = ztimeout!(lock1.acquire(ACQUIRE)).unwrap();
+    assert!(lock1.is_held());
+    assert!(ztimeout!(lock2.try_acquire()).unwrap().is_none());
+    assert!(ztimeout!(lock2.acquire(SLEEP)).is_err());
+    assert!(!lock2.is_held());
+
+    let token1 = guard1.fencing_token();
+    drop(guard1);
+    assert!(!lock1.is_held());
+
+    let guard2 = ztimeout!(lock2.acquire(ACQUIRE)).unwrap();
+    assert!(lock2.is_held());
+    assert!(guard2.fencing_token() > token1);
+    assert!(ztimeout!(lock1.try_acquire()).unwrap().is_none());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn lock_concurrent_acquisitions() {
+    let (session1, session2) = open_sessions(47461).await;
+    let lock1 = ztimeout!(DistributedLock::new(session1, "test/lock/concurrent")).unwrap();
+    let lock2 = ztimeout!(DistributedLock::new(session2, "test/lock/concurrent")).unwrap();
+    tokio::time::sleep(SLEEP).await;
+
+    let (res1, res2) = ztimeout!(async { tokio::join!(lock1.try_acquire(), lock2.try_acquire()) });
+    let (guard1, guard2) = (res1.unwrap(), res2.unwrap());
+    assert!(guard1.is_none() || guard2.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn lock_released_on_connectivity_loss() {
+    // the holder is a client attached to router2, itself connected to router1
+    let mut c1 = config::default();
+    c1.set_mode(Some(WhatAmI::Router)).unwrap();
+    c1.listen
+        .set_endpoints(vec!["tcp/localhost:47462".parse().unwrap()])
+        .unwrap();
+    c1.scouting.multicast.set_enabled(Some(false)).unwrap();
+    let router1 = ztimeout!(zenoh::open(c1).res_async()).unwrap().into_arc();
+    let mut c2 = config::default();
+    c2.set_mode(Some(WhatAmI::Router)).unwrap();
+    c2.listen
+        .set_endpoints(vec!["tcp/localhost:47463".parse().unwrap()])
+        .unwrap();
+    c2.connect
+        .set_endpoints(vec!["tcp/localhost:47462".parse().unwrap()])
+        .unwrap();
+    c2.scouting.multicast.set_enabled(Some(false)).unwrap();
+    let router2 = ztimeout!(zenoh::open(c2).res_async()).unwrap();
+    let mut c3 = config::client(["tcp/localhost:47463".parse::<EndPoint>().unwrap()]);
+    c3.scouting.multicast.set_enabled(Some(false)).unwrap();
+    let client = ztimeout!(zenoh::open(c3).res_async()).unwrap().into_arc();
+    tokio::time::sleep(SLEEP).await;
+
+    let lock1 = ztimeout!(DistributedLock::new(client, "test/lock/connectivity")).unwrap();
+    let lock2 = ztimeout!(DistributedLock::new(router1, "test/lock/connectivity")).unwrap();
+    tokio::time::sleep(SLEEP).await;
+
+    let guard1 = ztimeout!(lock1.acquire(ACQUIRE)).unwrap();
+    assert!(ztimeout!(lock2.try_acquire()).unwrap().is_none());
+
+    ztimeout!(router2.close().res_async()).unwrap();
+
+    let guard2 = ztimeout!(lock2.acquire(ACQUIRE)).unwrap();
+    assert!(guard2.fencing_token() > guard1.fencing_token());
+}
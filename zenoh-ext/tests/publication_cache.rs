@@ -0,0 +1,249 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use zenoh::config::ModeDependentValue;
+use zenoh::prelude::r#async::*;
+use zenoh::Session;
+use zenoh_core::ztimeout;
+use zenoh_ext::SessionExt;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+const SLEEP: Duration = Duration::from_millis(500);
+
+async fn open_session() -> Session {
+    let mut config = config::peer();
+    config.scouting.multicast.set_enabled(Some(false)).unwrap();
+    config
+        .timestamping
+        .set_enabled(Some(ModeDependentValue::Unique(true)))
+        .unwrap();
+    ztimeout!(zenoh::open(config).res_async()).unwrap()
+}
+
+fn log_path(name: &str) -> PathBuf {
+    let mut path = std::env::temp_dir();
+    path.push(format!("zenoh_pub_cache_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+// Returns the payloads of the replies to a query, sorted
+async fn cached(session: &Session, selector: &str) -> Vec<String> {
+    let replies = ztimeout!(session
+        .get(selector)
+        .consolidation(ConsolidationMode::None)
+        .res_async())
+    .unwrap();
+    let mut payloads = vec![];
+    while let Ok(reply) = ztimeout!(replies.recv_async()) {
+        let sample = reply.sample.unwrap();
+        payloads.push(String::from_utf8(sample.payload.contiguous().into_owned()).unwrap());
+    }
+    payloads.sort();
+    payloads
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn pub_cache_retention() {
+    let session = open_session().await;
+    let _cache = ztimeout!(session
+        .declare_publication_cache("test/pub_cache/retention/*")
+        .history(usize::MAX)
+        .retention(Duration::from_secs(1))
+        .res_async())
+    .unwrap();
+
+    ztimeout!(session.put("test/pub_cache/retention/a", "1").res_async()).unwrap();
+    ztimeout!(session.put("test/pub_cache/retention/a", "2").res_async()).unwrap();
+    tokio::time::sleep(SLEEP).await;
+    assert_eq!(
+        cached(&session, "test/pub_cache/retention/*").await,
+        ["1", "2"]
+    );
+
+    // the samples older than the retention are not replied anymore
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    ztimeout!(session.put("test/pub_cache/retention/b", "3").res_async()).unwrap();
+    tokio::time::sleep(SLEEP).await;
+    assert_eq!(cached(&session, "test/pub_cache/retention/*").await, ["3"]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn pub_cache_persistence() {
+    let path = log_path("persistence");
+    let session = open_session().await;
+    let cache = ztimeout!(session
+        .declare_publication_cache("test/pub_cache/persistence/*")
+        .history(2)
+        .persistence(&path)
+        .res_async())
+    .unwrap();
+    for value in ["1", "2", "3"] {
+        ztimeout!(session
+            .put("test/pub_cache/persistence/a", value)
+            .res_async())
+        .unwrap();
+    }
+    ztimeout!(session.put("test/pub_cache/persistence/b", "4").res_async()).unwrap();
+    tokio::time::sleep(SLEEP).await;
+    ztimeout!(cache.close().res_async()).unwrap();
+    ztimeout!(session.close().res_async()).unwrap();
+
+    // the cache is restored by a new session, within its history
+    let session = open_session().await;
+    let _cache = ztimeout!(session
+        .declare_publication_cache("test/pub_cache/persistence/*")
+        .history(2)
+        .persistence(&path)
+        .res_async())
+    .unwrap();
+    assert_eq!(
+        cached(&session, "test/pub_cache/persistence/*").await,
+        ["2", "3", "4"]
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn pub_cache_truncated_record() {
+    let path = log_path("truncated");
+    let session = open_session().await;
+    let cache = ztimeout!(session
+        .declare_publication_cache("test/pub_cache/truncated/*")
+        .history(usize::MAX)
+        .persistence(&path)
+        .res_async())
+    .unwrap();
+    ztimeout!(session.put("test/pub_cache/truncated/a", "1").res_async()).unwrap();
+    tokio::time::sleep(SLEEP).await;
+    ztimeout!(cache.close().res_async()).unwrap();
+
+    // a record truncated by a crash while it was written
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    file.write_all(&[100, 0, 0, 0, 1, 2, 3]).unwrap();
+    drop(file);
+
+    // the valid records are restored, and the next ones are not lost behind the truncated one
+    let cache = ztimeout!(session
+        .declare_publication_cache("test/pub_cache/truncated/*")
+        .history(usize::MAX)
+        .persistence(&path)
+        .res_async())
+    .unwrap();
+    assert_eq!(cached(&session, "test/pub_cache/truncated/*").await, ["1"]);
+    ztimeout!(session.put("test/pub_cache/truncated/a", "2").res_async()).unwrap();
+    tokio::time::sleep(SLEEP).await;
+    ztimeout!(cache.close().res_async()).unwrap();
+
+    let _cache = ztimeout!(session
+        .declare_publication_cache("test/pub_cache/truncated/*")
+        .history(usize::MAX)
+        .persistence(&path)
+        .res_async())
+    .unwrap();
+    assert_eq!(
+        cached(&session, "test/pub_cache/truncated/*").await,
+        ["1", "2"]
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn pub_cache_compaction() {
+    const MAX_SIZE: usize = 4 * 1024;
+
+    let path = log_path("compaction");
+    let session = open_session().await;
+    let cache = ztimeout!(session
+        .declare_publication_cache("test/pub_cache/compaction/*")
+        .history(1)
+        .persistence(&path)
+        .persistence_max_size(MAX_SIZE)
+        .res_async())
+    .unwrap();
+    for i in 0..200 {
+        let value = format!("{i:0>100}");
+        ztimeout!(session
+            .put("test/pub_cache/compaction/a", value)
+            .res_async())
+        .unwrap();
+    }
+    tokio::time::sleep(SLEEP).await;
+    ztimeout!(cache.close().res_async()).unwrap();
+
+    // the file is rewritten with the content of the cache when it exceeds its maximum size
+    let size = std::fs::metadata(&path).unwrap().len() as usize;
+    assert!(size <= MAX_SIZE, "{size} > {MAX_SIZE}");
+
+    let _cache = ztimeout!(session
+        .declare_publication_cache("test/pub_cache/compaction/*")
+        .history(1)
+        .persistence(&path)
+        .persistence_max_size(MAX_SIZE)
+        .res_async())
+    .unwrap();
+    assert_eq!(
+        cached(&session, "test/pub_cache/compaction/*").await,
+        [format!("{:0>100}", 199)]
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn pub_cache_compaction_oversized() {
+    const MAX_SIZE: usize = 4 * 1024;
+
+    let path = log_path("oversized");
+    let session = open_session().await;
+    let cache = ztimeout!(session
+        .declare_publication_cache("test/pub_cache/oversized/*")
+        .history(1)
+        .persistence(&path)
+        .persistence_max_size(MAX_SIZE)
+        .res_async())
+    .unwrap();
+    let large = "x".repeat(MAX_SIZE * 3 / 4);
+    ztimeout!(session
+        .put("test/pub_cache/oversized/large", large.clone())
+        .res_async())
+    .unwrap();
+    for i in 0..100 {
+        let value = format!("{i:0>100}");
+        ztimeout!(session
+            .put("test/pub_cache/oversized/small", value)
+            .res_async())
+        .unwrap();
+    }
+    tokio::time::sleep(SLEEP).await;
+    ztimeout!(cache.close().res_async()).unwrap();
+
+    // the newest sample of each key is kept, even larger than half of the maximum size
+    let _cache = ztimeout!(session
+        .declare_publication_cache("test/pub_cache/oversized/*")
+        .history(1)
+        .persistence(&path)
+        .persistence_max_size(MAX_SIZE)
+        .res_async())
+    .unwrap();
+    assert_eq!(
+        cached(&session, "test/pub_cache/oversized/*").await,
+        [format!("{:0>100}", 99), large]
+    );
+    let _ = std::fs::remove_file(&path);
+}
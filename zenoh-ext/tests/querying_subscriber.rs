@@ -0,0 +1,107 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use std::time::Duration;
+use zenoh::config::{Config, ModeDependentValue};
+use zenoh::prelude::r#async::*;
+use zenoh::Session;
+use zenoh_core::ztimeout;
+use zenoh_ext::{SessionExt, SubscriberBuilderExt};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+const SLEEP: Duration = Duration::from_millis(500);
+const RECONNECTION: Duration = Duration::from_secs(5);
+
+fn timestamped(mut config: Config) -> Config {
+    config.scouting.multicast.set_enabled(Some(false)).unwrap();
+    config
+        .timestamping
+        .set_enabled(Some(ModeDependentValue::Unique(true)))
+        .unwrap();
+    config
+}
+
+// A router publishing on `test/recovery/a`, whose publication cache is persisted in `path`
+async fn open_router(zid: ZenohId, endpoint: &str) -> Session {
+    let mut config = timestamped(config::default());
+    config.set_mode(Some(WhatAmI::Router)).unwrap();
+    config.set_id(zid).unwrap();
+    config
+        .listen
+        .set_endpoints(vec![endpoint.parse().unwrap()])
+        .unwrap();
+    ztimeout!(zenoh::open(config).res_async()).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn querying_subscriber_recovery_on_reconnection() {
+    let endpoint = "tcp/localhost:47490";
+    let zid = ZenohId::rand();
+    let mut path = std::env::temp_dir();
+    path.push(format!("zenoh_query_sub_recovery_{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let router = open_router(zid, endpoint).await;
+    let cache = ztimeout!(router
+        .declare_publication_cache("test/recovery/a")
+        .history(usize::MAX)
+        .persistence(&path)
+        .res_async())
+    .unwrap();
+    let client = timestamped(config::client([endpoint.parse::<EndPoint>().unwrap()]));
+    let client = ztimeout!(zenoh::open(client).res_async())
+        .unwrap()
+        .into_arc();
+    let subscriber = ztimeout!(client
+        .declare_subscriber("test/recovery/a")
+        .querying()
+        .recovery(true)
+        .res_async())
+    .unwrap();
+    tokio::time::sleep(SLEEP).await;
+
+    ztimeout!(router.put("test/recovery/a", "1").res_async()).unwrap();
+    let sample = ztimeout!(subscriber.recv_async()).unwrap();
+    assert_eq!(sample.value.to_string(), "1");
+    tokio::time::sleep(SLEEP).await;
+    ztimeout!(cache.close().res_async()).unwrap();
+    ztimeout!(router.close().res_async()).unwrap();
+
+    // a publication missed by the client while the router is down
+    let isolated = timestamped(config::peer());
+    let isolated = ztimeout!(zenoh::open(isolated).res_async()).unwrap();
+    let cache = ztimeout!(isolated
+        .declare_publication_cache("test/recovery/a")
+        .history(usize::MAX)
+        .persistence(&path)
+        .res_async())
+    .unwrap();
+    ztimeout!(isolated.put("test/recovery/a", "2").res_async()).unwrap();
+    tokio::time::sleep(SLEEP).await;
+    ztimeout!(cache.close().res_async()).unwrap();
+    ztimeout!(isolated.close().res_async()).unwrap();
+
+    // it is recovered when the client reconnects to the router, without the one already received
+    let router = open_router(zid, endpoint).await;
+    let _cache = ztimeout!(router
+        .declare_publication_cache("test/recovery/a")
+        .history(usize::MAX)
+        .persistence(&path)
+        .res_async())
+    .unwrap();
+    let sample = ztimeout!(subscriber.recv_async()).unwrap();
+    assert_eq!(sample.value.to_string(), "2");
+    tokio::time::sleep(RECONNECTION).await;
+    assert!(subscriber.try_recv().is_err());
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,190 @@
+//
+// Copyright (c) 2024 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use zenoh::prelude::r#async::*;
+use zenoh::Session;
+use zenoh_core::ztimeout;
+use zenoh_ext::rpc::{RpcClient, RpcError};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+const SLEEP: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum CalcError {
+    DivisionByZero,
+}
+
+#[zenoh_ext::rpc::service]
+trait Calculator {
+    /// Divides `a` by `b`.
+    async fn div(&self, a: i64, b: i64) -> Result<i64, CalcError>;
+
+    /// Returns the name of the instance.
+    async fn name(&self) -> Result<String, CalcError>;
+
+    /// Never returns.
+    async fn hang(&self) -> Result<(), CalcError>;
+}
+
+struct Calc {
+    name: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+// Sets its flag when the method is dropped before its completion
+struct Cancelled(Arc<AtomicBool>);
+
+impl Drop for Cancelled {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[zenoh_ext::rpc::async_trait]
+impl Calculator for Calc {
+    async fn div(&self, a: i64, b: i64) -> Result<i64, CalcError> {
+        a.checked_div(b).ok_or(CalcError::DivisionByZero)
+    }
+
+    async fn name(&self) -> Result<String, CalcError> {
+        Ok(self.name.clone())
+    }
+
+    async fn hang(&self) -> Result<(), CalcError> {
+        let _cancelled = Cancelled(self.cancelled.clone());
+        std::future::pending().await
+    }
+}
+
+fn new_calc(name: &str) -> (Calc, Arc<AtomicBool>) {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let calc = Calc {
+        name: name.to_string(),
+        cancelled: cancelled.clone(),
+    };
+    (calc, cancelled)
+}
+
+async fn open_sessions(port: u16) -> (Arc<Session>, Arc<Session>) {
+    let endpoint = format!("tcp/localhost:{port}");
+    let mut c1 = config::peer();
+    c1.listen
+        .set_endpoints(vec![endpoint.parse().unwrap()])
+        .unwrap();
+    c1.scouting.multicast.set_enabled(Some(false)).unwrap();
+    let session1 = ztimeout!(zenoh::open(c1).res_async()).unwrap().into_arc();
+    let mut c2 = config::peer();
+    c2.connect
+        .set_endpoints(vec![endpoint.parse().unwrap()])
+        .unwrap();
+    c2.scouting.multicast.set_enabled(Some(false)).unwrap();
+    let session2 = ztimeout!(zenoh::open(c2).res_async()).unwrap().into_arc();
+    tokio::time::sleep(SLEEP).await;
+    (session1, session2)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn rpc_calls() {
+    let (session1, session2) = open_sessions(47480).await;
+    let client = ztimeout!(CalculatorClient::new(session2.clone(), "test/rpc/calls")).unwrap();
+    assert!(matches!(
+        ztimeout!(client.div(6, 3)),
+        Err(RpcError::Unavailable)
+    ));
+
+    let (calc, _) = new_calc("calc");
+    let _server = ztimeout!(calc.into_rpc_server(session1, "test/rpc/calls").declare()).unwrap();
+    tokio::time::sleep(SLEEP).await;
+
+    assert_eq!(ztimeout!(client.div(6, 3)).unwrap(), 2);
+    assert!(matches!(
+        ztimeout!(client.div(1, 0)),
+        Err(RpcError::Service(CalcError::DivisionByZero))
+    ));
+
+    // the failures of the calls are not service errors
+    let untyped = ztimeout!(RpcClient::new(session2, "test/rpc/calls")).unwrap();
+    assert!(matches!(
+        ztimeout!(untyped.call::<_, i64, CalcError>("mul", &(6i64, 3i64))),
+        Err(RpcError::Failed(_))
+    ));
+    assert!(matches!(
+        ztimeout!(untyped.call::<_, i64, CalcError>("div", &1i64)),
+        Err(RpcError::Failed(_))
+    ));
+    assert!(matches!(
+        ztimeout!(untyped.call::<_, i64, CalcError>("*", &())),
+        Err(RpcError::Failed(_))
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn rpc_load_balancing() {
+    let (session1, session2) = open_sessions(47481).await;
+    let (calc1, _) = new_calc("calc1");
+    let (calc2, _) = new_calc("calc2");
+    let server1 = ztimeout!(calc1
+        .into_rpc_server(session1.clone(), "test/rpc/balancing")
+        .declare())
+    .unwrap();
+    let _server2 = ztimeout!(calc2
+        .into_rpc_server(session1, "test/rpc/balancing")
+        .declare())
+    .unwrap();
+    tokio::time::sleep(SLEEP).await;
+
+    let client = ztimeout!(CalculatorClient::new(session2, "test/rpc/balancing")).unwrap();
+    assert_eq!(client.client().instances().len(), 2);
+    let mut names = HashSet::new();
+    for _ in 0..4 {
+        names.insert(ztimeout!(client.name()).unwrap());
+    }
+    assert_eq!(
+        names,
+        HashSet::from(["calc1".to_string(), "calc2".to_string()])
+    );
+
+    // the instances that are gone are no longer called
+    drop(server1);
+    tokio::time::sleep(SLEEP).await;
+    assert_eq!(client.client().instances().len(), 1);
+    for _ in 0..2 {
+        assert_eq!(ztimeout!(client.name()).unwrap(), "calc2");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn rpc_deadline() {
+    let (session1, session2) = open_sessions(47482).await;
+    let (calc, cancelled) = new_calc("calc");
+    let _server = ztimeout!(calc
+        .into_rpc_server(session1, "test/rpc/deadline")
+        .declare())
+    .unwrap();
+    tokio::time::sleep(SLEEP).await;
+
+    let client = ztimeout!(CalculatorClient::new(session2, "test/rpc/deadline"))
+        .unwrap()
+        .with_timeout(Duration::from_millis(500));
+    assert!(matches!(ztimeout!(client.hang()), Err(RpcError::Timeout)));
+
+    // the call is cancelled on the server
+    tokio::time::sleep(SLEEP).await;
+    assert!(cancelled.load(Ordering::SeqCst));
+    assert_eq!(ztimeout!(client.div(6, 3)).unwrap(), 2);
+}
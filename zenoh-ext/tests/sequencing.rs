@@ -0,0 +1,63 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use std::time::Duration;
+use zenoh::prelude::r#async::*;
+use zenoh::sample::Attachment;
+use zenoh_core::ztimeout;
+use zenoh_ext::SequencingSessionExt;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+const SLEEP: Duration = Duration::from_millis(500);
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn sequenced_publication_attachment() {
+    let mut config = config::peer();
+    config.scouting.multicast.set_enabled(Some(false)).unwrap();
+    let session = ztimeout!(zenoh::open(config).res_async()).unwrap();
+    let subscriber = ztimeout!(session
+        .declare_sequenced_subscriber("test/sequencing/attachment")
+        .res_async())
+    .unwrap();
+    let publisher = ztimeout!(session
+        .declare_sequenced_publisher("test/sequencing/attachment")
+        .res_async())
+    .unwrap();
+    tokio::time::sleep(SLEEP).await;
+
+    // a publication that is not resolved does not consume a sequence number
+    drop(publisher.put("0"));
+    assert_eq!(publisher.next_sn(), 0);
+
+    let mut attachment = Attachment::new();
+    attachment.insert("user", "entry");
+    ztimeout!(publisher.put("1").with_attachment(attachment).res_async()).unwrap();
+    ztimeout!(publisher.put("2").res_async()).unwrap();
+
+    // the attachment of the user is kept along with the sequence number
+    let sample = ztimeout!(subscriber.recv_async()).unwrap();
+    assert_eq!(
+        sample
+            .attachment()
+            .unwrap()
+            .get(&"user")
+            .unwrap()
+            .as_slice(),
+        b"entry"
+    );
+    let sample = ztimeout!(subscriber.recv_async()).unwrap();
+    assert!(sample.attachment().unwrap().get(&"user").is_none());
+    assert_eq!(publisher.next_sn(), 2);
+    let stats = subscriber.stats();
+    assert_eq!((stats.sources, stats.received, stats.lost), (1, 2, 0));
+}
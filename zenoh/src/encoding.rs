@@ -0,0 +1,272 @@
+//
+// Copyright (c) 2024 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+//! Registry of encodings beyond the fixed [`KnownEncoding`] table.
+//!
+//! A [`RegisteredEncoding`] associates a MIME type with a compact numeric identifier. It is carried as an
+//! [`Encoding`] with the `application/custom` prefix and a `#<id>` suffix, optionally followed by
+//! `;<schema>` to reference the schema of the payload (e.g. the name of a protobuf message).
+//! Such encodings are thus valid for the peers that are unaware of the registry, which only see
+//! their string representation (e.g. `application/custom#2;my.package.Message`).
+//!
+//! The identifiers below [`FIRST_USER_ID`] are reserved for the encodings registered by zenoh
+//! (e.g. [`CBOR`], [`PROTOBUF`]). The applications must register the same identifiers for the same
+//! MIME types on all the peers.
+//!
+//! # Examples
+//! ```
+//! use zenoh::encoding::{self, EncodingExt, PROTOBUF};
+//!
+//! let encoding = PROTOBUF.with_schema("my.package.Message").unwrap();
+//! assert_eq!(encoding.registered(), Some(PROTOBUF));
+//! assert_eq!(encoding.schema(), Some("my.package.Message"));
+//! assert_eq!(encoding.mime(), "application/protobuf;my.package.Message");
+//!
+//! let flatbuffers = encoding::register(1024, "application/x-flatbuffers").unwrap();
+//! assert_eq!(encoding::from_mime("application/x-flatbuffers"), flatbuffers.encoding());
+//! ```
+use crate::prelude::{Encoding, KnownEncoding};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use zenoh_core::{zread, zwrite};
+use zenoh_result::{bail, ZResult};
+
+const ID_SEPARATOR: char = '#';
+const SCHEMA_SEPARATOR: char = ';';
+
+/// The first identifier available for the encodings registered by the applications.
+pub const FIRST_USER_ID: u16 = 256;
+
+/// An encoding registered with a compact numeric identifier, see the [module](self) documentation.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RegisteredEncoding {
+    id: u16,
+    mime: Cow<'static, str>,
+}
+
+/// CBOR ([RFC 8949](https://www.rfc-editor.org/rfc/rfc8949)).
+pub const CBOR: RegisteredEncoding = RegisteredEncoding::builtin(1, "application/cbor");
+/// Protocol Buffers, the schema being the fully qualified name of the message.
+pub const PROTOBUF: RegisteredEncoding = RegisteredEncoding::builtin(2, "application/protobuf");
+/// OMG CDR, as used by ROS 2, the schema being the name of the message type.
+pub const CDR: RegisteredEncoding = RegisteredEncoding::builtin(3, "application/cdr");
+/// Apache Arrow IPC streaming format.
+pub const ARROW_IPC: RegisteredEncoding =
+    RegisteredEncoding::builtin(4, "application/vnd.apache.arrow.stream");
+/// [bincode](https://docs.rs/bincode).
+pub const BINCODE: RegisteredEncoding = RegisteredEncoding::builtin(5, "application/x-bincode");
+
+const BUILTINS: [RegisteredEncoding; 5] = [CBOR, PROTOBUF, CDR, ARROW_IPC, BINCODE];
+
+impl RegisteredEncoding {
+    const fn builtin(id: u16, mime: &'static str) -> Self {
+        RegisteredEncoding {
+            id,
+            mime: Cow::Borrowed(mime),
+        }
+    }
+
+    /// Returns the identifier of this encoding.
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// Returns the MIME type of this encoding.
+    pub fn mime(&self) -> &str {
+        &self.mime
+    }
+
+    /// Returns the compact [`Encoding`] of this encoding, without schema.
+    pub fn encoding(&self) -> Encoding {
+        Encoding::WithSuffix(
+            KnownEncoding::AppCustom,
+            format!("{ID_SEPARATOR}{}", self.id).into(),
+        )
+    }
+
+    /// Returns the compact [`Encoding`] of this encoding, with the given schema.
+    ///
+    /// Fails if the resulting suffix exceeds the 255 bytes allowed by [`Encoding`].
+    pub fn with_schema(&self, schema: &str) -> ZResult<Encoding> {
+        self.encoding()
+            .with_suffix(format!("{SCHEMA_SEPARATOR}{schema}"))
+    }
+
+    /// Returns true if the given encoding is this encoding, with or without schema,
+    /// either in its compact form or as its MIME type.
+    pub fn matches(&self, encoding: &Encoding) -> bool {
+        match parse(encoding) {
+            Some((id, _)) => id == self.id,
+            None => {
+                let mime = encoding.to_string();
+                match mime.strip_prefix(self.mime()) {
+                    Some(rest) => rest.is_empty() || rest.starts_with(SCHEMA_SEPARATOR),
+                    None => false,
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for RegisteredEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.mime())
+    }
+}
+
+struct Registry {
+    by_id: HashMap<u16, RegisteredEncoding>,
+    by_mime: HashMap<Cow<'static, str>, u16>,
+}
+
+impl Registry {
+    fn insert(&mut self, encoding: RegisteredEncoding) {
+        self.by_mime.insert(encoding.mime.clone(), encoding.id);
+        self.by_id.insert(encoding.id, encoding);
+    }
+}
+
+lazy_static::lazy_static!(
+    static ref REGISTRY: RwLock<Registry> = {
+        let mut registry = Registry {
+            by_id: HashMap::new(),
+            by_mime: HashMap::new(),
+        };
+        for encoding in BUILTINS {
+            registry.insert(encoding);
+        }
+        RwLock::new(registry)
+    };
+);
+
+/// Registers an encoding with the given identifier and MIME type.
+///
+/// Registering again the same identifier with the same MIME type returns the existing encoding.
+/// Fails if the identifier is reserved ([`FIRST_USER_ID`]) or registered for another MIME type,
+/// if the MIME type is registered with another identifier, or if it is empty or contains a `;`.
+pub fn register<S>(id: u16, mime: S) -> ZResult<RegisteredEncoding>
+where
+    S: Into<String>,
+{
+    let mime: String = mime.into();
+    if mime.is_empty() || mime.contains(SCHEMA_SEPARATOR) {
+        bail!("Invalid MIME type for encoding {}: '{}'", id, mime);
+    }
+    let mut registry = zwrite!(REGISTRY);
+    if let Some(existing) = registry.by_id.get(&id) {
+        if existing.mime() == mime {
+            return Ok(existing.clone());
+        }
+        bail!("Encoding {} is already registered as {}", id, existing);
+    }
+    if id < FIRST_USER_ID {
+        bail!(
+            "Encoding {} is reserved: use an id from {}",
+            id,
+            FIRST_USER_ID
+        );
+    }
+    if let Some(existing) = registry.by_mime.get(mime.as_str()) {
+        bail!(
+            "Encoding {} is already registered with id {}",
+            mime,
+            existing
+        );
+    }
+    let encoding = RegisteredEncoding {
+        id,
+        mime: mime.into(),
+    };
+    registry.insert(encoding.clone());
+    Ok(encoding)
+}
+
+/// Returns the encoding registered with the given identifier, if any.
+pub fn get(id: u16) -> Option<RegisteredEncoding> {
+    zread!(REGISTRY).by_id.get(&id).cloned()
+}
+
+/// Returns the encoding registered with the given MIME type, if any.
+pub fn get_by_mime(mime: &str) -> Option<RegisteredEncoding> {
+    let registry = zread!(REGISTRY);
+    let id = registry.by_mime.get(mime)?;
+    registry.by_id.get(id).cloned()
+}
+
+/// Converts a MIME type, optionally followed by `;<schema>`, to an [`Encoding`]:
+/// in its compact form if it is registered, or else as with `Encoding::from`.
+pub fn from_mime(mime: &str) -> Encoding {
+    let (base, schema) = match mime.split_once(SCHEMA_SEPARATOR) {
+        Some((base, schema)) => (base, Some(schema)),
+        None => (mime, None),
+    };
+    if let Some(registered) = get_by_mime(base) {
+        match schema {
+            Some(schema) => {
+                if let Ok(encoding) = registered.with_schema(schema) {
+                    return encoding;
+                }
+            }
+            None => return registered.encoding(),
+        }
+    }
+    Encoding::from(mime.to_string())
+}
+
+// Returns the identifier and the schema of an encoding in compact form
+fn parse(encoding: &Encoding) -> Option<(u16, Option<&str>)> {
+    if *encoding.prefix() != KnownEncoding::AppCustom {
+        return None;
+    }
+    let suffix = encoding.suffix().strip_prefix(ID_SEPARATOR)?;
+    let (id, schema) = match suffix.split_once(SCHEMA_SEPARATOR) {
+        Some((id, schema)) => (id, Some(schema)),
+        None => (suffix, None),
+    };
+    Some((id.parse().ok()?, schema))
+}
+
+/// Extension trait to resolve the registered encodings from an [`Encoding`].
+pub trait EncodingExt {
+    /// Returns the registered encoding of this encoding in compact form, if its identifier is registered.
+    fn registered(&self) -> Option<RegisteredEncoding>;
+
+    /// Returns the schema of this encoding in compact form, if any.
+    fn schema(&self) -> Option<&str>;
+
+    /// Returns the MIME type of this encoding, resolving its compact form if registered,
+    /// followed by `;<schema>` if it has a schema.
+    fn mime(&self) -> String;
+}
+
+impl EncodingExt for Encoding {
+    fn registered(&self) -> Option<RegisteredEncoding> {
+        parse(self).and_then(|(id, _)| get(id))
+    }
+
+    fn schema(&self) -> Option<&str> {
+        parse(self).and_then(|(_, schema)| schema)
+    }
+
+    fn mime(&self) -> String {
+        match (self.registered(), self.schema()) {
+            (Some(registered), Some(schema)) => {
+                format!("{}{SCHEMA_SEPARATOR}{}", registered.mime(), schema)
+            }
+            (Some(registered), None) => registered.mime().to_string(),
+            (None, _) => self.to_string(),
+        }
+    }
+}
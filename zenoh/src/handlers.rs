@@ -14,6 +14,11 @@
 
 //! Callback handler trait.
 use crate::API_DATA_RECEPTION_CHANNEL_SIZE;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use zenoh_result::{zerror, ZResult};
 
 /// An alias for `Arc<T>`.
 pub type Dyn<T> = std::sync::Arc<T>;
@@ -80,6 +85,175 @@ impl<T: Send + Sync + 'static> IntoCallbackReceiverPair<'static, T>
     }
 }
 
+/// A handler keeping the last `capacity` received elements, dropping the oldest one when full.
+///
+/// Unlike a bounded channel, it never blocks the callback when the receiver is too slow:
+/// the number of dropped elements is reported by [`RingBufferReceiver::dropped`].
+///
+/// # Examples
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use zenoh::handlers::RingBuffer;
+/// use zenoh::prelude::r#async::*;
+///
+/// let session = zenoh::open(config::peer()).res().await.unwrap();
+/// let subscriber = session
+///     .declare_subscriber("key/expression")
+///     .with(RingBuffer::new(16))
+///     .res()
+///     .await
+///     .unwrap();
+/// while let Ok(sample) = subscriber.recv_async().await {
+///     println!("Received: {} ({} dropped)", sample, subscriber.dropped());
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RingBuffer {
+    capacity: usize,
+}
+
+impl RingBuffer {
+    /// Creates a ring buffer handler keeping at most `capacity` elements.
+    ///
+    /// # Panics
+    /// If `capacity` is 0.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "RingBuffer capacity must be greater than 0");
+        RingBuffer { capacity }
+    }
+}
+
+struct RingBufferState<T> {
+    buffer: Mutex<VecDeque<T>>,
+    capacity: usize,
+    dropped: AtomicU64,
+}
+
+impl<T> RingBufferState<T> {
+    fn pop(&self) -> Option<T> {
+        zlock!(self.buffer).pop_front()
+    }
+}
+
+/// The receiver of a [`RingBuffer`] handler.
+///
+/// Its `recv` functions return an error once the callback is dropped (e.g. the subscriber is undeclared)
+/// and all the remaining elements have been received.
+pub struct RingBufferReceiver<T> {
+    state: Arc<RingBufferState<T>>,
+    notifier: flume::Receiver<()>,
+}
+
+impl<T> RingBufferReceiver<T> {
+    /// Receives the oldest element of the buffer, returns `None` if it is empty.
+    pub fn try_recv(&self) -> ZResult<Option<T>> {
+        match self.state.pop() {
+            Some(t) => Ok(Some(t)),
+            None if self.notifier.is_disconnected() => Err(zerror!("RingBuffer closed").into()),
+            None => Ok(None),
+        }
+    }
+
+    /// Receives the oldest element of the buffer, waiting for one if it is empty.
+    pub fn recv(&self) -> ZResult<T> {
+        loop {
+            if let Some(t) = self.state.pop() {
+                return Ok(t);
+            }
+            if self.notifier.recv().is_err() {
+                return self.disconnected();
+            }
+        }
+    }
+
+    /// Receives the oldest element of the buffer, waiting for one for at most `timeout` if it is empty.
+    pub fn recv_timeout(&self, timeout: Duration) -> ZResult<Option<T>> {
+        loop {
+            if let Some(t) = self.state.pop() {
+                return Ok(Some(t));
+            }
+            match self.notifier.recv_timeout(timeout) {
+                Ok(()) => continue,
+                Err(flume::RecvTimeoutError::Timeout) => return Ok(None),
+                Err(flume::RecvTimeoutError::Disconnected) => return self.disconnected().map(Some),
+            }
+        }
+    }
+
+    /// Receives the oldest element of the buffer, waiting asynchronously for one if it is empty.
+    pub async fn recv_async(&self) -> ZResult<T> {
+        loop {
+            if let Some(t) = self.state.pop() {
+                return Ok(t);
+            }
+            if self.notifier.recv_async().await.is_err() {
+                return self.disconnected();
+            }
+        }
+    }
+
+    /// Returns the number of elements in the buffer.
+    pub fn len(&self) -> usize {
+        zlock!(self.state.buffer).len()
+    }
+
+    /// Returns true if the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        zlock!(self.state.buffer).is_empty()
+    }
+
+    /// Returns the maximum number of elements kept in the buffer.
+    pub fn capacity(&self) -> usize {
+        self.state.capacity
+    }
+
+    /// Returns the number of elements dropped to make room for newer ones.
+    pub fn dropped(&self) -> u64 {
+        self.state.dropped.load(Ordering::Relaxed)
+    }
+
+    // An element may have been pushed between the last pop and the disconnection
+    fn disconnected(&self) -> ZResult<T> {
+        self.state
+            .pop()
+            .ok_or_else(|| zerror!("RingBuffer closed").into())
+    }
+}
+
+impl<T: Send + 'static> IntoCallbackReceiverPair<'static, T> for RingBuffer {
+    type Receiver = RingBufferReceiver<T>;
+
+    fn into_cb_receiver_pair(self) -> (Callback<'static, T>, Self::Receiver) {
+        let state = Arc::new(RingBufferState {
+            buffer: Mutex::new(VecDeque::with_capacity(self.capacity)),
+            capacity: self.capacity,
+            dropped: AtomicU64::new(0),
+        });
+        // Holds at most one pending notification: the receiver checks the buffer each time it is woken up
+        let (sender, notifier) = flume::bounded(1);
+        let receiver = RingBufferReceiver {
+            state: state.clone(),
+            notifier,
+        };
+        (
+            Dyn::new(move |t| {
+                {
+                    let mut buffer = zlock!(state.buffer);
+                    if buffer.len() >= state.capacity {
+                        buffer.pop_front();
+                        state.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    buffer.push_back(t);
+                }
+                let _ = sender.try_send(());
+            }),
+            receiver,
+        )
+    }
+}
+
 /// A function that can transform a [`FnMut`]`(T)` to
 /// a [`Fn`]`(T)` with the help of a [`Mutex`](std::sync::Mutex).
 pub fn locked<T>(fnmut: impl FnMut(T)) -> impl Fn(T) {
@@ -139,6 +139,7 @@ pub use net::runtime;
 pub mod selector;
 #[deprecated = "This module is now a separate crate. Use the crate directly for shorter compile-times"]
 pub use zenoh_config as config;
+pub mod encoding;
 pub mod handlers;
 pub mod info;
 #[cfg(feature = "unstable")]
@@ -147,9 +148,12 @@ pub mod liveliness;
 pub mod plugins;
 pub mod prelude;
 pub mod publication;
+#[cfg(feature = "unstable")]
+pub mod querier;
 pub mod query;
 pub mod queryable;
 pub mod sample;
+pub mod serialization;
 pub mod subscriber;
 pub mod value;
 #[cfg(feature = "shared-memory")]
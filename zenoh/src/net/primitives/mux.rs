@@ -18,11 +18,21 @@ use crate::net::routing::{
     RoutingContext,
 };
 use std::sync::OnceLock;
-use zenoh_protocol::network::{
-    Declare, NetworkBody, NetworkMessage, Push, Request, Response, ResponseFinal,
+use zenoh_protocol::{
+    network::{Declare, NetworkBody, NetworkMessage, Push, Request, Response, ResponseFinal},
+    transport::init,
 };
 use zenoh_transport::{multicast::TransportMulticast, unicast::TransportUnicast};
 
+// The Cancel extension being mandatory, the nodes that did not negotiate the query cancellation
+// would close the session on it: the cancellation is not propagated to them
+fn is_supported(msg: &ResponseFinal, transport: &TransportUnicast) -> bool {
+    msg.ext_cancel.is_none()
+        || transport
+            .get_patch()
+            .map_or(false, |patch| patch >= init::ext::PATCH_QUERY_CANCELLATION)
+}
+
 pub struct Mux {
     pub handler: TransportUnicast,
     pub(crate) face: OnceLock<WeakFace>,
@@ -137,6 +147,9 @@ impl Primitives for Mux {
     }
 
     fn send_response_final(&self, msg: ResponseFinal) {
+        if !is_supported(&msg, &self.handler) {
+            return;
+        }
         let msg = NetworkMessage {
             body: NetworkBody::ResponseFinal(msg),
             #[cfg(feature = "stats")]
@@ -266,6 +279,9 @@ impl EPrimitives for Mux {
     }
 
     fn send_response_final(&self, ctx: RoutingContext<ResponseFinal>) {
+        if !is_supported(&ctx.msg, &self.handler) {
+            return;
+        }
         let ctx = RoutingContext {
             msg: NetworkMessage {
                 body: NetworkBody::ResponseFinal(ctx.msg),
@@ -409,6 +425,10 @@ impl Primitives for McastMux {
     }
 
     fn send_response_final(&self, msg: ResponseFinal) {
+        // the query cancellation is not negotiated on multicast transports
+        if msg.ext_cancel.is_some() {
+            return;
+        }
         let msg = NetworkMessage {
             body: NetworkBody::ResponseFinal(msg),
             #[cfg(feature = "stats")]
@@ -538,6 +558,10 @@ impl EPrimitives for McastMux {
     }
 
     fn send_response_final(&self, ctx: RoutingContext<ResponseFinal>) {
+        // the query cancellation is not negotiated on multicast transports
+        if ctx.msg.ext_cancel.is_some() {
+            return;
+        }
         let ctx = RoutingContext {
             msg: NetworkMessage {
                 body: NetworkBody::ResponseFinal(ctx.msg),
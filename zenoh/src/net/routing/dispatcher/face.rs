@@ -290,7 +290,11 @@ impl Primitives for Face {
     }
 
     fn send_response_final(&self, msg: ResponseFinal) {
-        route_send_response_final(&self.tables, &mut self.state.clone(), msg.rid);
+        if msg.ext_cancel.is_some() {
+            route_cancel_query(&self.tables, &self.state, msg.rid);
+        } else {
+            route_send_response_final(&self.tables, &mut self.state.clone(), msg.rid);
+        }
     }
 
     fn send_close(&self) {
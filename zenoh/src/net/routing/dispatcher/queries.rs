@@ -462,6 +462,22 @@ pub(crate) fn disable_matches_query_routes(_tables: &mut Tables, res: &mut Arc<R
     }
 }
 
+#[zenoh_macros::unstable]
+#[inline]
+pub(crate) fn get_local_query_route(
+    tables: &Tables,
+    res: &Option<Arc<Resource>>,
+    expr: &mut RoutingExpr,
+) -> Arc<QueryTargetQablSet> {
+    res.as_ref()
+        .and_then(|res| res.query_route(WhatAmI::Client, 0))
+        .unwrap_or_else(|| {
+            tables
+                .hat_code
+                .compute_query_route(tables, expr, 0, WhatAmI::Client)
+        })
+}
+
 #[inline]
 fn get_query_route(
     tables: &Tables,
@@ -655,6 +671,7 @@ pub fn route_query(
                                 rid: qid,
                                 ext_qos: response::ext::QoSType::response_final_default(),
                                 ext_tstamp: None,
+                                ext_cancel: None,
                             },
                             expr.full_expr().to_string(),
                         ));
@@ -731,6 +748,7 @@ pub fn route_query(
                             rid: qid,
                             ext_qos: response::ext::QoSType::response_final_default(),
                             ext_tstamp: None,
+                            ext_cancel: None,
                         },
                         expr.full_expr().to_string(),
                     ));
@@ -749,6 +767,7 @@ pub fn route_query(
                         rid: qid,
                         ext_qos: response::ext::QoSType::response_final_default(),
                         ext_tstamp: None,
+                        ext_cancel: None,
                     },
                     "".to_string(),
                 ));
@@ -836,6 +855,56 @@ pub(crate) fn route_send_response_final(
     }
 }
 
+pub(crate) fn route_cancel_query(
+    tables_ref: &Arc<TablesLock>,
+    face: &Arc<FaceState>,
+    qid: RequestId,
+) {
+    let rtables = zread!(tables_ref.tables);
+    let queries_lock = zwrite!(tables_ref.queries_lock);
+    let mut cancelled = vec![];
+    for outface in rtables.faces.values() {
+        let mut outface = outface.clone();
+        let out_qids = outface
+            .pending_queries
+            .iter()
+            .filter(|(_, (query, _))| Arc::ptr_eq(&query.src_face, face) && query.src_qid == qid)
+            .map(|(out_qid, _)| *out_qid)
+            .collect::<Vec<RequestId>>();
+        for out_qid in out_qids {
+            // The query is dropped without being finalized: the requester doesn't expect a final reply
+            if let Some((_, cancellation_token)) = get_mut_unchecked(&mut outface)
+                .pending_queries
+                .remove(&out_qid)
+            {
+                cancellation_token.cancel();
+                cancelled.push((outface.clone(), out_qid));
+            }
+        }
+    }
+    drop(queries_lock);
+    drop(rtables);
+
+    if cancelled.is_empty() {
+        tracing::debug!("Cancel query {}:{}: Query not found!", face, qid);
+    }
+    for (outface, out_qid) in cancelled {
+        tracing::debug!("Propagate cancel query {}:{} to {}", face, qid, outface);
+        outface
+            .primitives
+            .clone()
+            .send_response_final(RoutingContext::with_expr(
+                ResponseFinal {
+                    rid: out_qid,
+                    ext_qos: response::ext::QoSType::response_final_default(),
+                    ext_tstamp: None,
+                    ext_cancel: Some(response::ext::CancelType::new()),
+                },
+                "".to_string(),
+            ));
+    }
+}
+
 pub(crate) fn finalize_pending_queries(tables_ref: &TablesLock, face: &mut Arc<FaceState>) {
     let queries_lock = zwrite!(tables_ref.queries_lock);
     for (_, query) in get_mut_unchecked(face).pending_queries.drain() {
@@ -858,6 +927,7 @@ pub(crate) fn finalize_pending_query(query: (Arc<Query>, CancellationToken)) {
                     rid: query.src_qid,
                     ext_qos: response::ext::QoSType::response_final_default(),
                     ext_tstamp: None,
+                    ext_cancel: None,
                 },
                 "".to_string(),
             ));
@@ -26,6 +26,7 @@ use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::sync::Arc;
 use std::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 use tracing::{error, trace};
 use zenoh_buffers::buffer::SplitBuffer;
 use zenoh_config::{unwrap_or_default, ConfigValidator, ValidatedMap, WhatAmI};
@@ -153,6 +154,11 @@ impl AdminSpace {
                 .unwrap(),
             Arc::new(metrics),
         );
+        #[cfg(feature = "shared-memory")]
+        handlers.insert(
+            format!("@/{whatami_str}/{zid_str}/shm").try_into().unwrap(),
+            Arc::new(shm_data),
+        );
         if runtime.state.whatami == WhatAmI::Router {
             handlers.insert(
                 format!("@/{whatami_str}/{zid_str}/linkstate/routers")
@@ -183,6 +189,12 @@ impl AdminSpace {
                 .unwrap(),
             Arc::new(queryables_data),
         );
+        handlers.insert(
+            format!("@/{whatami_str}/{zid_str}/schema/**")
+                .try_into()
+                .unwrap(),
+            Arc::new(schemas_data),
+        );
 
         #[cfg(all(feature = "unstable", feature = "plugins"))]
         handlers.insert(
@@ -427,6 +439,7 @@ impl Primitives for AdminSpace {
                         rid: msg.id,
                         ext_qos: ext::QoSType::response_final_default(),
                         ext_tstamp: None,
+                        ext_cancel: None,
                     });
                     return;
                 }
@@ -440,6 +453,7 @@ impl Primitives for AdminSpace {
                         rid: msg.id,
                         ext_qos: ext::QoSType::response_final_default(),
                         ext_tstamp: None,
+                        ext_cancel: None,
                     });
                     return;
                 }
@@ -459,6 +473,8 @@ impl Primitives for AdminSpace {
                     primitives,
                     #[cfg(feature = "unstable")]
                     attachment: query.ext_attachment.map(Into::into),
+                    cancellation_token: CancellationToken::new(),
+                    registration: None,
                 }),
             };
 
@@ -636,6 +652,9 @@ zenoh_build{{version="{}"}} 1
             .openmetrics_text(),
     );
 
+    #[cfg(all(feature = "stats", feature = "shared-memory"))]
+    metrics.push_str(&shm_openmetrics_text());
+
     if let Err(e) = query
         .reply(Ok(Sample::new(
             reply_key,
@@ -647,6 +666,80 @@ zenoh_build{{version="{}"}} 1
     }
 }
 
+#[cfg(all(feature = "stats", feature = "shared-memory"))]
+fn shm_openmetrics_text() -> String {
+    use zenoh_shm::SharedMemoryManagerStats as Stats;
+    type Gauge = (&'static str, &'static str, fn(&Stats) -> f64);
+    const GAUGES: [Gauge; 7] = [
+        ("size", "Size of the shared memory segments.", |s| {
+            s.size as f64
+        }),
+        ("allocated", "Allocated shared memory.", |s| {
+            s.allocated as f64
+        }),
+        (
+            "largest_free_chunk",
+            "Size of the largest free chunk.",
+            |s| s.largest_free_chunk as f64,
+        ),
+        (
+            "fragmentation",
+            "Share of the free memory not in the largest free chunk.",
+            |s| s.fragmentation,
+        ),
+        ("busy_chunks", "Number of allocated chunks.", |s| {
+            s.busy_chunks as f64
+        }),
+        (
+            "lent",
+            "References on buffers outstanding in remote readers.",
+            |s| s.lent.values().sum::<usize>() as f64,
+        ),
+        (
+            "reclaimed",
+            "References reclaimed from failed remote readers.",
+            |s| s.reclaimed as f64,
+        ),
+    ];
+
+    let managers = zenoh_shm::managers_stats();
+    let mut text = String::new();
+    for (name, help, value) in GAUGES {
+        text.push_str(&format!(
+            "# HELP zenoh_shm_{name} {help}\n# TYPE zenoh_shm_{name} gauge\n"
+        ));
+        for m in managers.iter() {
+            text.push_str(&format!(
+                "zenoh_shm_{name}{{manager=\"{}\"}} {}\n",
+                m.id,
+                value(m)
+            ));
+        }
+    }
+    text
+}
+
+#[cfg(feature = "shared-memory")]
+fn shm_data(context: &AdminContext, query: Query) {
+    let reply_key: OwnedKeyExpr = format!(
+        "@/{}/{}/shm",
+        context.runtime.state.whatami, context.runtime.state.zid
+    )
+    .try_into()
+    .unwrap();
+    let json = json!({ "managers": zenoh_shm::managers_stats() });
+    if let Err(e) = query
+        .reply(Ok(Sample::new(
+            reply_key,
+            Value::from(json.to_string().as_bytes().to_vec())
+                .encoding(KnownEncoding::AppJson.into()),
+        )))
+        .res()
+    {
+        tracing::error!("Error sending AdminSpace reply: {:?}", e);
+    }
+}
+
 fn routers_linkstate_data(context: &AdminContext, query: Query) {
     let reply_key: OwnedKeyExpr = format!(
         "@/{}/{}/linkstate/routers",
@@ -755,6 +848,194 @@ fn queryables_data(context: &AdminContext, query: Query) {
     }
 }
 
+// JSON schemas of the replies of the admin space, published under `@/<whatami>/<zid>/schema/<key>`
+// where `<key>` is the key of the replies relative to `@/<whatami>/<zid>` (without its variable part)
+fn schemas_data(context: &AdminContext, query: Query) {
+    let root_key = format!(
+        "@/{}/{}/schema",
+        context.runtime.state.whatami, context.runtime.state.zid
+    );
+    #[allow(unused_mut)]
+    let mut schemas = vec![
+        (String::from("router"), router_schema()),
+        (
+            String::from("metrics"),
+            text_schema("Metrics", "The metrics, in the OpenMetrics text format"),
+        ),
+        (
+            String::from("linkstate"),
+            text_schema("Linkstate", "The linkstate graph, in the DOT format"),
+        ),
+        (String::from("subscriber"), sources_schema("Subscriber")),
+        (String::from("queryable"), sources_schema("Queryable")),
+    ];
+
+    #[cfg(feature = "shared-memory")]
+    schemas.push((String::from("shm"), shm_schema()));
+
+    #[cfg(all(feature = "unstable", feature = "plugins"))]
+    {
+        schemas.push((String::from("plugins"), plugin_status_schema()));
+        let guard = context.runtime.plugins_manager();
+        for plugin in guard.started_plugins_iter() {
+            match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                plugin.instance().adminspace_schemas()
+            })) {
+                Ok(responses) => schemas.extend(responses.into_iter().map(|response| {
+                    (
+                        format!("status/plugins/{}/{}", plugin.name(), response.key),
+                        response.value,
+                    )
+                })),
+                Err(_) => tracing::error!(
+                    "Plugin {} panicked while returning its admin space schemas",
+                    plugin.name()
+                ),
+            }
+        }
+    }
+
+    for (key, schema) in schemas {
+        let key_expr = match KeyExpr::try_from(format!("{root_key}/{key}")) {
+            Ok(key_expr) => key_expr,
+            Err(_) => {
+                tracing::error!("Error: invalid admin space schema key {}/{}", root_key, key);
+                continue;
+            }
+        };
+        if query.key_expr().intersects(&key_expr) {
+            if let Err(e) = query
+                .reply(Ok(Sample::new(
+                    key_expr,
+                    Value::from(schema.to_string()).encoding(KnownEncoding::AppJson.into()),
+                )))
+                .res()
+            {
+                tracing::error!("Error sending AdminSpace reply: {:?}", e);
+            }
+        }
+    }
+}
+
+fn router_schema() -> serde_json::Value {
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "Router",
+        "description": "The state of the zenoh node, with the `stats` if requested with the `_stats` parameter",
+        "type": "object",
+        "required": ["zid", "version", "metadata", "locators", "sessions", "plugins"],
+        "properties": {
+            "zid": { "type": "string" },
+            "version": { "type": "string" },
+            "metadata": {},
+            "locators": { "type": "array", "items": { "type": "string" } },
+            "sessions": { "type": "array", "items": { "$ref": "#/definitions/Session" } },
+            "plugins": {
+                "type": ["object", "null"],
+                "additionalProperties": {
+                    "type": "object",
+                    "required": ["path"],
+                    "properties": { "path": { "type": "string" } }
+                }
+            },
+            "stats": { "type": "object" }
+        },
+        "definitions": {
+            "Session": {
+                "type": "object",
+                "required": ["peer", "whatami", "links"],
+                "properties": {
+                    "peer": { "type": "string" },
+                    "whatami": { "type": "string" },
+                    "links": { "type": "array", "items": { "type": "string" } },
+                    "stats": { "type": "object" }
+                }
+            }
+        }
+    })
+}
+
+fn text_schema(title: &str, description: &str) -> serde_json::Value {
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": title,
+        "description": description,
+        "type": "string",
+        "contentMediaType": "text/plain"
+    })
+}
+
+#[cfg(feature = "shared-memory")]
+fn shm_schema() -> serde_json::Value {
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "SharedMemory",
+        "description": "The statistics of the shared memory managers of the zenoh node, as of their last garbage collection or allocation failure",
+        "type": "object",
+        "required": ["managers"],
+        "properties": {
+            "managers": { "type": "array", "items": { "$ref": "#/definitions/Manager" } }
+        },
+        "definitions": {
+            "Manager": {
+                "type": "object",
+                "required": ["id", "segments", "size", "allocated", "largest_free_chunk", "fragmentation", "busy_chunks", "lent", "reclaimed"],
+                "properties": {
+                    "id": { "type": "string" },
+                    "segments": { "type": "integer" },
+                    "size": { "type": "integer" },
+                    "allocated": { "type": "integer" },
+                    "largest_free_chunk": { "type": "integer" },
+                    "fragmentation": { "type": "number" },
+                    "busy_chunks": { "type": "integer" },
+                    "lent": { "type": "object", "additionalProperties": { "type": "integer" } },
+                    "reclaimed": { "type": "integer" }
+                }
+            }
+        }
+    })
+}
+
+fn sources_schema(title: &str) -> serde_json::Value {
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": title,
+        "description": "The zenoh ids of the routers, peers and clients declaring the resource",
+        "type": "object",
+        "required": ["routers", "peers", "clients"],
+        "properties": {
+            "routers": { "type": "array", "items": { "type": "string" } },
+            "peers": { "type": "array", "items": { "type": "string" } },
+            "clients": { "type": "array", "items": { "type": "string" } }
+        }
+    })
+}
+
+#[cfg(all(feature = "unstable", feature = "plugins"))]
+fn plugin_status_schema() -> serde_json::Value {
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "PluginStatus",
+        "type": "object",
+        "required": ["name", "path", "state", "report"],
+        "properties": {
+            "name": { "type": "string" },
+            "version": { "type": "string" },
+            "long_version": { "type": ["string", "null"] },
+            "path": { "type": "string" },
+            "state": { "type": "string", "enum": ["Declared", "Loaded", "Started"] },
+            "report": {
+                "type": "object",
+                "required": ["level"],
+                "properties": {
+                    "level": { "type": "string", "enum": ["Info", "Warning", "Error"] },
+                    "messages": { "type": "array", "items": { "type": "string" } }
+                }
+            }
+        }
+    })
+}
+
 #[cfg(all(feature = "unstable", feature = "plugins"))]
 fn plugins_data(context: &AdminContext, query: Query) {
     let guard = context.runtime.plugins_manager();
@@ -34,7 +34,7 @@ pub trait ZenohPlugin: Plugin<StartArgs = Runtime, Instance = RunningPlugin> {}
 
 impl StructVersion for RunningPlugin {
     fn struct_version() -> u64 {
-        1
+        2
     }
     fn struct_features() -> &'static str {
         crate::FEATURES
@@ -117,6 +117,15 @@ pub trait RunningPluginTrait: Send + Sync + PluginControl {
     ) -> ZResult<Vec<Response>> {
         Ok(Vec::new())
     }
+    /// Used to publish the JSON schemas of the replies of [RunningPluginTrait::adminspace_getter] in the administration space.
+    /// Each [Response] associates the JSON schema of a reply with the key of this reply, relative to the `plugin_status_key`
+    /// (for example "version" for the replies on "@/router/ROUTER_ID/status/plugins/PLUGIN_NAME/version").
+    /// The schemas are published under "@/router/ROUTER_ID/schema/status/plugins/PLUGIN_NAME".
+    ///
+    /// Implementing it is optional: by default, no schema is published for the replies of the plugin.
+    fn adminspace_schemas(&self) -> Vec<Response> {
+        Vec::new()
+    }
 }
 
 /// The zenoh plugins manager. It handles the full lifetime of plugins, from loading to destruction.
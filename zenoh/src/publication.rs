@@ -416,7 +416,7 @@ impl<'a> Publisher<'a> {
     #[zenoh_macros::unstable]
     pub fn matching_listener(&self) -> MatchingListenerBuilder<'_, DefaultHandler> {
         MatchingListenerBuilder {
-            publisher: PublisherRef::Borrow(self),
+            source: MatchingSource::Publisher(PublisherRef::Borrow(self)),
             handler: DefaultHandler,
         }
     }
@@ -559,7 +559,7 @@ impl PublisherDeclarations for std::sync::Arc<Publisher<'static>> {
     #[zenoh_macros::unstable]
     fn matching_listener(&self) -> MatchingListenerBuilder<'static, DefaultHandler> {
         MatchingListenerBuilder {
-            publisher: PublisherRef::Shared(self.clone()),
+            source: MatchingSource::Publisher(PublisherRef::Shared(self.clone())),
             handler: DefaultHandler,
         }
     }
@@ -997,7 +997,8 @@ impl TryFrom<ProtocolPriority> for Priority {
     }
 }
 
-/// A struct that indicates if there exist Subscribers matching the Publisher's key expression.
+/// A struct that indicates if there exist Subscribers matching the Publisher's key expression,
+/// or Queryables matching the [`Querier`](crate::querier::Querier)'s key expression.
 ///
 /// # Examples
 /// ```
@@ -1039,13 +1040,91 @@ impl MatchingStatus {
     pub fn matching_subscribers(&self) -> bool {
         self.matching
     }
+
+    /// Return true if there exist Queryables matching the Querier's key expression and target.
+    ///
+    /// # Examples
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use zenoh::prelude::r#async::*;
+    ///
+    /// let session = zenoh::open(config::peer()).res().await.unwrap().into_arc();
+    /// let querier = session.declare_querier("key/expression").res().await.unwrap();
+    /// let matching_queryables: bool = querier
+    ///     .matching_status()
+    ///     .res()
+    ///     .await
+    ///     .unwrap()
+    ///     .matching_queryables();
+    /// # }
+    /// ```
+    pub fn matching_queryables(&self) -> bool {
+        self.matching
+    }
+}
+
+/// The entities whose matching is reported by a [`MatchingListener`].
+#[zenoh_macros::unstable]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MatchingTarget {
+    /// The Subscribers matching a Publisher.
+    Subscribers,
+    /// The Queryables matching a Querier, for its query target.
+    Queryables(QueryTarget),
+}
+
+#[zenoh_macros::unstable]
+impl MatchingTarget {
+    pub(crate) fn is_queryables(&self) -> bool {
+        matches!(self, MatchingTarget::Queryables(_))
+    }
+}
+
+/// The Publisher or the Querier of a [`MatchingListener`].
+#[zenoh_macros::unstable]
+#[derive(Debug)]
+pub(crate) enum MatchingSource<'a> {
+    Publisher(PublisherRef<'a>),
+    Querier(&'a crate::querier::Querier<'a>),
+}
+
+#[zenoh_macros::unstable]
+impl<'a> MatchingSource<'a> {
+    pub(crate) fn session(&self) -> &SessionRef<'a> {
+        match self {
+            MatchingSource::Publisher(publisher) => &publisher.session,
+            MatchingSource::Querier(querier) => &querier.session,
+        }
+    }
+
+    pub(crate) fn key_expr(&self) -> &KeyExpr<'a> {
+        match self {
+            MatchingSource::Publisher(publisher) => &publisher.key_expr,
+            MatchingSource::Querier(querier) => &querier.key_expr,
+        }
+    }
+
+    pub(crate) fn destination(&self) -> Locality {
+        match self {
+            MatchingSource::Publisher(publisher) => publisher.destination,
+            MatchingSource::Querier(querier) => querier.destination,
+        }
+    }
+
+    pub(crate) fn target(&self) -> MatchingTarget {
+        match self {
+            MatchingSource::Publisher(_) => MatchingTarget::Subscribers,
+            MatchingSource::Querier(querier) => MatchingTarget::Queryables(querier.target),
+        }
+    }
 }
 
 /// A builder for initializing a [`MatchingListener`].
 #[zenoh_macros::unstable]
 #[derive(Debug)]
 pub struct MatchingListenerBuilder<'a, Handler> {
-    pub(crate) publisher: PublisherRef<'a>,
+    pub(crate) source: MatchingSource<'a>,
     pub handler: Handler,
 }
 
@@ -1081,12 +1160,9 @@ impl<'a> MatchingListenerBuilder<'a, DefaultHandler> {
     where
         Callback: Fn(MatchingStatus) + Send + Sync + 'static,
     {
-        let MatchingListenerBuilder {
-            publisher,
-            handler: _,
-        } = self;
+        let MatchingListenerBuilder { source, handler: _ } = self;
         MatchingListenerBuilder {
-            publisher,
+            source,
             handler: callback,
         }
     }
@@ -1153,11 +1229,8 @@ impl<'a> MatchingListenerBuilder<'a, DefaultHandler> {
     where
         Handler: crate::prelude::IntoCallbackReceiverPair<'static, MatchingStatus>,
     {
-        let MatchingListenerBuilder {
-            publisher,
-            handler: _,
-        } = self;
-        MatchingListenerBuilder { publisher, handler }
+        let MatchingListenerBuilder { source, handler: _ } = self;
+        MatchingListenerBuilder { source, handler }
     }
 }
 
@@ -1179,12 +1252,17 @@ where
     #[zenoh_macros::unstable]
     fn res_sync(self) -> <Self as Resolvable>::To {
         let (callback, receiver) = self.handler.into_cb_receiver_pair();
-        self.publisher
-            .session
-            .declare_matches_listener_inner(&self.publisher, callback)
+        self.source
+            .session()
+            .declare_matches_listener_inner(
+                self.source.key_expr(),
+                self.source.destination(),
+                self.source.target(),
+                callback,
+            )
             .map(|listener_state| MatchingListener {
                 listener: MatchingListenerInner {
-                    publisher: self.publisher,
+                    source: self.source,
                     state: listener_state,
                     alive: true,
                 },
@@ -1213,6 +1291,7 @@ pub(crate) struct MatchingListenerState {
     pub(crate) current: std::sync::Mutex<bool>,
     pub(crate) key_expr: KeyExpr<'static>,
     pub(crate) destination: Locality,
+    pub(crate) target: MatchingTarget,
     pub(crate) callback: Callback<'static, MatchingStatus>,
 }
 
@@ -1228,7 +1307,7 @@ impl std::fmt::Debug for MatchingListenerState {
 
 #[zenoh_macros::unstable]
 pub(crate) struct MatchingListenerInner<'a> {
-    pub(crate) publisher: PublisherRef<'a>,
+    pub(crate) source: MatchingSource<'a>,
     pub(crate) state: std::sync::Arc<MatchingListenerState>,
     pub(crate) alive: bool,
 }
@@ -1337,8 +1416,8 @@ impl SyncResolve for MatchingListenerUndeclaration<'_> {
     fn res_sync(mut self) -> <Self as Resolvable>::To {
         self.subscriber.alive = false;
         self.subscriber
-            .publisher
-            .session
+            .source
+            .session()
             .undeclare_matches_listener_inner(self.subscriber.state.id)
     }
 }
@@ -1357,8 +1436,8 @@ impl Drop for MatchingListenerInner<'_> {
     fn drop(&mut self) {
         if self.alive {
             let _ = self
-                .publisher
-                .session
+                .source
+                .session()
                 .undeclare_matches_listener_inner(self.state.id);
         }
     }
@@ -0,0 +1,312 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+//! Querying primitives.
+//!
+//! see [`Querier`]
+
+use crate::handlers::DefaultHandler;
+use crate::prelude::*;
+use crate::publication::{MatchingListenerBuilder, MatchingSource, MatchingStatus, MatchingTarget};
+use crate::query::{GetBuilder, QueryConsolidation, QueryTarget};
+use crate::SessionRef;
+use std::future::Ready;
+use std::time::Duration;
+use zenoh_core::{AsyncResolve, Resolvable, Resolve, ResolveClosure, SyncResolve};
+use zenoh_result::ZResult;
+
+/// A builder for initializing a [`Querier`].
+///
+/// # Examples
+/// ```
+/// # #[tokio::main]
+/// # async fn main() {
+/// use zenoh::prelude::r#async::*;
+/// use zenoh::query::*;
+///
+/// let session = zenoh::open(config::peer()).res().await.unwrap();
+/// let querier = session
+///     .declare_querier("key/expression")
+///     .target(QueryTarget::All)
+///     .consolidation(ConsolidationMode::None)
+///     .res()
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[must_use = "Resolvables do nothing unless you resolve them using the `res` method from either `SyncResolve` or `AsyncResolve`"]
+#[derive(Debug)]
+pub struct QuerierBuilder<'a, 'b: 'a> {
+    pub(crate) session: SessionRef<'a>,
+    pub(crate) key_expr: ZResult<KeyExpr<'b>>,
+    pub(crate) target: QueryTarget,
+    pub(crate) consolidation: QueryConsolidation,
+    pub(crate) destination: Locality,
+    pub(crate) timeout: Duration,
+}
+
+impl<'a, 'b> QuerierBuilder<'a, 'b> {
+    /// Change the target of the queries.
+    #[inline]
+    pub fn target(mut self, target: QueryTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Change the consolidation mode of the queries.
+    #[inline]
+    pub fn consolidation<QC: Into<QueryConsolidation>>(mut self, consolidation: QC) -> Self {
+        self.consolidation = consolidation.into();
+        self
+    }
+
+    /// Restrict the matching queryables that will receive the queries
+    /// to the ones that have the given [`Locality`](crate::prelude::Locality).
+    #[inline]
+    pub fn allowed_destination(mut self, destination: Locality) -> Self {
+        self.destination = destination;
+        self
+    }
+
+    /// Set the timeout of the queries.
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl<'a, 'b> Resolvable for QuerierBuilder<'a, 'b> {
+    type To = ZResult<Querier<'a>>;
+}
+
+impl<'a, 'b> SyncResolve for QuerierBuilder<'a, 'b> {
+    fn res_sync(self) -> <Self as Resolvable>::To {
+        let mut key_expr = self.key_expr?;
+        if !key_expr.is_fully_optimized(&self.session) {
+            key_expr = self.session._declare_keyexpr(Ok(key_expr)).res_sync()?;
+        }
+        let querier = Querier {
+            session: self.session,
+            key_expr,
+            target: self.target,
+            consolidation: self.consolidation,
+            destination: self.destination,
+            timeout: self.timeout,
+        };
+        tracing::trace!("querier({:?})", querier.key_expr);
+        Ok(querier)
+    }
+}
+
+impl<'a, 'b> AsyncResolve for QuerierBuilder<'a, 'b> {
+    type Future = Ready<Self::To>;
+
+    fn res_async(self) -> Self::Future {
+        std::future::ready(self.res_sync())
+    }
+}
+
+/// A querier that allows to send queries to queryables.
+///
+/// Unlike [`Session::get`](crate::Session::get), a querier resolves its key expression once
+/// and can report whether queryables currently match it through its
+/// [`matching_status`](Querier::matching_status) and its [`matching_listener`](Querier::matching_listener).
+///
+/// # Examples
+/// ```
+/// # #[tokio::main]
+/// # async fn main() {
+/// use zenoh::prelude::r#async::*;
+///
+/// let session = zenoh::open(config::peer()).res().await.unwrap().into_arc();
+/// let querier = session.declare_querier("key/expression").res().await.unwrap();
+/// if querier.matching_status().res().await.unwrap().matching_queryables() {
+///     let replies = querier.get().res().await.unwrap();
+///     while let Ok(reply) = replies.recv_async().await {
+///         println!("Received {:?}", reply.sample)
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Querier<'a> {
+    pub(crate) session: SessionRef<'a>,
+    pub(crate) key_expr: KeyExpr<'a>,
+    pub(crate) target: QueryTarget,
+    pub(crate) consolidation: QueryConsolidation,
+    pub(crate) destination: Locality,
+    pub(crate) timeout: Duration,
+}
+
+impl<'a> Querier<'a> {
+    pub fn key_expr(&self) -> &KeyExpr<'a> {
+        &self.key_expr
+    }
+
+    /// Return the target of the queries.
+    #[inline]
+    pub fn target(&self) -> QueryTarget {
+        self.target
+    }
+
+    /// Return the consolidation mode of the queries.
+    #[inline]
+    pub fn consolidation(&self) -> QueryConsolidation {
+        self.consolidation
+    }
+
+    /// Return the timeout of the queries.
+    #[inline]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Query the key expression of this querier.
+    ///
+    /// The returned [`GetBuilder`] is initialized with the settings of the querier,
+    /// which can still be changed for this query only.
+    ///
+    /// # Examples
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use zenoh::prelude::r#async::*;
+    ///
+    /// let session = zenoh::open(config::peer()).res().await.unwrap().into_arc();
+    /// let querier = session.declare_querier("key/expression").res().await.unwrap();
+    /// let replies = querier.get().res().await.unwrap();
+    /// while let Ok(reply) = replies.recv_async().await {
+    ///     println!("Received {:?}", reply.sample)
+    /// }
+    /// # }
+    /// ```
+    pub fn get(&self) -> GetBuilder<'_, 'a, DefaultHandler> {
+        self.get_with_parameters("")
+    }
+
+    /// Query the key expression of this querier with the given selector parameters.
+    ///
+    /// # Examples
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use zenoh::prelude::r#async::*;
+    ///
+    /// let session = zenoh::open(config::peer()).res().await.unwrap().into_arc();
+    /// let querier = session.declare_querier("key/expression").res().await.unwrap();
+    /// let replies = querier.get_with_parameters("value>1").res().await.unwrap();
+    /// # }
+    /// ```
+    pub fn get_with_parameters<P>(&self, parameters: P) -> GetBuilder<'_, 'a, DefaultHandler>
+    where
+        P: Into<String>,
+    {
+        let mut selector = Selector::from(&self.key_expr);
+        selector.parameters = parameters.into().into();
+        GetBuilder {
+            session: &self.session,
+            selector: Ok(selector),
+            scope: Ok(None),
+            target: self.target,
+            consolidation: self.consolidation,
+            destination: self.destination,
+            timeout: self.timeout,
+            value: None,
+            attachment: None,
+            handler: DefaultHandler,
+        }
+    }
+
+    /// Return the [`MatchingStatus`] of the querier.
+    ///
+    /// [`MatchingStatus::matching_queryables`] will return true if there exist Queryables
+    /// matching the Querier's key expression and target and false otherwise.
+    ///
+    /// # Examples
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use zenoh::prelude::r#async::*;
+    ///
+    /// let session = zenoh::open(config::peer()).res().await.unwrap().into_arc();
+    /// let querier = session.declare_querier("key/expression").res().await.unwrap();
+    /// let matching_queryables: bool = querier
+    ///     .matching_status()
+    ///     .res()
+    ///     .await
+    ///     .unwrap()
+    ///     .matching_queryables();
+    /// # }
+    /// ```
+    pub fn matching_status(&self) -> impl Resolve<ZResult<MatchingStatus>> + '_ {
+        zenoh_core::ResolveFuture::new(async move {
+            self.session.matching_status_of(
+                self.key_expr(),
+                MatchingTarget::Queryables(self.target),
+                self.destination,
+            )
+        })
+    }
+
+    /// Return a [`MatchingListener`](crate::publication::MatchingListener) for this Querier.
+    ///
+    /// The [`MatchingListener`](crate::publication::MatchingListener) that will send a
+    /// notification each time the [`MatchingStatus`] of the Querier changes.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use zenoh::prelude::r#async::*;
+    ///
+    /// let session = zenoh::open(config::peer()).res().await.unwrap();
+    /// let querier = session.declare_querier("key/expression").res().await.unwrap();
+    /// let matching_listener = querier.matching_listener().res().await.unwrap();
+    /// while let Ok(matching_status) = matching_listener.recv_async().await {
+    ///     if matching_status.matching_queryables() {
+    ///         println!("Querier has matching queryables.");
+    ///     } else {
+    ///         println!("Querier has NO MORE matching queryables.");
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn matching_listener(&self) -> MatchingListenerBuilder<'_, DefaultHandler> {
+        MatchingListenerBuilder {
+            source: MatchingSource::Querier(self),
+            handler: DefaultHandler,
+        }
+    }
+
+    /// Undeclares the [`Querier`].
+    ///
+    /// # Examples
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use zenoh::prelude::r#async::*;
+    ///
+    /// let session = zenoh::open(config::peer()).res().await.unwrap();
+    /// let querier = session.declare_querier("key/expression").res().await.unwrap();
+    /// querier.undeclare().res().await.unwrap();
+    /// # }
+    /// ```
+    pub fn undeclare(self) -> impl Resolve<ZResult<()>> + 'a {
+        ResolveClosure::new(move || {
+            drop(self);
+            Ok(())
+        })
+    }
+}
@@ -20,9 +20,11 @@ use crate::prelude::*;
 use crate::sample::Attachment;
 use crate::Session;
 use std::collections::HashMap;
+use std::fmt;
 use std::future::Ready;
 use std::time::Duration;
-use zenoh_core::{AsyncResolve, Resolvable, SyncResolve};
+use zenoh_core::{AsyncResolve, Resolvable, Resolve, ResolveClosure, SyncResolve};
+use zenoh_protocol::network::RequestId;
 use zenoh_result::ZResult;
 
 /// The [`Queryable`](crate::queryable::Queryable)s that should be target of a [`get`](Session::get).
@@ -348,6 +350,30 @@ impl<'a, 'b, Handler> GetBuilder<'a, 'b, Handler> {
             handler,
         }
     }
+
+    /// Make this query cancellable: it resolves to its receiver together with a [`QueryCancellation`].
+    ///
+    /// # Examples
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use zenoh::prelude::r#async::*;
+    ///
+    /// let session = zenoh::open(config::peer()).res().await.unwrap();
+    /// let (replies, cancellation) = session
+    ///     .get("key/expression")
+    ///     .cancellable()
+    ///     .res()
+    ///     .await
+    ///     .unwrap();
+    /// cancellation.cancel().res().await.unwrap();
+    /// assert!(replies.recv_async().await.is_err());
+    /// # }
+    /// ```
+    #[inline]
+    pub fn cancellable(self) -> CancellableGetBuilder<'a, 'b, Handler> {
+        CancellableGetBuilder { get: self }
+    }
 }
 
 pub(crate) const _REPLY_KEY_EXPR_ANY_SEL_PARAM: &str = "_anyke";
@@ -376,6 +402,16 @@ where
     Handler::Receiver: Send,
 {
     fn res_sync(self) -> <Self as Resolvable>::To {
+        self.send().map(|(_, receiver)| receiver)
+    }
+}
+
+impl<Handler> GetBuilder<'_, '_, Handler>
+where
+    Handler: IntoCallbackReceiverPair<'static, Reply> + Send,
+    Handler::Receiver: Send,
+{
+    fn send(self) -> ZResult<(RequestId, Handler::Receiver)> {
         let (callback, receiver) = self.handler.into_cb_receiver_pair();
 
         self.session
@@ -391,7 +427,7 @@ where
                 self.attachment,
                 callback,
             )
-            .map(|_| receiver)
+            .map(|qid| (qid, receiver))
     }
 }
 
@@ -406,3 +442,77 @@ where
         std::future::ready(self.res_sync())
     }
 }
+
+/// A builder for initializing a cancellable `query`, returned by [`GetBuilder::cancellable`].
+#[must_use = "Resolvables do nothing unless you resolve them using the `res` method from either `SyncResolve` or `AsyncResolve`"]
+#[derive(Debug)]
+pub struct CancellableGetBuilder<'a, 'b, Handler> {
+    get: GetBuilder<'a, 'b, Handler>,
+}
+
+impl<Handler> Resolvable for CancellableGetBuilder<'_, '_, Handler>
+where
+    Handler: IntoCallbackReceiverPair<'static, Reply> + Send,
+    Handler::Receiver: Send,
+{
+    type To = ZResult<(Handler::Receiver, QueryCancellation)>;
+}
+
+impl<Handler> SyncResolve for CancellableGetBuilder<'_, '_, Handler>
+where
+    Handler: IntoCallbackReceiverPair<'static, Reply> + Send,
+    Handler::Receiver: Send,
+{
+    fn res_sync(self) -> <Self as Resolvable>::To {
+        let session = Session::clone(self.get.session);
+        let destination = self.get.destination;
+        let (qid, receiver) = self.get.send()?;
+        Ok((
+            receiver,
+            QueryCancellation {
+                session,
+                qid,
+                destination,
+            },
+        ))
+    }
+}
+
+impl<Handler> AsyncResolve for CancellableGetBuilder<'_, '_, Handler>
+where
+    Handler: IntoCallbackReceiverPair<'static, Reply> + Send,
+    Handler::Receiver: Send,
+{
+    type Future = Ready<Self::To>;
+
+    fn res_async(self) -> Self::Future {
+        std::future::ready(self.res_sync())
+    }
+}
+
+/// A handle to cancel a query, returned by a [`CancellableGetBuilder`].
+pub struct QueryCancellation {
+    session: Session,
+    qid: RequestId,
+    destination: Locality,
+}
+
+impl QueryCancellation {
+    /// Cancels the query.
+    ///
+    /// No more replies are received: the receiver of the query is closed without waiting for the
+    /// final reply. The cancellation is propagated to the queryables still processing the query,
+    /// which can check it with [`Query::is_cancelled`](crate::queryable::Query::is_cancelled).
+    /// Cancelling a complete query has no effect.
+    pub fn cancel(&self) -> impl Resolve<ZResult<()>> + '_ {
+        ResolveClosure::new(move || self.session.cancel_query(self.qid, self.destination))
+    }
+}
+
+impl fmt::Debug for QueryCancellation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryCancellation")
+            .field("qid", &self.qid)
+            .finish()
+    }
+}
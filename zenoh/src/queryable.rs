@@ -22,14 +22,17 @@ use crate::query::ReplyKeyExpr;
 #[zenoh_macros::unstable]
 use crate::sample::Attachment;
 use crate::sample::DataInfo;
+use crate::IncomingQueries;
 use crate::SessionRef;
 use crate::Undeclarable;
 
 use std::fmt;
+use std::future::Future;
 use std::future::Ready;
 use std::ops::Deref;
-use std::sync::Arc;
-use zenoh_core::{AsyncResolve, Resolvable, SyncResolve};
+use std::sync::{Arc, Weak};
+use tokio_util::sync::CancellationToken;
+use zenoh_core::{zlock, AsyncResolve, Resolvable, SyncResolve};
 use zenoh_protocol::core::WireExpr;
 use zenoh_protocol::network::{response, Mapping, RequestId, Response, ResponseFinal};
 use zenoh_protocol::zenoh::ext::ValueType;
@@ -50,14 +53,25 @@ pub(crate) struct QueryInner {
     pub(crate) primitives: Arc<dyn Primitives>,
     #[cfg(feature = "unstable")]
     pub(crate) attachment: Option<Attachment>,
+    /// Cancelled when the querier cancels this Query.
+    pub(crate) cancellation_token: CancellationToken,
+    /// The cancellation tokens the one of this Query is registered in, if any,
+    /// and whether this Query was issued by the same session.
+    pub(crate) registration: Option<(Weak<IncomingQueries>, bool)>,
 }
 
 impl Drop for QueryInner {
     fn drop(&mut self) {
+        if let Some((state, local)) = self.registration.take() {
+            if let Some(state) = state.upgrade() {
+                zlock!(state).remove(&(local, self.qid));
+            }
+        }
         self.primitives.send_response_final(ResponseFinal {
             rid: self.qid,
             ext_qos: response::ext::QoSType::response_final_default(),
             ext_tstamp: None,
+            ext_cancel: None,
         });
     }
 }
@@ -101,6 +115,38 @@ impl Query {
         self.inner.attachment.as_ref()
     }
 
+    /// Returns true if the querier cancelled this Query: its replies are not expected anymore.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancellation_token.is_cancelled()
+    }
+
+    /// Returns a future completing when the querier cancels this Query.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use zenoh::prelude::r#async::*;
+    ///
+    /// let session = zenoh::open(config::peer()).res().await.unwrap();
+    /// let queryable = session.declare_queryable("key/expression").res().await.unwrap();
+    /// while let Ok(query) = queryable.recv_async().await {
+    ///     tokio::select! {
+    ///         _ = query.cancelled() => println!("Query cancelled: {}", query.selector()),
+    ///         _ = tokio::time::sleep(std::time::Duration::from_secs(1)) => {
+    ///             let sample = Sample::try_from("key/expression", "value").unwrap();
+    ///             query.reply(Ok(sample)).res().await.unwrap();
+    ///         }
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    #[inline]
+    pub fn cancelled(&self) -> impl Future<Output = ()> + Send + '_ {
+        self.inner.cancellation_token.cancelled()
+    }
+
     /// Sends a reply to this Query.
     ///
     /// By default, queries only accept replies whose key expression intersects with the query's.
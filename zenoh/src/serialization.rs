@@ -0,0 +1,334 @@
+//
+// Copyright (c) 2024 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+//! Typed serialization of [`Value`] payloads.
+//!
+//! A [`Serializer`] converts any [`serde`] serializable type to and from the payload of a [`Value`],
+//! whose [`Encoding`] identifies the format. [`Json`], [`Cbor`] and [`Bincode`] are provided.
+//!
+//! # Examples
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() {
+//! use serde::{Deserialize, Serialize};
+//! use zenoh::prelude::r#async::*;
+//! use zenoh::serialization::{Json, Typed};
+//!
+//! #[derive(Serialize, Deserialize, Debug)]
+//! struct Position {
+//!     x: f64,
+//!     y: f64,
+//! }
+//!
+//! let session = zenoh::open(config::peer()).res().await.unwrap();
+//! let subscriber = session
+//!     .declare_subscriber("robot/position")
+//!     .with(Typed::<Position, _>::new(Json))
+//!     .res()
+//!     .await
+//!     .unwrap();
+//! let publisher = session
+//!     .declare_publisher("robot/position")
+//!     .res()
+//!     .await
+//!     .unwrap()
+//!     .typed::<Position, _>(Json);
+//! publisher
+//!     .put(&Position { x: 1.0, y: 2.0 })
+//!     .unwrap()
+//!     .res()
+//!     .await
+//!     .unwrap();
+//! while let Ok(sample) = subscriber.recv_async().await {
+//!     match sample.value {
+//!         Ok(position) => println!("Received {:?}", position),
+//!         Err(e) => println!("Invalid sample on {}: {}", sample.sample.key_expr, e),
+//!     }
+//! }
+//! # }
+//! ```
+use crate::encoding;
+use crate::handlers::{Callback, DefaultHandler, Dyn, IntoCallbackReceiverPair};
+use crate::prelude::{Encoding, KeyExpr, Sample, SplitBuffer};
+use crate::publication::{Publication, Publisher};
+use crate::value::Value;
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use zenoh_core::Resolve;
+use zenoh_result::{bail, zerror, ZResult};
+
+/// A serialization format for the payloads of [`Value`]s, identified by an [`Encoding`].
+pub trait Serializer: Send + Sync + 'static {
+    /// The encoding of the values serialized with this Serializer.
+    fn encoding(&self) -> Encoding;
+
+    /// Returns true if the values with the given encoding can be deserialized with this Serializer.
+    fn accepts(&self, encoding: &Encoding) -> bool {
+        *encoding == self.encoding()
+    }
+
+    /// Serializes `t` into bytes.
+    fn serialize_bytes<T: Serialize + ?Sized>(&self, t: &T) -> ZResult<Vec<u8>>;
+
+    /// Deserializes a `T` from bytes.
+    fn deserialize_bytes<T: DeserializeOwned>(&self, bytes: &[u8]) -> ZResult<T>;
+}
+
+/// The JSON [`Serializer`], with the `application/json` encoding.
+///
+/// The values with the `text/json` encoding are also accepted for deserialization.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl Serializer for Json {
+    fn encoding(&self) -> Encoding {
+        Encoding::APP_JSON
+    }
+
+    fn accepts(&self, encoding: &Encoding) -> bool {
+        *encoding == Encoding::APP_JSON || *encoding == Encoding::TEXT_JSON
+    }
+
+    fn serialize_bytes<T: Serialize + ?Sized>(&self, t: &T) -> ZResult<Vec<u8>> {
+        Ok(serde_json::to_vec(t)?)
+    }
+
+    fn deserialize_bytes<T: DeserializeOwned>(&self, bytes: &[u8]) -> ZResult<T> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// The CBOR ([RFC 8949](https://www.rfc-editor.org/rfc/rfc8949)) [`Serializer`], with the registered
+/// [`CBOR`](encoding::CBOR) encoding.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cbor;
+
+impl Serializer for Cbor {
+    fn encoding(&self) -> Encoding {
+        encoding::CBOR.encoding()
+    }
+
+    fn accepts(&self, encoding: &Encoding) -> bool {
+        encoding::CBOR.matches(encoding)
+    }
+
+    fn serialize_bytes<T: Serialize + ?Sized>(&self, t: &T) -> ZResult<Vec<u8>> {
+        let mut bytes = vec![];
+        ciborium::ser::into_writer(t, &mut bytes).map_err(|e| zerror!("{}", e))?;
+        Ok(bytes)
+    }
+
+    fn deserialize_bytes<T: DeserializeOwned>(&self, bytes: &[u8]) -> ZResult<T> {
+        Ok(ciborium::de::from_reader(bytes).map_err(|e| zerror!("{}", e))?)
+    }
+}
+
+/// A compact binary [`Serializer`] based on [`bincode`](https://docs.rs/bincode), with the registered
+/// [`BINCODE`](encoding::BINCODE) encoding.
+///
+/// The format is not self-describing: the publishers and subscribers must agree on the serialized type.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+impl Serializer for Bincode {
+    fn encoding(&self) -> Encoding {
+        encoding::BINCODE.encoding()
+    }
+
+    fn accepts(&self, encoding: &Encoding) -> bool {
+        encoding::BINCODE.matches(encoding)
+    }
+
+    fn serialize_bytes<T: Serialize + ?Sized>(&self, t: &T) -> ZResult<Vec<u8>> {
+        Ok(bincode::serialize(t)?)
+    }
+
+    fn deserialize_bytes<T: DeserializeOwned>(&self, bytes: &[u8]) -> ZResult<T> {
+        // the length prefixes cannot claim more than the received bytes
+        Ok(bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(bytes.len() as u64)
+            .deserialize(bytes)?)
+    }
+}
+
+impl Value {
+    /// Creates a Value by serializing `t` with the given [`Serializer`], and sets its encoding accordingly.
+    pub fn serialize<S, T>(serializer: &S, t: &T) -> ZResult<Value>
+    where
+        S: Serializer,
+        T: Serialize + ?Sized,
+    {
+        Ok(Value::from(serializer.serialize_bytes(t)?).encoding(serializer.encoding()))
+    }
+
+    /// Deserializes this Value with the given [`Serializer`].
+    ///
+    /// Fails if the encoding of this Value is not accepted by the Serializer.
+    pub fn deserialize<S, T>(&self, serializer: &S) -> ZResult<T>
+    where
+        S: Serializer,
+        T: DeserializeOwned,
+    {
+        if !serializer.accepts(&self.encoding) {
+            bail!(
+                "Unexpected encoding {}: expected {}",
+                self.encoding,
+                serializer.encoding()
+            );
+        }
+        serializer.deserialize_bytes(&self.payload.contiguous())
+    }
+}
+
+/// A [`Publisher`] of values of type `T`, serialized with a [`Serializer`].
+///
+/// Created by [`Publisher::typed`].
+pub struct TypedPublisher<'a, T: ?Sized, S> {
+    publisher: Publisher<'a>,
+    serializer: S,
+    _type: PhantomData<fn(&T)>,
+}
+
+impl<'a, T, S> TypedPublisher<'a, T, S>
+where
+    T: Serialize + ?Sized,
+    S: Serializer,
+{
+    /// Returns the [`KeyExpr`] of this TypedPublisher.
+    #[inline]
+    pub fn key_expr(&self) -> &KeyExpr<'a> {
+        self.publisher.key_expr()
+    }
+
+    /// Returns the [`Serializer`] of this TypedPublisher.
+    #[inline]
+    pub fn serializer(&self) -> &S {
+        &self.serializer
+    }
+
+    /// Serializes and puts a value, fails if it cannot be serialized.
+    #[inline]
+    pub fn put(&self, value: &T) -> ZResult<Publication> {
+        Ok(self
+            .publisher
+            .put(Value::serialize(&self.serializer, value)?))
+    }
+
+    /// Deletes data.
+    #[inline]
+    pub fn delete(&self) -> Publication {
+        self.publisher.delete()
+    }
+
+    /// Returns the untyped [`Publisher`].
+    #[inline]
+    pub fn into_inner(self) -> Publisher<'a> {
+        self.publisher
+    }
+
+    /// Undeclares this TypedPublisher.
+    #[inline]
+    pub fn undeclare(self) -> impl Resolve<ZResult<()>> + 'a {
+        self.publisher.undeclare()
+    }
+}
+
+impl<'a> Publisher<'a> {
+    /// Converts this Publisher into a [`TypedPublisher`] of values of type `T`, serialized with the given [`Serializer`].
+    pub fn typed<T, S>(self, serializer: S) -> TypedPublisher<'a, T, S>
+    where
+        T: Serialize + ?Sized,
+        S: Serializer,
+    {
+        TypedPublisher {
+            publisher: self,
+            serializer,
+            _type: PhantomData,
+        }
+    }
+}
+
+/// A [`Sample`] together with the result of the deserialization of its value.
+#[derive(Debug)]
+pub struct TypedSample<T> {
+    /// The received sample.
+    pub sample: Sample,
+    /// The deserialized value, or the error preventing its deserialization.
+    pub value: ZResult<T>,
+}
+
+/// A handler deserializing the received [`Sample`]s into values of type `T` with a [`Serializer`],
+/// and passing them as [`TypedSample`]s to another handler.
+///
+/// A sample that cannot be deserialized is still passed, with its deserialization error.
+pub struct Typed<T, S, Handler = DefaultHandler> {
+    serializer: S,
+    handler: Handler,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T, S> Typed<T, S, DefaultHandler> {
+    /// Creates a handler deserializing with the given [`Serializer`], to a [`DefaultHandler`].
+    pub fn new(serializer: S) -> Self {
+        Typed {
+            serializer,
+            handler: DefaultHandler,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T, S, Handler> Typed<T, S, Handler> {
+    /// Passes the [`TypedSample`]s to the given handler.
+    pub fn with<H>(self, handler: H) -> Typed<T, S, H>
+    where
+        H: IntoCallbackReceiverPair<'static, TypedSample<T>>,
+    {
+        Typed {
+            serializer: self.serializer,
+            handler,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T, S, Handler> IntoCallbackReceiverPair<'static, Sample> for Typed<T, S, Handler>
+where
+    T: DeserializeOwned + Send + 'static,
+    S: Serializer,
+    Handler: IntoCallbackReceiverPair<'static, TypedSample<T>>,
+{
+    type Receiver = Handler::Receiver;
+
+    fn into_cb_receiver_pair(self) -> (Callback<'static, Sample>, Self::Receiver) {
+        let (callback, receiver) = self.handler.into_cb_receiver_pair();
+        let serializer = self.serializer;
+        (
+            Dyn::new(move |sample: Sample| {
+                let value = sample.value.deserialize(&serializer);
+                callback(TypedSample { sample, value })
+            }),
+            receiver,
+        )
+    }
+}
+
+/// A [`Subscriber`](crate::subscriber::Subscriber) receiving values of type `T` through a `flume` channel,
+/// declared with a [`Typed`] handler.
+pub type TypedSubscriber<'a, T> =
+    crate::subscriber::Subscriber<'a, flume::Receiver<TypedSample<T>>>;
@@ -26,6 +26,8 @@ use crate::net::runtime::Runtime;
 use crate::prelude::Locality;
 use crate::prelude::{KeyExpr, Parameters};
 use crate::publication::*;
+#[zenoh_macros::unstable]
+use crate::querier::QuerierBuilder;
 use crate::query::*;
 use crate::queryable::*;
 use crate::runtime::RuntimeBuilder;
@@ -50,12 +52,15 @@ use std::sync::atomic::{AtomicU16, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::RwLock;
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{error, trace, warn};
 use uhlc::HLC;
 use zenoh_buffers::ZBuf;
 use zenoh_collections::SingleOrVec;
 use zenoh_config::unwrap_or_default;
-use zenoh_core::{zconfigurable, zread, Resolve, ResolveClosure, ResolveFuture, SyncResolve};
+use zenoh_core::{
+    zconfigurable, zlock, zread, Resolve, ResolveClosure, ResolveFuture, SyncResolve,
+};
 use zenoh_protocol::network::AtomicRequestId;
 use zenoh_protocol::network::RequestId;
 use zenoh_protocol::{
@@ -71,7 +76,7 @@ use zenoh_protocol::{
         },
         ext,
         request::{self, ext::TargetType, Request},
-        Mapping, Push, Response, ResponseFinal,
+        response, Mapping, Push, Response, ResponseFinal,
     },
     zenoh::{
         query::{
@@ -93,6 +98,8 @@ zconfigurable! {
     pub(crate) static ref API_OPEN_SESSION_DELAY: u64 = 500;
 }
 
+pub(crate) type IncomingQueries = std::sync::Mutex<HashMap<(bool, RequestId), CancellationToken>>;
+
 pub(crate) struct SessionState {
     pub(crate) primitives: Option<Arc<Face>>, // @TODO replace with MaybeUninit ??
     pub(crate) expr_id_counter: AtomicExprId, // @TODO: manage rollover and uniqueness
@@ -108,6 +115,9 @@ pub(crate) struct SessionState {
     #[cfg(feature = "unstable")]
     pub(crate) matching_listeners: HashMap<Id, Arc<MatchingListenerState>>,
     pub(crate) queries: HashMap<RequestId, QueryState>,
+    /// The cancellation tokens of the received queries, by origin (local or remote) and id.
+    /// They are locked apart from the state, since a Query can be dropped while the state is locked.
+    pub(crate) incoming_queries: Arc<IncomingQueries>,
     pub(crate) aggregated_subscribers: Vec<OwnedKeyExpr>,
     //pub(crate) aggregated_publishers: Vec<OwnedKeyExpr>,
 }
@@ -132,6 +142,7 @@ impl SessionState {
             #[cfg(feature = "unstable")]
             matching_listeners: HashMap::new(),
             queries: HashMap::new(),
+            incoming_queries: Arc::new(IncomingQueries::default()),
             aggregated_subscribers,
             //aggregated_publishers,
         }
@@ -337,6 +348,28 @@ impl<'s, 'a> SessionDeclarations<'s, 'a> for SessionRef<'a> {
         }
     }
     #[zenoh_macros::unstable]
+    fn declare_querier<'b, TryIntoKeyExpr>(
+        &'s self,
+        key_expr: TryIntoKeyExpr,
+    ) -> QuerierBuilder<'a, 'b>
+    where
+        TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
+        <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>,
+    {
+        let timeout = {
+            let conf = self.runtime.config().lock();
+            Duration::from_millis(unwrap_or_default!(conf.queries_default_timeout()))
+        };
+        QuerierBuilder {
+            session: self.clone(),
+            key_expr: key_expr.try_into().map_err(Into::into),
+            target: QueryTarget::default(),
+            consolidation: QueryConsolidation::default(),
+            destination: Locality::default(),
+            timeout,
+        }
+    }
+    #[zenoh_macros::unstable]
     fn liveliness(&'s self) -> Liveliness<'a> {
         Liveliness {
             session: self.clone(),
@@ -616,6 +649,17 @@ impl<'a> SessionDeclarations<'a, 'a> for Session {
         SessionRef::Borrow(self).declare_publisher(key_expr)
     }
     #[zenoh_macros::unstable]
+    fn declare_querier<'b, TryIntoKeyExpr>(
+        &'a self,
+        key_expr: TryIntoKeyExpr,
+    ) -> QuerierBuilder<'a, 'b>
+    where
+        TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
+        <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>,
+    {
+        SessionRef::Borrow(self).declare_querier(key_expr)
+    }
+    #[zenoh_macros::unstable]
     fn liveliness(&'a self) -> Liveliness {
         SessionRef::Borrow(self).liveliness()
     }
@@ -649,7 +693,7 @@ impl Session {
         self._declare_keyexpr(key_expr)
     }
 
-    fn _declare_keyexpr<'a, 'b: 'a>(
+    pub(crate) fn _declare_keyexpr<'a, 'b: 'a>(
         &'a self,
         key_expr: ZResult<KeyExpr<'b>>,
     ) -> impl Resolve<ZResult<KeyExpr<'b>>> + 'a {
@@ -1086,7 +1130,7 @@ impl Session {
             #[cfg(feature = "unstable")]
             {
                 let state = zread!(self.state);
-                self.update_status_up(&state, &key_expr)
+                self.update_status_up(&state, &key_expr, false)
             }
         }
 
@@ -1150,7 +1194,7 @@ impl Session {
                             #[cfg(feature = "unstable")]
                             {
                                 let state = zread!(self.state);
-                                self.update_status_down(&state, &sub_state.key_expr)
+                                self.update_status_down(&state, &sub_state.key_expr, false)
                             }
                         }
                     }
@@ -1177,7 +1221,7 @@ impl Session {
                             #[cfg(feature = "unstable")]
                             {
                                 let state = zread!(self.state);
-                                self.update_status_down(&state, &sub_state.key_expr)
+                                self.update_status_down(&state, &sub_state.key_expr, false)
                             }
                         }
                     }
@@ -1228,6 +1272,14 @@ impl Session {
                         ext_info: qabl_info,
                     }),
                 });
+
+                #[cfg(feature = "unstable")]
+                {
+                    let state = zread!(self.state);
+                    if let Ok(expr) = state.local_wireexpr_to_expr(key_expr) {
+                        self.update_status_up(&state, &expr, true)
+                    }
+                }
             }
         }
         #[cfg(not(feature = "complete_n"))]
@@ -1256,6 +1308,14 @@ impl Session {
                         ext_info: qabl_info,
                     }),
                 });
+
+                #[cfg(feature = "unstable")]
+                {
+                    let state = zread!(self.state);
+                    if let Ok(expr) = state.local_wireexpr_to_expr(key_expr) {
+                        self.update_status_up(&state, &expr, true)
+                    }
+                }
             }
         }
         Ok(qable_state)
@@ -1321,6 +1381,16 @@ impl Session {
                                     ext_info: qabl_info,
                                 }),
                             });
+
+                            #[cfg(feature = "unstable")]
+                            {
+                                let state = zread!(self.state);
+                                if let Ok(expr) =
+                                    state.local_wireexpr_to_expr(&qable_state.key_expr)
+                                {
+                                    self.update_status_down(&state, &expr, true)
+                                }
+                            }
                         }
                         #[cfg(not(feature = "complete_n"))]
                         {
@@ -1340,6 +1410,16 @@ impl Session {
                                         ext_info: qabl_info,
                                     }),
                                 });
+
+                                #[cfg(feature = "unstable")]
+                                {
+                                    let state = zread!(self.state);
+                                    if let Ok(expr) =
+                                        state.local_wireexpr_to_expr(&qable_state.key_expr)
+                                    {
+                                        self.update_status_down(&state, &expr, true)
+                                    }
+                                }
                             }
                         }
                     }
@@ -1357,6 +1437,14 @@ impl Session {
                             },
                         }),
                     });
+
+                    #[cfg(feature = "unstable")]
+                    {
+                        let state = zread!(self.state);
+                        if let Ok(expr) = state.local_wireexpr_to_expr(&qable_state.key_expr) {
+                            self.update_status_down(&state, &expr, true)
+                        }
+                    }
                 }
             }
             Ok(())
@@ -1427,18 +1515,21 @@ impl Session {
     #[zenoh_macros::unstable]
     pub(crate) fn declare_matches_listener_inner(
         &self,
-        publisher: &Publisher,
+        key_expr: &KeyExpr,
+        destination: Locality,
+        target: MatchingTarget,
         callback: Callback<'static, MatchingStatus>,
     ) -> ZResult<Arc<MatchingListenerState>> {
         let mut state = zwrite!(self.state);
 
         let id = state.decl_id_counter.fetch_add(1, Ordering::SeqCst);
-        tracing::trace!("matches_listener({:?}) => {id}", publisher.key_expr);
+        tracing::trace!("matches_listener({:?}) => {id}", key_expr);
         let listener_state = Arc::new(MatchingListenerState {
             id,
             current: std::sync::Mutex::new(false),
-            destination: publisher.destination,
-            key_expr: publisher.key_expr.clone().into_owned(),
+            destination,
+            key_expr: key_expr.clone().into_owned(),
+            target,
             callback,
         });
         state.matching_listeners.insert(id, listener_state.clone());
@@ -1446,8 +1537,8 @@ impl Session {
         match listener_state.current.lock() {
             Ok(mut current) => {
                 if self
-                    .matching_status(&publisher.key_expr, listener_state.destination)
-                    .map(|s| s.matching_subscribers())
+                    .matching_status_of(key_expr, target, destination)
+                    .map(|s| s.matching)
                     .unwrap_or(true)
                 {
                     *current = true;
@@ -1465,6 +1556,17 @@ impl Session {
         key_expr: &KeyExpr,
         destination: Locality,
     ) -> ZResult<MatchingStatus> {
+        self.matching_status_of(key_expr, MatchingTarget::Subscribers, destination)
+    }
+
+    #[zenoh_macros::unstable]
+    pub(crate) fn matching_status_of(
+        &self,
+        key_expr: &KeyExpr,
+        target: MatchingTarget,
+        destination: Locality,
+    ) -> ZResult<MatchingStatus> {
+        use crate::net::routing::dispatcher::face::FaceState;
         use crate::net::routing::dispatcher::tables::RoutingExpr;
         let router = self.runtime.router();
         let tables = zread!(router.tables.tables);
@@ -1473,25 +1575,40 @@ impl Session {
             key_expr.as_str(),
         );
 
-        let route = crate::net::routing::dispatcher::pubsub::get_local_data_route(
-            &tables,
-            &res,
-            &mut RoutingExpr::new(&tables.root_res, key_expr.as_str()),
-        );
+        let mut expr = RoutingExpr::new(&tables.root_res, key_expr.as_str());
+        let faces: Vec<Arc<FaceState>> = match target {
+            MatchingTarget::Subscribers => {
+                crate::net::routing::dispatcher::pubsub::get_local_data_route(
+                    &tables, &res, &mut expr,
+                )
+                .values()
+                .map(|dir| dir.0.clone())
+                .collect()
+            }
+            MatchingTarget::Queryables(query_target) => {
+                crate::net::routing::dispatcher::queries::get_local_query_route(
+                    &tables, &res, &mut expr,
+                )
+                .iter()
+                .filter(|qabl| query_target != QueryTarget::AllComplete || qabl.complete > 0)
+                .map(|qabl| qabl.direction.0.clone())
+                .collect()
+            }
+        };
 
         drop(tables);
         let matching = match destination {
-            Locality::Any => !route.is_empty(),
+            Locality::Any => !faces.is_empty(),
             Locality::Remote => {
                 if let Some(face) = zread!(self.state).primitives.as_ref() {
-                    route.values().any(|dir| !Arc::ptr_eq(&dir.0, &face.state))
+                    faces.iter().any(|f| !Arc::ptr_eq(f, &face.state))
                 } else {
-                    !route.is_empty()
+                    !faces.is_empty()
                 }
             }
             Locality::SessionLocal => {
                 if let Some(face) = zread!(self.state).primitives.as_ref() {
-                    route.values().any(|dir| Arc::ptr_eq(&dir.0, &face.state))
+                    faces.iter().any(|f| Arc::ptr_eq(f, &face.state))
                 } else {
                     false
                 }
@@ -1501,9 +1618,14 @@ impl Session {
     }
 
     #[zenoh_macros::unstable]
-    pub(crate) fn update_status_up(&self, state: &SessionState, key_expr: &KeyExpr) {
+    pub(crate) fn update_status_up(
+        &self,
+        state: &SessionState,
+        key_expr: &KeyExpr,
+        queryables: bool,
+    ) {
         for msub in state.matching_listeners.values() {
-            if key_expr.intersects(&msub.key_expr) {
+            if msub.target.is_queryables() == queryables && key_expr.intersects(&msub.key_expr) {
                 // Cannot hold session lock when calling tables (matching_status())
                 // TODO: check which ZRuntime should be used
                 self.task_controller
@@ -1514,10 +1636,12 @@ impl Session {
                             match msub.current.lock() {
                                 Ok(mut current) => {
                                     if !*current {
-                                        if let Ok(status) = session
-                                            .matching_status(&msub.key_expr, msub.destination)
-                                        {
-                                            if status.matching_subscribers() {
+                                        if let Ok(status) = session.matching_status_of(
+                                            &msub.key_expr,
+                                            msub.target,
+                                            msub.destination,
+                                        ) {
+                                            if status.matching {
                                                 *current = true;
                                                 let callback = msub.callback.clone();
                                                 (callback)(status)
@@ -1539,9 +1663,14 @@ impl Session {
     }
 
     #[zenoh_macros::unstable]
-    pub(crate) fn update_status_down(&self, state: &SessionState, key_expr: &KeyExpr) {
+    pub(crate) fn update_status_down(
+        &self,
+        state: &SessionState,
+        key_expr: &KeyExpr,
+        queryables: bool,
+    ) {
         for msub in state.matching_listeners.values() {
-            if key_expr.intersects(&msub.key_expr) {
+            if msub.target.is_queryables() == queryables && key_expr.intersects(&msub.key_expr) {
                 // Cannot hold session lock when calling tables (matching_status())
                 // TODO: check which ZRuntime should be used
                 self.task_controller
@@ -1552,10 +1681,12 @@ impl Session {
                             match msub.current.lock() {
                                 Ok(mut current) => {
                                     if *current {
-                                        if let Ok(status) = session
-                                            .matching_status(&msub.key_expr, msub.destination)
-                                        {
-                                            if !status.matching_subscribers() {
+                                        if let Ok(status) = session.matching_status_of(
+                                            &msub.key_expr,
+                                            msub.target,
+                                            msub.destination,
+                                        ) {
+                                            if !status.matching {
                                                 *current = false;
                                                 let callback = msub.callback.clone();
                                                 (callback)(status)
@@ -1748,7 +1879,7 @@ impl Session {
         value: Option<Value>,
         #[cfg(feature = "unstable")] attachment: Option<Attachment>,
         callback: Callback<'static, Reply>,
-    ) -> ZResult<()> {
+    ) -> ZResult<RequestId> {
         tracing::trace!("get({}, {:?}, {:?})", selector, target, consolidation);
         let mut state = zwrite!(self.state);
         let consolidation = match consolidation.mode {
@@ -1871,6 +2002,33 @@ impl Session {
                 attachment,
             );
         }
+        Ok(qid)
+    }
+
+    pub(crate) fn cancel_query(&self, qid: RequestId, destination: Locality) -> ZResult<()> {
+        trace!("cancel_query({})", qid);
+        let mut state = zwrite!(self.state);
+        if state.queries.remove(&qid).is_none() {
+            // The query is already complete
+            return Ok(());
+        }
+        let local = zlock!(state.incoming_queries).remove(&(true, qid));
+        let primitives = state.primitives.as_ref().cloned();
+        drop(state);
+        if let Some(cancellation_token) = local {
+            cancellation_token.cancel();
+        }
+        if destination != Locality::SessionLocal {
+            match primitives {
+                Some(primitives) => primitives.send_response_final(ResponseFinal {
+                    rid: qid,
+                    ext_qos: response::ext::QoSType::response_final_default(),
+                    ext_tstamp: None,
+                    ext_cancel: Some(response::ext::CancelType::new()),
+                }),
+                None => zenoh_result::bail!("Session closed"),
+            }
+        }
         Ok(())
     }
 
@@ -1886,7 +2044,8 @@ impl Session {
         body: Option<QueryBodyType>,
         #[cfg(feature = "unstable")] attachment: Option<Attachment>,
     ) {
-        let (primitives, key_expr, callbacks) = {
+        let cancellation_token = CancellationToken::new();
+        let (primitives, key_expr, callbacks, incoming_queries) = {
             let state = zread!(self.state);
             match state.wireexpr_to_keyexpr(key_expr, local) {
                 Ok(key_expr) => {
@@ -1913,10 +2072,14 @@ impl Session {
                         )
                         .map(|qable| qable.callback.clone())
                         .collect::<Vec<Arc<dyn Fn(Query) + Send + Sync>>>();
+                    let key_expr = key_expr.into_owned();
+                    zlock!(state.incoming_queries).insert((local, qid), cancellation_token.clone());
+                    let incoming_queries = Arc::downgrade(&state.incoming_queries);
                     (
                         state.primitives.as_ref().unwrap().clone(),
-                        key_expr.into_owned(),
+                        key_expr,
                         callbacks,
+                        incoming_queries,
                     )
                 }
                 Err(err) => {
@@ -1947,6 +2110,8 @@ impl Session {
                 },
                 #[cfg(feature = "unstable")]
                 attachment,
+                cancellation_token,
+                registration: Some((incoming_queries, local)),
             }),
         };
         for callback in callbacks.iter() {
@@ -2080,6 +2245,38 @@ impl<'s> SessionDeclarations<'s, 'static> for Arc<Session> {
         }
     }
 
+    /// Create a [`Querier`](crate::querier::Querier) for the given key expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_expr` - The key expression matching the resources to query
+    ///
+    /// # Examples
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use zenoh::prelude::r#async::*;
+    ///
+    /// let session = zenoh::open(config::peer()).res().await.unwrap().into_arc();
+    /// let querier = session.declare_querier("key/expression")
+    ///     .res()
+    ///     .await
+    ///     .unwrap();
+    /// let replies = querier.get().res().await.unwrap();
+    /// # }
+    /// ```
+    #[zenoh_macros::unstable]
+    fn declare_querier<'b, TryIntoKeyExpr>(
+        &'s self,
+        key_expr: TryIntoKeyExpr,
+    ) -> QuerierBuilder<'static, 'b>
+    where
+        TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
+        <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>,
+    {
+        SessionRef::Shared(self.clone()).declare_querier(key_expr)
+    }
+
     /// Obtain a [`Liveliness`] struct tied to this Zenoh [`Session`].
     ///
     /// # Examples
@@ -2148,7 +2345,7 @@ impl Primitives for Session {
                     let state = zread!(self.state);
                     match state.wireexpr_to_keyexpr(&m.wire_expr, false) {
                         Ok(expr) => {
-                            self.update_status_up(&state, &expr);
+                            self.update_status_up(&state, &expr, false);
 
                             if expr
                                 .as_str()
@@ -2181,7 +2378,7 @@ impl Primitives for Session {
                     let state = zread!(self.state);
                     match state.wireexpr_to_keyexpr(&m.ext_wire_expr.wire_expr, false) {
                         Ok(expr) => {
-                            self.update_status_down(&state, &expr);
+                            self.update_status_down(&state, &expr, false);
 
                             if expr
                                 .as_str()
@@ -2213,9 +2410,35 @@ impl Primitives for Session {
             }
             zenoh_protocol::network::DeclareBody::DeclareQueryable(m) => {
                 trace!("recv DeclareQueryable {} {:?}", m.id, m.wire_expr);
+                #[cfg(feature = "unstable")]
+                {
+                    let state = zread!(self.state);
+                    match state.wireexpr_to_keyexpr(&m.wire_expr, false) {
+                        Ok(expr) => self.update_status_up(&state, &expr, true),
+                        Err(err) => {
+                            tracing::error!(
+                                "Received DeclareQueryable for unkown wire_expr: {}",
+                                err
+                            )
+                        }
+                    }
+                }
             }
             zenoh_protocol::network::DeclareBody::UndeclareQueryable(m) => {
                 trace!("recv UndeclareQueryable {:?}", m.id);
+                #[cfg(feature = "unstable")]
+                {
+                    let state = zread!(self.state);
+                    match state.wireexpr_to_keyexpr(&m.ext_wire_expr.wire_expr, false) {
+                        Ok(expr) => self.update_status_down(&state, &expr, true),
+                        Err(err) => {
+                            tracing::error!(
+                                "Received Forget Queryable for unkown key_expr: {}",
+                                err
+                            )
+                        }
+                    }
+                }
             }
             DeclareBody::DeclareToken(_) => todo!(),
             DeclareBody::UndeclareToken(_) => todo!(),
@@ -2498,6 +2721,15 @@ impl Primitives for Session {
     fn send_response_final(&self, msg: ResponseFinal) {
         trace!("recv ResponseFinal {:?}", msg);
         let mut state = zwrite!(self.state);
+        if msg.ext_cancel.is_some() {
+            let cancellation_token = zlock!(state.incoming_queries).remove(&(false, msg.rid));
+            if let Some(cancellation_token) = cancellation_token {
+                drop(state);
+                trace!("Cancel query {}", msg.rid);
+                cancellation_token.cancel();
+            }
+            return;
+        }
         match state.queries.get_mut(&msg.rid) {
             Some(query) => {
                 query.nb_final -= 1;
@@ -2661,6 +2893,35 @@ pub trait SessionDeclarations<'s, 'a> {
         TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
         <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>;
 
+    /// Create a [`Querier`](crate::querier::Querier) for the given key expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_expr` - The key expression matching the resources to query
+    ///
+    /// # Examples
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use zenoh::prelude::r#async::*;
+    ///
+    /// let session = zenoh::open(config::peer()).res().await.unwrap().into_arc();
+    /// let querier = session.declare_querier("key/expression")
+    ///     .res()
+    ///     .await
+    ///     .unwrap();
+    /// let replies = querier.get().res().await.unwrap();
+    /// # }
+    /// ```
+    #[zenoh_macros::unstable]
+    fn declare_querier<'b, TryIntoKeyExpr>(
+        &'s self,
+        key_expr: TryIntoKeyExpr,
+    ) -> QuerierBuilder<'a, 'b>
+    where
+        TryIntoKeyExpr: TryInto<KeyExpr<'b>>,
+        <TryIntoKeyExpr as TryInto<KeyExpr<'b>>>::Error: Into<zenoh_result::Error>;
+
     /// Obtain a [`Liveliness`] struct tied to this Zenoh [`Session`].
     ///
     /// # Examples
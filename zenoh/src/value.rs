@@ -87,6 +87,53 @@ impl std::fmt::Display for Value {
 
 impl std::error::Error for Value {}
 
+// Shared memory access
+#[cfg(feature = "shared-memory")]
+impl Value {
+    /// Returns the [`SharedMemoryBuf`] carrying the payload of this Value, without copy, if the payload
+    /// is entirely made of a single shared memory buffer.
+    ///
+    /// The buffer is borrowed from this Value: the shared memory chunk it points to remains valid
+    /// as long as this Value is alive, or as long as a clone of the buffer is kept.
+    pub fn as_shm(&self) -> Option<&SharedMemoryBuf> {
+        let mut zslices = self.payload.zslices();
+        let zslice = zslices.next()?;
+        if zslices.next().is_some() {
+            return None;
+        }
+        let shmb = zslice.downcast_ref::<SharedMemoryBuf>()?;
+        (zslice.range() == (0..shmb.len())).then_some(shmb)
+    }
+
+    /// Returns the bytes of the [`SharedMemoryBuf`] carrying the payload of this Value for in-place
+    /// modification, if the payload is entirely made of a single shared memory buffer that is not
+    /// referenced by any other Value or process, i.e. whose reference count is one.
+    pub fn as_shm_mut(&mut self) -> Option<&mut [u8]> {
+        if !self.is_shm_exclusive() {
+            return None;
+        }
+        self.payload.zslices_mut().next()?.as_mut_slice()
+    }
+
+    /// Moves the [`SharedMemoryBuf`] carrying the payload of this Value out of it, under the same
+    /// conditions as [`Value::as_shm_mut`], so that it can be modified in place and published again.
+    ///
+    /// Returns this Value unchanged if its payload cannot be moved.
+    pub fn try_into_shm(mut self) -> Result<SharedMemoryBuf, Value> {
+        if self.as_shm_mut().is_some() {
+            if let Some(shmb) = self.as_shm() {
+                // The clone references the chunk before this Value releases it on drop
+                return Ok(shmb.clone());
+            }
+        }
+        Err(self)
+    }
+
+    fn is_shm_exclusive(&self) -> bool {
+        self.as_shm().map_or(false, |shmb| shmb.ref_count() == 1)
+    }
+}
+
 // Shared memory conversion
 #[cfg(feature = "shared-memory")]
 impl From<Arc<SharedMemoryBuf>> for Value {
@@ -0,0 +1,62 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+#[cfg(feature = "unstable")]
+#[test]
+fn adminspace_schemas() {
+    use zenoh::prelude::sync::*;
+
+    let mut config = Config::default();
+    config.insert_json5("adminspace/enabled", "true").unwrap();
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    let zenoh = zenoh::open(config).res().unwrap();
+    let root = format!("@/peer/{}", zenoh.zid());
+
+    let get_json = |selector: &str| -> Vec<(String, serde_json::Value)> {
+        zenoh
+            .get(selector)
+            .res()
+            .unwrap()
+            .into_iter()
+            .filter_map(|reply| reply.sample.ok())
+            .map(|sample| {
+                let value = String::from_utf8(sample.payload.contiguous().to_vec()).unwrap();
+                (
+                    sample.key_expr.to_string(),
+                    serde_json::from_str(&value).unwrap(),
+                )
+            })
+            .collect()
+    };
+
+    let schemas = get_json(&format!("{root}/schema/**"));
+    for key in ["router", "metrics", "linkstate", "subscriber", "queryable"] {
+        assert!(
+            schemas
+                .iter()
+                .any(|(k, _)| *k == format!("{root}/schema/{key}")),
+            "missing schema {key}"
+        );
+    }
+
+    // the reply on the root key has the required properties of its schema
+    let router_schema = get_json(&format!("{root}/schema/router"));
+    assert_eq!(router_schema.len(), 1);
+    let router = get_json(&root);
+    assert_eq!(router.len(), 1);
+    for property in router_schema[0].1["required"].as_array().unwrap() {
+        assert!(router[0].1.get(property.as_str().unwrap()).is_some());
+    }
+}
@@ -0,0 +1,77 @@
+//
+// Copyright (c) 2024 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use std::time::Duration;
+use zenoh::encoding::{self, EncodingExt, CBOR, FIRST_USER_ID, PROTOBUF};
+use zenoh::prelude::sync::*;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[test]
+fn encoding_registry() {
+    // builtin encodings are reserved
+    assert!(encoding::register(CBOR.id(), "application/other").is_err());
+    assert!(encoding::register(FIRST_USER_ID - 1, "application/other").is_err());
+    assert_eq!(encoding::register(CBOR.id(), CBOR.mime()).unwrap(), CBOR);
+
+    let registered = encoding::register(FIRST_USER_ID, "application/x-test-registry").unwrap();
+    assert_eq!(encoding::get(FIRST_USER_ID), Some(registered.clone()));
+    assert_eq!(
+        encoding::get_by_mime("application/x-test-registry"),
+        Some(registered.clone())
+    );
+    assert!(encoding::register(FIRST_USER_ID, "application/x-test-other").is_err());
+    assert!(encoding::register(FIRST_USER_ID + 1, "application/x-test-registry").is_err());
+    assert!(encoding::register(FIRST_USER_ID + 1, "application/x;test").is_err());
+
+    // the compact form is a regular encoding, unknown identifiers are not resolved
+    let compact = registered.with_schema("schema").unwrap();
+    assert_eq!(
+        compact.to_string(),
+        format!("application/custom#{}", FIRST_USER_ID) + ";schema"
+    );
+    assert_eq!(compact.registered(), Some(registered.clone()));
+    assert_eq!(compact.schema(), Some("schema"));
+    assert_eq!(compact.mime(), "application/x-test-registry;schema");
+    assert_eq!(
+        encoding::from_mime("application/x-test-registry;schema"),
+        compact
+    );
+    assert!(registered.matches(&compact));
+    assert!(registered.matches(&Encoding::from("application/x-test-registry;schema")));
+    assert!(!CBOR.matches(&compact));
+
+    let unknown = Encoding::from("application/custom#65535");
+    assert_eq!(unknown.registered(), None);
+    assert_eq!(unknown.mime(), "application/custom#65535");
+    assert_eq!(encoding::from_mime("text/plain"), Encoding::TEXT_PLAIN);
+}
+
+#[test]
+fn encoding_registry_pubsub() {
+    let zenoh = zenoh::open(Config::default()).res().unwrap();
+    let subscriber = zenoh.declare_subscriber("test/encoding").res().unwrap();
+    let encoding = PROTOBUF.with_schema("test.Message").unwrap();
+    zenoh
+        .put(
+            "test/encoding",
+            Value::from(vec![0u8, 1, 2]).encoding(encoding.clone()),
+        )
+        .res()
+        .unwrap();
+
+    let sample = subscriber.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(sample.encoding, encoding);
+    assert_eq!(sample.encoding.registered(), Some(PROTOBUF));
+    assert_eq!(sample.encoding.schema(), Some("test.Message"));
+}
@@ -0,0 +1,56 @@
+//
+// Copyright (c) 2024 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use std::time::Duration;
+use zenoh::handlers::RingBuffer;
+use zenoh::prelude::sync::*;
+
+const SLEEP: Duration = Duration::from_millis(100);
+
+#[test]
+fn ring_buffer_drops_oldest() {
+    let zenoh = zenoh::open(Config::default()).res().unwrap();
+    let subscriber = zenoh
+        .declare_subscriber("test/ring_buffer")
+        .with(RingBuffer::new(3))
+        .res()
+        .unwrap();
+    for i in 0..10 {
+        zenoh.put("test/ring_buffer", i.to_string()).res().unwrap();
+    }
+    std::thread::sleep(SLEEP);
+
+    assert_eq!(subscriber.capacity(), 3);
+    assert_eq!(subscriber.len(), 3);
+    assert_eq!(subscriber.dropped(), 7);
+    for i in 7..10 {
+        let sample = subscriber.recv().unwrap();
+        assert_eq!(
+            std::str::from_utf8(&sample.payload.contiguous()).unwrap(),
+            i.to_string()
+        );
+    }
+    assert!(subscriber.try_recv().unwrap().is_none());
+    assert!(subscriber.recv_timeout(SLEEP).unwrap().is_none());
+}
+
+#[test]
+fn ring_buffer_closed() {
+    let (callback, receiver) = RingBuffer::new(3).into_cb_receiver_pair();
+    callback(1);
+    drop(callback);
+
+    assert_eq!(receiver.recv().unwrap(), 1);
+    assert!(receiver.recv().is_err());
+    assert!(receiver.try_recv().is_err());
+}
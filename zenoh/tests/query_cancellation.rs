@@ -0,0 +1,70 @@
+//
+// Copyright (c) 2024 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use std::time::Duration;
+use zenoh::prelude::r#async::*;
+use zenoh_core::ztimeout;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const SLEEP: Duration = Duration::from_secs(1);
+
+async fn test_query_cancellation(requester: &Session, responder: &Session, key_expr: &str) {
+    let queryable = ztimeout!(responder.declare_queryable(key_expr).res_async()).unwrap();
+    tokio::time::sleep(SLEEP).await;
+
+    let (replies, cancellation) =
+        ztimeout!(requester.get(key_expr).cancellable().res_async()).unwrap();
+    let query = ztimeout!(queryable.recv_async()).unwrap();
+    assert!(!query.is_cancelled());
+
+    ztimeout!(cancellation.cancel().res_async()).unwrap();
+    // the receiver is closed without waiting for the final reply
+    assert!(ztimeout!(replies.recv_async()).is_err());
+    ztimeout!(query.cancelled());
+    assert!(query.is_cancelled());
+
+    // replying to a cancelled query is not an error, the reply is dropped
+    let reply = Sample::new(query.key_expr().clone(), "late");
+    ztimeout!(query.reply(Ok(reply)).res_async()).unwrap();
+    drop(query);
+
+    // cancelling again has no effect
+    ztimeout!(cancellation.cancel().res_async()).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn query_cancellation_local() {
+    let zenoh = ztimeout!(zenoh::open(Config::default()).res_async()).unwrap();
+    test_query_cancellation(&zenoh, &zenoh, "test/query_cancellation/local").await;
+    ztimeout!(zenoh.close().res_async()).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn query_cancellation_remote() {
+    let endpoint: EndPoint = "tcp/127.0.0.1:47470".parse().unwrap();
+
+    let mut config = config::peer();
+    config.listen.endpoints = vec![endpoint.clone()];
+    config.scouting.multicast.set_enabled(Some(false)).unwrap();
+    let peer01 = ztimeout!(zenoh::open(config).res_async()).unwrap();
+
+    let mut config = config::peer();
+    config.connect.endpoints = vec![endpoint];
+    config.scouting.multicast.set_enabled(Some(false)).unwrap();
+    let peer02 = ztimeout!(zenoh::open(config).res_async()).unwrap();
+
+    test_query_cancellation(&peer01, &peer02, "test/query_cancellation/remote").await;
+
+    ztimeout!(peer01.close().res_async()).unwrap();
+    ztimeout!(peer02.close().res_async()).unwrap();
+}
@@ -0,0 +1,91 @@
+//
+// Copyright (c) 2024 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use zenoh::prelude::sync::*;
+use zenoh::serialization::{Bincode, Cbor, Json, Serializer, Typed};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Position {
+    name: String,
+    x: f64,
+    y: f64,
+}
+
+fn roundtrip<S: Serializer>(serializer: S) {
+    let position = Position {
+        name: "robot".into(),
+        x: 1.5,
+        y: -2.0,
+    };
+    let value = Value::serialize(&serializer, &position).unwrap();
+    assert_eq!(value.encoding, serializer.encoding());
+    assert_eq!(
+        value.deserialize::<_, Position>(&serializer).unwrap(),
+        position
+    );
+    assert!(value
+        .clone()
+        .encoding(Encoding::TEXT_PLAIN)
+        .deserialize::<_, Position>(&serializer)
+        .is_err());
+}
+
+#[test]
+fn serializers_roundtrip() {
+    roundtrip(Json);
+    roundtrip(Cbor);
+    roundtrip(Bincode);
+}
+
+#[test]
+fn bincode_length_limit() {
+    // a length prefix claiming more bytes than received is rejected without allocating them
+    let mut bytes = u64::MAX.to_le_bytes().to_vec();
+    bytes.extend_from_slice(b"robot");
+    assert!(Bincode.deserialize_bytes::<String>(&bytes).is_err());
+    assert!(Bincode.deserialize_bytes::<Vec<u8>>(&bytes).is_err());
+}
+
+#[test]
+fn typed_pubsub() {
+    let zenoh = zenoh::open(Config::default()).res().unwrap();
+    let subscriber = zenoh
+        .declare_subscriber("test/serialization")
+        .with(Typed::<Position, _>::new(Cbor))
+        .res()
+        .unwrap();
+    let publisher = zenoh
+        .declare_publisher("test/serialization")
+        .res()
+        .unwrap()
+        .typed::<Position, _>(Cbor);
+
+    let position = Position {
+        name: "robot".into(),
+        x: 3.0,
+        y: 4.0,
+    };
+    publisher.put(&position).unwrap().res().unwrap();
+    let sample = subscriber.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(sample.value.unwrap(), position);
+
+    // values that cannot be deserialized are received with their error
+    zenoh.put("test/serialization", "not cbor").res().unwrap();
+    let sample = subscriber.recv_timeout(TIMEOUT).unwrap();
+    assert!(sample.value.is_err());
+    assert_eq!(sample.sample.key_expr.as_str(), "test/serialization");
+}
@@ -0,0 +1,179 @@
+//
+// Copyright (c) 2023 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+#[cfg(feature = "shared-memory")]
+#[test]
+fn value_shm_access() {
+    use zenoh::prelude::sync::*;
+    use zenoh::shm::SharedMemoryManager;
+
+    let id = format!("test_value_shm_access_{}", std::process::id());
+    let mut manager = SharedMemoryManager::make(id, 4_096).unwrap();
+    let mut shmb = manager.alloc(16).unwrap();
+    unsafe { shmb.as_mut_slice() }.fill(1);
+
+    // a buffer referenced by another value is readable, but cannot be modified
+    let value: Value = shmb.into();
+    let copy = value.clone();
+    assert_eq!(value.as_shm().unwrap().as_slice(), &[1; 16]);
+    let mut value = value.try_into_shm().unwrap_err();
+    assert!(value.as_shm_mut().is_none());
+    drop(copy);
+
+    // once it is the only reference, it is modified in place
+    value.as_shm_mut().unwrap().fill(2);
+    let shmb = value.try_into_shm().unwrap();
+    assert_eq!(shmb.ref_count(), 1);
+    assert_eq!(shmb.as_slice(), &[2; 16]);
+
+    // payloads that are not in shared memory are not exposed
+    let value = Value::from("not in shared memory");
+    assert!(value.as_shm().is_none());
+}
+
+#[cfg(feature = "shared-memory")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn shm_through_router() {
+    use std::time::Duration;
+    use zenoh::prelude::r#async::*;
+    use zenoh::shm::SharedMemoryManager;
+    use zenoh_core::ztimeout;
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+    const ENDPOINT: &str = "tcp/127.0.0.1:31447";
+
+    let open = |mode: WhatAmI| {
+        let mut config = Config::default();
+        config.set_mode(Some(mode)).unwrap();
+        config.scouting.multicast.set_enabled(Some(false)).unwrap();
+        config.transport.shared_memory.set_enabled(true).unwrap();
+        if mode == WhatAmI::Router {
+            config.listen.endpoints = vec![ENDPOINT.parse().unwrap()];
+        } else {
+            config.connect.endpoints = vec![ENDPOINT.parse().unwrap()];
+        }
+        zenoh::open(config).res_async()
+    };
+    let router = ztimeout!(open(WhatAmI::Router)).unwrap();
+    let publisher = ztimeout!(open(WhatAmI::Client)).unwrap();
+    let subscriber = ztimeout!(open(WhatAmI::Client)).unwrap();
+
+    let sub = ztimeout!(subscriber.declare_subscriber("test/shm").res_async()).unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let id = format!("test_shm_through_router_{}", std::process::id());
+    let mut manager = SharedMemoryManager::make(id, 4_096).unwrap();
+    let mut shmb = manager.alloc(64).unwrap();
+    unsafe { shmb.as_mut_slice() }.fill(42);
+    let segment = shmb.owner();
+    ztimeout!(publisher.put("test/shm", shmb).res_async()).unwrap();
+
+    // the subscriber reads the buffer from the segment of the publisher, copied neither by the router nor by itself
+    let sample = ztimeout!(sub.recv_async()).unwrap();
+    let shmb = sample
+        .as_shm()
+        .expect("the payload should be in shared memory");
+    assert_eq!(shmb.owner(), segment);
+    assert_eq!(shmb.as_slice(), &[42; 64]);
+
+    drop(sample);
+    drop(sub);
+    ztimeout!(subscriber.close().res_async()).unwrap();
+    ztimeout!(publisher.close().res_async()).unwrap();
+    ztimeout!(router.close().res_async()).unwrap();
+}
+
+#[cfg(all(feature = "shared-memory", unix))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn shm_router_failure() {
+    use std::{
+        process::{Command, Stdio},
+        time::Duration,
+    };
+    use zenoh::prelude::r#async::*;
+    use zenoh::shm::SharedMemoryManager;
+    use zenoh_core::ztimeout;
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+    const ENDPOINT: &str = "tcp/127.0.0.1:31448";
+    // Set in the environment of the process running the router
+    const ROUTER_ENV: &str = "ZENOH_TEST_SHM_ROUTER";
+
+    let open = |mode: WhatAmI| {
+        let mut config = Config::default();
+        config.set_mode(Some(mode)).unwrap();
+        config.scouting.multicast.set_enabled(Some(false)).unwrap();
+        config.transport.shared_memory.set_enabled(true).unwrap();
+        if mode == WhatAmI::Router {
+            config.listen.endpoints = vec![ENDPOINT.parse().unwrap()];
+        } else {
+            config.connect.endpoints = vec![ENDPOINT.parse().unwrap()];
+        }
+        zenoh::open(config).res_async()
+    };
+
+    // the router runs in its own process, in which this test is run again, until it is killed
+    if std::env::var_os(ROUTER_ENV).is_some() {
+        let _router = ztimeout!(open(WhatAmI::Router)).unwrap();
+        tokio::time::sleep(TIMEOUT).await;
+        return;
+    }
+    let mut router = Command::new(std::env::current_exe().unwrap())
+        .args(["shm_router_failure", "--exact"])
+        .env(ROUTER_ENV, "1")
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let connect = || async {
+        loop {
+            match open(WhatAmI::Client).await {
+                Ok(session) => break session,
+                Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        }
+    };
+    let publisher = ztimeout!(connect());
+    let subscriber = ztimeout!(connect());
+
+    let sub = ztimeout!(subscriber.declare_subscriber("test/shm").res_async()).unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let id = format!("test_shm_router_failure_{}", std::process::id());
+    let mut manager = SharedMemoryManager::make(id, 4_096).unwrap();
+    let mut shmb = manager.alloc(64).unwrap();
+    unsafe { shmb.as_mut_slice() }.fill(42);
+    ztimeout!(publisher.put("test/shm", shmb).res_async()).unwrap();
+    let sample = ztimeout!(sub.recv_async()).unwrap();
+
+    // the router fails after forwarding the buffer, its process being dead once reaped
+    router.kill().unwrap();
+    router.wait().unwrap();
+    ztimeout!(async {
+        while publisher.info().routers_zid().res_async().await.count() > 0 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    });
+
+    // the reference the router passed on to the subscriber is not reclaimed by the publisher
+    let shmb = sample
+        .as_shm()
+        .expect("the payload should be in shared memory");
+    assert!(shmb.ref_count() >= 1);
+    assert_eq!(shmb.as_slice(), &[42; 64]);
+    assert_eq!(manager.stats().reclaimed, 0);
+
+    drop(sample);
+    drop(sub);
+    ztimeout!(subscriber.close().res_async()).unwrap();
+    ztimeout!(publisher.close().res_async()).unwrap();
+}
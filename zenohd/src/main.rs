@@ -72,6 +72,11 @@ struct Args {
     /// By default zenohd replies to multicast scouting messages for being discovered by peers and clients. This option disables this feature.
     #[arg(long)]
     no_multicast_scouting: bool,
+    /// By default zenohd, when built with the `shared-memory` feature, enables shared memory so that the buffers
+    /// published in shared memory by the local clients are forwarded to the other local clients without copy.
+    /// This option disables this feature.
+    #[arg(long)]
+    no_shm: bool,
     /// Configures HTTP interface for the REST API (enabled by default on port 8000). Accepted values:
     ///   - a port number
     ///   - a string with format `<local_ip>:<port_number>` (to bind the HTTP server to a specific interface)
@@ -144,6 +149,22 @@ fn config_from_args(args: &Args) -> Config {
                 .unwrap();
         }
     }
+    // apply '--no-shm' to config only if explicitly set (overwritting config),
+    // or if no config file is set (to enable shared memory by default)
+    #[cfg(feature = "shared-memory")]
+    if args.no_shm || args.config.is_none() {
+        config
+            .transport
+            .shared_memory
+            .set_enabled(!args.no_shm)
+            .unwrap();
+    }
+    #[cfg(not(feature = "shared-memory"))]
+    if args.no_shm {
+        tracing::warn!(
+            "Option --no-shm is ignored: zenohd was built without shared memory support"
+        );
+    }
     config.adminspace.set_enabled(true).unwrap();
     config.plugins_loading.set_enabled(true).unwrap();
     if !args.plugin_search_dir.is_empty() {